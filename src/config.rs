@@ -1,5 +1,6 @@
 use anyhow::{bail, Result};
 use serde::Deserialize;
+use std::time::Duration;
 
 pub const DEFAULT_IMAGE: &str = "alpine:latest";
 
@@ -33,6 +34,8 @@ pub struct BoxConfigInput {
     pub env: Vec<String>,
     pub local: bool,
     pub strategy: Option<String>,
+    /// Sessions in the same batch manifest that must be created first.
+    pub depends_on: Vec<String>,
 }
 
 fn resolve_command(command: Option<Vec<String>>) -> Result<Vec<String>> {
@@ -46,21 +49,199 @@ fn resolve_command(command: Option<Vec<String>>) -> Result<Vec<String>> {
     }
 }
 
+fn shell_on_empty_from(file_config: &FileConfig) -> bool {
+    file_config
+        .defaults
+        .as_ref()
+        .and_then(|d| d.shell_on_empty)
+        .unwrap_or(false)
+}
+
+fn clone_depth_from(file_config: &FileConfig) -> Option<u32> {
+    file_config
+        .defaults
+        .as_ref()
+        .and_then(|d| d.clone_depth)
+        .filter(|&depth| depth > 0)
+}
+
+/// Default history depth for new clone-strategy workspaces (`clone_depth` in
+/// the `[defaults]` table). `None` (the default, or 0) clones full history.
+pub fn load_clone_depth() -> Option<u32> {
+    clone_depth_from(&load_file_config())
+}
+
+/// The user's shell from `$SHELL`, falling back to `/bin/sh`.
+fn login_shell() -> String {
+    std::env::var("SHELL")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "/bin/sh".to_string())
+}
+
+/// Workspace strategies: `clone` (git clone --local), `worktree` (detached
+/// git worktree) and `branch` (git worktree on a new `box/<workspace>` branch).
+pub const STRATEGIES: &[&str] = &["clone", "worktree", "branch"];
+
+/// Whether a strategy's workspace is a git worktree of the project.
+pub fn is_worktree_strategy(strategy: &str) -> bool {
+    matches!(strategy, "worktree" | "branch")
+}
+
+/// Check that `strategy` is one of `STRATEGIES`.
+pub fn validate_strategy(strategy: &str) -> Result<()> {
+    if !STRATEGIES.contains(&strategy) {
+        bail!(
+            "Invalid strategy '{}'. Must be 'clone', 'worktree' or 'branch'.",
+            strategy
+        );
+    }
+    Ok(())
+}
+
 fn resolve_strategy(strategy: Option<String>) -> Result<String> {
     let s = strategy
         .or_else(|| std::env::var("BOX_STRATEGY").ok().filter(|v| !v.is_empty()))
         .unwrap_or_else(|| "clone".to_string());
-    match s.as_str() {
-        "clone" | "worktree" => Ok(s),
-        _ => bail!("Invalid strategy '{}'. Must be 'clone' or 'worktree'.", s),
+    validate_strategy(&s)?;
+    Ok(s)
+}
+
+/// File at the project's git root holding per-repository defaults.
+pub const PROJECT_CONFIG_FILE: &str = ".box.toml";
+
+/// Per-repository defaults from `.box.toml`. Explicit flags override these,
+/// and these override the `BOX_DEFAULT_*` environment variables.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub image: Option<String>,
+    pub command: Option<Vec<String>>,
+    pub strategy: Option<String>,
+    pub mount_path: Option<String>,
+    #[serde(default)]
+    pub env: Vec<String>,
+}
+
+/// Read `.box.toml` from the git root containing `project_dir`. A missing
+/// file yields the default (empty) config; a malformed one is an error.
+pub fn load_project_config(project_dir: &str) -> Result<ProjectConfig> {
+    let (path, content) = match read_project_config(project_dir)? {
+        Some(found) => found,
+        None => return Ok(ProjectConfig::default()),
+    };
+    let project: ProjectConfig = toml::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid '{}': {}", path.display(), e))?;
+    if let Some(ref strategy) = project.strategy {
+        if !STRATEGIES.contains(&strategy.as_str()) {
+            bail!(
+                "Invalid strategy '{}' in '{}'. Must be 'clone', 'worktree' or 'branch' (--strategy overrides it).",
+                strategy,
+                path.display()
+            );
+        }
     }
+    if let Some(entry) = project.env.iter().find(|e| !e.contains('=')) {
+        bail!(
+            "Invalid env entry '{}' in '{}': expected KEY=VALUE.",
+            entry,
+            path.display()
+        );
+    }
+    Ok(project)
+}
+
+/// Path and contents of the `.box.toml` at the git root containing
+/// `project_dir`, or `None` when there is none.
+pub fn read_project_config(project_dir: &str) -> Result<Option<(std::path::PathBuf, String)>> {
+    let path = match crate::git::find_root(std::path::Path::new(project_dir)) {
+        Some(root) => root.join(PROJECT_CONFIG_FILE),
+        None => return Ok(None),
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(Some((path, content))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => bail!("Cannot read '{}': {}", path.display(), e),
+    }
+}
+
+/// File listing the `.box.toml` files the user has agreed to use, one
+/// `<sha256> <path>` line each. A `.box.toml` can run commands on the host,
+/// so it is only used once its exact contents have been trusted.
+pub fn trusted_projects_path() -> Result<std::path::PathBuf> {
+    Ok(std::path::PathBuf::from(home_dir()?)
+        .join(".box")
+        .join("trusted_projects"))
+}
+
+fn project_config_digest(content: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn trust_entry(path: &std::path::Path, content: &str) -> String {
+    format!("{} {}", project_config_digest(content), path.display())
+}
+
+/// Whether `store` lists `path` with exactly these contents. Editing the
+/// file (or moving it) makes it untrusted again.
+pub fn is_project_config_trusted(
+    store: &std::path::Path,
+    path: &std::path::Path,
+    content: &str,
+) -> bool {
+    let entry = trust_entry(path, content);
+    std::fs::read_to_string(store)
+        .map(|trusted| trusted.lines().any(|line| line == entry))
+        .unwrap_or(false)
+}
+
+/// Record `path` with these contents as trusted in `store`.
+pub fn trust_project_config(
+    store: &std::path::Path,
+    path: &std::path::Path,
+    content: &str,
+) -> Result<()> {
+    use std::io::Write;
+    if let Some(parent) = store.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(store)
+        .map_err(|e| anyhow::anyhow!("Cannot write '{}': {}", store.display(), e))?;
+    writeln!(file, "{}", trust_entry(path, content))?;
+    Ok(())
+}
+
+/// Put `.box.toml` env entries before explicit ones, dropping any whose key
+/// is set explicitly.
+fn merge_env(defaults: Vec<String>, explicit: Vec<String>) -> Vec<String> {
+    let key = |e: &str| e.split('=').next().unwrap_or_default().to_string();
+    let mut env: Vec<String> = defaults
+        .into_iter()
+        .filter(|d| !explicit.iter().any(|e| key(e) == key(d)))
+        .collect();
+    env.extend(explicit);
+    env
 }
 
 pub fn resolve(input: BoxConfigInput) -> Result<BoxConfig> {
-    let command = resolve_command(input.command)?;
-    let strategy = resolve_strategy(input.strategy)?;
+    let project = load_project_config(&input.project_dir)?;
+    let command = resolve_command(input.command.or(project.command))?;
+    let strategy = resolve_strategy(input.strategy.or(project.strategy))?;
+    let env = merge_env(project.env, input.env);
 
     if input.local {
+        let command = if command.is_empty() && shell_on_empty_from(&load_file_config()) {
+            vec![login_shell()]
+        } else {
+            command
+        };
         return Ok(BoxConfig {
             name: input.name,
 
@@ -68,7 +249,7 @@ pub fn resolve(input: BoxConfigInput) -> Result<BoxConfig> {
             image: String::new(),
             mount_path: String::new(),
             command,
-            env: vec![],
+            env,
             local: true,
             strategy,
         });
@@ -76,8 +257,9 @@ pub fn resolve(input: BoxConfigInput) -> Result<BoxConfig> {
 
     let mount_path = input
         .mount_path
+        .or(project.mount_path)
         .unwrap_or_else(|| derive_mount_path(&input.project_dir));
-    let image = input.image.unwrap_or_else(|| {
+    let image = input.image.or(project.image).unwrap_or_else(|| {
         std::env::var("BOX_DEFAULT_IMAGE").unwrap_or_else(|_| DEFAULT_IMAGE.to_string())
     });
 
@@ -87,66 +269,659 @@ pub fn resolve(input: BoxConfigInput) -> Result<BoxConfig> {
         image,
         mount_path,
         command,
-        env: input.env,
+        env,
         local: false,
         strategy,
     })
 }
 
+#[derive(Deserialize)]
+struct BatchManifest {
+    #[serde(default)]
+    sessions: Vec<BatchSession>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchSession {
+    name: String,
+    image: Option<String>,
+    command: Option<Vec<String>>,
+    #[serde(default)]
+    env: Vec<String>,
+    /// Defaults to a Docker session when `image` is set, local otherwise.
+    local: Option<bool>,
+    strategy: Option<String>,
+    #[serde(default)]
+    depends_on: Vec<String>,
+}
+
+/// Read a `KEY=VALUE` env file, skipping blank lines and `#` comments.
+pub fn parse_env_file(path: &std::path::Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read env file '{}': {}", path.display(), e))?;
+    parse_env_lines(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid env file '{}': {}", path.display(), e))
+}
+
+fn parse_env_lines(content: &str) -> Result<Vec<String>> {
+    let mut env = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                env.push(format!("{}={}", key.trim(), value))
+            }
+            _ => bail!("line {}: expected KEY=VALUE, got '{}'", i + 1, line),
+        }
+    }
+    Ok(env)
+}
+
+/// Parse a batch-create manifest (`[[sessions]]` tables) into config inputs,
+/// ordered so every session comes after the sessions it `depends_on`.
+/// `project_dir` is left empty for the caller to fill in.
+pub fn parse_batch_manifest(path: &std::path::Path) -> Result<Vec<BoxConfigInput>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read manifest '{}': {}", path.display(), e))?;
+    let manifest: BatchManifest = toml::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid manifest '{}': {}", path.display(), e))?;
+    order_batch_sessions(manifest.sessions)
+}
+
+fn order_batch_sessions(sessions: Vec<BatchSession>) -> Result<Vec<BoxConfigInput>> {
+    let names: Vec<&str> = sessions.iter().map(|s| s.name.as_str()).collect();
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            bail!(
+                "Session '{}' is defined more than once in the manifest.",
+                name
+            );
+        }
+    }
+    for s in &sessions {
+        if let Some(dep) = s.depends_on.iter().find(|d| !names.contains(&d.as_str())) {
+            bail!(
+                "Session '{}' depends on '{}', which is not defined in the manifest.",
+                s.name,
+                dep
+            );
+        }
+    }
+
+    // Repeatedly emit, in file order, every session whose dependencies are done.
+    let mut done: Vec<String> = Vec::new();
+    let mut pending: Vec<BatchSession> = sessions;
+    let mut ordered = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|s| s.depends_on.iter().all(|d| done.contains(d)));
+        if ready.is_empty() {
+            let cycle: Vec<&str> = blocked.iter().map(|s| s.name.as_str()).collect();
+            bail!("Dependency cycle between sessions: {}", cycle.join(", "));
+        }
+        for s in ready {
+            done.push(s.name.clone());
+            ordered.push(BoxConfigInput {
+                local: s.local.unwrap_or(s.image.is_none()),
+                name: s.name,
+                image: s.image,
+                mount_path: None,
+                project_dir: String::new(),
+                command: s.command,
+                env: s.env,
+                strategy: s.strategy,
+                depends_on: s.depends_on,
+            });
+        }
+        pending = blocked;
+    }
+    Ok(ordered)
+}
+
+/// Parse a human-readable duration: a whole number followed by a unit of
+/// `s` (seconds), `m` (minutes), `h` (hours), `d` (days) or `w` (weeks),
+/// e.g. `90s`, `1h`, `2d`, `1w`.
+pub fn parse_duration(s: &str) -> Result<std::time::Duration> {
+    let s = s.trim();
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid duration '{}'. Use a number followed by s, m, h, d or w (e.g. 2d).",
+            s
+        )
+    };
+    let unit_pos = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (digits, unit) = s.split_at(unit_pos);
+    let value: u64 = digits.parse().map_err(|_| invalid())?;
+    let multiplier: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let secs = value.checked_mul(multiplier).ok_or_else(invalid)?;
+    Ok(std::time::Duration::from_secs(secs))
+}
+
 /// Default prefix key: Ctrl+P (0x10).
 const DEFAULT_PREFIX_KEY: u8 = 0x10;
 
 #[derive(Deserialize, Default)]
 struct FileConfig {
+    runtime: Option<String>,
+    workspaces_dir: Option<String>,
     mux: Option<MuxFileConfig>,
+    logging: Option<LoggingFileConfig>,
+    hooks: Option<HooksFileConfig>,
+    defaults: Option<DefaultsFileConfig>,
+    list: Option<ListFileConfig>,
+    events: Option<EventsFileConfig>,
 }
 
 #[derive(Deserialize, Default)]
 struct MuxFileConfig {
     prefix_key: Option<String>,
+    scrollback_lines: Option<usize>,
+    show_scroll_status: Option<bool>,
+    bracketed_paste: Option<bool>,
+    header: Option<bool>,
+    idle_timeout_secs: Option<u64>,
+    wheel_lines: Option<usize>,
+    page_fraction: Option<f64>,
+    keys: Option<MuxKeysFileConfig>,
 }
 
-/// Parse a prefix key string like "Ctrl+B" into its control byte (0x01..0x1A).
-/// Returns `None` for invalid strings.
-fn parse_prefix_key(s: &str) -> Option<u8> {
-    let s = s.trim();
-    let rest = s.strip_prefix("Ctrl+")?;
-    if rest.len() != 1 {
-        return None;
-    }
-    let ch = rest.chars().next()?.to_ascii_uppercase();
-    if ch.is_ascii_uppercase() {
-        Some(ch as u8 - b'A' + 1)
-    } else {
-        None
-    }
+#[derive(Deserialize, Default)]
+struct MuxKeysFileConfig {
+    detach: Option<String>,
+    kill: Option<String>,
+    sidebar: Option<String>,
+    new: Option<String>,
+    scroll_up: Option<String>,
+    scroll_down: Option<String>,
+    split: Option<String>,
+    pane: Option<String>,
 }
 
-/// Load the mux prefix key from `~/.config/box/config.toml`.
-/// Returns the default (Ctrl+P = 0x10) if the file doesn't exist or the key
-/// is not set / invalid.
-pub fn load_mux_prefix_key() -> u8 {
+#[derive(Deserialize, Default)]
+struct ListFileConfig {
+    path_tail: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+struct EventsFileConfig {
+    enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+struct DefaultsFileConfig {
+    shell_on_empty: Option<bool>,
+    clone_depth: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+struct HooksFileConfig {
+    post_create: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct LoggingFileConfig {
+    max_log_size_bytes: Option<u64>,
+    max_log_files: Option<usize>,
+}
+
+/// Read `~/.config/box/config.toml`. A missing or malformed file yields the
+/// default (empty) config so callers fall back to built-in defaults.
+fn load_file_config() -> FileConfig {
     let home = match std::env::var("HOME") {
         Ok(h) if !h.is_empty() => h,
-        _ => return DEFAULT_PREFIX_KEY,
+        _ => return FileConfig::default(),
     };
     let path = std::path::Path::new(&home)
         .join(".config")
         .join("box")
         .join("config.toml");
-    let content = match std::fs::read_to_string(&path) {
-        Ok(c) => c,
-        Err(_) => return DEFAULT_PREFIX_KEY,
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Control bytes that terminals also send for Tab, Enter and Return, so they
+/// can't be told apart from those keys.
+const AMBIGUOUS_PREFIX_KEYS: &[(u8, &str)] = &[(0x09, "Tab"), (0x0a, "Enter"), (0x0d, "Enter")];
+
+/// Parse a prefix key string into its control byte. Accepts `Ctrl+A` through
+/// `Ctrl+Z`, `Ctrl+Space` (0x00) and `Ctrl+[` (0x1B), case-insensitively.
+/// Returns an error describing why other strings can't be used.
+fn parse_prefix_key(s: &str) -> Result<u8, String> {
+    let s = s.trim();
+    let rest = s
+        .get(..5)
+        .filter(|p| p.eq_ignore_ascii_case("ctrl+"))
+        .map(|_| &s[5..])
+        .ok_or_else(|| "expected the form Ctrl+<key>".to_string())?;
+    let key = if rest.eq_ignore_ascii_case("space") {
+        0x00
+    } else if rest == "[" {
+        0x1b
+    } else {
+        match rest.as_bytes() {
+            [c] if c.is_ascii_alphabetic() => c.to_ascii_uppercase() - b'A' + 1,
+            _ => return Err(format!("unsupported key '{}'", rest)),
+        }
     };
-    let file_config: FileConfig = match toml::from_str(&content) {
-        Ok(c) => c,
-        Err(_) => return DEFAULT_PREFIX_KEY,
+    if let Some((_, name)) = AMBIGUOUS_PREFIX_KEYS.iter().find(|(b, _)| *b == key) {
+        return Err(format!("it sends the same byte as {}", name));
+    }
+    Ok(key)
+}
+
+/// How a prefix or COMMAND mode key is written in config.toml, for hints:
+/// `Ctrl+Q` for control bytes, the character itself otherwise.
+pub fn key_label(key: u8) -> String {
+    match key {
+        0x00 => "Ctrl+Space".to_string(),
+        0x1b => "Ctrl+[".to_string(),
+        0x01..=0x1a => format!("Ctrl+{}", (b'A' + key - 1) as char),
+        _ => (key as char).to_string(),
+    }
+}
+
+/// Load the mux prefix key from `~/.config/box/config.toml`.
+/// Returns the default (Ctrl+P = 0x10) if the file doesn't exist or the key
+/// is not set. An unusable key is reported on stderr and also falls back to
+/// the default.
+pub fn load_mux_prefix_key() -> u8 {
+    let Some(s) = load_file_config().mux.and_then(|m| m.prefix_key) else {
+        return DEFAULT_PREFIX_KEY;
     };
+    match parse_prefix_key(&s) {
+        Ok(0x1b) => {
+            eprintln!(
+                "Warning: prefix_key \"{}\" is the Escape byte; Esc and arrow keys will also enter COMMAND mode.",
+                s
+            );
+            0x1b
+        }
+        Ok(key) => key,
+        Err(reason) => {
+            eprintln!(
+                "Warning: ignoring prefix_key \"{}\" ({}); using Ctrl+P.",
+                s, reason
+            );
+            DEFAULT_PREFIX_KEY
+        }
+    }
+}
+
+/// COMMAND mode key bindings, set in the `[mux.keys]` table of config.toml.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandKeys {
+    pub detach: u8,
+    pub kill: u8,
+    pub sidebar: u8,
+    pub new: u8,
+    pub scroll_up: u8,
+    pub scroll_down: u8,
+    pub split: u8,
+    pub pane: u8,
+}
+
+impl Default for CommandKeys {
+    fn default() -> Self {
+        CommandKeys {
+            detach: 0x11, // Ctrl+Q
+            kill: 0x18,   // Ctrl+X
+            sidebar: b'a',
+            new: b'n',
+            scroll_up: 0x10,   // Ctrl+P
+            scroll_down: 0x0e, // Ctrl+N
+            split: b'-',
+            pane: b'o',
+        }
+    }
+}
+
+/// Parse a COMMAND mode key: a single printable ASCII character (e.g. `q`)
+/// or a `Ctrl+<key>` as accepted by `parse_prefix_key`. `/` and Esc are
+/// reserved for search and leaving COMMAND mode.
+fn parse_command_key(s: &str) -> Result<u8, String> {
+    let s = s.trim();
+    let key = match s.as_bytes() {
+        [c] if c.is_ascii_graphic() => *c,
+        _ => parse_prefix_key(s)?,
+    };
+    match key {
+        b'/' => Err("it is reserved for search".to_string()),
+        0x1b => Err("it is reserved for leaving COMMAND mode".to_string()),
+        _ => Ok(key),
+    }
+}
+
+/// Resolve `[mux.keys]`, keeping the default for every action that is unset
+/// or unusable. Also returns a warning for each rejected setting.
+fn command_keys_from(file_config: &FileConfig) -> (CommandKeys, Vec<String>) {
+    let mut keys = CommandKeys::default();
+    let mut warnings = Vec::new();
+    let Some(cfg) = file_config.mux.as_ref().and_then(|m| m.keys.as_ref()) else {
+        return (keys, warnings);
+    };
+    let settings = [
+        ("detach", &cfg.detach, &mut keys.detach),
+        ("kill", &cfg.kill, &mut keys.kill),
+        ("sidebar", &cfg.sidebar, &mut keys.sidebar),
+        ("new", &cfg.new, &mut keys.new),
+        ("scroll_up", &cfg.scroll_up, &mut keys.scroll_up),
+        ("scroll_down", &cfg.scroll_down, &mut keys.scroll_down),
+        ("split", &cfg.split, &mut keys.split),
+        ("pane", &cfg.pane, &mut keys.pane),
+    ];
+    for (action, value, key) in settings {
+        let Some(s) = value else { continue };
+        match parse_command_key(s) {
+            Ok(parsed) => *key = parsed,
+            Err(reason) => warnings.push(format!(
+                "ignoring mux.keys.{} \"{}\" ({}).",
+                action, s, reason
+            )),
+        }
+    }
+
+    let bound = [
+        keys.detach,
+        keys.kill,
+        keys.sidebar,
+        keys.new,
+        keys.scroll_up,
+        keys.scroll_down,
+        keys.split,
+        keys.pane,
+    ];
+    if (0..bound.len()).any(|i| bound[i + 1..].contains(&bound[i])) {
+        warnings
+            .push("mux.keys binds one key to several actions; using the default keys.".to_string());
+        keys = CommandKeys::default();
+    }
+    (keys, warnings)
+}
+
+/// Load the COMMAND mode key bindings from `~/.config/box/config.toml`.
+/// Unusable settings are reported on stderr.
+pub fn load_mux_command_keys() -> CommandKeys {
+    let (keys, warnings) = command_keys_from(&load_file_config());
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    keys
+}
+
+/// Container runtimes box can drive; podman is used through its
+/// Docker-compatible CLI.
+pub const CONTAINER_RUNTIMES: &[&str] = &["docker", "podman"];
+
+fn container_runtime_from(env: Option<String>, file_config: &FileConfig) -> String {
+    env.filter(|s| !s.is_empty())
+        .or_else(|| file_config.runtime.clone())
+        .unwrap_or_else(|| "docker".to_string())
+}
+
+/// Container runtime binary from `$BOX_CONTAINER_RUNTIME`, then the top-level
+/// `runtime` key of config.toml. Defaults to `docker`.
+pub fn load_container_runtime() -> String {
+    container_runtime_from(
+        std::env::var("BOX_CONTAINER_RUNTIME").ok(),
+        &load_file_config(),
+    )
+}
+
+fn workspaces_dir_from(
+    env: Option<String>,
+    file_config: &FileConfig,
+    home: &str,
+) -> std::path::PathBuf {
+    let home = std::path::Path::new(home);
+    match env
+        .filter(|s| !s.is_empty())
+        .or_else(|| file_config.workspaces_dir.clone())
+    {
+        Some(path) => match path.strip_prefix("~/") {
+            Some(rest) => home.join(rest),
+            None => std::path::PathBuf::from(path),
+        },
+        None => home.join(".box").join("workspaces"),
+    }
+}
+
+/// Directory holding the workspaces of sessions under `home`:
+/// `$BOX_WORKSPACES_DIR`, then the top-level `workspaces_dir` key of
+/// config.toml (`~/` expands to the home directory). Defaults to
+/// `~/.box/workspaces`.
+pub fn workspaces_dir_in(home: &str) -> std::path::PathBuf {
+    workspaces_dir_from(
+        std::env::var("BOX_WORKSPACES_DIR").ok(),
+        &load_file_config(),
+        home,
+    )
+}
+
+/// `workspaces_dir_in` for the user's home directory.
+pub fn workspaces_dir() -> Result<std::path::PathBuf> {
+    Ok(workspaces_dir_in(&home_dir()?))
+}
+
+const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+/// Smaller values are raised to this so a typo can't disable scrolling.
+const MIN_SCROLLBACK_LINES: usize = 100;
+
+fn scrollback_from(file_config: &FileConfig) -> usize {
     file_config
         .mux
-        .and_then(|m| m.prefix_key)
-        .and_then(|s| parse_prefix_key(&s))
-        .unwrap_or(DEFAULT_PREFIX_KEY)
+        .as_ref()
+        .and_then(|m| m.scrollback_lines)
+        .unwrap_or(DEFAULT_SCROLLBACK_LINES)
+        .max(MIN_SCROLLBACK_LINES)
+}
+
+/// Load the number of scrollback lines kept per terminal from the `[mux]`
+/// table of config.toml. Defaults to 10000, with a minimum of 100.
+pub fn load_mux_scrollback() -> usize {
+    scrollback_from(&load_file_config())
+}
+
+fn scroll_status_from(file_config: &FileConfig) -> bool {
+    file_config
+        .mux
+        .as_ref()
+        .and_then(|m| m.show_scroll_status)
+        .unwrap_or(true)
+}
+
+/// Whether to show the `-- SCROLL n/max --` overlay while scrolled back
+/// (`show_scroll_status` in the `[mux]` table). Defaults to true.
+pub fn load_mux_show_scroll_status() -> bool {
+    scroll_status_from(&load_file_config())
+}
+
+fn bracketed_paste_from(file_config: &FileConfig) -> bool {
+    file_config
+        .mux
+        .as_ref()
+        .and_then(|m| m.bracketed_paste)
+        .unwrap_or(true)
+}
+
+/// Whether to wrap pasted input in bracketed paste markers for programs that
+/// ask for them (`bracketed_paste` in the `[mux]` table). Defaults to true.
+pub fn load_mux_bracketed_paste() -> bool {
+    bracketed_paste_from(&load_file_config())
+}
+
+fn path_tail_from(file_config: &FileConfig) -> usize {
+    file_config
+        .list
+        .as_ref()
+        .and_then(|l| l.path_tail)
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// How many trailing project path components `box list` shows unabbreviated
+/// (`path_tail` in the `[list]` table). Defaults to 1, with a minimum of 1.
+pub fn load_list_path_tail() -> usize {
+    path_tail_from(&load_file_config())
+}
+
+fn header_from(file_config: &FileConfig) -> bool {
+    file_config
+        .mux
+        .as_ref()
+        .and_then(|m| m.header)
+        .unwrap_or(true)
+}
+
+/// Whether the client keeps a row for the command bar (`header` in the
+/// `[mux]` table). When false the session gets the full terminal height and
+/// the bar only appears over the last row in COMMAND mode. Defaults to true.
+pub fn load_mux_header() -> bool {
+    header_from(&load_file_config())
+}
+
+fn idle_timeout_from(file_config: &FileConfig) -> Option<Duration> {
+    file_config
+        .mux
+        .as_ref()
+        .and_then(|m| m.idle_timeout_secs)
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// How long a local mux server may run with no client attached before it
+/// stops the session (`idle_timeout_secs` in the `[mux]` table). Only client
+/// attachment is watched, not whether the program is busy. `None` (the
+/// default, or 0) keeps servers running until `box stop`.
+pub fn load_mux_idle_timeout() -> Option<Duration> {
+    idle_timeout_from(&load_file_config())
+}
+
+/// How far the mouse wheel and the half-page keys scroll.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollSettings {
+    /// Lines moved per mouse wheel step.
+    pub wheel_lines: usize,
+    /// Fraction of the visible rows moved by Ctrl+U/Ctrl+D and PgUp/PgDn.
+    pub page_fraction: f64,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        ScrollSettings {
+            wheel_lines: 3,
+            page_fraction: 0.5,
+        }
+    }
+}
+
+impl ScrollSettings {
+    /// Lines moved by a page scroll in a terminal `rows` high (at least 1).
+    pub fn page_lines(&self, rows: u16) -> usize {
+        ((rows as f64 * self.page_fraction) as usize).max(1)
+    }
+}
+
+fn scroll_settings_from(file_config: &FileConfig) -> ScrollSettings {
+    let mux = file_config.mux.as_ref();
+    let default = ScrollSettings::default();
+    ScrollSettings {
+        wheel_lines: mux
+            .and_then(|m| m.wheel_lines)
+            .unwrap_or(default.wheel_lines)
+            .max(1),
+        page_fraction: mux
+            .and_then(|m| m.page_fraction)
+            .unwrap_or(default.page_fraction)
+            .clamp(0.0, 1.0),
+    }
+}
+
+/// Load `wheel_lines` and `page_fraction` from the `[mux]` table of
+/// config.toml. Defaults to 3 lines per wheel step and half a page.
+pub fn load_mux_scroll_settings() -> ScrollSettings {
+    scroll_settings_from(&load_file_config())
+}
+
+fn events_enabled_from(file_config: &FileConfig) -> bool {
+    file_config
+        .events
+        .as_ref()
+        .and_then(|e| e.enabled)
+        .unwrap_or(false)
+}
+
+/// Whether session state changes are appended to `~/.box/events.log`
+/// (`enabled` in the `[events]` table). Defaults to false.
+pub fn load_events_enabled() -> bool {
+    events_enabled_from(&load_file_config())
+}
+
+/// Rotation limits for a session's `server.log`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogRotation {
+    /// Rotate once the log grows beyond this many bytes.
+    pub max_bytes: u64,
+    /// Number of rotated files (`server.log.1` .. `server.log.N`) to keep.
+    pub max_files: usize,
+}
+
+const DEFAULT_MAX_LOG_SIZE_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_LOG_FILES: usize = 5;
+
+fn log_rotation_from(file_config: &FileConfig) -> LogRotation {
+    let logging = file_config.logging.as_ref();
+    LogRotation {
+        max_bytes: logging
+            .and_then(|l| l.max_log_size_bytes)
+            .unwrap_or(DEFAULT_MAX_LOG_SIZE_BYTES),
+        max_files: logging
+            .and_then(|l| l.max_log_files)
+            .unwrap_or(DEFAULT_MAX_LOG_FILES),
+    }
+}
+
+/// Load log rotation limits from the `[logging]` table of config.toml.
+/// Defaults to 10 MB and 5 rotated files.
+pub fn load_log_rotation() -> LogRotation {
+    log_rotation_from(&load_file_config())
+}
+
+fn post_create_hook_from(file_config: &FileConfig, home: &str) -> std::path::PathBuf {
+    let home = std::path::Path::new(home);
+    match file_config
+        .hooks
+        .as_ref()
+        .and_then(|h| h.post_create.as_deref())
+    {
+        Some(path) => match path.strip_prefix("~/") {
+            Some(rest) => home.join(rest),
+            None => std::path::PathBuf::from(path),
+        },
+        None => home.join(".box").join("hooks").join("post_create"),
+    }
+}
+
+/// Path of the hook to run after a session's workspace is created: `[hooks]
+/// post_create` in config.toml (`~/` expands to the home directory), else
+/// `~/.box/hooks/post_create`. Returns `None` when the file doesn't exist.
+pub fn load_post_create_hook() -> Option<std::path::PathBuf> {
+    let home = home_dir().ok()?;
+    let path = post_create_hook_from(&load_file_config(), &home);
+    path.exists().then_some(path)
 }
 
 pub fn derive_mount_path(project_dir: &str) -> String {
@@ -196,6 +971,17 @@ mod tests {
         assert_eq!(derive_mount_path("/myproject"), "/workspace/myproject");
     }
 
+    #[test]
+    fn test_validate_strategy() {
+        for s in STRATEGIES {
+            assert!(validate_strategy(s).is_ok());
+        }
+        assert!(validate_strategy("copy").is_err());
+        assert!(is_worktree_strategy("branch"));
+        assert!(is_worktree_strategy("worktree"));
+        assert!(!is_worktree_strategy("clone"));
+    }
+
     #[test]
     fn test_resolve_defaults() {
         let _lock = ENV_LOCK.lock().unwrap();
@@ -215,6 +1001,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
 
@@ -242,6 +1029,149 @@ mod tests {
         }
     }
 
+    fn project_with_config(content: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), content).unwrap();
+        dir
+    }
+
+    fn project_input(dir: &tempfile::TempDir) -> BoxConfigInput {
+        BoxConfigInput {
+            name: "test".to_string(),
+            image: None,
+            mount_path: None,
+            project_dir: dir.path().to_string_lossy().to_string(),
+            command: None,
+            env: vec![],
+            local: false,
+            strategy: None,
+            depends_on: vec![],
+        }
+    }
+
+    #[test]
+    fn test_resolve_project_config_overrides_env() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        let saved_image = std::env::var("BOX_DEFAULT_IMAGE").ok();
+        let saved_cmd = std::env::var("BOX_DEFAULT_CMD").ok();
+        std::env::set_var("BOX_DEFAULT_IMAGE", "ubuntu:latest");
+        std::env::set_var("BOX_DEFAULT_CMD", "bash");
+        let dir = project_with_config(
+            "image = \"node:20\"\ncommand = [\"npm\", \"run\", \"dev\"]\nstrategy = \"worktree\"\nmount_path = \"/src\"\nenv = [\"PORT=3000\"]\n",
+        );
+
+        let config = resolve(project_input(&dir)).unwrap();
+
+        assert_eq!(config.image, "node:20");
+        assert_eq!(config.command, vec!["npm", "run", "dev"]);
+        assert_eq!(config.strategy, "worktree");
+        assert_eq!(config.mount_path, "/src");
+        assert_eq!(config.env, vec!["PORT=3000"]);
+
+        match saved_image {
+            Some(v) => std::env::set_var("BOX_DEFAULT_IMAGE", v),
+            None => std::env::remove_var("BOX_DEFAULT_IMAGE"),
+        }
+        match saved_cmd {
+            Some(v) => std::env::set_var("BOX_DEFAULT_CMD", v),
+            None => std::env::remove_var("BOX_DEFAULT_CMD"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_flags_override_project_config() {
+        let dir = project_with_config(
+            "image = \"node:20\"\ncommand = [\"npm\"]\nstrategy = \"worktree\"\nenv = [\"PORT=3000\", \"DEBUG=1\"]\n",
+        );
+
+        let config = resolve(BoxConfigInput {
+            image: Some("alpine:3".to_string()),
+            command: Some(vec!["bash".to_string()]),
+            strategy: Some("clone".to_string()),
+            depends_on: vec![],
+            env: vec!["PORT=8080".to_string()],
+            ..project_input(&dir)
+        })
+        .unwrap();
+
+        assert_eq!(config.image, "alpine:3");
+        assert_eq!(config.command, vec!["bash"]);
+        assert_eq!(config.strategy, "clone");
+        assert_eq!(config.env, vec!["DEBUG=1", "PORT=8080"]);
+    }
+
+    #[test]
+    fn test_resolve_local_keeps_project_env() {
+        let dir = project_with_config("command = [\"bash\"]\nenv = [\"PORT=3000\"]\n");
+
+        let config = resolve(BoxConfigInput {
+            local: true,
+            ..project_input(&dir)
+        })
+        .unwrap();
+
+        assert_eq!(config.command, vec!["bash"]);
+        assert_eq!(config.env, vec!["PORT=3000"]);
+    }
+
+    #[test]
+    fn test_load_project_config_errors() {
+        let dir = project_with_config("strategy = \"copy\"\n");
+        let err = load_project_config(&dir.path().to_string_lossy()).unwrap_err();
+        assert!(err.to_string().contains("Invalid strategy 'copy'"));
+
+        let dir = project_with_config("env = [\"PORT\"]\n");
+        assert!(load_project_config(&dir.path().to_string_lossy()).is_err());
+
+        let dir = project_with_config("imgae = \"node:20\"\n");
+        assert!(load_project_config(&dir.path().to_string_lossy()).is_err());
+    }
+
+    #[test]
+    fn test_project_config_trust() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("box").join("trusted_projects");
+        let path = dir.path().join(PROJECT_CONFIG_FILE);
+
+        assert!(!is_project_config_trusted(
+            &store,
+            &path,
+            "command = [\"make\"]\n"
+        ));
+        trust_project_config(&store, &path, "command = [\"make\"]\n").unwrap();
+        assert!(is_project_config_trusted(
+            &store,
+            &path,
+            "command = [\"make\"]\n"
+        ));
+        // Changed contents or a different location need trusting again.
+        assert!(!is_project_config_trusted(
+            &store,
+            &path,
+            "command = [\"rm\"]\n"
+        ));
+        assert!(!is_project_config_trusted(
+            &store,
+            &dir.path().join("other").join(PROJECT_CONFIG_FILE),
+            "command = [\"make\"]\n"
+        ));
+    }
+
+    #[test]
+    fn test_load_project_config_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        assert_eq!(
+            load_project_config(&dir.path().to_string_lossy()).unwrap(),
+            ProjectConfig::default()
+        );
+        assert_eq!(
+            load_project_config("/nonexistent/project").unwrap(),
+            ProjectConfig::default()
+        );
+    }
+
     #[test]
     fn test_resolve_mount_override() {
         let _lock = ENV_LOCK.lock().unwrap();
@@ -257,6 +1187,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
 
@@ -278,6 +1209,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
 
@@ -301,6 +1233,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(config.image, "ubuntu:latest");
@@ -327,6 +1260,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(config.image, "python:3.11");
@@ -336,6 +1270,473 @@ mod tests {
         }
     }
 
+    fn parse_manifest_str(content: &str) -> Result<Vec<BoxConfigInput>> {
+        let manifest: BatchManifest = toml::from_str(content)?;
+        order_batch_sessions(manifest.sessions)
+    }
+
+    #[test]
+    fn test_parse_batch_manifest_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("box.toml");
+        std::fs::write(
+            &path,
+            r#"
+[[sessions]]
+name = "myapp/server"
+image = "node:18"
+command = ["npm", "start"]
+env = ["PORT=3000"]
+
+[[sessions]]
+name = "myapp/notes"
+"#,
+        )
+        .unwrap();
+        let inputs = parse_batch_manifest(&path).unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].name, "myapp/server");
+        assert_eq!(inputs[0].image.as_deref(), Some("node:18"));
+        assert_eq!(
+            inputs[0].command,
+            Some(vec!["npm".to_string(), "start".to_string()])
+        );
+        assert_eq!(inputs[0].env, vec!["PORT=3000"]);
+        assert!(!inputs[0].local);
+        assert!(inputs[1].local);
+        assert!(inputs[1].command.is_none());
+    }
+
+    #[test]
+    fn test_parse_env_lines() {
+        let env =
+            parse_env_lines("# secrets\n\nAPI_KEY=abc\n  DEBUG=1  \nURL=a=b\nNAME = x\n").unwrap();
+        assert_eq!(env, vec!["API_KEY=abc", "DEBUG=1", "URL=a=b", "NAME= x"]);
+        let err = parse_env_lines("A=1\nnot an assignment\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+        assert!(parse_env_lines("=value\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_env_file_missing() {
+        let err = parse_env_file(std::path::Path::new("/nonexistent/.env")).unwrap_err();
+        assert!(err.to_string().contains("Cannot read env file"));
+    }
+
+    #[test]
+    fn test_parse_batch_manifest_missing_file() {
+        let err = parse_batch_manifest(std::path::Path::new("/nonexistent/box.toml"))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Cannot read manifest"));
+    }
+
+    #[test]
+    fn test_batch_manifest_orders_dependencies() {
+        let inputs = parse_manifest_str(
+            r#"
+[[sessions]]
+name = "app/server"
+image = "node:18"
+depends_on = ["app/db"]
+
+[[sessions]]
+name = "app/db"
+image = "postgres:15"
+"#,
+        )
+        .unwrap();
+        let names: Vec<&str> = inputs.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["app/db", "app/server"]);
+        assert!(inputs[0].depends_on.is_empty());
+        assert_eq!(inputs[1].depends_on, vec!["app/db"]);
+    }
+
+    #[test]
+    fn test_batch_manifest_unknown_dependency() {
+        let err = parse_manifest_str(
+            r#"
+[[sessions]]
+name = "app/server"
+depends_on = ["app/db"]
+"#,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("not defined in the manifest"));
+    }
+
+    #[test]
+    fn test_batch_manifest_cycle() {
+        let err = parse_manifest_str(
+            r#"
+[[sessions]]
+name = "a/one"
+depends_on = ["a/two"]
+
+[[sessions]]
+name = "a/two"
+depends_on = ["a/one"]
+"#,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("Dependency cycle"));
+    }
+
+    #[test]
+    fn test_batch_manifest_duplicate_and_unknown_field() {
+        assert!(
+            parse_manifest_str("[[sessions]]\nname = \"a\"\n[[sessions]]\nname = \"a\"\n").is_err()
+        );
+        assert!(parse_manifest_str("[[sessions]]\nname = \"a\"\nimgae = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        use std::time::Duration;
+        assert_eq!(parse_duration("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+        assert_eq!(
+            parse_duration("2d").unwrap(),
+            Duration::from_secs(2 * 86400)
+        );
+        assert_eq!(
+            parse_duration("1w").unwrap(),
+            Duration::from_secs(7 * 86400)
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_zero_and_whitespace() {
+        assert_eq!(parse_duration("0h").unwrap(), std::time::Duration::ZERO);
+        assert_eq!(
+            parse_duration(" 3d ").unwrap(),
+            std::time::Duration::from_secs(3 * 86400)
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_invalid() {
+        for input in [
+            "", "h", "10", "10x", "1.5h", "-1d", "1 d", "1hh", "1H", "d1",
+        ] {
+            assert!(parse_duration(input).is_err(), "accepted {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_overflow() {
+        assert!(parse_duration("99999999999999999999s").is_err());
+        assert!(parse_duration("18446744073709551615w").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration_error_message() {
+        let err = parse_duration("5y").unwrap_err();
+        assert!(err.to_string().contains("Invalid duration '5y'"));
+    }
+
+    #[test]
+    fn test_parse_prefix_key_letters() {
+        assert_eq!(parse_prefix_key("Ctrl+B"), Ok(0x02));
+        assert_eq!(parse_prefix_key("ctrl+b"), Ok(0x02));
+        assert_eq!(parse_prefix_key("CTRL+a"), Ok(0x01));
+        assert_eq!(parse_prefix_key("  Ctrl+Z "), Ok(0x1a));
+    }
+
+    #[test]
+    fn test_parse_prefix_key_space_and_bracket() {
+        assert_eq!(parse_prefix_key("Ctrl+Space"), Ok(0x00));
+        assert_eq!(parse_prefix_key("ctrl+space"), Ok(0x00));
+        assert_eq!(parse_prefix_key("Ctrl+["), Ok(0x1b));
+    }
+
+    #[test]
+    fn test_parse_prefix_key_rejects_ambiguous() {
+        assert!(parse_prefix_key("Ctrl+M").unwrap_err().contains("Enter"));
+        assert!(parse_prefix_key("Ctrl+J").unwrap_err().contains("Enter"));
+        assert!(parse_prefix_key("Ctrl+I").unwrap_err().contains("Tab"));
+    }
+
+    #[test]
+    fn test_key_label_round_trips() {
+        for label in ["Ctrl+P", "Ctrl+Space", "Ctrl+["] {
+            assert_eq!(key_label(parse_prefix_key(label).unwrap()), label);
+        }
+        assert_eq!(key_label(CommandKeys::default().detach), "Ctrl+Q");
+        assert_eq!(key_label(b'-'), "-");
+    }
+
+    #[test]
+    fn test_parse_prefix_key_rejects_invalid() {
+        assert!(parse_prefix_key("B").is_err());
+        assert!(parse_prefix_key("Alt+B").is_err());
+        assert!(parse_prefix_key("Ctrl+").is_err());
+        assert!(parse_prefix_key("Ctrl+1").is_err());
+        assert!(parse_prefix_key("Ctrl+AB").is_err());
+        assert!(parse_prefix_key("Ctrl+é").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_key() {
+        assert_eq!(parse_command_key("q"), Ok(b'q'));
+        assert_eq!(parse_command_key("Ctrl+B"), Ok(0x02));
+        assert!(parse_command_key("/").is_err());
+        assert!(parse_command_key("Ctrl+[").is_err());
+        assert!(parse_command_key("Ctrl+M").is_err());
+        assert!(parse_command_key("qq").is_err());
+        assert!(parse_command_key("").is_err());
+    }
+
+    #[test]
+    fn test_command_keys_default() {
+        let (keys, warnings) = command_keys_from(&FileConfig::default());
+        assert_eq!(keys, CommandKeys::default());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_command_keys_from_file() {
+        let file_config: FileConfig =
+            toml::from_str("[mux.keys]\ndetach = \"Ctrl+B\"\nnew = \"c\"\n").unwrap();
+        let (keys, warnings) = command_keys_from(&file_config);
+        assert!(warnings.is_empty());
+        assert_eq!(
+            keys,
+            CommandKeys {
+                detach: 0x02,
+                new: b'c',
+                ..CommandKeys::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_command_keys_invalid_and_duplicate() {
+        let file_config: FileConfig =
+            toml::from_str("[mux.keys]\nkill = \"Alt+K\"\nsidebar = \"s\"\n").unwrap();
+        let (keys, warnings) = command_keys_from(&file_config);
+        assert_eq!(keys.kill, 0x18);
+        assert_eq!(keys.sidebar, b's');
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("mux.keys.kill"));
+
+        let file_config: FileConfig = toml::from_str("[mux.keys]\nkill = \"a\"\n").unwrap();
+        let (keys, warnings) = command_keys_from(&file_config);
+        assert_eq!(keys, CommandKeys::default());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_container_runtime_default() {
+        assert_eq!(
+            container_runtime_from(None, &FileConfig::default()),
+            "docker"
+        );
+        assert_eq!(
+            container_runtime_from(Some(String::new()), &FileConfig::default()),
+            "docker"
+        );
+    }
+
+    #[test]
+    fn test_container_runtime_env_overrides_file() {
+        let file_config: FileConfig = toml::from_str("runtime = \"podman\"\n").unwrap();
+        assert_eq!(container_runtime_from(None, &file_config), "podman");
+        assert_eq!(
+            container_runtime_from(Some("docker".to_string()), &file_config),
+            "docker"
+        );
+    }
+
+    #[test]
+    fn test_workspaces_dir_resolution() {
+        let default = FileConfig::default();
+        assert_eq!(
+            workspaces_dir_from(None, &default, "/home/u"),
+            std::path::Path::new("/home/u/.box/workspaces")
+        );
+        let file_config: FileConfig = toml::from_str("workspaces_dir = \"~/big/ws\"\n").unwrap();
+        assert_eq!(
+            workspaces_dir_from(None, &file_config, "/home/u"),
+            std::path::Path::new("/home/u/big/ws")
+        );
+        assert_eq!(
+            workspaces_dir_from(Some("/mnt/ws".to_string()), &file_config, "/home/u"),
+            std::path::Path::new("/mnt/ws")
+        );
+        assert_eq!(
+            workspaces_dir_from(Some(String::new()), &default, "/home/u"),
+            std::path::Path::new("/home/u/.box/workspaces")
+        );
+    }
+
+    #[test]
+    fn test_scrollback_default() {
+        assert_eq!(scrollback_from(&FileConfig::default()), 10_000);
+    }
+
+    #[test]
+    fn test_scrollback_from_file() {
+        let file_config: FileConfig = toml::from_str("[mux]\nscrollback_lines = 50000\n").unwrap();
+        assert_eq!(scrollback_from(&file_config), 50_000);
+    }
+
+    #[test]
+    fn test_scrollback_clamped_to_minimum() {
+        let file_config: FileConfig = toml::from_str("[mux]\nscrollback_lines = 5\n").unwrap();
+        assert_eq!(scrollback_from(&file_config), 100);
+    }
+
+    #[test]
+    fn test_scroll_status_toggle() {
+        assert!(scroll_status_from(&FileConfig::default()));
+        let file_config: FileConfig =
+            toml::from_str("[mux]\nshow_scroll_status = false\n").unwrap();
+        assert!(!scroll_status_from(&file_config));
+    }
+
+    #[test]
+    fn test_bracketed_paste_toggle() {
+        assert!(bracketed_paste_from(&FileConfig::default()));
+        let file_config: FileConfig = toml::from_str("[mux]\nbracketed_paste = false\n").unwrap();
+        assert!(!bracketed_paste_from(&file_config));
+    }
+
+    #[test]
+    fn test_path_tail() {
+        assert_eq!(path_tail_from(&FileConfig::default()), 1);
+        let file_config: FileConfig = toml::from_str("[list]\npath_tail = 2\n").unwrap();
+        assert_eq!(path_tail_from(&file_config), 2);
+        let file_config: FileConfig = toml::from_str("[list]\npath_tail = 0\n").unwrap();
+        assert_eq!(path_tail_from(&file_config), 1);
+    }
+
+    #[test]
+    fn test_header_toggle() {
+        assert!(header_from(&FileConfig::default()));
+        let file_config: FileConfig = toml::from_str("[mux]\nheader = false\n").unwrap();
+        assert!(!header_from(&file_config));
+    }
+
+    #[test]
+    fn test_shell_on_empty_toggle() {
+        assert!(!shell_on_empty_from(&FileConfig::default()));
+        let file_config: FileConfig =
+            toml::from_str("[defaults]\nshell_on_empty = true\n").unwrap();
+        assert!(shell_on_empty_from(&file_config));
+    }
+
+    #[test]
+    fn test_clone_depth() {
+        assert_eq!(clone_depth_from(&FileConfig::default()), None);
+        let file_config: FileConfig = toml::from_str("[defaults]\nclone_depth = 0\n").unwrap();
+        assert_eq!(clone_depth_from(&file_config), None);
+        let file_config: FileConfig = toml::from_str("[defaults]\nclone_depth = 50\n").unwrap();
+        assert_eq!(clone_depth_from(&file_config), Some(50));
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        assert_eq!(idle_timeout_from(&FileConfig::default()), None);
+        let file_config: FileConfig = toml::from_str("[mux]\nidle_timeout_secs = 0\n").unwrap();
+        assert_eq!(idle_timeout_from(&file_config), None);
+        let file_config: FileConfig = toml::from_str("[mux]\nidle_timeout_secs = 1800\n").unwrap();
+        assert_eq!(
+            idle_timeout_from(&file_config),
+            Some(Duration::from_secs(1800))
+        );
+    }
+
+    #[test]
+    fn test_events_enabled() {
+        assert!(!events_enabled_from(&FileConfig::default()));
+        let file_config: FileConfig = toml::from_str("[events]\nenabled = true\n").unwrap();
+        assert!(events_enabled_from(&file_config));
+    }
+
+    #[test]
+    fn test_scroll_settings_defaults() {
+        let scroll = scroll_settings_from(&FileConfig::default());
+        assert_eq!(scroll, ScrollSettings::default());
+        assert_eq!(scroll.page_lines(24), 12);
+    }
+
+    #[test]
+    fn test_scroll_settings_from_file() {
+        let file_config: FileConfig =
+            toml::from_str("[mux]\nwheel_lines = 1\npage_fraction = 0.25\n").unwrap();
+        let scroll = scroll_settings_from(&file_config);
+        assert_eq!(scroll.wheel_lines, 1);
+        assert_eq!(scroll.page_lines(40), 10);
+    }
+
+    #[test]
+    fn test_scroll_settings_clamped() {
+        let file_config: FileConfig =
+            toml::from_str("[mux]\nwheel_lines = 0\npage_fraction = -1.0\n").unwrap();
+        let scroll = scroll_settings_from(&file_config);
+        assert_eq!(scroll.wheel_lines, 1);
+        assert_eq!(scroll.page_lines(40), 1);
+
+        let file_config: FileConfig = toml::from_str("[mux]\npage_fraction = 3.0\n").unwrap();
+        assert_eq!(scroll_settings_from(&file_config).page_lines(40), 40);
+    }
+
+    #[test]
+    fn test_log_rotation_defaults() {
+        let rotation = log_rotation_from(&FileConfig::default());
+        assert_eq!(rotation.max_bytes, 10 * 1024 * 1024);
+        assert_eq!(rotation.max_files, 5);
+    }
+
+    #[test]
+    fn test_log_rotation_from_file() {
+        let file_config: FileConfig =
+            toml::from_str("[logging]\nmax_log_size_bytes = 1024\nmax_log_files = 2\n").unwrap();
+        assert_eq!(
+            log_rotation_from(&file_config),
+            LogRotation {
+                max_bytes: 1024,
+                max_files: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_log_rotation_partial_file() {
+        let file_config: FileConfig = toml::from_str("[logging]\nmax_log_files = 1\n").unwrap();
+        let rotation = log_rotation_from(&file_config);
+        assert_eq!(rotation.max_bytes, 10 * 1024 * 1024);
+        assert_eq!(rotation.max_files, 1);
+    }
+
+    #[test]
+    fn test_post_create_hook_default() {
+        assert_eq!(
+            post_create_hook_from(&FileConfig::default(), "/home/u"),
+            std::path::Path::new("/home/u/.box/hooks/post_create")
+        );
+    }
+
+    #[test]
+    fn test_post_create_hook_from_file() {
+        let file_config: FileConfig =
+            toml::from_str("[hooks]\npost_create = \"~/bin/setup.sh\"\n").unwrap();
+        assert_eq!(
+            post_create_hook_from(&file_config, "/home/u"),
+            std::path::Path::new("/home/u/bin/setup.sh")
+        );
+        let file_config: FileConfig =
+            toml::from_str("[hooks]\npost_create = \"/opt/hooks/setup\"\n").unwrap();
+        assert_eq!(
+            post_create_hook_from(&file_config, "/home/u"),
+            std::path::Path::new("/opt/hooks/setup")
+        );
+    }
+
     #[test]
     fn test_home_dir_returns_value() {
         let _lock = ENV_LOCK.lock().unwrap();
@@ -390,6 +1791,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
 
@@ -426,6 +1828,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(config.command, vec!["bash".to_string()]);
@@ -451,6 +1854,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(config.command, vec!["sh".to_string()]);
@@ -476,6 +1880,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(
@@ -508,6 +1913,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(config.command, Vec::<String>::new());
@@ -533,6 +1939,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         });
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("BOX_DEFAULT_CMD"));
@@ -558,6 +1965,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(config.command, Vec::<String>::new());
@@ -582,6 +1990,7 @@ mod tests {
             local: true,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(config.command, vec!["bash".to_string()]);
@@ -608,6 +2017,7 @@ mod tests {
             local: false,
 
             strategy: None,
+            depends_on: vec![],
         })
         .unwrap();
         assert_eq!(config.command, Vec::<String>::new());
@@ -1,4 +1,4 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use std::path::Path;
 use std::process::Command;
 
@@ -8,34 +8,130 @@ use crate::session;
 
 /// Create a workspace directory on the host for the session.
 /// Dispatches to clone or worktree strategy based on the `strategy` parameter.
+/// A new workspace is checked out at `from` when given, else at the project's HEAD.
 /// Returns the host path.
 pub fn ensure_workspace(
     home: &str,
     name: &str,
     project_dir: &str,
     strategy: &str,
+    from: Option<&str>,
+) -> Result<String> {
+    ensure_workspace_with(home, name, project_dir, strategy, from, None, false)
+}
+
+/// Like [`ensure_workspace`], but with `quiet` set the git commands are not
+/// echoed and their output is only shown if they fail.
+pub fn ensure_workspace_with(
+    home: &str,
+    name: &str,
+    project_dir: &str,
+    strategy: &str,
+    from: Option<&str>,
+    clone_depth: Option<u32>,
+    quiet: bool,
 ) -> Result<String> {
     match strategy {
-        "worktree" => ensure_workspace_worktree(home, name, project_dir),
-        _ => ensure_workspace_clone(home, name, project_dir),
+        "worktree" => ensure_workspace_worktree(home, name, project_dir, from, quiet),
+        "branch" => ensure_workspace_branch(home, name, project_dir, from, quiet),
+        _ => ensure_workspace_clone(home, name, project_dir, from, clone_depth, quiet),
     }
 }
 
-/// Create a workspace via `git clone --local`.
+/// Run a workspace setup command, echoing it first unless `quiet`. Quiet runs
+/// capture the command's output and only print it if the command fails.
+fn run_setup_command(cmd: &mut Command, label: &str, quiet: bool) -> Result<bool> {
+    if !quiet {
+        let args: Vec<String> = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+        eprintln!("\x1b[2mrunning {}:\x1b[0m", label);
+        eprintln!(
+            "{} {}",
+            cmd.get_program().to_string_lossy(),
+            shell_words::join(&args)
+        );
+        return Ok(cmd.status()?.success());
+    }
+    let output = cmd.output()?;
+    if !output.status.success() {
+        use std::io::Write;
+        let _ = std::io::stderr().write_all(&output.stderr);
+    }
+    Ok(output.status.success())
+}
+
+/// Create a workspace via `git clone --local`, then `git checkout <from>`.
+/// With `depth`, makes a shallow clone instead: git ignores `--depth` for
+/// `--local` clones, so the project is cloned through a `file://` URL.
 /// Returns the host path. The directory is writable by the owner and group so container users with the appropriate group can write.
-fn ensure_workspace_clone(home: &str, name: &str, project_dir: &str) -> Result<String> {
-    let dir_path = Path::new(home).join(".box").join("workspaces").join(name);
+fn ensure_workspace_clone(
+    home: &str,
+    name: &str,
+    project_dir: &str,
+    from: Option<&str>,
+    depth: Option<u32>,
+    quiet: bool,
+) -> Result<String> {
+    let dir_path = config::workspaces_dir_in(home).join(name);
     let dir = dir_path.to_string_lossy().to_string();
     let git_dir = dir_path.join(".git");
 
     if !Path::new(&git_dir).exists() {
-        eprintln!("\x1b[2mrunning clone command:\x1b[0m");
-        eprintln!("git clone --local {} {}", project_dir, dir);
-        let status = Command::new("git")
-            .args(["clone", "--local", project_dir, &dir])
-            .status()?;
-        if !status.success() {
-            bail!("git clone --local failed");
+        let mut clone = Command::new("git");
+        clone.arg("clone");
+        match depth {
+            Some(depth) => {
+                // A shallow clone only fetches the default branch otherwise
+                clone.args(["--depth", &depth.to_string(), "--no-single-branch"]);
+                clone.arg(format!("file://{}", project_dir));
+            }
+            None => {
+                clone.args(["--local", project_dir]);
+            }
+        }
+        clone.arg(&dir);
+        if !run_setup_command(&mut clone, "clone command", quiet)? {
+            bail!("git clone failed");
+        }
+        if let Some(from) = from {
+            // A commit older than the shallow history isn't in the clone;
+            // fetch it on its own
+            if let Some(depth) = depth {
+                if !crate::git::ref_exists(&dir_path, from) {
+                    let fetched = run_setup_command(
+                        Command::new("git").args([
+                            "-C",
+                            &dir,
+                            "fetch",
+                            "--depth",
+                            &depth.to_string(),
+                            "origin",
+                            from,
+                        ]),
+                        "fetch command",
+                        quiet,
+                    )?;
+                    if !fetched {
+                        let _ = std::fs::remove_dir_all(&dir_path);
+                        bail!(
+                            "'{}' is not within the last {} commit(s) of any branch, and fetching it failed. Raise --clone-depth or leave it out.",
+                            from,
+                            depth
+                        );
+                    }
+                }
+            }
+            let checked_out = run_setup_command(
+                Command::new("git").args(["-C", &dir, "checkout", from]),
+                "checkout command",
+                quiet,
+            )?;
+            if !checked_out {
+                let _ = std::fs::remove_dir_all(&dir_path);
+                bail!("git checkout {} failed", from);
+            }
         }
 
         // git clone --local sets origin to the host path, which won't exist
@@ -47,11 +143,11 @@ fn ensure_workspace_clone(home: &str, name: &str, project_dir: &str) -> Result<S
             if output.status.success() {
                 let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
                 if !url.is_empty() {
-                    eprintln!("\x1b[2mrunning remote update:\x1b[0m");
-                    eprintln!("git remote set-url origin {}", url);
-                    let _ = Command::new("git")
-                        .args(["-C", &dir, "remote", "set-url", "origin", &url])
-                        .status();
+                    let _ = run_setup_command(
+                        Command::new("git").args(["-C", &dir, "remote", "set-url", "origin", &url]),
+                        "remote update",
+                        quiet,
+                    );
                 }
             }
         }
@@ -68,18 +164,69 @@ fn ensure_workspace_clone(home: &str, name: &str, project_dir: &str) -> Result<S
     Ok(dir)
 }
 
-/// Create a workspace via `git worktree add --detach`.
-fn ensure_workspace_worktree(home: &str, name: &str, project_dir: &str) -> Result<String> {
-    let dir_path = Path::new(home).join(".box").join("workspaces").join(name);
+/// Create a workspace via `git worktree add --detach`, or with `from`,
+/// `git worktree add <dir> <from>` (which checks out `from` if it is a branch).
+fn ensure_workspace_worktree(
+    home: &str,
+    name: &str,
+    project_dir: &str,
+    from: Option<&str>,
+    quiet: bool,
+) -> Result<String> {
+    let dir_path = config::workspaces_dir_in(home).join(name);
+    let dir = dir_path.to_string_lossy().to_string();
+
+    if !dir_path.exists() {
+        // Detached even at a branch: the branch may be checked out in the
+        // project already, and workspace commits must not move it
+        let mut args = vec!["-C", project_dir, "worktree", "add", "--detach", &dir];
+        args.extend(from);
+        let added = run_setup_command(Command::new("git").args(&args), "worktree command", quiet)?;
+        if !added {
+            bail!("git worktree add failed");
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = std::fs::metadata(&dir)?.permissions();
+        perms.set_mode(0o775);
+        std::fs::set_permissions(&dir, perms)?;
+    }
+
+    Ok(dir)
+}
+
+/// Branch created for a workspace by the `branch` strategy.
+pub fn workspace_branch(name: &str) -> String {
+    format!("box/{}", name)
+}
+
+/// Create a workspace via `git worktree add -b box/<name>`, starting the
+/// branch at `from` if given, or checking out the branch instead if it already
+/// exists (e.g. kept from an earlier workspace).
+fn ensure_workspace_branch(
+    home: &str,
+    name: &str,
+    project_dir: &str,
+    from: Option<&str>,
+    quiet: bool,
+) -> Result<String> {
+    let dir_path = config::workspaces_dir_in(home).join(name);
     let dir = dir_path.to_string_lossy().to_string();
+    let branch = workspace_branch(name);
 
     if !dir_path.exists() {
-        eprintln!("\x1b[2mrunning worktree command:\x1b[0m");
-        eprintln!("git -C {} worktree add --detach {}", project_dir, dir);
-        let status = Command::new("git")
-            .args(["-C", project_dir, "worktree", "add", "--detach", &dir])
-            .status()?;
-        if !status.success() {
+        let exists = branch_exists(&["-C", project_dir], &branch);
+        let mut args = vec!["-C", project_dir, "worktree", "add"];
+        if exists {
+            args.extend([dir.as_str(), branch.as_str()]);
+        } else {
+            args.extend(["-b", branch.as_str(), dir.as_str()]);
+            args.extend(from);
+        }
+        if !run_setup_command(Command::new("git").args(&args), "worktree command", quiet)? {
             bail!("git worktree add failed");
         }
     }
@@ -95,10 +242,12 @@ fn ensure_workspace_worktree(home: &str, name: &str, project_dir: &str) -> Resul
     Ok(dir)
 }
 
-/// Remove the workspace for a session. Dispatches based on strategy.
-pub fn remove_workspace(name: &str, strategy: &str) {
+/// Remove the workspace for a session. Dispatches based on strategy;
+/// `project_dir` is the repository a worktree was added to.
+pub fn remove_workspace(name: &str, strategy: &str, project_dir: &str) {
     match strategy {
-        "worktree" => remove_workspace_worktree(name),
+        "worktree" => remove_workspace_worktree(name, project_dir),
+        "branch" => remove_workspace_branch(name, project_dir),
         _ => remove_workspace_clone(name),
     }
 }
@@ -106,23 +255,118 @@ pub fn remove_workspace(name: &str, strategy: &str) {
 /// Remove the workspace directory for a clone-based session.
 fn remove_workspace_clone(name: &str) {
     if let Ok(home) = config::home_dir() {
-        let dir = Path::new(&home).join(".box").join("workspaces").join(name);
+        let dir = config::workspaces_dir_in(&home).join(name);
         let _ = std::fs::remove_dir_all(&dir);
     }
 }
 
-/// Remove the workspace for a worktree-based session.
-fn remove_workspace_worktree(name: &str) {
-    if let Ok(home) = config::home_dir() {
-        let dir = Path::new(&home).join(".box").join("workspaces").join(name);
-        let _ = Command::new("git")
-            .args(["worktree", "remove", "--force", &dir.to_string_lossy()])
-            .status();
+/// Remove the workspace for a worktree-based session. If git can't remove
+/// it (e.g. the project moved), the directory is deleted and the project's
+/// stale worktree entries are pruned.
+fn remove_workspace_worktree(name: &str, project_dir: &str) {
+    let Ok(home) = config::home_dir() else {
+        return;
+    };
+    let dir = config::workspaces_dir_in(&home).join(name);
+    let _ = Command::new("git")
+        .args(["-C", project_dir, "worktree", "remove", "--force"])
+        .arg(&dir)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+    if dir.exists() {
+        let _ = std::fs::remove_dir_all(&dir);
     }
+    prune_worktrees(&["-C", project_dir]);
+}
+
+/// Drop worktree entries whose directory is gone from the repository
+/// selected by `repo_args` (e.g. `["-C", dir]`).
+fn prune_worktrees(repo_args: &[&str]) {
+    let _ = Command::new("git")
+        .args(repo_args)
+        .args(["worktree", "prune"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+}
+
+/// Remove the worktree for a branch-based session, then delete its
+/// `box/<name>` branch unless it has unmerged commits.
+fn remove_workspace_branch(name: &str, project_dir: &str) {
+    let Ok(home) = config::home_dir() else {
+        return;
+    };
+    let dir = config::workspaces_dir_in(&home).join(name);
+    // Resolve the project's git dir before the worktree (and its link) is gone.
+    let Some(git_dir) = Command::new("git")
+        .arg("-C")
+        .arg(&dir)
+        .args(["rev-parse", "--path-format=absolute", "--git-common-dir"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+    else {
+        remove_workspace_worktree(name, project_dir);
+        return;
+    };
+    let _ = Command::new("git")
+        .args(["--git-dir", &git_dir, "worktree", "remove", "--force"])
+        .arg(&dir)
+        .status();
+    if dir.exists() {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    prune_worktrees(&["--git-dir", &git_dir]);
+    let branch = workspace_branch(name);
+    if !branch_exists(&["--git-dir", &git_dir], &branch) {
+        return;
+    }
+    let deleted = Command::new("git")
+        .args(["--git-dir", &git_dir, "branch", "-d", &branch])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if !deleted {
+        eprintln!(
+            "Kept branch '{}' because it has unmerged commits. Delete it with `git branch -D {}`.",
+            branch, branch
+        );
+    }
+}
+
+/// Whether a local branch exists in the repository selected by `repo_args`
+/// (e.g. `["-C", dir]`).
+fn branch_exists(repo_args: &[&str], branch: &str) -> bool {
+    Command::new("git")
+        .args(repo_args)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("refs/heads/{}", branch))
+        .stdout(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// The container runtime binary to invoke (`docker` unless overridden by
+/// `BOX_CONTAINER_RUNTIME` or `runtime` in config.toml), resolved once.
+pub fn runtime() -> &'static str {
+    static RUNTIME: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    RUNTIME.get_or_init(config::load_container_runtime)
 }
 
 pub fn check() -> Result<()> {
-    let docker_exists = Command::new("docker")
+    let runtime = runtime();
+    if !config::CONTAINER_RUNTIMES.contains(&runtime) {
+        bail!(
+            "Unknown container runtime '{}' (from BOX_CONTAINER_RUNTIME or config.toml). Expected one of: {}.",
+            runtime,
+            config::CONTAINER_RUNTIMES.join(", ")
+        );
+    }
+
+    let docker_exists = Command::new(runtime)
         .arg("version")
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
@@ -131,39 +375,80 @@ pub fn check() -> Result<()> {
         .unwrap_or(false);
 
     if !docker_exists {
+        if runtime == "podman" {
+            bail!("podman is not installed (selected as the container runtime). See https://podman.io/docs/installation");
+        }
         bail!("docker is not installed. See https://docs.docker.com/get-docker/");
     }
 
-    let info = Command::new("docker")
+    let info = Command::new(runtime)
         .arg("info")
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .status()?;
 
     if !info.success() {
+        if runtime == "podman" {
+            bail!("`podman info` failed. Check your podman setup (e.g. `podman machine start`).");
+        }
         bail!("Docker daemon is not running. Please start Docker.");
     }
 
     Ok(())
 }
 
+/// The container engine's server version, if the daemon answers.
+pub fn server_version() -> Option<String> {
+    let output = Command::new(runtime())
+        .args(["version", "--format", "{{.Server.Version}}"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
 pub struct DockerRunConfig<'a> {
     pub name: &'a str,
-    pub project_dir: &'a str,
     pub image: &'a str,
     pub mount_path: &'a str,
     pub cmd: &'a [String],
     pub env: &'a [String],
     pub home: &'a str,
     pub docker_args: Option<&'a str>,
+    /// Session whose container volumes to mount (resolved to its container name).
+    pub volumes_from: Option<&'a str>,
+    /// Host Docker socket to mount at `/var/run/docker.sock`.
+    pub docker_socket_mount: Option<&'a str>,
+    /// `--storage-opt` entries (e.g. `size=20G`).
+    pub storage_opts: &'a [String],
+    /// `HOST:CONTAINER` port mappings, each published with `-p`.
+    pub ports: &'a [String],
+    /// `--memory`, `--cpus` and `--pids-limit` limits.
+    pub resources: &'a session::Resources,
+    /// Working directory relative to the mount path (default: the mount path).
+    pub workdir: Option<&'a str>,
     pub detach: bool,
-    pub strategy: &'a str,
+    /// Don't echo the docker command or print the detached container's ID.
+    pub quiet: bool,
+}
+
+/// Default Docker daemon socket, on the host and inside containers.
+pub const DOCKER_SOCKET_PATH: &str = "/var/run/docker.sock";
+
+/// The container's working directory: the mount path, or `workdir` below it.
+/// `docker exec` inherits it, so commands run there too.
+pub fn container_workdir(mount_path: &str, workdir: Option<&str>) -> String {
+    match workdir {
+        Some(dir) => format!("{}/{}", mount_path.trim_end_matches('/'), dir),
+        None => mount_path.to_string(),
+    }
 }
 
 /// Build the docker run argument list without executing. Used by run_container and tests.
 pub fn build_run_args(cfg: &DockerRunConfig) -> Result<Vec<String>> {
     let ws = session::workspace_name(cfg.name);
-    let workspace_dir = Path::new(cfg.home).join(".box").join("workspaces").join(ws);
+    let workspace_dir = config::workspaces_dir_in(cfg.home).join(ws);
     let workspace_dir = workspace_dir.to_string_lossy();
     let container_name = container_label(cfg.name);
     let interactive_flag = if cfg.detach { "-d" } else { "-it" };
@@ -177,7 +462,7 @@ pub fn build_run_args(cfg: &DockerRunConfig) -> Result<Vec<String>> {
         "-v".into(),
         format!("{}:{}", workspace_dir, cfg.mount_path),
         "-w".into(),
-        cfg.mount_path.into(),
+        container_workdir(cfg.mount_path, cfg.workdir),
     ];
 
     // Mount host ~/.gitconfig so git user.name/user.email etc. are available
@@ -187,6 +472,28 @@ pub fn build_run_args(cfg: &DockerRunConfig) -> Result<Vec<String>> {
         args.push(format!("{}:/etc/gitconfig:ro", gitconfig.display()));
     }
 
+    if let Some(source) = cfg.volumes_from {
+        args.push("--volumes-from".into());
+        args.push(container_label(source));
+    }
+
+    if let Some(socket) = cfg.docker_socket_mount {
+        args.push("-v".into());
+        args.push(format!("{}:{}", socket, DOCKER_SOCKET_PATH));
+    }
+
+    for opt in cfg.storage_opts {
+        args.push("--storage-opt".into());
+        args.push(opt.clone());
+    }
+
+    for port in cfg.ports {
+        args.push("-p".into());
+        args.push(port.clone());
+    }
+
+    args.extend(cfg.resources.docker_args());
+
     if let Some(extra) = cfg.docker_args {
         if !extra.is_empty() {
             match shell_words::split(extra) {
@@ -212,43 +519,69 @@ pub fn build_run_args(cfg: &DockerRunConfig) -> Result<Vec<String>> {
     Ok(args)
 }
 
-pub fn run_container(cfg: &DockerRunConfig) -> Result<i32> {
-    let ws = session::workspace_name(cfg.name);
-    ensure_workspace(cfg.home, ws, cfg.project_dir, cfg.strategy)?;
+/// Copy of `docker run` args with the value of every `-e`/`--env KEY=VALUE`
+/// replaced by `***`, so echoing the command doesn't print secrets.
+fn mask_env_values(args: &[String]) -> Vec<String> {
+    let mut masked = Vec::with_capacity(args.len());
+    let mut env_next = false;
+    for arg in args {
+        if env_next {
+            masked.push(match arg.split_once('=') {
+                Some((key, _)) => format!("{}=***", key),
+                None => arg.clone(),
+            });
+        } else {
+            masked.push(arg.clone());
+        }
+        env_next = arg == "-e" || arg == "--env";
+    }
+    masked
+}
 
+/// Run a session's container. The workspace must already exist.
+pub fn run_container(cfg: &DockerRunConfig) -> Result<i32> {
     let args = build_run_args(cfg)?;
-    eprintln!("\x1b[2mrunning container:\x1b[0m");
-    eprintln!("docker {}\n", shell_words::join(&args));
+    if !cfg.quiet {
+        eprintln!("\x1b[2mrunning container:\x1b[0m");
+        eprintln!(
+            "{} {}\n",
+            runtime(),
+            shell_words::join(mask_env_values(&args))
+        );
+    }
 
     if cfg.detach {
-        let output = Command::new("docker").args(&args).output()?;
+        let output = Command::new(runtime()).args(&args).output()?;
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            bail!("docker run failed: {}", stderr.trim());
+            bail!("{} run failed: {}", runtime(), stderr.trim());
+        }
+        if !cfg.quiet {
+            let container_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            println!("{}", container_id);
+            println!("Run `box {}` to attach.", cfg.name);
         }
-        let container_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        println!("{}", container_id);
-        println!("Run `box {}` to attach.", cfg.name);
         Ok(0)
     } else {
-        let mut docker_cmd = vec!["docker".to_string()];
+        let mut docker_cmd = vec![runtime().to_string()];
         docker_cmd.extend(args);
         mux::run_standalone(mux::MuxConfig {
             session_name: cfg.name.to_string(),
             command: docker_cmd,
             working_dir: None,
             prefix_key: crate::config::load_mux_prefix_key(),
+            resize_on_attach: false,
         })
     }
 }
 
 /// Docker container name for a session (replaces / with -).
-fn container_label(name: &str) -> String {
+pub fn container_label(name: &str) -> String {
     format!("box-{}", name.replace('/', "-"))
 }
 
 pub fn container_exists(name: &str) -> bool {
-    Command::new("docker")
+    Command::new(runtime())
         .args(["container", "inspect", &container_label(name)])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
@@ -258,7 +591,7 @@ pub fn container_exists(name: &str) -> bool {
 }
 
 pub fn container_is_running(name: &str) -> bool {
-    let output = Command::new("docker")
+    let output = Command::new(runtime())
         .args([
             "container",
             "inspect",
@@ -277,7 +610,7 @@ pub fn container_is_running(name: &str) -> bool {
 /// Return the set of session names whose containers are currently running.
 /// Container names are `box-workspace-session` format; maps back to `workspace/session`.
 pub fn running_sessions() -> std::collections::HashSet<String> {
-    let output = Command::new("docker")
+    let output = Command::new(runtime())
         .args(["ps", "--filter", "name=box-", "--format", "{{.Names}}"])
         .stderr(std::process::Stdio::null())
         .output();
@@ -291,12 +624,52 @@ pub fn running_sessions() -> std::collections::HashSet<String> {
     }
 }
 
+/// CPU percentage and memory usage (e.g. `12.5MiB / 1GiB`) of the running
+/// containers of `names`, keyed by session name, from one `docker stats`.
+/// Sessions without a running container are missing from the map.
+pub fn stats(names: &[String]) -> std::collections::HashMap<String, (f64, String)> {
+    if names.is_empty() {
+        return std::collections::HashMap::new();
+    }
+    let output = Command::new(runtime())
+        .args([
+            "stats",
+            "--no-stream",
+            "--format",
+            "{{.Name}}\t{{.CPUPerc}}\t{{.MemUsage}}",
+        ])
+        .args(names.iter().map(|n| container_label(n)))
+        .stderr(std::process::Stdio::null())
+        .output();
+    // `docker stats` fails outright if any named container isn't running,
+    // so fall back to the stats that were printed, if any.
+    match output {
+        Ok(o) => parse_stats(&String::from_utf8_lossy(&o.stdout), names),
+        Err(_) => std::collections::HashMap::new(),
+    }
+}
+
+fn parse_stats(output: &str, names: &[String]) -> std::collections::HashMap<String, (f64, String)> {
+    let by_label: std::collections::HashMap<String, &String> =
+        names.iter().map(|n| (container_label(n), n)).collect();
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = by_label.get(fields.next()?.trim())?;
+            let cpu = fields.next()?.trim().trim_end_matches('%').parse().ok()?;
+            let mem = fields.next()?.trim().to_string();
+            Some(((*name).clone(), (cpu, mem)))
+        })
+        .collect()
+}
+
 pub fn start_container(name: &str) -> Result<i32> {
     // Start container in background first, then attach separately.
     // This avoids the PTY size race condition that `docker start -ai` has,
     // where the terminal inside may not receive the correct dimensions.
     let label = container_label(name);
-    let status = Command::new("docker")
+    let status = Command::new(runtime())
         .args(["start", &label])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::inherit())
@@ -309,20 +682,36 @@ pub fn start_container(name: &str) -> Result<i32> {
     attach_container(name)
 }
 
+/// Block until the container exits (`docker wait`) and return its exit code.
+pub fn wait_container(name: &str) -> Result<i32> {
+    let output = Command::new(runtime())
+        .args(["wait", &container_label(name)])
+        .stderr(std::process::Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        bail!("Failed to wait for container of session '{}'.", name);
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .with_context(|| format!("Unexpected `{} wait` output", runtime()))
+}
+
 pub fn attach_container(name: &str) -> Result<i32> {
     let label = container_label(name);
     mux::run_standalone(mux::MuxConfig {
         session_name: name.to_string(),
-        command: vec!["docker".to_string(), "attach".to_string(), label],
+        command: vec![runtime().to_string(), "attach".to_string(), label],
         working_dir: None,
         prefix_key: crate::config::load_mux_prefix_key(),
+        resize_on_attach: true,
     })
 }
 
 pub fn exec_container(name: &str, cmd: &[String]) -> Result<i32> {
     let label = container_label(name);
     let mut docker_cmd = vec![
-        "docker".to_string(),
+        runtime().to_string(),
         "exec".to_string(),
         "-it".to_string(),
         label,
@@ -334,12 +723,32 @@ pub fn exec_container(name: &str, cmd: &[String]) -> Result<i32> {
         command: docker_cmd,
         working_dir: None,
         prefix_key: crate::config::load_mux_prefix_key(),
+        resize_on_attach: false,
     })
 }
 
+/// Run a command in a running container without a TTY, streaming its output
+/// to our stdout/stderr (`docker exec -i`).
+pub fn exec_container_plain(name: &str, cmd: &[String]) -> Result<i32> {
+    let status = Command::new(runtime())
+        .args(["exec", "-i", &container_label(name)])
+        .args(cmd)
+        .status()?;
+    Ok(mux::exit_code(status))
+}
+
+/// Run a command in a running container in the background (`docker exec -d`).
+pub fn exec_container_detached(name: &str, cmd: &[String]) -> Result<i32> {
+    let status = Command::new(runtime())
+        .args(["exec", "-d", &container_label(name)])
+        .args(cmd)
+        .status()?;
+    Ok(status.code().unwrap_or(1))
+}
+
 pub fn start_container_detached(name: &str) -> Result<i32> {
     let label = container_label(name);
-    let status = Command::new("docker")
+    let status = Command::new(runtime())
         .args(["start", &label])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::inherit())
@@ -354,9 +763,44 @@ pub fn start_container_detached(name: &str) -> Result<i32> {
     }
 }
 
+/// Print a container's output via `docker logs` (only the last `lines` lines
+/// when given), optionally following new output.
+pub fn container_logs(name: &str, lines: Option<usize>, follow: bool) -> Result<i32> {
+    let tail = lines.map_or_else(|| "all".to_string(), |n| n.to_string());
+    let label = container_label(name);
+    let mut args = vec!["logs", "--tail", &tail];
+    if follow {
+        args.push("-f");
+    }
+    args.push(&label);
+    let status = Command::new(runtime()).args(&args).status()?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Return `docker top` output for a running container with the columns
+/// in `process::PS_FIELDS`.
+pub fn container_top(name: &str) -> Result<String> {
+    let output = Command::new(runtime())
+        .args([
+            "top",
+            &container_label(name),
+            "-eo",
+            crate::process::PS_FIELDS,
+        ])
+        .stderr(std::process::Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "docker top failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
 pub fn stop_container(name: &str) -> Result<i32> {
     let label = container_label(name);
-    let status = Command::new("docker")
+    let status = Command::new(runtime())
         .args(["stop", &label])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::inherit())
@@ -370,34 +814,1046 @@ pub fn stop_container(name: &str) -> Result<i32> {
     }
 }
 
+/// Return a session container's ID and status (e.g. `running`, `exited`).
+pub fn container_state(name: &str) -> Option<(String, String)> {
+    let output = Command::new(runtime())
+        .args([
+            "container",
+            "inspect",
+            "-f",
+            "{{.Id}} {{.State.Status}}",
+            &container_label(name),
+        ])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let out = String::from_utf8_lossy(&output.stdout);
+    let (id, status) = out.trim().split_once(' ')?;
+    Some((id.to_string(), status.to_string()))
+}
+
+/// What an existing container runs, read from `docker container inspect` so
+/// an adopted container is recorded as it is rather than with box defaults.
+#[derive(Debug, Default, PartialEq)]
+pub struct ContainerSettings {
+    pub image: String,
+    pub command: Vec<String>,
+    /// Environment set on the container beyond what its image defines.
+    pub env: Vec<String>,
+    /// Published ports as `[IP:]HOST:CONTAINER[/udp]`.
+    pub ports: Vec<String>,
+    /// Destination of the container's first bind mount, other than the Docker socket.
+    pub mount_path: Option<String>,
+    pub docker_socket_mount: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct InspectContainer {
+    #[serde(default)]
+    config: InspectContainerConfig,
+    #[serde(default)]
+    host_config: InspectHostConfig,
+    #[serde(default)]
+    mounts: Vec<InspectMount>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct InspectContainerConfig {
+    #[serde(default)]
+    image: String,
+    env: Option<Vec<String>>,
+    cmd: Option<Vec<String>>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct InspectHostConfig {
+    port_bindings: Option<std::collections::BTreeMap<String, Option<Vec<InspectPortBinding>>>>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectPortBinding {
+    #[serde(default)]
+    host_ip: String,
+    #[serde(default)]
+    host_port: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectMount {
+    #[serde(default, rename = "Type")]
+    kind: String,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    destination: String,
+}
+
+/// Read the settings of a session's existing container.
+pub fn container_settings(name: &str) -> Result<ContainerSettings> {
+    let output = Command::new(runtime())
+        .args(["container", "inspect", &container_label(name)])
+        .stderr(std::process::Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        bail!("Container '{}' not found.", container_label(name));
+    }
+    let mut settings = parse_container_settings(&String::from_utf8_lossy(&output.stdout))?;
+    // Variables baked into the image aren't the container's own settings
+    if let Ok(image) = image_metadata(&settings.image) {
+        settings.env.retain(|var| !image.env.contains(var));
+    }
+    Ok(settings)
+}
+
+fn parse_container_settings(json: &str) -> Result<ContainerSettings> {
+    let containers: Vec<InspectContainer> = serde_json::from_str(json)?;
+    let Some(container) = containers.into_iter().next() else {
+        bail!("docker container inspect returned no containers");
+    };
+
+    let mut ports = Vec::new();
+    for (port, bindings) in container.host_config.port_bindings.unwrap_or_default() {
+        let port = port.strip_suffix("/tcp").unwrap_or(&port);
+        for binding in bindings.unwrap_or_default() {
+            let host = match binding.host_ip.as_str() {
+                "" | "0.0.0.0" | "::" => binding.host_port,
+                ip => format!("{}:{}", ip, binding.host_port),
+            };
+            ports.push(format!("{}:{}", host, port));
+        }
+    }
+
+    let mut mount_path = None;
+    let mut docker_socket_mount = None;
+    for mount in container.mounts.into_iter().filter(|m| m.kind == "bind") {
+        if mount.destination == DOCKER_SOCKET_PATH {
+            docker_socket_mount = Some(mount.source);
+        } else if mount_path.is_none() {
+            mount_path = Some(mount.destination);
+        }
+    }
+
+    Ok(ContainerSettings {
+        image: container.config.image,
+        command: container.config.cmd.unwrap_or_default(),
+        env: container.config.env.unwrap_or_default(),
+        ports,
+        mount_path,
+        docker_socket_mount,
+    })
+}
+
+/// Rename a session's container to match a new session name.
+pub fn rename_container(old: &str, new: &str) -> Result<()> {
+    let output = Command::new(runtime())
+        .args(["rename", &container_label(old), &container_label(new)])
+        .stderr(std::process::Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "docker rename failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
 pub fn remove_container(name: &str) {
     let label = container_label(name);
-    let _ = Command::new("docker")
+    let _ = Command::new(runtime())
         .args(["rm", "-f", &label])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .status();
 }
 
+/// Shell script run inside a container to capture its environment: sorted
+/// variables followed by the installed package list from whichever package
+/// manager is available.
+const ENV_SNAPSHOT_SCRIPT: &str = "env | sort; echo '# packages'; \
+    if command -v dpkg-query >/dev/null 2>&1; then dpkg-query -W -f='${Package} ${Version}\\n'; \
+    elif command -v apk >/dev/null 2>&1; then apk info -v; \
+    elif command -v rpm >/dev/null 2>&1; then rpm -qa; fi 2>/dev/null | sort";
+
+/// Capture a snapshot of a running container's environment variables and
+/// installed packages, used for baseline/drift comparison.
+pub fn container_env_snapshot(name: &str) -> Result<String> {
+    let output = Command::new(runtime())
+        .args([
+            "exec",
+            &container_label(name),
+            "sh",
+            "-c",
+            ENV_SNAPSHOT_SCRIPT,
+        ])
+        .stderr(std::process::Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        bail!("Failed to capture environment of session '{}'.", name);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Capabilities Docker grants a container by default.
+pub const DEFAULT_CAPABILITIES: &[&str] = &[
+    "AUDIT_WRITE",
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "MKNOD",
+    "NET_BIND_SERVICE",
+    "NET_RAW",
+    "SETFCAP",
+    "SETGID",
+    "SETPCAP",
+    "SETUID",
+    "SYS_CHROOT",
+];
+
+/// Capability changes applied to a container relative to Docker's default set.
+#[derive(Debug, Default, PartialEq)]
+pub struct CapabilitySet {
+    pub added: Vec<String>,
+    pub dropped: Vec<String>,
+}
+
+impl CapabilitySet {
+    /// Effective capabilities: the default set plus `added`, minus `dropped`.
+    /// `ALL` in `dropped` clears the default set before additions are applied;
+    /// `ALL` in `added` is reported as-is.
+    pub fn effective(&self) -> Vec<String> {
+        if self.added.iter().any(|c| c == "ALL") {
+            return vec!["ALL".to_string()];
+        }
+        let mut caps: std::collections::BTreeSet<String> =
+            if self.dropped.iter().any(|c| c == "ALL") {
+                Default::default()
+            } else {
+                DEFAULT_CAPABILITIES
+                    .iter()
+                    .map(|c| c.to_string())
+                    .filter(|c| !self.dropped.contains(c))
+                    .collect()
+            };
+        caps.extend(self.added.iter().cloned());
+        caps.into_iter().collect()
+    }
+}
+
+/// Normalize a capability name as written in docker flags (`cap_net_admin`,
+/// `CAP_NET_ADMIN`, `NET_ADMIN`) to its bare upper-case form.
+fn normalize_capability(cap: &str) -> String {
+    let upper = cap.trim().to_ascii_uppercase();
+    upper
+        .strip_prefix("CAP_")
+        .map(|s| s.to_string())
+        .unwrap_or(upper)
+}
+
+/// Read the capability flags (`--cap-add`/`--cap-drop`) a container was created with.
+pub fn container_capabilities(name: &str) -> Result<CapabilitySet> {
+    let output = Command::new(runtime())
+        .args([
+            "container",
+            "inspect",
+            "-f",
+            "{{range .HostConfig.CapAdd}}{{.}} {{end}}|{{range .HostConfig.CapDrop}}{{.}} {{end}}",
+            &container_label(name),
+        ])
+        .stderr(std::process::Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        bail!("Container for session '{}' not found.", name);
+    }
+    Ok(parse_capabilities(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_capabilities(output: &str) -> CapabilitySet {
+    let (added, dropped) = output.trim().split_once('|').unwrap_or((output, ""));
+    let parse = |s: &str| s.split_whitespace().map(normalize_capability).collect();
+    CapabilitySet {
+        added: parse(added),
+        dropped: parse(dropped),
+    }
+}
+
+/// A container's cgroup limits. `None` means the limit is not set.
+#[derive(Debug, Default, PartialEq)]
+pub struct ResourceLimits {
+    pub memory: Option<u64>,
+    pub memory_swap: Option<u64>,
+    pub nano_cpus: Option<u64>,
+    pub cpu_quota: Option<u64>,
+    pub cpu_period: Option<u64>,
+    pub cpu_shares: Option<u64>,
+    pub pids_limit: Option<u64>,
+    pub blkio_weight: Option<u64>,
+    pub ulimits: Vec<Ulimit>,
+}
+
+/// A `--ulimit` entry. `None` means unlimited.
+#[derive(Debug, PartialEq)]
+pub struct Ulimit {
+    pub name: String,
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Human-readable `(label, value)` rows for display.
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let show = |v: Option<u64>, f: &dyn Fn(u64) -> String| {
+            v.map(f).unwrap_or_else(|| "unlimited".to_string())
+        };
+        let plain = |n: u64| n.to_string();
+        let micros = |n: u64| format!("{}us", n);
+        vec![
+            ("Memory Limit", show(self.memory, &format_bytes)),
+            ("Memory Swap Limit", show(self.memory_swap, &format_bytes)),
+            (
+                "CPUs",
+                show(self.nano_cpus, &|n| format!("{:.2}", n as f64 / 1e9)),
+            ),
+            ("CPU Quota", show(self.cpu_quota, &micros)),
+            ("CPU Period", show(self.cpu_period, &micros)),
+            ("CPU Shares", show(self.cpu_shares, &plain)),
+            ("PID Limit", show(self.pids_limit, &plain)),
+            ("Blkio Weight", show(self.blkio_weight, &plain)),
+        ]
+    }
+}
+
+/// Format a byte count using binary units (e.g. `512 MiB`).
+pub fn format_bytes(n: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = n as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value.fract() == 0.0 {
+        format!("{} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Read the cgroup limits and ulimits a container was created with.
+pub fn container_resource_limits(name: &str) -> Result<ResourceLimits> {
+    let output = Command::new(runtime())
+        .args([
+            "container",
+            "inspect",
+            "-f",
+            "{{.HostConfig.Memory}} {{.HostConfig.MemorySwap}} {{.HostConfig.NanoCpus}} \
+             {{.HostConfig.CpuQuota}} {{.HostConfig.CpuPeriod}} {{.HostConfig.CpuShares}} \
+             {{.HostConfig.PidsLimit}} {{.HostConfig.BlkioWeight}}|\
+             {{range .HostConfig.Ulimits}}{{.Name}}={{.Soft}}:{{.Hard}} {{end}}",
+            &container_label(name),
+        ])
+        .stderr(std::process::Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        bail!("Container for session '{}' not found.", name);
+    }
+    Ok(parse_resource_limits(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_resource_limits(output: &str) -> ResourceLimits {
+    // Zero, negative (-1) and `<nil>` all mean the limit is not set.
+    let limit = |s: Option<&str>| s.and_then(|s| s.parse::<u64>().ok()).filter(|&n| n > 0);
+    let (values, ulimits) = output.trim().split_once('|').unwrap_or((output, ""));
+    let mut values = values.split_whitespace();
+    let mut next = || limit(values.next());
+    ResourceLimits {
+        memory: next(),
+        memory_swap: next(),
+        nano_cpus: next(),
+        cpu_quota: next(),
+        cpu_period: next(),
+        cpu_shares: next(),
+        pids_limit: next(),
+        blkio_weight: next(),
+        ulimits: ulimits
+            .split_whitespace()
+            .filter_map(|entry| {
+                let (name, range) = entry.split_once('=')?;
+                let (soft, hard) = range.split_once(':')?;
+                Some(Ulimit {
+                    name: name.to_string(),
+                    soft: limit(Some(soft)),
+                    hard: limit(Some(hard)),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Return the host `address:port` a container port is published on, if any.
+pub fn container_port_mapping(name: &str, port: u16) -> Option<String> {
+    let output = Command::new(runtime())
+        .args(["port", &container_label(name), &format!("{}/tcp", port)])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|l| l.trim().replace("0.0.0.0", "127.0.0.1"))
+        .filter(|l| !l.is_empty())
+}
+
+/// Forward `ip:port` in a running container to `127.0.0.1:port` with a
+/// background `socat`, so a server that only listens on loopback can be
+/// reached on the container's IP. The tunnel lasts until the container stops.
+pub fn start_port_tunnel(name: &str, ip: &str, port: u16) -> Result<()> {
+    let label = container_label(name);
+    let has_socat = Command::new(runtime())
+        .args(["exec", &label, "sh", "-c", "command -v socat"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if !has_socat {
+        bail!(
+            "socat is not installed in session '{}', so port {} can't be tunnelled. Install it in the image, or publish the port.",
+            name,
+            port
+        );
+    }
+    let status = Command::new(runtime())
+        .args(["exec", "-d", &label, "socat"])
+        .arg(format!("TCP-LISTEN:{},bind={},fork,reuseaddr", port, ip))
+        .arg(format!("TCP:127.0.0.1:{}", port))
+        .status()?;
+    if !status.success() {
+        bail!("Failed to start a socat tunnel in session '{}'.", name);
+    }
+    Ok(())
+}
+
+/// Return the container's IP address on its first attached network.
+pub fn container_ip(name: &str) -> Option<String> {
+    let output = Command::new(runtime())
+        .args([
+            "container",
+            "inspect",
+            "-f",
+            "{{range .NetworkSettings.Networks}}{{.IPAddress}} {{end}}",
+            &container_label(name),
+        ])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(|s| s.to_string())
+}
+
+/// Storage drivers accepted by `box create --storage-driver`.
+pub const KNOWN_STORAGE_DRIVERS: &[&str] = &["overlay2", "devicemapper", "btrfs", "zfs", "aufs"];
+
+/// Return the storage driver the Docker daemon is configured with.
+pub fn daemon_storage_driver() -> Option<String> {
+    let output = Command::new(runtime())
+        .args(["info", "--format", "{{.Driver}}"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let driver = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!driver.is_empty()).then_some(driver)
+}
+
+/// Check whether an image is present in the local image store.
+pub fn image_exists(image: &str) -> bool {
+    Command::new(runtime())
+        .args(["image", "inspect", image])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Return the image's default environment (`Config.Env`) as `KEY=VALUE` entries.
+pub fn image_env(image: &str) -> Result<Vec<String>> {
+    let output = Command::new(runtime())
+        .args([
+            "image",
+            "inspect",
+            "-f",
+            "{{range .Config.Env}}{{println .}}{{end}}",
+            image,
+        ])
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "Image '{}' not found locally; run `docker pull {}` first.",
+            image,
+            image
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect())
+}
+
+/// Pull an image from its registry, streaming docker's progress output.
+pub fn pull_image(image: &str) -> Result<()> {
+    pull_image_with(image, false)
+}
+
+/// Like [`pull_image`]; with `quiet` set nothing is printed unless it fails.
+pub fn pull_image_with(image: &str, quiet: bool) -> Result<()> {
+    let mut cmd = Command::new(runtime());
+    if quiet {
+        cmd.args(["pull", "-q", image])
+            .stdout(std::process::Stdio::null());
+    } else {
+        eprintln!("\x1b[2mpulling image:\x1b[0m");
+        eprintln!("docker pull {}", image);
+        cmd.args(["pull", image])
+            .stdout(std::process::Stdio::inherit());
+    }
+    let status = cmd.stderr(std::process::Stdio::inherit()).status()?;
+    if !status.success() {
+        bail!(
+            "Image '{}' not found locally and could not be pulled.",
+            image
+        );
+    }
+    Ok(())
+}
+
+/// Metadata about a local image, from `docker image inspect`.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImageMetadata {
+    pub id: String,
+    pub created: String,
+    pub size: u64,
+    pub os: String,
+    pub architecture: String,
+    pub exposed_ports: Vec<String>,
+    pub env: Vec<String>,
+    pub entrypoint: Vec<String>,
+    pub cmd: Vec<String>,
+    pub labels: std::collections::BTreeMap<String, String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectImage {
+    id: String,
+    #[serde(default)]
+    created: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    os: String,
+    #[serde(default)]
+    architecture: String,
+    #[serde(default)]
+    config: Option<InspectImageConfig>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct InspectImageConfig {
+    exposed_ports: Option<std::collections::BTreeMap<String, serde_json::Value>>,
+    env: Option<Vec<String>>,
+    entrypoint: Option<Vec<String>>,
+    cmd: Option<Vec<String>>,
+    labels: Option<std::collections::BTreeMap<String, String>>,
+}
+
+/// Read an image's metadata via `docker image inspect`.
+pub fn image_metadata(image: &str) -> Result<ImageMetadata> {
+    let output = Command::new(runtime())
+        .args(["image", "inspect", image])
+        .stderr(std::process::Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "Image '{}' not found locally; run `docker pull {}` first.",
+            image,
+            image
+        );
+    }
+    parse_image_metadata(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_image_metadata(json: &str) -> Result<ImageMetadata> {
+    let images: Vec<InspectImage> = serde_json::from_str(json)?;
+    let Some(image) = images.into_iter().next() else {
+        bail!("docker image inspect returned no images");
+    };
+    let config = image.config.unwrap_or_default();
+    Ok(ImageMetadata {
+        id: image.id,
+        created: image.created,
+        size: image.size,
+        os: image.os,
+        architecture: image.architecture,
+        exposed_ports: config
+            .exposed_ports
+            .unwrap_or_default()
+            .into_keys()
+            .collect(),
+        env: config.env.unwrap_or_default(),
+        entrypoint: config.entrypoint.unwrap_or_default(),
+        cmd: config.cmd.unwrap_or_default(),
+        labels: config.labels.unwrap_or_default(),
+    })
+}
+
+/// Print an image's layer history via `docker history`.
+pub fn image_history(image: &str) -> Result<i32> {
+    let status = Command::new(runtime()).args(["history", image]).status()?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Result of comparing a local image against its registry.
+#[derive(Debug, PartialEq)]
+pub enum ImageUpdateStatus {
+    UpToDate,
+    UpdateAvailable {
+        local_digest: String,
+        remote_digest: String,
+    },
+    /// The image is not pulled from a registry, or the registry could not be queried.
+    Unknown,
+}
+
+/// Check whether the registry has a newer version of `image` than the local
+/// copy, by comparing the local repo digests with the remote manifest digest.
+/// Read-only: nothing is pulled.
+pub fn check_image_update(image: &str) -> Result<ImageUpdateStatus> {
+    let output = Command::new(runtime())
+        .args([
+            "image",
+            "inspect",
+            "-f",
+            "{{range .RepoDigests}}{{println .}}{{end}}",
+            image,
+        ])
+        .stderr(std::process::Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        bail!("Image '{}' not found locally.", image);
+    }
+    let local = parse_repo_digests(&String::from_utf8_lossy(&output.stdout));
+
+    let remote = Command::new(runtime())
+        .args(["buildx", "imagetools", "inspect", image])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| parse_imagetools_digest(&String::from_utf8_lossy(&o.stdout)));
+
+    Ok(image_update_status(&local, remote))
+}
+
+/// Extract `sha256:...` digests from `repo@sha256:...` lines.
+fn parse_repo_digests(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|l| l.trim().split_once('@'))
+        .map(|(_, digest)| digest.to_string())
+        .collect()
+}
+
+/// Extract the manifest digest from `docker buildx imagetools inspect` output.
+fn parse_imagetools_digest(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Digest:"))
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty())
+}
+
+fn image_update_status(local: &[String], remote: Option<String>) -> ImageUpdateStatus {
+    match (local.first(), remote) {
+        (Some(_), Some(remote)) if local.contains(&remote) => ImageUpdateStatus::UpToDate,
+        (Some(local_digest), Some(remote_digest)) => ImageUpdateStatus::UpdateAvailable {
+            local_digest: local_digest.clone(),
+            remote_digest,
+        },
+        _ => ImageUpdateStatus::Unknown,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    static NO_RESOURCES: session::Resources = session::Resources {
+        memory: None,
+        cpus: None,
+        pids_limit: None,
+    };
+
     fn default_config<'a>() -> DockerRunConfig<'a> {
         DockerRunConfig {
             name: "sess/default",
-            project_dir: "/tmp/project",
             image: "alpine:latest",
             mount_path: "/workspace",
             cmd: &[],
             env: &[],
             home: "/home/user",
             docker_args: None,
+            volumes_from: None,
+            docker_socket_mount: None,
+            storage_opts: &[],
+            ports: &[],
+            resources: &NO_RESOURCES,
+            workdir: None,
             detach: false,
-            strategy: "clone",
+            quiet: false,
         }
     }
 
+    #[test]
+    fn test_build_run_args_volumes_from() {
+        let args = build_run_args(&DockerRunConfig {
+            volumes_from: Some("data/default"),
+            ..default_config()
+        })
+        .unwrap();
+        let pos = args.iter().position(|a| a == "--volumes-from").unwrap();
+        assert_eq!(args[pos + 1], "box-data-default");
+        assert!(pos < args.iter().position(|a| a == "alpine:latest").unwrap());
+    }
+
+    #[test]
+    fn test_parse_capabilities() {
+        let caps = parse_capabilities("NET_ADMIN cap_sys_ptrace |all \n");
+        assert_eq!(caps.added, vec!["NET_ADMIN", "SYS_PTRACE"]);
+        assert_eq!(caps.dropped, vec!["ALL"]);
+        assert_eq!(parse_capabilities("|\n"), CapabilitySet::default());
+    }
+
+    #[test]
+    fn test_parse_resource_limits() {
+        let limits = parse_resource_limits(
+            "536870912 -1 1500000000 50000 100000 0 <nil> 0|nofile=1024:4096 nproc=-1:-1 \n",
+        );
+        assert_eq!(limits.memory, Some(536870912));
+        assert_eq!(limits.memory_swap, None);
+        assert_eq!(limits.nano_cpus, Some(1500000000));
+        assert_eq!(limits.cpu_quota, Some(50000));
+        assert_eq!(limits.cpu_period, Some(100000));
+        assert_eq!(limits.cpu_shares, None);
+        assert_eq!(limits.pids_limit, None);
+        assert_eq!(limits.blkio_weight, None);
+        assert_eq!(
+            limits.ulimits,
+            vec![
+                Ulimit {
+                    name: "nofile".to_string(),
+                    soft: Some(1024),
+                    hard: Some(4096),
+                },
+                Ulimit {
+                    name: "nproc".to_string(),
+                    soft: None,
+                    hard: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_resource_limits_unset() {
+        let limits = parse_resource_limits("0 0 0 0 0 0 0 0|\n");
+        assert_eq!(limits, ResourceLimits::default());
+    }
+
+    #[test]
+    fn test_resource_limits_rows() {
+        let limits = ResourceLimits {
+            memory: Some(512 * 1024 * 1024),
+            nano_cpus: Some(1_500_000_000),
+            pids_limit: Some(100),
+            ..Default::default()
+        };
+        let rows = limits.rows();
+        assert_eq!(rows[0], ("Memory Limit", "512 MiB".to_string()));
+        assert_eq!(rows[1], ("Memory Swap Limit", "unlimited".to_string()));
+        assert_eq!(rows[2], ("CPUs", "1.50".to_string()));
+        assert_eq!(rows[3], ("CPU Quota", "unlimited".to_string()));
+        assert_eq!(rows[6], ("PID Limit", "100".to_string()));
+    }
+
+    #[test]
+    fn test_parse_repo_digests() {
+        let out = "ubuntu@sha256:aaa\ndocker.io/library/ubuntu@sha256:bbb\n\n";
+        assert_eq!(parse_repo_digests(out), vec!["sha256:aaa", "sha256:bbb"]);
+        assert!(parse_repo_digests("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_imagetools_digest() {
+        let out = "Name:      docker.io/library/ubuntu:22.04\n\
+                   MediaType: application/vnd.oci.image.index.v1+json\n\
+                   Digest:    sha256:ccc\n\
+                   \n\
+                   Manifests:\n";
+        assert_eq!(parse_imagetools_digest(out).as_deref(), Some("sha256:ccc"));
+        assert_eq!(parse_imagetools_digest("Name: x\n"), None);
+    }
+
+    #[test]
+    fn test_image_update_status() {
+        let local = vec!["sha256:aaa".to_string(), "sha256:bbb".to_string()];
+        assert_eq!(
+            image_update_status(&local, Some("sha256:bbb".to_string())),
+            ImageUpdateStatus::UpToDate
+        );
+        assert_eq!(
+            image_update_status(&local, Some("sha256:ccc".to_string())),
+            ImageUpdateStatus::UpdateAvailable {
+                local_digest: "sha256:aaa".to_string(),
+                remote_digest: "sha256:ccc".to_string(),
+            }
+        );
+        assert_eq!(
+            image_update_status(&local, None),
+            ImageUpdateStatus::Unknown
+        );
+        // Locally built images have no repo digests
+        assert_eq!(
+            image_update_status(&[], Some("sha256:ccc".to_string())),
+            ImageUpdateStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_parse_container_settings() {
+        let json = r#"[{
+            "Config": {
+                "Image": "node:20",
+                "Env": ["PATH=/usr/bin", "API_URL=http://api"],
+                "Cmd": ["npm", "run", "dev"]
+            },
+            "HostConfig": {
+                "PortBindings": {
+                    "3000/tcp": [{"HostIp": "", "HostPort": "8080"}],
+                    "53/udp": [{"HostIp": "127.0.0.1", "HostPort": "5353"}],
+                    "9000/tcp": null
+                }
+            },
+            "Mounts": [
+                {"Type": "volume", "Source": "/var/lib/docker/volumes/x", "Destination": "/data"},
+                {"Type": "bind", "Source": "/var/run/docker.sock", "Destination": "/var/run/docker.sock"},
+                {"Type": "bind", "Source": "/home/me/app", "Destination": "/app"}
+            ]
+        }]"#;
+        let settings = parse_container_settings(json).unwrap();
+        assert_eq!(
+            settings,
+            ContainerSettings {
+                image: "node:20".to_string(),
+                command: vec!["npm".into(), "run".into(), "dev".into()],
+                env: vec!["PATH=/usr/bin".into(), "API_URL=http://api".into()],
+                ports: vec!["8080:3000".into(), "127.0.0.1:5353:53/udp".into()],
+                mount_path: Some("/app".to_string()),
+                docker_socket_mount: Some("/var/run/docker.sock".to_string()),
+            }
+        );
+        assert!(parse_container_settings("[]").is_err());
+    }
+
+    #[test]
+    fn test_parse_image_metadata() {
+        let json = r#"[{
+            "Id": "sha256:abc",
+            "Created": "2024-05-01T12:00:00.000000000Z",
+            "Size": 78643200,
+            "Os": "linux",
+            "Architecture": "amd64",
+            "Config": {
+                "ExposedPorts": {"8080/tcp": {}, "443/tcp": {}},
+                "Env": ["PATH=/usr/bin", "LANG=C.UTF-8"],
+                "Entrypoint": null,
+                "Cmd": ["/bin/bash"],
+                "Labels": {"org.opencontainers.image.version": "22.04"}
+            }
+        }]"#;
+        let meta = parse_image_metadata(json).unwrap();
+        assert_eq!(meta.id, "sha256:abc");
+        assert_eq!(meta.size, 78643200);
+        assert_eq!(meta.os, "linux");
+        assert_eq!(meta.architecture, "amd64");
+        assert_eq!(meta.exposed_ports, vec!["443/tcp", "8080/tcp"]);
+        assert_eq!(meta.env, vec!["PATH=/usr/bin", "LANG=C.UTF-8"]);
+        assert!(meta.entrypoint.is_empty());
+        assert_eq!(meta.cmd, vec!["/bin/bash"]);
+        assert_eq!(
+            meta.labels
+                .get("org.opencontainers.image.version")
+                .map(|s| s.as_str()),
+            Some("22.04")
+        );
+    }
+
+    #[test]
+    fn test_parse_image_metadata_null_config() {
+        let meta = parse_image_metadata(r#"[{"Id": "sha256:abc", "Config": null}]"#).unwrap();
+        assert_eq!(meta.id, "sha256:abc");
+        assert!(meta.env.is_empty());
+        assert!(meta.labels.is_empty());
+        assert!(parse_image_metadata("[]").is_err());
+        assert!(parse_image_metadata("not json").is_err());
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(2 * 1024 * 1024 * 1024), "2 GiB");
+    }
+
+    #[test]
+    fn test_effective_capabilities_default() {
+        let caps = CapabilitySet::default();
+        assert_eq!(caps.effective().len(), DEFAULT_CAPABILITIES.len());
+    }
+
+    #[test]
+    fn test_effective_capabilities_add_and_drop() {
+        let caps = CapabilitySet {
+            added: vec!["NET_ADMIN".to_string()],
+            dropped: vec!["NET_RAW".to_string(), "MKNOD".to_string()],
+        };
+        let effective = caps.effective();
+        assert!(effective.contains(&"NET_ADMIN".to_string()));
+        assert!(!effective.contains(&"NET_RAW".to_string()));
+        assert!(!effective.contains(&"MKNOD".to_string()));
+        assert_eq!(effective.len(), DEFAULT_CAPABILITIES.len() - 1);
+    }
+
+    #[test]
+    fn test_effective_capabilities_drop_all() {
+        let caps = CapabilitySet {
+            added: vec!["CHOWN".to_string(), "SETUID".to_string()],
+            dropped: vec!["ALL".to_string()],
+        };
+        assert_eq!(caps.effective(), vec!["CHOWN", "SETUID"]);
+    }
+
+    #[test]
+    fn test_effective_capabilities_add_all() {
+        let caps = CapabilitySet {
+            added: vec!["ALL".to_string()],
+            dropped: vec![],
+        };
+        assert_eq!(caps.effective(), vec!["ALL"]);
+    }
+
+    #[test]
+    fn test_build_run_args_workdir() {
+        let args = build_run_args(&default_config()).unwrap();
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "-w" && w[1] == "/workspace"));
+
+        let args = build_run_args(&DockerRunConfig {
+            workdir: Some("packages/api"),
+            ..default_config()
+        })
+        .unwrap();
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "-w" && w[1] == "/workspace/packages/api"));
+        assert_eq!(container_workdir("/", Some("src")), "/src");
+    }
+
+    #[test]
+    fn test_build_run_args_docker_socket_mount() {
+        let args = build_run_args(&DockerRunConfig {
+            docker_socket_mount: Some("/run/user/1000/docker.sock"),
+            ..default_config()
+        })
+        .unwrap();
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "-v" && w[1] == "/run/user/1000/docker.sock:/var/run/docker.sock"));
+
+        let args = build_run_args(&default_config()).unwrap();
+        assert!(!args.iter().any(|a| a.contains("docker.sock")));
+    }
+
+    #[test]
+    fn test_build_run_args_storage_opts() {
+        let opts = vec!["size=20G".to_string()];
+        let args = build_run_args(&DockerRunConfig {
+            storage_opts: &opts,
+            ..default_config()
+        })
+        .unwrap();
+        let pos = args.iter().position(|a| a == "--storage-opt").unwrap();
+        assert_eq!(args[pos + 1], "size=20G");
+        assert!(pos < args.iter().position(|a| a == "alpine:latest").unwrap());
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let names = vec!["app/zsh".to_string(), "api/server".to_string()];
+        let output = "box-app-zsh\t1.25%\t12.5MiB / 1.9GiB\n\
+                      box-other-x\t3.00%\t1MiB / 1GiB\n\
+                      box-api-server\t--\t-- / --\n";
+        let stats = parse_stats(output, &names);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats["app/zsh"], (1.25, "12.5MiB / 1.9GiB".to_string()));
+    }
+
+    #[test]
+    fn test_build_run_args_ports() {
+        let ports = vec!["8080:80".to_string(), "5432:5432".to_string()];
+        let args = build_run_args(&DockerRunConfig {
+            ports: &ports,
+            ..default_config()
+        })
+        .unwrap();
+        let published: Vec<_> = args
+            .windows(2)
+            .filter(|w| w[0] == "-p")
+            .map(|w| w[1].as_str())
+            .collect();
+        assert_eq!(published, vec!["8080:80", "5432:5432"]);
+        assert!(
+            args.iter().position(|a| a == "-p").unwrap()
+                < args.iter().position(|a| a == "alpine:latest").unwrap()
+        );
+    }
+
     #[test]
     fn test_build_run_args_basic() {
         let args = build_run_args(&DockerRunConfig {
@@ -573,6 +2029,38 @@ mod tests {
         assert_eq!(args[last_two[1] + 1], "BAZ");
     }
 
+    #[test]
+    fn test_mask_env_values() {
+        let args: Vec<String> = [
+            "run",
+            "-e",
+            "API_KEY=secret",
+            "--env",
+            "A=b=c",
+            "-e",
+            "PATH",
+            "img",
+            "X=1",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            mask_env_values(&args),
+            vec![
+                "run",
+                "-e",
+                "API_KEY=***",
+                "--env",
+                "A=***",
+                "-e",
+                "PATH",
+                "img",
+                "X=1"
+            ]
+        );
+    }
+
     #[test]
     fn test_build_run_args_empty_env() {
         let args = build_run_args(&default_config()).unwrap();
@@ -609,4 +2097,160 @@ mod tests {
         assert_eq!(args[image_pos + 1], "sleep");
         assert_eq!(args[image_pos + 2], "60");
     }
+
+    #[test]
+    fn test_ensure_workspace_branch_creates_and_reuses_branch() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&project)
+                .args(args)
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        git(&[
+            "-c",
+            "user.name=T",
+            "-c",
+            "user.email=t@e",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ]);
+        let home = tmp.path().join("home");
+        let home = home.to_str().unwrap();
+        let project_dir = project.to_str().unwrap();
+
+        let dir = ensure_workspace_branch(home, "feat", project_dir, None, true).unwrap();
+        let head = Command::new("git")
+            .args(["-C", &dir, "symbolic-ref", "--short", "HEAD"])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&head.stdout).trim(), "box/feat");
+        assert!(branch_exists(&["-C", project_dir], "box/feat"));
+
+        // A kept branch is checked out again instead of failing on `-b`.
+        git(&["worktree", "remove", "--force", &dir]);
+        ensure_workspace_branch(home, "feat", project_dir, None, true).unwrap();
+        assert!(Path::new(&dir).join(".git").exists());
+    }
+
+    #[test]
+    fn test_ensure_workspace_from_ref() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let git = |dir: &Path, args: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["-c", "user.name=T", "-c", "user.email=t@e"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {:?} failed", args);
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        git(&project, &["init", "-q"]);
+        git(&project, &["commit", "-q", "--allow-empty", "-m", "one"]);
+        git(&project, &["tag", "v1"]);
+        let v1 = git(&project, &["rev-parse", "HEAD"]);
+        git(&project, &["commit", "-q", "--allow-empty", "-m", "two"]);
+        let home = tmp.path().join("home");
+        let home = home.to_str().unwrap();
+        let project_dir = project.to_str().unwrap();
+
+        assert!(crate::git::ref_exists(&project, "v1"));
+        assert!(!crate::git::ref_exists(&project, "no-such-ref"));
+
+        for strategy in ["clone", "worktree", "branch"] {
+            let dir = ensure_workspace_with(
+                home,
+                strategy,
+                project_dir,
+                strategy,
+                Some("v1"),
+                None,
+                true,
+            )
+            .unwrap();
+            assert_eq!(
+                git(Path::new(&dir), &["rev-parse", "HEAD"]),
+                v1,
+                "{}",
+                strategy
+            );
+        }
+
+        // The project's checked-out branch works too, and commits in the
+        // workspace leave it alone
+        let branch = git(&project, &["symbolic-ref", "--short", "HEAD"]);
+        let two = git(&project, &["rev-parse", "HEAD"]);
+        for strategy in ["clone", "worktree", "branch"] {
+            let name = format!("{}-branch", strategy);
+            let dir = ensure_workspace_with(
+                home,
+                &name,
+                project_dir,
+                strategy,
+                Some(&branch),
+                None,
+                true,
+            )
+            .unwrap();
+            assert_eq!(
+                git(Path::new(&dir), &["rev-parse", "HEAD"]),
+                two,
+                "{}",
+                strategy
+            );
+            git(
+                Path::new(&dir),
+                &["commit", "-q", "--allow-empty", "-m", "ws"],
+            );
+            assert_eq!(git(&project, &["rev-parse", &branch]), two, "{}", strategy);
+        }
+        assert!(ensure_workspace_with(
+            home,
+            "bad",
+            project_dir,
+            "clone",
+            Some("no-such-ref"),
+            None,
+            true
+        )
+        .is_err());
+        assert!(!config::workspaces_dir_in(home).join("bad").exists());
+
+        let dir = ensure_workspace_with(home, "shallow", project_dir, "clone", None, Some(1), true)
+            .unwrap();
+        assert_eq!(git(Path::new(&dir), &["rev-list", "--count", "HEAD"]), "1");
+        assert_eq!(
+            git(Path::new(&dir), &["rev-parse", "--is-shallow-repository"]),
+            "true"
+        );
+
+        // A commit below the shallow history is fetched on its own
+        let one = git(&project, &["rev-parse", "HEAD~1"]);
+        let dir = ensure_workspace_with(
+            home,
+            "shallow-old",
+            project_dir,
+            "clone",
+            Some(&one),
+            Some(1),
+            true,
+        )
+        .unwrap();
+        assert_eq!(git(Path::new(&dir), &["rev-parse", "HEAD"]), one);
+    }
 }
@@ -1,4 +1,6 @@
+use anyhow::{bail, Result};
 use std::path::Path;
+use std::process::Command;
 
 pub fn is_repo(dir: &Path) -> bool {
     dir.join(".git").exists()
@@ -18,6 +20,451 @@ pub fn find_root(dir: &Path) -> Option<&Path> {
     }
 }
 
+/// Whether the worktree checked out at `dir` still points at an existing
+/// administrative directory in its repository. Directories that aren't
+/// worktrees (a `.git` directory, or no `.git` at all) count as intact.
+pub fn worktree_link_ok(dir: &Path) -> bool {
+    let git = dir.join(".git");
+    if !git.is_file() {
+        return true;
+    }
+    let Ok(contents) = std::fs::read_to_string(&git) else {
+        return false;
+    };
+    match contents.trim().strip_prefix("gitdir:") {
+        Some(target) => dir.join(target.trim()).is_dir(),
+        None => false,
+    }
+}
+
+/// Whether `rev` names a commit in the repository at `dir` (a branch, tag or
+/// commit id, as accepted by `git rev-parse`).
+pub fn ref_exists(dir: &Path, rev: &str) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--verify", "--quiet", "--end-of-options"])
+        .arg(format!("{}^{{commit}}", rev))
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Whether the working tree at `dir` has uncommitted or untracked changes.
+/// Returns false if `git status` cannot be run.
+pub fn has_uncommitted_changes(dir: &Path) -> bool {
+    std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .map(|o| o.status.success() && !o.stdout.is_empty())
+        .unwrap_or(false)
+}
+
+/// Run git in `dir` and return trimmed stdout, failing with `what` on error.
+fn git_output(dir: &Path, args: &[&str], envs: &[(&str, &Path)], what: &str) -> Result<String> {
+    Ok(git_stdout(dir, args, envs, what)?.trim().to_string())
+}
+
+/// Like [`git_output`], but with stdout untouched, for output whose leading
+/// whitespace matters.
+fn git_stdout(dir: &Path, args: &[&str], envs: &[(&str, &Path)], what: &str) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .envs(envs.iter().copied())
+        .stderr(std::process::Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "{} failed: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A commit holding the current state of `src`, including uncommitted and
+/// untracked changes: HEAD itself when the tree is clean, otherwise a snapshot
+/// commit on top of it built from a temporary index, so `src`'s working tree,
+/// index and HEAD are left untouched.
+pub fn snapshot_commit(src: &Path) -> Result<String> {
+    let head = git_output(src, &["rev-parse", "HEAD"], &[], "git rev-parse")?;
+    if !has_uncommitted_changes(src) {
+        return Ok(head);
+    }
+    let tree = snapshot_tree(src)?;
+    git_output(
+        src,
+        &[
+            "commit-tree",
+            &tree,
+            "-p",
+            &head,
+            "-m",
+            "box fork: snapshot of uncommitted changes",
+        ],
+        &[],
+        "git commit-tree",
+    )
+}
+
+/// Create a new worktree at `dst` on a new `branch`, starting from the
+/// current state of `src` (see `snapshot_commit`).
+pub fn fork_workspace(src: &Path, dst: &Path, branch: &str) -> Result<()> {
+    let start = snapshot_commit(src)?;
+    let dst = dst.to_string_lossy();
+    git_output(
+        src,
+        &["worktree", "add", "-b", branch, &dst, &start],
+        &[],
+        "git worktree add",
+    )?;
+    Ok(())
+}
+
+/// Rename branch `old` to `new` in the repository at `dir`.
+pub fn rename_branch(dir: &Path, old: &str, new: &str) -> Result<()> {
+    git_output(dir, &["branch", "-m", old, new], &[], "git branch -m")?;
+    Ok(())
+}
+
+/// Fetch `commit` from the repository at `src` into the one at `dst`. The
+/// commit may be unreferenced (e.g. from `snapshot_commit`), so it is
+/// fetched through a temporary ref in `src`.
+pub fn fetch_commit(dst: &Path, src: &Path, commit: &str) -> Result<()> {
+    let tmp_ref = format!("refs/box-fork/{}", std::process::id());
+    git_output(
+        src,
+        &["update-ref", &tmp_ref, commit],
+        &[],
+        "git update-ref",
+    )?;
+    let src_path = src.to_string_lossy();
+    let fetched = git_output(
+        dst,
+        &["fetch", "--quiet", "--no-tags", &src_path, &tmp_ref],
+        &[],
+        "git fetch",
+    );
+    let _ = git_output(src, &["update-ref", "-d", &tmp_ref], &[], "git update-ref");
+    fetched.map(|_| ())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitStatus {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+}
+
+impl GitStatus {
+    /// Single-letter indicator as shown by `git status --short`.
+    pub fn indicator(self) -> char {
+        match self {
+            GitStatus::Modified => 'M',
+            GitStatus::Added => 'A',
+            GitStatus::Deleted => 'D',
+            GitStatus::Renamed => 'R',
+            GitStatus::Untracked => '?',
+        }
+    }
+
+    fn from_code(code: char) -> Option<GitStatus> {
+        match code {
+            'M' | 'T' | 'U' => Some(GitStatus::Modified),
+            'A' | 'C' => Some(GitStatus::Added),
+            'D' => Some(GitStatus::Deleted),
+            'R' => Some(GitStatus::Renamed),
+            '?' => Some(GitStatus::Untracked),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitChange {
+    pub status: GitStatus,
+    pub path: String,
+}
+
+/// Files changed in `workspace`: uncommitted changes when `since` is None,
+/// otherwise changes relative to commit `since` (committed or not).
+/// Untracked files are included in both cases.
+pub fn changed_files(workspace: &Path, since: Option<&str>) -> Result<Vec<GitChange>> {
+    let Some(since) = since else {
+        // The first status column is a space for unstaged changes
+        let out = git_stdout(
+            workspace,
+            &["status", "--porcelain", "--untracked-files=all"],
+            &[],
+            "git status",
+        )?;
+        return Ok(parse_porcelain(&out));
+    };
+    let diff = git_output(
+        workspace,
+        &["diff", "--name-status", "-M", since, "--"],
+        &[],
+        "git diff",
+    )?;
+    let untracked = git_output(
+        workspace,
+        &["ls-files", "--others", "--exclude-standard"],
+        &[],
+        "git ls-files",
+    )?;
+    let mut changes = parse_name_status(&diff);
+    changes.extend(
+        untracked
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|path| GitChange {
+                status: GitStatus::Untracked,
+                path: path.to_string(),
+            }),
+    );
+    Ok(changes)
+}
+
+/// `git diff --stat` of `workspace` against `since` (default `HEAD`).
+pub fn diff_stat(workspace: &Path, since: Option<&str>) -> Result<String> {
+    // Keep the indentation of the first file line
+    let out = git_stdout(
+        workspace,
+        &["diff", "--stat", since.unwrap_or("HEAD"), "--"],
+        &[],
+        "git diff",
+    )?;
+    Ok(out.trim_end().to_string())
+}
+
+/// Parse `git status --porcelain` (v1) lines. The worktree status takes
+/// precedence unless it is unchanged; renames are shown as `old -> new`.
+fn parse_porcelain(output: &str) -> Vec<GitChange> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut codes = line.chars();
+            let (x, y) = (codes.next()?, codes.next()?);
+            let path = line.get(3..)?.to_string();
+            let status = GitStatus::from_code(if y == ' ' { x } else { y })
+                .or_else(|| GitStatus::from_code(x))?;
+            Some(GitChange { status, path })
+        })
+        .collect()
+}
+
+/// Parse `git diff --name-status` lines (`M\tpath`, `R100\told\tnew`).
+fn parse_name_status(output: &str) -> Vec<GitChange> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let status = GitStatus::from_code(fields.next()?.chars().next()?)?;
+            let paths: Vec<&str> = fields.collect();
+            let path = match paths.as_slice() {
+                [path] => path.to_string(),
+                [from, to] => format!("{} -> {}", from, to),
+                _ => return None,
+            };
+            Some(GitChange { status, path })
+        })
+        .collect()
+}
+
+/// Global git config keys copied by `box create --inherit-git-config`.
+pub const INHERITED_CONFIG_KEYS: &[&str] = &[
+    "user.name",
+    "user.email",
+    "core.autocrlf",
+    "init.defaultBranch",
+];
+
+/// Copy `keys` from the global git config into `workspace`'s local config.
+/// Keys that are not set globally are skipped. A worktree workspace shares
+/// its config with the project, so the keys go into its per-worktree config
+/// (enabling `extensions.worktreeConfig`) instead.
+pub fn inherit_config(workspace: &Path, keys: &[&str]) -> Result<()> {
+    inherit_config_with_env(workspace, keys, &[])
+}
+
+fn inherit_config_with_env(workspace: &Path, keys: &[&str], envs: &[(&str, &Path)]) -> Result<()> {
+    let scope = if workspace.join(".git").is_file() {
+        git_output(
+            workspace,
+            &["config", "--local", "extensions.worktreeConfig", "true"],
+            envs,
+            "git config",
+        )?;
+        "--worktree"
+    } else {
+        "--local"
+    };
+    for key in keys {
+        let output = Command::new("git")
+            .args(["config", "--global", "--get", key])
+            .envs(envs.iter().copied())
+            .stderr(std::process::Stdio::null())
+            .output()?;
+        // Exit status 1 means the key is unset
+        if !output.status.success() {
+            continue;
+        }
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        git_output(
+            workspace,
+            &["config", scope, key, &value],
+            envs,
+            "git config",
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a tree object for the working tree of `dir`, including untracked
+/// files, using a temporary index so the real index is left untouched.
+fn snapshot_tree(dir: &Path) -> Result<String> {
+    let tmp = temp_index_path(dir)?;
+    let envs = [("GIT_INDEX_FILE", tmp.as_path())];
+    let tree = (|| -> Result<String> {
+        git_output(dir, &["read-tree", "HEAD"], &envs, "git read-tree")?;
+        git_output(dir, &["add", "-A"], &envs, "git add")?;
+        git_output(dir, &["write-tree"], &envs, "git write-tree")
+    })();
+    let _ = std::fs::remove_file(&tmp);
+    tree
+}
+
+/// Binary patch of all uncommitted changes in `dir` (including untracked
+/// files) relative to HEAD, or None when the working tree is clean.
+pub fn uncommitted_patch(dir: &Path) -> Result<Option<Vec<u8>>> {
+    if !has_uncommitted_changes(dir) {
+        return Ok(None);
+    }
+    let tree = snapshot_tree(dir)?;
+    // Not git_output: binary hunks end with a blank line git apply requires
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["diff", "--binary", "HEAD", &tree])
+        .stderr(std::process::Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(Some(output.stdout))
+}
+
+/// Apply a patch file produced by `uncommitted_patch` to the working tree of `dir`.
+pub fn apply_patch(dir: &Path, patch: &Path) -> Result<()> {
+    let patch = patch.to_string_lossy();
+    git_output(
+        dir,
+        &["apply", "--whitespace=nowarn", &patch],
+        &[],
+        "git apply",
+    )?;
+    Ok(())
+}
+
+/// The commit checked out at `dir`, if any.
+pub fn head_commit(dir: &Path) -> Option<String> {
+    git_output(
+        dir,
+        &["rev-parse", "--verify", "HEAD"],
+        &[],
+        "git rev-parse",
+    )
+    .ok()
+}
+
+/// The branch checked out at `dir`, or None on a detached HEAD.
+pub fn current_branch(dir: &Path) -> Option<String> {
+    git_output(
+        dir,
+        &["symbolic-ref", "--quiet", "--short", "HEAD"],
+        &[],
+        "git symbolic-ref",
+    )
+    .ok()
+}
+
+/// Commits in the workspace's history that exist nowhere else: not on any
+/// branch, tag or remote-tracking branch of `project_dir` (which a worktree
+/// shares), or, for a clone that has commits the project lacks, not on any of
+/// the clone's remote-tracking branches or tags. Recreating the workspace
+/// would lose them.
+pub fn unsaved_commits(workspace: &Path, project_dir: &Path) -> Result<Vec<String>> {
+    let Some(head) = head_commit(workspace) else {
+        return Ok(Vec::new());
+    };
+    let output = if ref_exists(project_dir, &head) {
+        git_output(
+            project_dir,
+            &[
+                "rev-list",
+                &head,
+                "--not",
+                "--branches",
+                "--tags",
+                "--remotes",
+            ],
+            &[],
+            "git rev-list",
+        )?
+    } else {
+        git_output(
+            workspace,
+            &["rev-list", "HEAD", "--not", "--tags", "--remotes"],
+            &[],
+            "git rev-list",
+        )?
+    };
+    Ok(output.lines().map(String::from).collect())
+}
+
+/// Check out `commit` at `dir`: on `branch` (created or reset to the commit)
+/// if given, detached otherwise.
+pub fn checkout_commit(dir: &Path, commit: &str, branch: Option<&str>) -> Result<()> {
+    match branch {
+        Some(branch) => git_output(
+            dir,
+            &["checkout", "-q", "-B", branch, commit],
+            &[],
+            "git checkout",
+        )?,
+        None => git_output(
+            dir,
+            &["checkout", "-q", "--detach", commit],
+            &[],
+            "git checkout",
+        )?,
+    };
+    Ok(())
+}
+
+/// Path for a throwaway index file inside the repository's git directory.
+fn temp_index_path(dir: &Path) -> Result<std::path::PathBuf> {
+    let git_dir = git_output(
+        dir,
+        &["rev-parse", "--absolute-git-dir"],
+        &[],
+        "git rev-parse",
+    )?;
+    Ok(Path::new(&git_dir).join(format!("box-fork-index-{}", std::process::id())))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -42,6 +489,23 @@ mod tests {
         assert!(is_repo(tmp.path()));
     }
 
+    #[test]
+    fn test_worktree_link_ok() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(worktree_link_ok(tmp.path()));
+
+        let admin = tmp.path().join("repo/.git/worktrees/ws");
+        std::fs::create_dir_all(&admin).unwrap();
+        let ws = tmp.path().join("ws");
+        std::fs::create_dir(&ws).unwrap();
+        std::fs::write(ws.join(".git"), format!("gitdir: {}\n", admin.display())).unwrap();
+        assert!(worktree_link_ok(&ws));
+
+        // Re-initializing the repository drops its worktree entries
+        std::fs::remove_dir_all(tmp.path().join("repo")).unwrap();
+        assert!(!worktree_link_ok(&ws));
+    }
+
     #[test]
     fn test_is_repo_false() {
         let tmp = tempfile::tempdir().unwrap();
@@ -79,6 +543,348 @@ mod tests {
         assert_eq!(find_root(&sub), Some(tmp.path()));
     }
 
+    #[test]
+    fn test_has_uncommitted_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::process::Command::new("git")
+            .args(["init", tmp.path().to_str().unwrap()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!has_uncommitted_changes(tmp.path()));
+        std::fs::write(tmp.path().join("new.txt"), "x").unwrap();
+        assert!(has_uncommitted_changes(tmp.path()));
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_fork_workspace_carries_uncommitted_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        git(&src, &["init", "-q"]);
+        git(&src, &["config", "user.name", "Test"]);
+        git(&src, &["config", "user.email", "test@example.com"]);
+        std::fs::write(src.join("a.txt"), "one").unwrap();
+        git(&src, &["add", "a.txt"]);
+        git(&src, &["commit", "-q", "-m", "init"]);
+        std::fs::write(src.join("a.txt"), "two").unwrap();
+        std::fs::write(src.join("b.txt"), "new").unwrap();
+
+        let dst = tmp.path().join("dst");
+        fork_workspace(&src, &dst, "box-fork/test").unwrap();
+
+        assert_eq!(std::fs::read_to_string(dst.join("a.txt")).unwrap(), "two");
+        assert_eq!(std::fs::read_to_string(dst.join("b.txt")).unwrap(), "new");
+        assert!(!has_uncommitted_changes(&dst));
+        // Source keeps its pending changes
+        assert!(has_uncommitted_changes(&src));
+        assert_eq!(std::fs::read_to_string(src.join("a.txt")).unwrap(), "two");
+    }
+
+    #[test]
+    fn test_unsaved_commits() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        git(&project, &["init", "-q"]);
+        git(&project, &["config", "user.name", "Test"]);
+        git(&project, &["config", "user.email", "test@example.com"]);
+        git(&project, &["commit", "-q", "--allow-empty", "-m", "init"]);
+
+        // A detached worktree shares the project's objects
+        let worktree = tmp.path().join("worktree");
+        git(
+            &project,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "--detach",
+                worktree.to_str().unwrap(),
+            ],
+        );
+        assert!(unsaved_commits(&worktree, &project).unwrap().is_empty());
+        git(&worktree, &["commit", "-q", "--allow-empty", "-m", "wip"]);
+        assert_eq!(unsaved_commits(&worktree, &project).unwrap().len(), 1);
+
+        // A clone's new commits are only in the clone
+        let clone = tmp.path().join("clone");
+        git(
+            tmp.path(),
+            &["clone", "-q", project.to_str().unwrap(), "clone"],
+        );
+        git(&clone, &["config", "user.name", "Test"]);
+        git(&clone, &["config", "user.email", "test@example.com"]);
+        assert!(unsaved_commits(&clone, &project).unwrap().is_empty());
+        git(&clone, &["commit", "-q", "--allow-empty", "-m", "one"]);
+        git(&clone, &["commit", "-q", "--allow-empty", "-m", "two"]);
+        assert_eq!(unsaved_commits(&clone, &project).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_fork_workspace_clean_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        git(&src, &["init", "-q"]);
+        git(&src, &["config", "user.name", "Test"]);
+        git(&src, &["config", "user.email", "test@example.com"]);
+        std::fs::write(src.join("a.txt"), "one").unwrap();
+        git(&src, &["add", "a.txt"]);
+        git(&src, &["commit", "-q", "-m", "init"]);
+
+        let dst = tmp.path().join("dst");
+        fork_workspace(&src, &dst, "box-fork/clean").unwrap();
+        assert_eq!(std::fs::read_to_string(dst.join("a.txt")).unwrap(), "one");
+    }
+
+    #[test]
+    fn test_snapshot_commit_carries_uncommitted_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        git(&src, &["init", "-q"]);
+        git(&src, &["config", "user.name", "Test"]);
+        git(&src, &["config", "user.email", "test@example.com"]);
+        std::fs::write(src.join("a.txt"), "one").unwrap();
+        git(&src, &["add", "a.txt"]);
+        git(&src, &["commit", "-q", "-m", "init"]);
+        let head = head_commit(&src).unwrap();
+        std::fs::write(src.join("a.txt"), "two").unwrap();
+        std::fs::write(src.join("b.txt"), "new").unwrap();
+
+        let snapshot = snapshot_commit(&src).unwrap();
+        assert_ne!(snapshot, head);
+        let dst = tmp.path().join("dst");
+        git(tmp.path(), &["clone", "-q", src.to_str().unwrap(), "dst"]);
+        fetch_commit(&dst, &src, &snapshot).unwrap();
+        checkout_commit(&dst, &snapshot, Some("box-fork/test")).unwrap();
+
+        assert_eq!(std::fs::read_to_string(dst.join("a.txt")).unwrap(), "two");
+        assert_eq!(std::fs::read_to_string(dst.join("b.txt")).unwrap(), "new");
+        assert!(!has_uncommitted_changes(&dst));
+        assert_eq!(current_branch(&dst).as_deref(), Some("box-fork/test"));
+        // Source keeps its pending changes and no temporary ref
+        assert!(has_uncommitted_changes(&src));
+        assert_eq!(std::fs::read_to_string(src.join("a.txt")).unwrap(), "two");
+        assert_eq!(head_commit(&src).as_deref(), Some(head.as_str()));
+        let refs = git_output(&src, &["for-each-ref"], &[], "git for-each-ref").unwrap();
+        assert!(!refs.contains("box-fork"));
+    }
+
+    #[test]
+    fn test_snapshot_commit_clean_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        git(&src, &["init", "-q"]);
+        git(&src, &["config", "user.name", "Test"]);
+        git(&src, &["config", "user.email", "test@example.com"]);
+        std::fs::write(src.join("a.txt"), "one").unwrap();
+        git(&src, &["add", "a.txt"]);
+        git(&src, &["commit", "-q", "-m", "init"]);
+
+        assert_eq!(snapshot_commit(&src).ok(), head_commit(&src));
+    }
+
+    #[test]
+    fn test_inherit_config_copies_set_keys() {
+        let tmp = tempfile::tempdir().unwrap();
+        let global = tmp.path().join("gitconfig");
+        std::fs::write(
+            &global,
+            "[user]\n\tname = Test User\n\temail = test@example.com\n",
+        )
+        .unwrap();
+        let ws = tmp.path().join("ws");
+        std::fs::create_dir_all(&ws).unwrap();
+        git(&ws, &["init", "-q"]);
+
+        let envs = [("GIT_CONFIG_GLOBAL", global.as_path())];
+        inherit_config_with_env(&ws, INHERITED_CONFIG_KEYS, &envs).unwrap();
+
+        let local = std::fs::read_to_string(ws.join(".git").join("config")).unwrap();
+        assert!(local.contains("name = Test User"));
+        assert!(local.contains("email = test@example.com"));
+        assert!(!local.contains("autocrlf"));
+        assert!(!local.contains("defaultBranch"));
+    }
+
+    #[test]
+    fn test_inherit_config_worktree_leaves_project_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let global = tmp.path().join("gitconfig");
+        std::fs::write(&global, "[user]\n\tname = Test User\n").unwrap();
+        let project = tmp.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        git(&project, &["init", "-q"]);
+        let identity = ["-c", "user.name=a", "-c", "user.email=a@example.com"];
+        git(
+            &project,
+            &[
+                &identity[..],
+                &["commit", "-q", "--allow-empty", "-m", "init"],
+            ]
+            .concat(),
+        );
+        let ws = tmp.path().join("ws");
+        git(
+            &project,
+            &["worktree", "add", "-q", "--detach", &ws.to_string_lossy()],
+        );
+
+        let envs = [("GIT_CONFIG_GLOBAL", global.as_path())];
+        inherit_config_with_env(&ws, &["user.name"], &envs).unwrap();
+
+        let project_config = std::fs::read_to_string(project.join(".git").join("config")).unwrap();
+        assert!(!project_config.contains("Test User"));
+        let out = Command::new("git")
+            .args([
+                "-C",
+                &ws.to_string_lossy(),
+                "config",
+                "--worktree",
+                "user.name",
+            ])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "Test User");
+    }
+
+    #[test]
+    fn test_inherit_config_requires_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let global = tmp.path().join("gitconfig");
+        std::fs::write(&global, "[user]\n\tname = Test User\n").unwrap();
+        let envs = [("GIT_CONFIG_GLOBAL", global.as_path())];
+        assert!(inherit_config_with_env(tmp.path(), &["user.name"], &envs).is_err());
+    }
+
+    #[test]
+    fn test_parse_porcelain() {
+        let out = " M src/main.rs\nA  new.rs\n D gone.rs\nR  old.rs -> renamed.rs\n?? notes.txt\nMM both.rs\n";
+        let changes = parse_porcelain(out);
+        let got: Vec<(char, &str)> = changes
+            .iter()
+            .map(|c| (c.status.indicator(), c.path.as_str()))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ('M', "src/main.rs"),
+                ('A', "new.rs"),
+                ('D', "gone.rs"),
+                ('R', "old.rs -> renamed.rs"),
+                ('?', "notes.txt"),
+                ('M', "both.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_name_status() {
+        let changes = parse_name_status("M\ta.rs\nR100\told.rs\tnew.rs\nD\tb.rs\n");
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[1].status, GitStatus::Renamed);
+        assert_eq!(changes[1].path, "old.rs -> new.rs");
+        assert_eq!(changes[2].status, GitStatus::Deleted);
+    }
+
+    #[test]
+    fn test_changed_files_since_commit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws = tmp.path();
+        git(ws, &["init", "-q"]);
+        git(ws, &["config", "user.name", "Test"]);
+        git(ws, &["config", "user.email", "test@example.com"]);
+        std::fs::write(ws.join("a.txt"), "one").unwrap();
+        git(ws, &["add", "a.txt"]);
+        git(ws, &["commit", "-q", "-m", "init"]);
+        let base = git_output(ws, &["rev-parse", "HEAD"], &[], "git rev-parse").unwrap();
+
+        std::fs::write(ws.join("b.txt"), "two").unwrap();
+        git(ws, &["add", "b.txt"]);
+        git(ws, &["commit", "-q", "-m", "second"]);
+        std::fs::write(ws.join("a.txt"), "changed").unwrap();
+        std::fs::write(ws.join("c.txt"), "untracked").unwrap();
+
+        let uncommitted = changed_files(ws, None).unwrap();
+        assert_eq!(
+            uncommitted,
+            vec![
+                GitChange {
+                    status: GitStatus::Modified,
+                    path: "a.txt".to_string()
+                },
+                GitChange {
+                    status: GitStatus::Untracked,
+                    path: "c.txt".to_string()
+                },
+            ]
+        );
+
+        let since = changed_files(ws, Some(&base)).unwrap();
+        let got: Vec<(char, &str)> = since
+            .iter()
+            .map(|c| (c.status.indicator(), c.path.as_str()))
+            .collect();
+        assert_eq!(got, vec![('M', "a.txt"), ('A', "b.txt"), ('?', "c.txt")]);
+
+        let stat = diff_stat(ws, Some(&base)).unwrap();
+        assert!(stat.starts_with(" a.txt"));
+        assert!(stat.contains("2 files changed"));
+        assert!(changed_files(ws, Some("not-a-commit")).is_err());
+    }
+
+    #[test]
+    fn test_uncommitted_patch_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        git(&src, &["init", "-q"]);
+        git(&src, &["config", "user.name", "Test"]);
+        git(&src, &["config", "user.email", "test@example.com"]);
+        std::fs::write(src.join("a.txt"), "one\n").unwrap();
+        git(&src, &["add", "a.txt"]);
+        git(&src, &["commit", "-q", "-m", "init"]);
+        assert!(uncommitted_patch(&src).unwrap().is_none());
+
+        std::fs::write(src.join("a.txt"), "two\n").unwrap();
+        std::fs::write(src.join("b.bin"), [0u8, 159, 146, 150]).unwrap();
+        let patch = uncommitted_patch(&src).unwrap().unwrap();
+        // The real index is untouched
+        assert!(git_output(&src, &["diff", "--cached"], &[], "git diff")
+            .unwrap()
+            .is_empty());
+
+        let dst = tmp.path().join("dst");
+        git(
+            tmp.path(),
+            &["clone", "-q", src.to_str().unwrap(), dst.to_str().unwrap()],
+        );
+        let patch_file = tmp.path().join("changes.patch");
+        std::fs::write(&patch_file, patch).unwrap();
+        apply_patch(&dst, &patch_file).unwrap();
+        assert_eq!(std::fs::read_to_string(dst.join("a.txt")).unwrap(), "two\n");
+        assert_eq!(
+            std::fs::read(dst.join("b.bin")).unwrap(),
+            [0u8, 159, 146, 150]
+        );
+    }
+
     #[test]
     fn test_find_root_no_repo() {
         let tmp = tempfile::tempdir().unwrap();
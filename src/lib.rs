@@ -0,0 +1,19 @@
+//! Session model of `box`, for tools built on top of it.
+//!
+//! The stable surface is:
+//!
+//! - [`session`]: [`session::list`], [`session::load`], [`session::Session`],
+//!   [`session::SessionSummary`] and the name helpers [`session::parse_name`],
+//!   [`session::full_name`], [`session::workspace_name`] and
+//!   [`session::validate_name`].
+//! - [`config`]: [`config::resolve`], [`config::BoxConfig`] and
+//!   [`config::home_dir`].
+//!
+//! Other public items of these modules exist for the `box` binary and may
+//! change between releases. Docker and the terminal multiplexer stay private
+//! to the binary.
+
+pub mod config;
+#[doc(hidden)]
+pub mod git;
+pub mod session;
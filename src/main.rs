@@ -1,21 +1,22 @@
-mod config;
 mod docker;
-mod git;
 mod mux;
-mod session;
+mod process;
 mod tui;
 
+use box_cli::{config, git, session};
+
 use anyhow::{bail, Result};
 use clap::{Parser, Subcommand};
 use std::fs;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::Path;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 #[derive(Parser)]
 #[command(
     name = "box",
     about = "Sandboxed Docker environments for git repos (supports --local mode)",
-    after_help = "Examples:\n  box                                         # interactive session manager\n  box create my-feature                        # create a new session\n  box create my-feature --image ubuntu -- bash # create with options\n  box create my-feature --local                # create a local session (no Docker)\n  box resume my-feature                        # resume a session\n  box resume my-feature -d                     # resume in background\n  box stop my-feature                          # stop a running session\n  box exec my-feature -- ls -la                # run a command in a session\n  box list                                     # list all sessions\n  box list -q --running                        # names of running sessions\n  box remove my-feature                        # remove a session\n  box cd my-feature                            # print project directory\n  box path my-feature                          # print workspace path\n  box origin                                   # cd back to origin project from workspace\n  box upgrade                                  # self-update"
+    after_help = "Examples:\n  box                                         # interactive session manager\n  box create my-feature                        # create a new session\n  box create my-feature --image ubuntu -- bash # create with options\n  box create my-feature --local                # create a local session (no Docker)\n  box resume my-feature                        # resume a session\n  box resume my-feature -d                     # resume in background\n  box stop my-feature                          # stop a running session\n  box exec my-feature -- ls -la                # run a command in a session\n  box list                                     # list all sessions\n  box list -q --running                        # names of running sessions\n  box remove my-feature                        # remove a session\n  box cd my-feature                            # print project directory\n  box path my-feature                          # print workspace path\n  box origin                                   # cd back to origin project from workspace\n  box upgrade                                  # self-update\n\nExit status:\n  Attaching to a session exits with the command's exit code, or 100 when you detach."
 )]
 struct Cli {
     #[command(subcommand)]
@@ -25,30 +26,270 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Create a new session
-    Create(CreateArgs),
+    Create(Box<CreateArgs>),
     /// Resume an existing session
     Resume(ResumeArgs),
     /// Remove a session (must be stopped first)
     Remove(RemoveArgs),
     /// Stop a running session
     Stop(StopArgs),
+    /// Block until a session's command exits and return its exit code
+    Wait {
+        /// Session name
+        name: String,
+    },
+    /// Remove all stopped sessions
+    Prune {
+        /// Print the sessions that would be removed without removing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Only prune sessions of the current project directory
+        #[arg(long, short)]
+        project: bool,
+        /// Remove without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Also remove workspaces with uncommitted changes or unpushed commits
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Move a stopped session out of `box list` into the archive
+    Archive {
+        /// Session name
+        name: String,
+    },
+    /// Restore an archived session
+    Unarchive {
+        /// Session name
+        name: String,
+    },
     /// Run a command in a running session
     Exec(ExecArgs),
     /// List sessions
     #[command(alias = "ls")]
     List(ListArgs),
+    /// Show all details of a session
+    Info {
+        /// Session name
+        name: String,
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Print the host project directory for a session
     Cd {
         /// Session name
         name: String,
+        /// Use the original project directory instead of the workspace
+        #[arg(long)]
+        origin: bool,
     },
     /// Print workspace path for a session
     Path {
         /// Session name
         name: String,
+        /// Print the original project directory instead of the workspace
+        #[arg(long)]
+        origin: bool,
     },
     /// Navigate back to the original project directory from a workspace
     Origin,
+    /// Show the environment a session's command will run with
+    ShowEnv {
+        /// Session name
+        name: String,
+    },
+    /// Print connection details for a debugger listening inside a session.
+    /// When a Docker session's port is unpublished and only open on the
+    /// container's loopback, it is tunnelled to the container's IP with socat
+    ConnectToDebugger {
+        /// Session name
+        name: String,
+        /// Port the debug adapter listens on
+        #[arg(long)]
+        port: u16,
+        /// VS Code debug type of the launch.json entry
+        #[arg(long = "type", default_value = "debugpy", value_parser = clap::builder::PossibleValuesParser::new(DEBUG_TYPES))]
+        debug_type: String,
+    },
+    /// Record a Docker session's environment and package list as its baseline
+    EnvironmentBaseline {
+        /// Session name
+        name: String,
+    },
+    /// Compare a Docker session's environment against its recorded baseline
+    EnvironmentDrift {
+        /// Session name
+        name: String,
+        /// Replace the baseline with the current environment
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Change the workspace strategy (clone, worktree or branch) of a stopped session's workspace
+    SetStrategy {
+        /// Session name
+        name: String,
+        /// New workspace strategy: clone, worktree or branch
+        strategy: String,
+        /// Discard uncommitted changes and unpushed commits in the workspace
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Recover a session whose server crashed or whose container stopped unexpectedly
+    Recover {
+        /// Session name
+        name: String,
+    },
+    /// Delete and recreate a workspace from its git repository, restarting running sessions
+    RebuildWorkspace {
+        /// Workspace name (or any session in it)
+        name: String,
+        /// Carry uncommitted changes over to the rebuilt workspace
+        #[arg(long, conflicts_with = "force")]
+        keep_changes: bool,
+        /// Discard uncommitted changes and commits not in the project or a remote
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Fork a session's workspace (including uncommitted changes) into a new session
+    Fork {
+        /// Source session name
+        name: String,
+        /// New session name (must be in a new workspace)
+        new_name: String,
+    },
+    /// Create a new session with the image, command, env and strategy of an existing one
+    Duplicate {
+        /// Source session name
+        src: String,
+        /// New session name (a bare workspace name takes the session name from the command)
+        dest: String,
+        /// Run in the background
+        #[arg(short, long)]
+        detach: bool,
+    },
+    /// Show a Docker session's added, dropped and effective Linux capabilities
+    ListCapabilities {
+        /// Session name
+        name: String,
+    },
+    /// Print a session's recent terminal output without attaching
+    TailOutput {
+        /// Session name
+        name: String,
+        /// Number of lines to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+        /// Keep printing new output as it arrives
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Print a session's mux server log (local) or container logs (Docker)
+    Logs {
+        /// Session name
+        name: String,
+        /// Keep printing new output as it is written
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Rename a stopped session (workspace/old to new) or a whole workspace
+    Rename {
+        /// Current session or workspace name
+        old: String,
+        /// New name
+        new: String,
+        /// Rename a workspace even though its sessions' containers must be removed
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Move a stopped session out of its workspace into a standalone workspace
+    Promote {
+        /// Session name (workspace/session)
+        name: String,
+    },
+    /// Show metadata about the Docker image a session uses
+    DescribeImage {
+        /// Session name
+        name: String,
+        /// Also show the image build history
+        #[arg(long)]
+        history: bool,
+    },
+    /// Check whether a Docker session's image has a newer version in its registry
+    CheckImageUpdates {
+        /// Session name
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+        /// Check every Docker session
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show files changed in a session's workspace
+    ListChanges {
+        /// Session name
+        name: String,
+        /// Show a diffstat instead of the file list
+        #[arg(long)]
+        stat: bool,
+        /// Show changes since this commit instead of uncommitted changes
+        #[arg(long, value_name = "SHA")]
+        since_commit: Option<String>,
+    },
+    /// Show a Docker session's cgroup resource limits and ulimits
+    ShowResourceLimits {
+        /// Session name
+        name: String,
+    },
+    /// List the processes running in a session
+    ListProcesses {
+        /// Session name
+        name: String,
+        /// Sort by cpu, mem or pid
+        #[arg(long, default_value = "pid", value_parser = ["cpu", "mem", "pid"])]
+        sort_by: String,
+        /// Refresh every 2 seconds
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Wait until a port inside a session accepts TCP connections
+    WaitForPort {
+        /// Session name
+        name: String,
+        /// Port to probe
+        port: u16,
+        /// Seconds to wait before giving up
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+        /// Text shown next to the spinner while waiting
+        #[arg(long)]
+        message: Option<String>,
+    },
+    /// Create several sessions from a TOML manifest of [[sessions]] entries
+    BatchCreate {
+        /// Path to the manifest file
+        file: std::path::PathBuf,
+        /// Keep creating the remaining sessions after a failure
+        #[arg(long)]
+        continue_on_error: bool,
+    },
+    /// Rotate a session's mux server log (server.log)
+    LogRotate {
+        /// Session name
+        name: String,
+    },
+    /// Change the Docker image used when a stopped session's container is recreated
+    SetImage {
+        /// Session name
+        name: String,
+        /// New Docker image
+        image: String,
+    },
+    /// Change the command, image or environment of a stopped session
+    Edit(EditArgs),
+    /// Show a live table of sessions with their state and container CPU and memory use
+    Top,
+    /// Check the setup for common problems (HOME, ~/.box, Docker, git, stale servers)
+    Doctor,
     /// Self-update to the latest version
     Upgrade,
     /// Output shell configuration (e.g. eval "$(box config zsh)")
@@ -56,6 +297,12 @@ enum Commands {
         #[command(subcommand)]
         shell: ConfigShell,
     },
+    /// Print completion candidates for the shell completion scripts
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        #[command(subcommand)]
+        target: CompleteTarget,
+    },
 }
 
 #[derive(clap::Args, Debug)]
@@ -63,7 +310,8 @@ struct CreateArgs {
     /// Session name (omit to open the interactive session manager)
     name: Option<String>,
 
-    /// Run container in the background (detached)
+    /// Run in the background (detached). A local session's command starts in
+    /// its session server without attaching; attach later with `box resume`
     #[arg(short = 'd')]
     detach: bool,
 
@@ -81,20 +329,102 @@ struct CreateArgs {
     local: bool,
 
     /// Create a Docker session (container isolation)
-    #[arg(long)]
+    #[arg(long, conflicts_with = "local")]
     docker: bool,
 
-    /// Workspace strategy: clone (git clone --local) or worktree (git worktree add)
+    /// Workspace strategy: clone (git clone --local), worktree (git worktree add)
+    /// or branch (git worktree add on a new box/<workspace> branch)
     /// Default: $BOX_STRATEGY or "clone"
     #[arg(long)]
     strategy: Option<String>,
 
+    /// Drop all Linux capabilities except CHOWN, DAC_OVERRIDE, FOWNER, SETGID and SETUID.
+    /// Recommended secure default for containers that need no special privileges;
+    /// grant more with --docker-args "--cap-add <CAP>".
+    #[arg(long)]
+    minimal_caps: bool,
+
+    /// Send a desktop notification when the session's command exits (local sessions)
+    #[arg(long)]
+    notify: bool,
+
+    /// Mount all volumes from another Docker session's container
+    #[arg(long = "volumes-from", value_name = "SESSION")]
+    volumes_from: Option<String>,
+
+    /// Mount the host Docker socket into the container (default: /var/run/docker.sock).
+    /// Grants root-equivalent access to the host; use only in trusted environments.
+    #[arg(long = "docker-socket", value_name = "PATH", num_args = 0..=1, default_missing_value = docker::DOCKER_SOCKET_PATH)]
+    docker_socket: Option<String>,
+
+    /// Shorthand for a Docker session with --docker-socket, defaulting the image to docker:cli
+    #[arg(long = "docker-in-docker", conflicts_with_all = ["local", "minimal_caps"])]
+    docker_in_docker: bool,
+
+    /// Copy user.name, user.email, core.autocrlf and init.defaultBranch from the
+    /// global git config into the workspace's git config
+    #[arg(long = "inherit-git-config")]
+    inherit_git_config: bool,
+
+    /// Docker storage driver the session requires (overlay2, devicemapper, btrfs, zfs, aufs).
+    /// Docker uses one driver per daemon, so this must match the daemon's driver.
+    #[arg(long = "storage-driver", value_name = "DRIVER", value_parser = clap::builder::PossibleValuesParser::new(docker::KNOWN_STORAGE_DRIVERS))]
+    storage_driver: Option<String>,
+
+    /// Driver-specific storage option passed to docker run (repeatable, e.g. size=20G)
+    #[arg(long = "storage-opt", value_name = "KEY=VALUE", value_parser = parse_storage_opt)]
+    storage_opt: Vec<String>,
+
+    /// Publish a container port to the host as HOST:CONTAINER (repeatable, e.g. 8080:80)
+    #[arg(long, value_name = "HOST:CONTAINER", value_parser = parse_port_mapping)]
+    port: Vec<String>,
+
+    /// Memory limit for the container (e.g. 512m, 2g)
+    #[arg(long, value_parser = parse_memory_limit)]
+    memory: Option<String>,
+
+    /// Number of CPUs the container may use (e.g. 1.5)
+    #[arg(long, value_parser = parse_cpus)]
+    cpus: Option<String>,
+
+    /// Maximum number of processes in the container (-1 for unlimited)
+    #[arg(long = "pids-limit", value_parser = clap::value_parser!(i64).range(-1..))]
+    pids_limit: Option<i64>,
+
+    /// Read KEY=VALUE environment variables from a file (repeatable)
+    #[arg(long = "env-file", value_name = "PATH")]
+    env_file: Vec<std::path::PathBuf>,
+
+    /// Don't print the creation banner or progress; errors are still reported
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Start the command in this subdirectory of the workspace (e.g. packages/api)
+    #[arg(long, value_name = "RELPATH", value_parser = session::validate_workdir)]
+    workdir: Option<String>,
+
+    /// Pull the image before creating the container, even if it exists locally
+    #[arg(long, conflicts_with = "local")]
+    pull: bool,
+
+    /// Manage an existing container with the session's name instead of creating one
+    #[arg(long, conflicts_with_all = ["local", "pull"])]
+    adopt: bool,
+
+    /// Check out the new workspace at this branch, tag or commit instead of HEAD
+    #[arg(long, value_name = "REF")]
+    from: Option<String>,
+
+    /// Shallow-clone a new clone-strategy workspace with this many commits of history
+    #[arg(long = "clone-depth", value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    clone_depth: Option<u32>,
+
     /// Command to run in container (default: $BOX_DEFAULT_CMD if set)
     #[arg(last = true)]
     cmd: Vec<String>,
 }
 
-#[derive(clap::Args, Debug)]
+#[derive(clap::Args, Debug, Default)]
 struct ResumeArgs {
     /// Session name
     name: String,
@@ -104,9 +434,30 @@ struct ResumeArgs {
     detach: bool,
 
     /// Extra Docker flags (e.g. -e KEY=VALUE, -v /host:/container, --network host).
-    /// Overrides $BOX_DOCKER_ARGS when provided.
+    /// Overrides the flags saved at creation and $BOX_DOCKER_ARGS when provided.
     #[arg(long = "docker-args", allow_hyphen_values = true)]
     docker_args: Option<String>,
+
+    /// Run a stopped Docker session with a different image (saved for later resumes)
+    #[arg(long)]
+    image: Option<String>,
+
+    /// Replace the session's stopped container with a fresh one
+    #[arg(long)]
+    recreate: bool,
+
+    /// Pull the session's image before recreating its container
+    #[arg(long)]
+    pull: bool,
+
+    /// Type `cd <workspace>/<RELPATH>` into a local session's shell after attaching
+    #[arg(long, value_name = "RELPATH", value_parser = session::validate_workdir)]
+    workdir: Option<String>,
+
+    /// Watch a local session without sending it any input (only scrolling,
+    /// copying and detaching work)
+    #[arg(long = "read-only", conflicts_with_all = ["workdir", "detach"])]
+    read_only: bool,
 }
 
 #[derive(clap::Args, Debug)]
@@ -116,12 +467,22 @@ struct RemoveArgs {
     /// Stop running sessions before removing
     #[arg(short, long)]
     force: bool,
+    /// Remove a whole workspace without asking for confirmation
+    #[arg(short, long)]
+    yes: bool,
 }
 
 #[derive(clap::Args, Debug)]
 struct StopArgs {
     /// Session name
-    name: String,
+    #[arg(required_unless_present_any = ["all", "project"], conflicts_with_all = ["all", "project"])]
+    name: Option<String>,
+    /// Stop every running session
+    #[arg(long, conflicts_with = "project")]
+    all: bool,
+    /// Stop the running sessions of the current project directory
+    #[arg(long, short)]
+    project: bool,
 }
 
 #[derive(clap::Args, Debug)]
@@ -129,8 +490,46 @@ struct ExecArgs {
     /// Session name
     name: String,
 
+    /// Run the command in the background and return immediately
+    #[arg(short, long)]
+    detach: bool,
+
+    /// Stream output without mux chrome or a TTY (default when stdout is not a terminal)
+    #[arg(long, conflicts_with = "detach")]
+    no_tty: bool,
+
+    /// Read the command line from stdin, split with shell quoting rules
+    #[arg(long, conflicts_with = "cmd")]
+    stdin: bool,
+
     /// Command to run in the container
-    #[arg(last = true, required = true)]
+    #[arg(last = true, required_unless_present = "stdin")]
+    cmd: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct EditArgs {
+    /// Session name
+    name: String,
+
+    /// New Docker image
+    #[arg(long)]
+    image: Option<String>,
+
+    /// Set an environment variable (repeatable)
+    #[arg(long = "env", value_name = "KEY=VALUE")]
+    env: Vec<String>,
+
+    /// Remove an environment variable (repeatable)
+    #[arg(long = "unset-env", value_name = "KEY")]
+    unset_env: Vec<String>,
+
+    /// Remove a Docker session's stopped container so the changes apply on next resume
+    #[arg(long)]
+    recreate: bool,
+
+    /// New command the session launches
+    #[arg(last = true)]
     cmd: Vec<String>,
 }
 
@@ -148,6 +547,36 @@ struct ListArgs {
     /// Only print session names
     #[arg(long, short)]
     quiet: bool,
+    /// Print sessions as a JSON array (names only with --quiet)
+    #[arg(long)]
+    json: bool,
+    /// Show only sessions created within the given duration (e.g. 1h, 2d, 1w)
+    #[arg(long, value_name = "DURATION", value_parser = config::parse_duration)]
+    since: Option<std::time::Duration>,
+    /// Show only sessions created longer ago than the given duration
+    #[arg(long, value_name = "DURATION", value_parser = config::parse_duration)]
+    before: Option<std::time::Duration>,
+    /// Cap the table width at N characters, truncating PROJECT, CMD and IMAGE
+    #[arg(long, value_name = "N")]
+    format_width: Option<usize>,
+    /// Sort order: name, or most recently created/resumed first
+    #[arg(long, default_value = "name", value_parser = ["name", "created", "resumed"])]
+    sort: String,
+    /// Show only archived sessions
+    #[arg(long, conflicts_with = "running")]
+    archived: bool,
+    /// Group sessions under their workspace
+    #[arg(long, conflicts_with_all = ["quiet", "json", "format_width"])]
+    tree: bool,
+    /// Show only sessions created by the current user (older sessions always show)
+    #[arg(long)]
+    mine: bool,
+    /// Also write the listed sessions as JSON, with a snapshot_time, to a file
+    #[arg(long, value_name = "PATH")]
+    output_file: Option<std::path::PathBuf>,
+    /// Append to --output-file, one JSON array per line, instead of overwriting
+    #[arg(long, requires = "output_file")]
+    append: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -156,6 +585,18 @@ enum ConfigShell {
     Zsh,
     /// Output Bash completions
     Bash,
+    /// Output Fish completions
+    Fish,
+}
+
+#[derive(Subcommand, Debug)]
+enum CompleteTarget {
+    /// Session names, one per line
+    Sessions {
+        /// Follow each name with a tab and its project directory
+        #[arg(long)]
+        describe: bool,
+    },
 }
 
 fn is_local_mode() -> bool {
@@ -177,6 +618,7 @@ fn main() {
 
     let result = match cli.command {
         Some(Commands::Create(args)) => {
+            let args = *args;
             if std::env::var_os("BOX_SESSION").is_some() {
                 eprintln!(
                     "Error: cannot nest box sessions (already inside session {:?})",
@@ -187,29 +629,104 @@ fn main() {
             match args.name {
                 None => cmd_create_tui(),
                 Some(name) => {
-                    let local = if args.docker {
+                    let local = if args.docker || args.docker_in_docker {
                         false
                     } else {
                         args.local || is_local_mode()
                     };
-                    let docker_args = args
+                    let mut docker_args = args
                         .docker_args
                         .or_else(|| std::env::var("BOX_DOCKER_ARGS").ok())
                         .unwrap_or_default();
+                    if args.minimal_caps {
+                        docker_args = with_minimal_caps(&docker_args);
+                    }
                     let cmd = if args.cmd.is_empty() {
                         None
                     } else {
                         Some(args.cmd)
                     };
-                    cmd_create(
-                        &name,
-                        args.image,
-                        &docker_args,
-                        cmd,
-                        args.detach,
-                        local,
-                        args.strategy,
-                    )
+                    let docker_socket = args.docker_socket.or_else(|| {
+                        args.docker_in_docker
+                            .then(|| docker::DOCKER_SOCKET_PATH.to_string())
+                    });
+                    let image = args.image.or_else(|| {
+                        args.docker_in_docker
+                            .then(|| DOCKER_IN_DOCKER_IMAGE.to_string())
+                    });
+                    if args.minimal_caps && local {
+                        Err(anyhow::anyhow!(
+                            "--minimal-caps requires a Docker session (--docker)."
+                        ))
+                    } else if args.volumes_from.is_some() && local {
+                        Err(anyhow::anyhow!(
+                            "--volumes-from requires a Docker session (--docker)."
+                        ))
+                    } else if (args.storage_driver.is_some() || !args.storage_opt.is_empty())
+                        && local
+                    {
+                        Err(anyhow::anyhow!(
+                            "--storage-driver and --storage-opt require a Docker session (--docker)."
+                        ))
+                    } else if !args.port.is_empty() && local {
+                        Err(anyhow::anyhow!(
+                            "--port requires a Docker session (--docker); local sessions have no container to publish ports from."
+                        ))
+                    } else if (args.memory.is_some()
+                        || args.cpus.is_some()
+                        || args.pids_limit.is_some())
+                        && local
+                    {
+                        Err(anyhow::anyhow!(
+                            "--memory, --cpus and --pids-limit require a Docker session (--docker)."
+                        ))
+                    } else if docker_socket.is_some() && local {
+                        Err(anyhow::anyhow!(
+                            "--docker-socket requires a Docker session (--docker)."
+                        ))
+                    } else if args.pull && local {
+                        Err(anyhow::anyhow!(
+                            "--pull requires a Docker session (--docker)."
+                        ))
+                    } else if args.adopt && local {
+                        Err(anyhow::anyhow!(
+                            "--adopt requires a Docker session (--docker)."
+                        ))
+                    } else {
+                        load_env_files(&args.env_file).and_then(|env| {
+                            cmd_create(
+                                &name,
+                                CreateOptions {
+                                    image,
+                                    docker_args,
+                                    cmd,
+                                    detach: args.detach,
+                                    local,
+                                    strategy: args.strategy,
+                                    notify: args.notify,
+                                    volumes_from: args.volumes_from,
+                                    docker_socket,
+                                    inherit_git_config: args.inherit_git_config,
+                                    storage_driver: args.storage_driver,
+                                    storage_opts: args.storage_opt,
+                                    env,
+                                    ports: args.port,
+                                    resources: session::Resources {
+                                        memory: args.memory,
+                                        cpus: args.cpus,
+                                        pids_limit: args.pids_limit,
+                                    },
+                                    pull: args.pull,
+                                    adopt: args.adopt,
+                                    from_ref: args.from,
+                                    clone_depth: args.clone_depth,
+                                    workdir: args.workdir,
+                                    quiet: args.quiet,
+                                    ..Default::default()
+                                },
+                            )
+                        })
+                    }
                 }
             }
         }
@@ -221,23 +738,116 @@ fn main() {
                 );
                 std::process::exit(1);
             }
-            let docker_args = args
-                .docker_args
-                .or_else(|| std::env::var("BOX_DOCKER_ARGS").ok())
-                .unwrap_or_default();
-            cmd_resume(&args.name, &docker_args, args.detach)
+            cmd_resume(args)
+        }
+        Some(Commands::Remove(args)) => cmd_remove(&args.name, args.force, args.yes),
+        Some(Commands::Stop(args)) => match args.name {
+            Some(name) => cmd_stop(&name),
+            None => cmd_stop_many(args.project),
+        },
+        Some(Commands::Wait { name }) => cmd_wait(&name),
+        Some(Commands::Prune {
+            dry_run,
+            project,
+            yes,
+            force,
+        }) => cmd_prune(dry_run, project, yes, force),
+        Some(Commands::Archive { name }) => cmd_archive(&name),
+        Some(Commands::Unarchive { name }) => cmd_unarchive(&name),
+        Some(Commands::Exec(args)) => {
+            let no_tty = args.no_tty || !std::io::stdout().is_terminal();
+            let cmd = if args.stdin {
+                let mut input = String::new();
+                std::io::Read::read_to_string(&mut std::io::stdin(), &mut input)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|_| parse_command_line(&input))
+            } else {
+                Ok(args.cmd)
+            };
+            cmd.and_then(|cmd| cmd_exec(&args.name, &cmd, args.detach, no_tty))
         }
-        Some(Commands::Remove(args)) => cmd_remove(&args.name, args.force),
-        Some(Commands::Stop(args)) => cmd_stop(&args.name),
-        Some(Commands::Exec(args)) => cmd_exec(&args.name, &args.cmd),
         Some(Commands::List(args)) => cmd_list_sessions(&args),
-        Some(Commands::Cd { name }) => cmd_cd(&name),
-        Some(Commands::Path { name }) => cmd_path(&name),
+        Some(Commands::Info { name, json }) => cmd_info(&name, json),
+        Some(Commands::Cd { name, origin }) => cmd_cd(&name, origin),
+        Some(Commands::Path { name, origin }) => cmd_path(&name, origin),
         Some(Commands::Origin) => cmd_origin(),
+        Some(Commands::ShowEnv { name }) => cmd_show_env(&name),
+        Some(Commands::ConnectToDebugger {
+            name,
+            port,
+            debug_type,
+        }) => cmd_connect_to_debugger(&name, port, &debug_type),
+        Some(Commands::EnvironmentBaseline { name }) => cmd_environment_baseline(&name),
+        Some(Commands::EnvironmentDrift { name, reset }) => cmd_environment_drift(&name, reset),
+        Some(Commands::SetStrategy {
+            name,
+            strategy,
+            force,
+        }) => cmd_set_strategy(&name, &strategy, force),
+        Some(Commands::Recover { name }) => cmd_recover(&name),
+        Some(Commands::Rename { old, new, force }) => cmd_rename(&old, &new, force),
+        Some(Commands::RebuildWorkspace {
+            name,
+            keep_changes,
+            force,
+        }) => cmd_rebuild_workspace(&name, keep_changes, force),
+        Some(Commands::Fork { name, new_name }) => cmd_fork(&name, &new_name),
+        Some(Commands::Duplicate { src, dest, detach }) => {
+            if std::env::var_os("BOX_SESSION").is_some() {
+                eprintln!(
+                    "Error: cannot nest box sessions (already inside session {:?})",
+                    std::env::var("BOX_SESSION").unwrap_or_default()
+                );
+                std::process::exit(1);
+            }
+            cmd_duplicate(&src, &dest, detach)
+        }
+        Some(Commands::Promote { name }) => cmd_promote(&name),
+        Some(Commands::BatchCreate {
+            file,
+            continue_on_error,
+        }) => cmd_batch_create(&file, continue_on_error),
+        Some(Commands::ListCapabilities { name }) => cmd_list_capabilities(&name),
+        Some(Commands::ShowResourceLimits { name }) => cmd_show_resource_limits(&name),
+        Some(Commands::ListChanges {
+            name,
+            stat,
+            since_commit,
+        }) => cmd_list_changes(&name, stat, since_commit.as_deref()),
+        Some(Commands::DescribeImage { name, history }) => cmd_describe_image(&name, history),
+        Some(Commands::CheckImageUpdates { name, all }) => {
+            cmd_check_image_updates(name.as_deref(), all)
+        }
+        Some(Commands::ListProcesses {
+            name,
+            sort_by,
+            watch,
+        }) => cmd_list_processes(&name, &sort_by, watch),
+        Some(Commands::TailOutput {
+            name,
+            lines,
+            follow,
+        }) => cmd_tail_output(&name, lines, follow),
+        Some(Commands::WaitForPort {
+            name,
+            port,
+            timeout,
+            message,
+        }) => cmd_wait_for_port(&name, port, timeout, message.as_deref()),
+        Some(Commands::Logs { name, follow }) => cmd_logs(&name, follow),
+        Some(Commands::LogRotate { name }) => cmd_log_rotate(&name),
+        Some(Commands::SetImage { name, image }) => cmd_set_image(&name, &image),
+        Some(Commands::Edit(args)) => cmd_edit(&args),
+        Some(Commands::Top) => cmd_top(),
+        Some(Commands::Doctor) => cmd_doctor(),
         Some(Commands::Upgrade) => cmd_upgrade(),
         Some(Commands::Config { shell }) => match shell {
             ConfigShell::Zsh => cmd_config_zsh(),
             ConfigShell::Bash => cmd_config_bash(),
+            ConfigShell::Fish => cmd_config_fish(),
+        },
+        Some(Commands::Complete { target }) => match target {
+            CompleteTarget::Sessions { describe } => cmd_complete_sessions(describe),
         },
         None => cmd_default(),
     };
@@ -251,8 +861,40 @@ fn main() {
     }
 }
 
+/// Capability flags applied by `box create --minimal-caps`.
+const MINIMAL_CAPS_ARGS: &str = "--cap-drop all --cap-add CHOWN --cap-add DAC_OVERRIDE --cap-add FOWNER --cap-add SETGID --cap-add SETUID";
+
+/// Prepend the minimal capability set to user-supplied docker args, so any
+/// extra `--cap-add` flags grant capabilities on top of it.
+fn with_minimal_caps(docker_args: &str) -> String {
+    if docker_args.is_empty() {
+        MINIMAL_CAPS_ARGS.to_string()
+    } else {
+        format!("{} {}", MINIMAL_CAPS_ARGS, docker_args)
+    }
+}
+
+/// What `create` does with a new local session's command.
+#[derive(Debug, PartialEq)]
+enum LocalStart {
+    /// No command: the session only changes into the workspace.
+    Nothing,
+    /// `-d`: start the command in the session server and return.
+    Background,
+    /// Start the command and attach to it.
+    Attach,
+}
+
+fn local_start(command: &[String], detach: bool) -> LocalStart {
+    match (command.is_empty(), detach) {
+        (true, _) => LocalStart::Nothing,
+        (false, true) => LocalStart::Background,
+        (false, false) => LocalStart::Attach,
+    }
+}
+
 fn run_local_command(session_name: &str) -> Result<i32> {
-    mux::run(session_name)
+    mux::run(session_name, None, false)
 }
 
 fn output_cd_path(path: &str) {
@@ -265,8 +907,9 @@ fn output_cd_path(path: &str) {
 
 /// Shorten a project path for display by abbreviating intermediate components
 /// to their first character. e.g. `/Users/yusuke/projects/my-app` => `/U/y/p/my-app`
-/// The home directory prefix is replaced with `~` first.
-pub(crate) fn shorten_project_path(path: &str, home: &str) -> String {
+/// The last `tail` components (at least one) are kept whole, so with 2 it is
+/// `/U/y/projects/my-app`. The home directory prefix is replaced with `~` first.
+pub(crate) fn shorten_project_path(path: &str, home: &str, tail: usize) -> String {
     let (prefix, rest) = if !home.is_empty() {
         if let Some(r) = path.strip_prefix(home) {
             ("~", r)
@@ -284,13 +927,14 @@ pub(crate) fn shorten_project_path(path: &str, home: &str) -> String {
         return full;
     }
 
-    // Abbreviate all components except the first (empty for leading /) and last
-    let last = parts.len() - 1;
+    // Abbreviate all components except the first (empty for leading /) and
+    // the last `tail`
+    let keep_from = parts.len().saturating_sub(tail.max(1));
     let shortened: Vec<String> = parts
         .iter()
         .enumerate()
         .map(|(i, part)| {
-            if i == 0 || i == last || part.is_empty() {
+            if i == 0 || i >= keep_from || part.is_empty() {
                 part.to_string()
             } else {
                 part.chars()
@@ -304,6 +948,50 @@ pub(crate) fn shorten_project_path(path: &str, home: &str) -> String {
     shortened.join("/")
 }
 
+/// Truncate `s` to at most `max` terminal columns, ending with `…` when
+/// shortened. Wide characters (e.g. CJK) count as two columns and are never
+/// split.
+fn truncate_to(s: &str, max: usize) -> String {
+    if s.width() <= max {
+        return s.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > max - 1 {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+/// Left-align `s` in a field of `width` terminal columns. `format!`'s own
+/// padding counts characters, which misaligns wide characters.
+fn pad_to(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(s.width())))
+}
+
+/// Shrink `(width, min)` columns proportionally so their total fits in
+/// `available`. Columns never shrink below their `min` (the header width),
+/// so the result can still exceed `available` when it is very small.
+fn fit_columns(columns: &[(usize, usize)], available: usize) -> Vec<usize> {
+    let total: usize = columns.iter().map(|&(w, _)| w).sum();
+    if total <= available {
+        return columns.iter().map(|&(w, _)| w).collect();
+    }
+    columns
+        .iter()
+        .map(|&(w, min)| (w * available / total).max(min.min(w)))
+        .collect()
+}
+
 /// Resolve the current directory to a project_dir suitable for filtering sessions.
 ///
 /// 1. If the cwd is inside a workspace (`~/.box/workspaces/<name>/`), look up
@@ -315,9 +1003,7 @@ fn resolve_project_dir(
 ) -> Option<String> {
     // Check if we're inside a workspace directory
     if let Ok(home) = config::home_dir() {
-        let workspaces = std::path::PathBuf::from(&home)
-            .join(".box")
-            .join("workspaces");
+        let workspaces = config::workspaces_dir_in(&home);
         if let Ok(workspaces) = std::fs::canonicalize(&workspaces) {
             if cwd.starts_with(&workspaces) {
                 // Extract the workspace name (first component after workspaces/)
@@ -366,9 +1052,11 @@ fn cmd_default() -> Result<i32> {
     }
 
     // Prefer first running session, otherwise first session
-    let docker_args = std::env::var("BOX_DOCKER_ARGS").unwrap_or_default();
     let target = sessions.iter().find(|s| s.running).unwrap_or(&sessions[0]);
-    cmd_resume(&target.name, &docker_args, false)
+    cmd_resume(ResumeArgs {
+        name: target.name.clone(),
+        ..Default::default()
+    })
 }
 
 /// `box create` with no name: prompt for session details.
@@ -381,29 +1069,52 @@ fn cmd_create_tui() -> Result<i32> {
             command,
             local,
             strategy,
-        } => cmd_create(&name, image, &docker_args, command, false, local, strategy),
+        } => cmd_create(
+            &name,
+            CreateOptions {
+                image,
+                docker_args,
+                cmd: command,
+                local,
+                strategy,
+                ..Default::default()
+            },
+        ),
         _ => Ok(0),
     }
 }
 
-fn cmd_list_sessions(args: &ListArgs) -> Result<i32> {
-    let mut sessions = session::list()?;
-
+/// Fill in `running` for each session from Docker and the local mux servers.
+fn mark_running(sessions: &mut [session::SessionSummary]) -> Result<()> {
     let has_docker_sessions = sessions.iter().any(|s| !s.local);
     if has_docker_sessions {
         docker::check()?;
         let running = docker::running_sessions();
-        for s in &mut sessions {
+        for s in sessions.iter_mut() {
             if !s.local {
                 s.running = running.contains(&s.name.replace('/', "-"));
             }
         }
     }
-    for s in &mut sessions {
+    for s in sessions.iter_mut() {
         if s.local {
             s.running = session::is_local_running(&s.name);
         }
     }
+    Ok(())
+}
+
+fn cmd_list_sessions(args: &ListArgs) -> Result<i32> {
+    let mut sessions = if args.archived {
+        session::list_archived()?
+    } else {
+        let mut sessions = session::list()?;
+        mark_running(&mut sessions)?;
+        for s in sessions.iter().filter(|s| s.local && !s.running) {
+            session::reap_stale(&s.name);
+        }
+        sessions
+    };
 
     if args.running {
         sessions.retain(|s| s.running);
@@ -411,6 +1122,21 @@ fn cmd_list_sessions(args: &ListArgs) -> Result<i32> {
     if args.stopped {
         sessions.retain(|s| !s.running && !s.local);
     }
+    if args.mine {
+        if let Some(user) = session::current_user() {
+            sessions.retain(|s| s.created_by_user(&user));
+        }
+    }
+    if args.since.is_some() || args.before.is_some() {
+        let now = chrono::Utc::now();
+        sessions.retain(|s| match s.age(now) {
+            Some(age) => {
+                args.since.is_none_or(|since| age <= since)
+                    && args.before.is_none_or(|before| age > before)
+            }
+            None => false,
+        });
+    }
     if args.project {
         let cwd = std::env::current_dir()?;
         let cwd = std::fs::canonicalize(&cwd).unwrap_or(cwd);
@@ -421,6 +1147,22 @@ fn cmd_list_sessions(args: &ListArgs) -> Result<i32> {
             sessions.clear();
         }
     }
+    session::sort(&mut sessions, &args.sort);
+
+    if let Some(ref path) = args.output_file {
+        write_list_snapshot(path, &sessions, chrono::Utc::now(), args.append)?;
+    }
+
+    if args.json {
+        let json = if args.quiet {
+            let names: Vec<&str> = sessions.iter().map(|s| s.name.as_str()).collect();
+            serde_json::to_string_pretty(&names)?
+        } else {
+            serde_json::to_string_pretty(&sessions)?
+        };
+        println!("{}", json);
+        return Ok(0);
+    }
 
     if args.quiet {
         for s in &sessions {
@@ -435,6 +1177,14 @@ fn cmd_list_sessions(args: &ListArgs) -> Result<i32> {
     }
 
     let home = config::home_dir().unwrap_or_default();
+    let path_tail = config::load_list_path_tail();
+
+    if args.tree {
+        for line in list_tree_lines(&sessions, &home, path_tail) {
+            println!("{}", line);
+        }
+        return Ok(0);
+    }
 
     // Compute column widths
     let name_w = sessions
@@ -445,28 +1195,44 @@ fn cmd_list_sessions(args: &ListArgs) -> Result<i32> {
         .max(4);
     let mode_w = 6; // "docker" or "local"
     let status_w = 7; // "running" or "stopped"
-    let image_w = sessions
+    let mut image_w = sessions
         .iter()
-        .map(|s| s.image.len())
+        .map(|s| s.image.width())
         .max()
         .unwrap_or(0)
         .max(5);
 
-    let shorten_path = |p: &str| -> String { shorten_project_path(p, &home) };
+    let shorten_path = |p: &str| -> String { shorten_project_path(p, &home, path_tail) };
 
-    let project_w = sessions
+    let mut project_w = sessions
         .iter()
-        .map(|s| shorten_path(&s.project_dir).len())
+        .map(|s| shorten_path(&s.project_dir).width())
         .max()
         .unwrap_or(0)
         .max(7);
-    let command_w = sessions
+    let mut command_w = sessions
         .iter()
-        .map(|s| s.command.len())
+        .map(|s| s.command.width())
         .max()
         .unwrap_or(0)
         .max(3);
 
+    if let Some(max_width) = args.format_width {
+        let created_w = sessions
+            .iter()
+            .map(|s| s.created_at.width())
+            .max()
+            .unwrap_or(0)
+            .max(7);
+        // Leading indent, six column gaps, and the columns that never shrink
+        let fixed = 2 + 6 * 2 + name_w + mode_w + status_w + created_w;
+        let fitted = fit_columns(
+            &[(project_w, 7), (command_w, 3), (image_w, 5)],
+            max_width.saturating_sub(fixed),
+        );
+        (project_w, command_w, image_w) = (fitted[0], fitted[1], fitted[2]);
+    }
+
     println!(
         "\x1b[2m  {:<name_w$}  {:<project_w$}  {:<mode_w$}  {:<status_w$}  {:<command_w$}  {:<image_w$}  CREATED\x1b[0m",
         "NAME", "PROJECT", "MODE", "STATUS", "CMD", "IMAGE",
@@ -475,33 +1241,324 @@ fn cmd_list_sessions(args: &ListArgs) -> Result<i32> {
     for s in &sessions {
         let mode = if s.local { "local" } else { "docker" };
         let status = if s.running { "running" } else { "stopped" };
-        let project = shorten_path(&s.project_dir);
+        let project = pad_to(
+            &truncate_to(&shorten_path(&s.project_dir), project_w),
+            project_w,
+        );
+        let command = pad_to(&truncate_to(&s.command, command_w), command_w);
+        let image = pad_to(&truncate_to(&s.image, image_w), image_w);
         println!(
-            "  {:<name_w$}  {:<project_w$}  {:<mode_w$}  {:<status_w$}  {:<command_w$}  {:<image_w$}  {}",
-            s.name, project, mode, status, s.command, s.image, s.created_at,
+            "  {:<name_w$}  {}  {:<mode_w$}  {:<status_w$}  {}  {}  {}",
+            s.name, project, mode, status, command, image, s.created_at,
         );
     }
 
     Ok(0)
 }
 
-#[allow(clippy::too_many_arguments)]
-fn cmd_create(
-    name: &str,
+/// Write `sessions` in the `box list --json` format to `path`, each entry
+/// with a `snapshot_time`. Overwriting writes a pretty-printed array;
+/// appending adds one compact array per line, so every line is valid JSON.
+fn write_list_snapshot(
+    path: &Path,
+    sessions: &[session::SessionSummary],
+    now: chrono::DateTime<chrono::Utc>,
+    append: bool,
+) -> Result<()> {
+    let snapshot_time = now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let mut entries = Vec::with_capacity(sessions.len());
+    for s in sessions {
+        let mut entry = serde_json::to_value(s)?;
+        entry["snapshot_time"] = snapshot_time.clone().into();
+        entries.push(entry);
+    }
+    let entries = serde_json::Value::Array(entries);
+    let write = || -> std::io::Result<()> {
+        if append {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            writeln!(file, "{}", entries)
+        } else {
+            fs::write(path, format!("{:#}\n", entries))
+        }
+    };
+    write().map_err(|e| anyhow::anyhow!("Cannot write '{}': {}", path.display(), e))
+}
+
+/// Lines of `box list --tree`: each workspace with its project directory,
+/// followed by its sessions indented beneath. Workspaces appear in the
+/// order of their first session, so the requested sort still applies.
+fn list_tree_lines(
+    sessions: &[session::SessionSummary],
+    home: &str,
+    path_tail: usize,
+) -> Vec<String> {
+    let mut workspaces: Vec<(&str, Vec<&session::SessionSummary>)> = Vec::new();
+    for s in sessions {
+        let ws = session::workspace_name(&s.name);
+        match workspaces.iter_mut().find(|(name, _)| *name == ws) {
+            Some((_, members)) => members.push(s),
+            None => workspaces.push((ws, vec![s])),
+        }
+    }
+
+    let sess_part = |s: &session::SessionSummary| -> String {
+        s.name
+            .split_once('/')
+            .map_or(s.name.as_str(), |(_, sess)| sess)
+            .to_string()
+    };
+    let name_w = sessions
+        .iter()
+        .map(|s| sess_part(s).len())
+        .max()
+        .unwrap_or(0);
+    let command_w = sessions
+        .iter()
+        .map(|s| s.command.width())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (ws, members) in workspaces {
+        lines.push(format!(
+            "{}  \x1b[2m{}\x1b[0m",
+            ws,
+            shorten_project_path(&members[0].project_dir, home, path_tail)
+        ));
+        for s in members {
+            let mode = if s.local { "local" } else { "docker" };
+            let status = if s.running { "running" } else { "stopped" };
+            let line = format!(
+                "  {:<name_w$}  {:<6}  {:<7}  {}  {}",
+                sess_part(s),
+                mode,
+                status,
+                pad_to(&s.command, command_w),
+                if s.local { "" } else { s.image.as_str() },
+            );
+            lines.push(line.trim_end().to_string());
+        }
+    }
+    lines
+}
+
+/// Options for `cmd_create` beyond the session name.
+#[derive(Default)]
+struct CreateOptions {
     image: Option<String>,
-    docker_args: &str,
+    docker_args: String,
     cmd: Option<Vec<String>>,
     detach: bool,
     local: bool,
     strategy: Option<String>,
-) -> Result<i32> {
-    session::validate_name(name)?;
+    notify: bool,
+    volumes_from: Option<String>,
+    docker_socket: Option<String>,
+    inherit_git_config: bool,
+    storage_driver: Option<String>,
+    storage_opts: Vec<String>,
+    env: Vec<String>,
+    ports: Vec<String>,
+    resources: session::Resources,
+    pull: bool,
+    /// Take over an existing container with the session's name instead of
+    /// running a new one.
+    adopt: bool,
+    /// Branch, tag or commit a new workspace is checked out at.
+    from_ref: Option<String>,
+    /// History depth for a new clone workspace; defaults to `[defaults] clone_depth`.
+    clone_depth: Option<u32>,
+    workdir: Option<String>,
+    /// Suppress the banner and progress output (errors and warnings still print).
+    quiet: bool,
+    /// Project directory for a new workspace; defaults to the current git root.
+    project_dir: Option<String>,
+    mount_path: Option<String>,
+}
+
+/// Fail if the session's `--workdir` is not a directory in the new workspace.
+/// Checked after the post_create hook, which may be what creates it.
+fn check_workdir(sess: &session::Session, workspace: &str) -> Result<()> {
+    if let Some(ref dir) = sess.workdir {
+        if !sess.working_dir(Path::new(workspace)).is_dir() {
+            bail!(
+                "Working directory '{}' does not exist in the workspace.",
+                dir
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Read and concatenate the entries of every `--env-file`, in order.
+fn load_env_files(paths: &[std::path::PathBuf]) -> Result<Vec<String>> {
+    let mut env = Vec::new();
+    for path in paths {
+        env.extend(config::parse_env_file(path)?);
+    }
+    Ok(env)
+}
+
+/// Split a command line read from stdin (`box exec --stdin`) into argv.
+fn parse_command_line(input: &str) -> Result<Vec<String>> {
+    let cmd = shell_words::split(input)
+        .map_err(|e| anyhow::anyhow!("Failed to parse command from stdin: {}", e))?;
+    if cmd.is_empty() {
+        bail!("No command given on stdin.");
+    }
+    Ok(cmd)
+}
+
+/// Parse a `--storage-opt` value, which must be `KEY=VALUE`.
+fn parse_storage_opt(s: &str) -> Result<String> {
+    match s.split_once('=') {
+        Some((key, _)) if !key.is_empty() => Ok(s.to_string()),
+        _ => bail!("expected KEY=VALUE (e.g. size=20G), got '{}'", s),
+    }
+}
+
+/// Parse a `--port` value, which must be `HOST:CONTAINER` port numbers.
+fn parse_port_mapping(s: &str) -> Result<String> {
+    let valid = |p: &str| p.parse::<u16>().is_ok_and(|n| n > 0);
+    match s.split_once(':') {
+        Some((host, container)) if valid(host) && valid(container) => Ok(s.to_string()),
+        _ => bail!(
+            "expected HOST:CONTAINER port numbers (e.g. 8080:80), got '{}'",
+            s
+        ),
+    }
+}
+
+/// Parse a `--memory` value: a positive number with an optional b, k, m or g
+/// suffix, as `docker run` accepts.
+fn parse_memory_limit(s: &str) -> Result<String> {
+    let digits = s.trim_end_matches(|c: char| "bkmgBKMG".contains(c));
+    let suffix_ok = s.len() - digits.len() <= 1;
+    match digits.parse::<u64>() {
+        Ok(n) if n > 0 && suffix_ok => Ok(s.to_string()),
+        _ => bail!("expected a size like 512m or 2g, got '{}'", s),
+    }
+}
+
+/// Parse a `--cpus` value, which must be a positive number.
+fn parse_cpus(s: &str) -> Result<String> {
+    match s.parse::<f64>() {
+        Ok(n) if n > 0.0 && n.is_finite() => Ok(s.to_string()),
+        _ => bail!("expected a positive number of CPUs (e.g. 1.5), got '{}'", s),
+    }
+}
+
+/// Check that the Docker daemon runs `driver`. Docker has no per-container
+/// storage driver, so a session can only require the daemon's driver.
+fn validate_storage_driver(driver: &str) -> Result<()> {
+    match docker::daemon_storage_driver() {
+        Some(ref daemon) if daemon == driver => Ok(()),
+        Some(daemon) => bail!(
+            "Docker daemon uses storage driver '{}', not '{}'. The storage driver is set per daemon \
+             (\"storage-driver\" in daemon.json), and not every driver is supported on every platform.",
+            daemon,
+            driver
+        ),
+        None => bail!("Could not determine the Docker daemon's storage driver."),
+    }
+}
+
+/// Image used by `--docker-in-docker` when no `--image` is given.
+const DOCKER_IN_DOCKER_IMAGE: &str = "docker:cli";
+
+/// Validate a `--docker-socket` path: it must be an absolute path to an
+/// existing file on the host.
+fn validate_docker_socket(path: &str) -> Result<()> {
+    if !Path::new(path).is_absolute() {
+        bail!("Docker socket path must be absolute: {}", path);
+    }
+    if !Path::new(path).exists() {
+        bail!("Docker socket not found: {}", path);
+    }
+    Ok(())
+}
+
+/// Warn that a session can control the host's Docker daemon.
+fn warn_docker_socket(path: &str) {
+    eprintln!(
+        "\x1b[1mWarning: mounting the Docker socket ({}) gives this session root-equivalent access to the host.\x1b[0m",
+        path
+    );
+    eprintln!("\x1b[1mOnly use --docker-socket with images and commands you trust.\x1b[0m");
+}
+
+/// Resolve and validate the source of `--volumes-from`: it must be an existing
+/// Docker session. Warns (but proceeds) when its container is not running.
+fn resolve_volumes_from(source: &str) -> Result<String> {
+    session::validate_name(source)?;
+    let full = session::full_name(source);
+    if !session::session_exists(&full)? {
+        bail!("Volumes source session '{}' not found.", full);
+    }
+    if session::load(&full)?.local {
+        bail!(
+            "Session '{}' is a local session; --volumes-from requires a Docker session.",
+            full
+        );
+    }
+    if !docker::container_is_running(&full) {
+        eprintln!(
+            "Warning: volumes source session '{}' is not running; its volumes are still shared.",
+            full
+        );
+    }
+    Ok(full)
+}
+
+fn cmd_create(name: &str, opts: CreateOptions) -> Result<i32> {
+    session::validate_name(name)?;
+
+    let CreateOptions {
+        image,
+        docker_args,
+        cmd,
+        detach,
+        local,
+        strategy,
+        notify,
+        volumes_from,
+        docker_socket,
+        inherit_git_config,
+        storage_driver,
+        storage_opts,
+        env,
+        ports,
+        resources,
+        pull,
+        adopt,
+        from_ref,
+        clone_depth,
+        workdir,
+        quiet,
+        project_dir,
+        mount_path,
+    } = opts;
+    let docker_args = docker_args.as_str();
+
+    if notify && !local {
+        bail!("--notify requires a local session (--local); Docker sessions can't send it.");
+    }
 
     let (ws, _sess_part) = session::parse_name(name);
     let has_explicit_session = name.contains('/');
 
     // If workspace already exists, inherit settings from the first session
-    let (project_dir, inherited_image, inherited_strategy) = if session::workspace_exists(ws)? {
+    let workspace_exists = session::workspace_exists(ws)?;
+    if workspace_exists && (from_ref.is_some() || clone_depth.is_some()) {
+        bail!(
+            "Workspace '{}' already exists; --from and --clone-depth only apply to a new workspace.",
+            ws
+        );
+    }
+    let (project_dir, inherited_image, inherited_strategy) = if workspace_exists {
         let ws_sessions = session::workspace_sessions(ws)?;
         if let Some(first) = ws_sessions.first() {
             let parent = session::load(&format!("{}/{}", ws, first))?;
@@ -513,6 +1570,8 @@ fn cmd_create(
         } else {
             return Err(anyhow::anyhow!("Workspace '{}' has no sessions.", ws));
         }
+    } else if let Some(project_dir) = project_dir {
+        (project_dir, None, None)
     } else {
         let cwd = fs::canonicalize(".")
             .map_err(|_| anyhow::anyhow!("Cannot resolve current directory."))?;
@@ -524,16 +1583,19 @@ fn cmd_create(
         (project_dir, None, None)
     };
 
+    let explicit_image = image.is_some();
+    ensure_project_config_trusted(&project_dir)?;
     // Resolve config first to know the command
     let mut cfg = config::resolve(config::BoxConfigInput {
         name: String::new(), // placeholder, set below
         image: image.or(inherited_image),
-        mount_path: None,
+        mount_path,
         project_dir,
         command: cmd,
-        env: vec![],
+        env,
         local,
         strategy: strategy.or(inherited_strategy),
+        depends_on: vec![],
     })?;
 
     // Derive session part from command basename when user gave a bare workspace name
@@ -560,87 +1622,436 @@ fn cmd_create(
             full
         );
     }
+    if clone_depth.is_some() && cfg.strategy != "clone" {
+        bail!(
+            "--clone-depth only applies to the clone strategy, not '{}'.",
+            cfg.strategy
+        );
+    }
+    let clone_depth = match cfg.strategy.as_str() {
+        "clone" if !workspace_exists => clone_depth.or_else(config::load_clone_depth),
+        _ => None,
+    };
+    if let Some(ref from) = from_ref {
+        if !git::is_repo(Path::new(&cfg.project_dir)) {
+            bail!(
+                "--from needs a git repository, and '{}' is not one.",
+                cfg.project_dir
+            );
+        }
+        if !git::ref_exists(Path::new(&cfg.project_dir), from) {
+            bail!(
+                "'{}' is not a branch, tag or commit in '{}'.",
+                from,
+                cfg.project_dir
+            );
+        }
+    }
 
     if local {
-        eprintln!("\x1b[2msession:\x1b[0m {}", full);
-        eprintln!("\x1b[2mmode:\x1b[0m local");
-        eprintln!("\x1b[2mstrategy:\x1b[0m {}", cfg.strategy);
-        if !cfg.command.is_empty() {
-            eprintln!("\x1b[2mcommand:\x1b[0m {}", shell_words::join(&cfg.command));
+        if !quiet {
+            eprintln!("\x1b[2msession:\x1b[0m {}", full);
+            eprintln!("\x1b[2mmode:\x1b[0m local");
+            eprintln!("\x1b[2mstrategy:\x1b[0m {}", cfg.strategy);
+            if !cfg.command.is_empty() {
+                eprintln!("\x1b[2mcommand:\x1b[0m {}", shell_words::join(&cfg.command));
+            }
+            if let Some(ref from) = from_ref {
+                eprintln!("\x1b[2mfrom:\x1b[0m {}", from);
+            }
+            eprintln!();
         }
-        eprintln!();
 
-        let sess = session::Session::from(cfg);
+        let mut sess = session::Session::from(cfg);
+        sess.notify_on_exit = notify;
+        sess.inherit_git_config = inherit_git_config;
+        sess.workdir = workdir;
+        sess.from_ref = from_ref;
         session::save(&sess)?;
 
         let home = config::home_dir()?;
-        let workspace = docker::ensure_workspace(&home, ws, &sess.project_dir, &sess.strategy)?;
-        output_cd_path(&workspace);
-
-        if !sess.command.is_empty() {
-            return run_local_command(&sess.name);
+        let workspace = docker::ensure_workspace_with(
+            &home,
+            ws,
+            &sess.project_dir,
+            &sess.strategy,
+            sess.from_ref.as_deref(),
+            clone_depth,
+            quiet,
+        )?;
+        if inherit_git_config {
+            git::inherit_config(Path::new(&workspace), git::INHERITED_CONFIG_KEYS)?;
         }
-        return Ok(0);
+        if let Err(e) = run_post_create_hook(&sess, &workspace, quiet)
+            .and_then(|()| check_workdir(&sess, &workspace))
+        {
+            let _ = remove_session_files(&full, &sess);
+            return Err(e);
+        }
+        session::record_event("create", &full);
+        // Without the shell wrapper the path goes to stdout, which quiet skips
+        if !quiet || std::env::var_os("BOX_CD_FILE").is_some() {
+            output_cd_path(&workspace);
+        }
+
+        return match local_start(&sess.command, detach) {
+            LocalStart::Nothing => Ok(0),
+            LocalStart::Background => {
+                mux::ensure_server(&sess.name)?;
+                if !quiet {
+                    println!("Session '{}' started in background.", sess.name);
+                    println!("Run `box resume {}` to attach.", sess.name);
+                }
+                Ok(0)
+            }
+            LocalStart::Attach => run_local_command(&sess.name),
+        };
     }
 
     docker::check()?;
 
-    eprintln!("\x1b[2msession:\x1b[0m {}", full);
-    eprintln!("\x1b[2mimage:\x1b[0m {}", cfg.image);
-    eprintln!("\x1b[2mmount:\x1b[0m {}", cfg.mount_path);
-    eprintln!("\x1b[2mstrategy:\x1b[0m {}", cfg.strategy);
-    if !cfg.command.is_empty() {
-        eprintln!("\x1b[2mcommand:\x1b[0m {}", shell_words::join(&cfg.command));
+    // Never silently replace a container box doesn't know about
+    match (docker::container_exists(&full), adopt) {
+        (true, false) => bail!(
+            "Container '{}' already exists but is not a box session. \
+             Use `box create --adopt {}` to manage it as this session, \
+             or remove it with `docker rm -f {}`.",
+            docker::container_label(&full),
+            full,
+            docker::container_label(&full)
+        ),
+        (false, true) => bail!(
+            "No container named '{}' to adopt.",
+            docker::container_label(&full)
+        ),
+        _ => {}
     }
-    if !docker_args.is_empty() {
-        eprintln!("\x1b[2mdocker args:\x1b[0m {}", docker_args);
+    // An adopted container is recorded as it runs, not with box defaults
+    let adopted = if adopt {
+        let settings = docker::container_settings(&full)?;
+        if !explicit_image {
+            cfg.image = settings.image.clone();
+        }
+        cfg.command = settings.command.clone();
+        cfg.env = settings.env.clone();
+        if let Some(path) = settings.mount_path {
+            cfg.mount_path = path;
+        }
+        Some((settings.ports, settings.docker_socket_mount))
+    } else {
+        None
+    };
+    let (ports, docker_socket) = adopted.unwrap_or((ports, docker_socket));
+
+    // Like `docker run`, fetch a missing image, but do it up front so a bad
+    // image name fails before any session state is written
+    if !adopt && (pull || !docker::image_exists(&cfg.image)) {
+        docker::pull_image_with(&cfg.image, quiet)?;
+    }
+
+    let volumes_from = volumes_from.map(|s| resolve_volumes_from(&s)).transpose()?;
+    if let Some(ref socket) = docker_socket {
+        validate_docker_socket(socket)?;
+    }
+    if let Some(ref driver) = storage_driver {
+        validate_storage_driver(driver)?;
+    }
+
+    if !quiet {
+        eprintln!("\x1b[2msession:\x1b[0m {}", full);
+        eprintln!("\x1b[2mimage:\x1b[0m {}", cfg.image);
+        eprintln!("\x1b[2mmount:\x1b[0m {}", cfg.mount_path);
+        eprintln!("\x1b[2mstrategy:\x1b[0m {}", cfg.strategy);
+        if !cfg.command.is_empty() {
+            eprintln!("\x1b[2mcommand:\x1b[0m {}", shell_words::join(&cfg.command));
+        }
+        if let Some(ref from) = from_ref {
+            eprintln!("\x1b[2mfrom:\x1b[0m {}", from);
+        }
+        if !docker_args.is_empty() {
+            eprintln!("\x1b[2mdocker args:\x1b[0m {}", docker_args);
+        }
+        if let Some(ref source) = volumes_from {
+            eprintln!("\x1b[2mvolumes from:\x1b[0m {}", source);
+        }
+        if let Some(ref driver) = storage_driver {
+            eprintln!("\x1b[2mstorage driver:\x1b[0m {}", driver);
+        }
+        if !storage_opts.is_empty() {
+            eprintln!("\x1b[2mstorage opts:\x1b[0m {}", storage_opts.join(" "));
+        }
+        if !ports.is_empty() {
+            eprintln!("\x1b[2mports:\x1b[0m {}", ports.join(" "));
+        }
+        if !resources.is_empty() {
+            eprintln!(
+                "\x1b[2mresources:\x1b[0m {}",
+                resources.docker_args().join(" ")
+            );
+        }
+        if let Some(ref socket) = docker_socket {
+            eprintln!("\x1b[2mdocker socket:\x1b[0m {}", socket);
+            eprintln!();
+        }
+    }
+    if let Some(ref socket) = docker_socket {
+        warn_docker_socket(socket);
+    }
+    if !quiet {
+        eprintln!();
     }
-    eprintln!();
 
-    let sess = session::Session::from(cfg);
+    let mut sess = session::Session::from(cfg);
+    sess.volumes_from = volumes_from;
+    sess.docker_socket_mount = docker_socket;
+    sess.inherit_git_config = inherit_git_config;
+    sess.storage_driver = storage_driver;
+    sess.storage_opts = storage_opts;
+    sess.ports = ports;
+    sess.resources = resources;
+    sess.workdir = workdir;
+    sess.from_ref = from_ref;
+    sess.docker_args = (!docker_args.is_empty()).then(|| docker_args.to_string());
     session::save(&sess)?;
 
+    // The adopted container already has its mounts, so there is no workspace
+    // to prepare, and nothing after this point may remove the container.
+    if adopt {
+        session::record_event("create", &full);
+        if !quiet {
+            println!(
+                "Adopted container '{}' as session '{}'.",
+                docker::container_label(&full),
+                full
+            );
+            println!("Run `box resume {}` to attach.", full);
+        }
+        return Ok(0);
+    }
+
     let home = config::home_dir()?;
+    let workspace = docker::ensure_workspace_with(
+        &home,
+        ws,
+        &sess.project_dir,
+        &sess.strategy,
+        sess.from_ref.as_deref(),
+        clone_depth,
+        quiet,
+    )?;
+    if inherit_git_config {
+        git::inherit_config(Path::new(&workspace), git::INHERITED_CONFIG_KEYS)?;
+    }
+    if let Err(e) = run_post_create_hook(&sess, &workspace, quiet)
+        .and_then(|()| check_workdir(&sess, &workspace))
+    {
+        let _ = remove_session_files(&full, &sess);
+        return Err(e);
+    }
+    session::record_event("create", &full);
     let docker_args_opt = if docker_args.is_empty() {
         None
     } else {
         Some(docker_args)
     };
 
-    docker::remove_container(&full);
     docker::run_container(&docker::DockerRunConfig {
         name: &full,
-        project_dir: &sess.project_dir,
         image: &sess.image,
         mount_path: &sess.mount_path,
         cmd: &sess.command,
         env: &sess.env,
         home: &home,
         docker_args: docker_args_opt,
+        volumes_from: sess.volumes_from.as_deref(),
+        docker_socket_mount: sess.docker_socket_mount.as_deref(),
+        storage_opts: &sess.storage_opts,
+        ports: &sess.ports,
+        resources: &sess.resources,
+        workdir: sess.workdir.as_deref(),
         detach,
-        strategy: &sess.strategy,
+        quiet,
     })
 }
 
-fn cmd_resume(name: &str, docker_args: &str, detach: bool) -> Result<i32> {
-    session::validate_name(name)?;
+/// Run the post-create hook, if installed, in a new session's workspace on
+/// the host. Its output goes to stderr; a non-zero exit is an error.
+fn run_post_create_hook(sess: &session::Session, workspace: &str, quiet: bool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
 
-    let full = session::full_name(name);
+    let Some(hook) = config::load_post_create_hook() else {
+        return Ok(());
+    };
+    if fs::metadata(&hook)?.permissions().mode() & 0o111 == 0 {
+        eprintln!(
+            "Warning: post_create hook '{}' is not executable; skipping it.",
+            hook.display()
+        );
+        return Ok(());
+    }
+
+    if !quiet {
+        eprintln!("\x1b[2mrunning post_create hook:\x1b[0m {}", hook.display());
+    }
+    let status = std::process::Command::new(&hook)
+        .current_dir(workspace)
+        .env("BOX_SESSION", &sess.name)
+        .env("BOX_WORKSPACE", workspace)
+        .env("BOX_PROJECT_DIR", &sess.project_dir)
+        .stdout(std::io::stderr())
+        .status()
+        .map_err(|e| anyhow::anyhow!("Cannot run post_create hook '{}': {}", hook.display(), e))?;
+    if !status.success() {
+        bail!(
+            "post_create hook '{}' exited with code {}; session '{}' was not created.",
+            hook.display(),
+            mux::exit_code(status),
+            sess.name
+        );
+    }
+    Ok(())
+}
+
+fn cmd_batch_create(file: &Path, continue_on_error: bool) -> Result<i32> {
+    let inputs = config::parse_batch_manifest(file)?;
+    if inputs.is_empty() {
+        bail!("Manifest '{}' defines no sessions.", file.display());
+    }
+    let docker_args = std::env::var("BOX_DOCKER_ARGS").unwrap_or_default();
+
+    let mut created = Vec::new();
+    let mut failed = Vec::new();
+    for input in inputs {
+        // Inputs come after their dependencies, so a failed one is already known
+        if let Some(dep) = input.depends_on.iter().find(|d| failed.contains(*d)) {
+            eprintln!(
+                "Error: skipping session '{}': dependency '{}' failed",
+                input.name, dep
+            );
+            failed.push(input.name);
+            continue;
+        }
+        eprintln!("\x1b[2mcreating session:\x1b[0m {}", input.name);
+        let result = cmd_create(
+            &input.name,
+            CreateOptions {
+                image: input.image,
+                docker_args: docker_args.clone(),
+                cmd: input.command,
+                detach: true,
+                local: input.local,
+                strategy: input.strategy,
+                env: input.env,
+                ..Default::default()
+            },
+        );
+        match result {
+            Ok(0) => created.push(input.name),
+            Ok(code) => {
+                eprintln!("Error: exited with code {}", code);
+                failed.push(input.name);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                failed.push(input.name);
+            }
+        }
+        if !failed.is_empty() && !continue_on_error {
+            break;
+        }
+    }
+
+    println!(
+        "Created {} session(s), {} failed.",
+        created.len(),
+        failed.len()
+    );
+    for name in &failed {
+        println!("  failed: {}", name);
+    }
+    Ok(if failed.is_empty() { 0 } else { 1 })
+}
+
+fn cmd_resume(args: ResumeArgs) -> Result<i32> {
+    let ResumeArgs {
+        name,
+        detach,
+        docker_args,
+        image,
+        recreate,
+        pull,
+        workdir,
+        read_only,
+    } = args;
+    session::validate_name(&name)?;
+
+    let full = session::resolve_prefix(&name)?;
     let ws = session::workspace_name(&full);
-    let sess = session::load(&full)?;
+    let mut sess = session::load(&full)?;
 
     if !Path::new(&sess.project_dir).is_dir() {
         bail!("Project directory '{}' no longer exists.", sess.project_dir);
     }
+    // Clone workspaces are self-contained; worktrees need their origin repo
+    if config::is_worktree_strategy(&sess.strategy) {
+        if git::find_root(Path::new(&sess.project_dir)).is_none() {
+            bail!(
+                "Project directory '{}' is no longer a git repository, which the session's {} workspace depends on.",
+                sess.project_dir,
+                sess.strategy
+            );
+        }
+        let workspace = config::workspaces_dir()?.join(ws);
+        if !git::worktree_link_ok(&workspace) {
+            bail!(
+                "Workspace for '{}' is no longer linked to the git repository at '{}' (was it re-initialized?). Run `box rebuild-workspace {}` to recreate it.",
+                full,
+                sess.project_dir,
+                full
+            );
+        }
+    }
+
+    if sess.local && (image.is_some() || recreate || pull) {
+        bail!(
+            "Session '{}' is a local session; --image, --recreate and --pull require a Docker session.",
+            full
+        );
+    }
+
+    if !sess.local && (workdir.is_some() || read_only) {
+        bail!(
+            "Session '{}' is a Docker session; --workdir and --read-only require a local session.",
+            full
+        );
+    }
 
     if sess.local {
-        session::touch_resumed_at(&full)?;
         let home = config::home_dir()?;
-        let workspace = Path::new(&home).join(".box").join("workspaces").join(ws);
-        output_cd_path(&workspace.to_string_lossy());
+        let workspace = config::workspaces_dir_in(&home).join(ws);
+        let target = match workdir {
+            Some(ref dir) => {
+                let target = workspace.join(dir);
+                if !target.is_dir() {
+                    bail!(
+                        "Working directory '{}' does not exist in the workspace.",
+                        dir
+                    );
+                }
+                Some(target)
+            }
+            None => None,
+        };
+        session::touch_resumed_at(&full)?;
+        session::record_event("resume", &full);
+        output_cd_path(&target.as_ref().unwrap_or(&workspace).to_string_lossy());
 
         if !sess.command.is_empty() {
-            return run_local_command(&full);
+            // The server owns the shell, so its cwd can only be changed by
+            // typing a cd into it.
+            let cd = target
+                .map(|t| format!("cd {}\n", shell_words::quote(&t.to_string_lossy())).into_bytes());
+            return mux::run(&full, cd, read_only);
         }
         return Ok(0);
     }
@@ -648,6 +2059,13 @@ fn cmd_resume(name: &str, docker_args: &str, detach: bool) -> Result<i32> {
     docker::check()?;
 
     if docker::container_is_running(&full) {
+        if image.is_some() || recreate {
+            bail!(
+                "Session '{}' is still running. Stop it first with `box stop {}`.",
+                full,
+                full
+            );
+        }
         if detach {
             println!("Session '{}' is already running.", full);
             return Ok(0);
@@ -655,10 +2073,60 @@ fn cmd_resume(name: &str, docker_args: &str, detach: bool) -> Result<i32> {
         return docker::attach_container(&full);
     }
 
+    // Everything that can fail is checked before the container or metadata
+    // is touched, so a bad image name leaves the session as it was
+    let container_exists = docker::container_exists(&full);
+    if let Some(ref image) = image {
+        if image.trim().is_empty() {
+            bail!("Image name is required.");
+        }
+        // Restarting the existing container would silently keep the old image
+        if !recreate && container_exists {
+            bail!(
+                "Session '{}' already has a container from image '{}'. Add --recreate to replace it.",
+                full,
+                sess.image
+            );
+        }
+    }
+    if pull && !recreate && container_exists {
+        bail!(
+            "Session '{}' already has a container; --pull only takes effect with --recreate.",
+            full
+        );
+    }
+    let run_new = recreate || !container_exists;
+    let volumes_from = if run_new {
+        // Re-resolve the volumes source since the container is being recreated
+        let volumes_from = sess
+            .volumes_from
+            .as_deref()
+            .map(resolve_volumes_from)
+            .transpose()?;
+        if let Some(ref socket) = sess.docker_socket_mount {
+            validate_docker_socket(socket)?;
+            warn_docker_socket(socket);
+        }
+        if let Some(ref driver) = sess.storage_driver {
+            validate_storage_driver(driver)?;
+        }
+        ensure_resume_image(image.as_deref().unwrap_or(&sess.image), pull)?;
+        volumes_from
+    } else {
+        None
+    };
+
+    if let Some(image) = image {
+        sess.image = image;
+        session::save(&sess)?;
+        eprintln!("\x1b[2mimage:\x1b[0m {}", sess.image);
+    }
+
     println!("Resuming session '{}'...", full);
     session::touch_resumed_at(&full)?;
+    session::record_event("resume", &full);
 
-    if docker::container_exists(&full) {
+    if !run_new {
         if detach {
             docker::start_container_detached(&full)
         } else {
@@ -666,29 +2134,111 @@ fn cmd_resume(name: &str, docker_args: &str, detach: bool) -> Result<i32> {
         }
     } else {
         let home = config::home_dir()?;
+        // Flags given now win over those saved at creation, then $BOX_DOCKER_ARGS
+        let docker_args = docker_args
+            .or_else(|| sess.docker_args.clone())
+            .or_else(|| std::env::var("BOX_DOCKER_ARGS").ok())
+            .unwrap_or_default();
         let docker_args_opt = if docker_args.is_empty() {
             None
         } else {
-            Some(docker_args)
+            Some(docker_args.as_str())
         };
 
+        docker::ensure_workspace(
+            &home,
+            session::workspace_name(&full),
+            &sess.project_dir,
+            &sess.strategy,
+            sess.from_ref.as_deref(),
+        )?;
         docker::remove_container(&full);
         docker::run_container(&docker::DockerRunConfig {
             name: &full,
-            project_dir: &sess.project_dir,
             image: &sess.image,
             mount_path: &sess.mount_path,
             cmd: &sess.command,
             env: &sess.env,
             home: &home,
             docker_args: docker_args_opt,
+            volumes_from: volumes_from.as_deref(),
+            docker_socket_mount: sess.docker_socket_mount.as_deref(),
+            storage_opts: &sess.storage_opts,
+            ports: &sess.ports,
+            resources: &sess.resources,
+            workdir: sess.workdir.as_deref(),
             detach,
-            strategy: &sess.strategy,
+            quiet: false,
         })
     }
 }
 
-fn cmd_remove(name: &str, force: bool) -> Result<i32> {
+/// Make sure the image a container is about to be recreated from is present.
+/// A session's image going missing is not silently re-pulled, since the
+/// registry may now serve something different; `--pull` opts into that.
+fn ensure_resume_image(image: &str, pull: bool) -> Result<()> {
+    if pull {
+        return docker::pull_image(image);
+    }
+    if !docker::image_exists(image) {
+        bail!(
+            "Image '{}' not found locally; run `docker pull {}` or pass --image",
+            image,
+            image
+        );
+    }
+    Ok(())
+}
+
+/// Whether a reply to a `[y/N]` prompt means yes.
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// Ask a `[y/N]` question on the terminal. Without a terminal to ask on
+/// (e.g. in a pipeline or cron) this fails rather than assume yes, so
+/// destructive commands need an explicit `--yes` there.
+fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() {
+        bail!(
+            "{} Not running in a terminal; pass --yes to confirm.",
+            question
+        );
+    }
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(is_yes(&answer))
+}
+
+/// Ask before using a `.box.toml` that hasn't been trusted with its current
+/// contents, since its command and env run on the host. Without a terminal
+/// there is no `--yes` to fall back on, so it always fails.
+fn ensure_project_config_trusted(project_dir: &str) -> Result<()> {
+    let Some((path, content)) = config::read_project_config(project_dir)? else {
+        return Ok(());
+    };
+    let store = config::trusted_projects_path()?;
+    if config::is_project_config_trusted(&store, &path, &content) {
+        return Ok(());
+    }
+    if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() {
+        bail!(
+            "'{}' is new or has changed since it was last trusted. Run box in a terminal to review and trust it.",
+            path.display()
+        );
+    }
+    eprintln!("{}:\n", path.display());
+    eprintln!("{}", content.trim_end());
+    eprintln!();
+    if !confirm("This project config is new or has changed. Trust it?")? {
+        bail!("Not using untrusted '{}'.", path.display());
+    }
+    config::trust_project_config(&store, &path, &content)
+}
+
+fn cmd_remove(name: &str, force: bool, yes: bool) -> Result<i32> {
     session::validate_name(name)?;
 
     // If no '/' in name, remove entire workspace (all sessions)
@@ -697,7 +2247,25 @@ fn cmd_remove(name: &str, force: bool) -> Result<i32> {
         if !session::workspace_exists(ws)? {
             bail!("Workspace '{}' not found.", ws);
         }
+        let archived = session::archived_workspace_sessions(ws)?;
+        if !archived.is_empty() {
+            bail!(
+                "Workspace '{}' is still used by archived session(s): {}. Unarchive and remove them first.",
+                ws,
+                archived.join(", ")
+            );
+        }
         let ws_sessions = session::workspace_sessions(ws)?;
+        if !yes
+            && !confirm(&format!(
+                "Remove workspace '{}' with {} session(s)?",
+                ws,
+                ws_sessions.len()
+            ))?
+        {
+            println!("Aborted.");
+            return Ok(1);
+        }
         let mut strategy = String::from("clone");
         let mut project_dir = String::new();
 
@@ -748,8 +2316,11 @@ fn cmd_remove(name: &str, force: bool) -> Result<i32> {
             }
         }
 
-        docker::remove_workspace(ws, &strategy);
+        docker::remove_workspace(ws, &strategy, &project_dir);
         session::remove_workspace_dir(ws)?;
+        for sess_name in &ws_sessions {
+            session::record_event("remove", &format!("{}/{}", ws, sess_name));
+        }
 
         if !project_dir.is_empty() {
             output_cd_path(&project_dir);
@@ -764,7 +2335,6 @@ fn cmd_remove(name: &str, force: bool) -> Result<i32> {
 
     // Individual session removal
     let full = session::full_name(name);
-    let ws = session::workspace_name(&full);
 
     if !session::session_exists(&full)? {
         bail!("Session '{}' not found.", full);
@@ -785,13 +2355,8 @@ fn cmd_remove(name: &str, force: bool) -> Result<i32> {
                 );
             }
         }
-        session::remove_dir(&full)?;
-        // If last session in workspace, remove workspace too
-        let remaining = session::workspace_sessions(ws).unwrap_or_default();
-        if remaining.is_empty() {
-            docker::remove_workspace(ws, &sess.strategy);
-            let _ = session::remove_workspace_dir(ws);
-        }
+        remove_session_files(&full, &sess)?;
+        session::record_event("remove", &full);
         output_cd_path(&sess.project_dir);
         println!("Session '{}' removed.", full);
         return Ok(0);
@@ -812,36 +2377,205 @@ fn cmd_remove(name: &str, force: bool) -> Result<i32> {
         }
     }
 
-    docker::remove_container(&full);
-    session::remove_dir(&full)?;
-    // If last session in workspace, remove workspace too
-    let remaining = session::workspace_sessions(ws).unwrap_or_default();
-    if remaining.is_empty() {
-        docker::remove_workspace(ws, &sess.strategy);
-        let _ = session::remove_workspace_dir(ws);
-    }
-
+    remove_session_files(&full, &sess)?;
+    session::record_event("remove", &full);
     output_cd_path(&sess.project_dir);
     println!("Session '{}' removed.", full);
     Ok(0)
 }
 
-fn cmd_stop(name: &str) -> Result<i32> {
-    session::validate_name(name)?;
-
-    let full = session::full_name(name);
-
-    if !session::session_exists(&full)? {
-        bail!("Session '{}' not found.", full);
+/// Remove a stopped session's container and metadata, and its workspace if
+/// no other session uses it.
+fn remove_session_files(full: &str, sess: &session::Session) -> Result<()> {
+    if !sess.local {
+        docker::remove_container(full);
+    }
+    session::remove_dir(full)?;
+    // If last session in workspace, remove workspace too; an archived
+    // session still needs it for when it is restored
+    let ws = session::workspace_name(full);
+    if !session::workspace_in_use(ws).unwrap_or(true) {
+        docker::remove_workspace(ws, &sess.strategy, &sess.project_dir);
+        let _ = session::remove_workspace_dir(ws);
     }
+    Ok(())
+}
 
+fn cmd_archive(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
     let sess = session::load(&full)?;
 
-    if sess.local {
+    let running = if sess.local {
+        session::is_local_running(&full)
+    } else {
+        docker::check()?;
+        docker::container_is_running(&full)
+    };
+    if running {
+        bail!(
+            "Session '{}' is still running. Stop it first with `box stop {}`.",
+            full,
+            full
+        );
+    }
+
+    session::archive(&full)?;
+    println!(
+        "Session '{}' archived. Run `box unarchive {}` to restore it.",
+        full, full
+    );
+    Ok(0)
+}
+
+fn cmd_unarchive(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    session::unarchive(&full)?;
+    let sess = session::load(&full)?;
+
+    // The workspace may have been cleaned up while the session was archived
+    let home = config::home_dir()?;
+    let ws = session::workspace_name(&full);
+    let workspace = config::workspaces_dir_in(&home).join(ws);
+    if !workspace.is_dir() {
+        if Path::new(&sess.project_dir).is_dir() {
+            docker::ensure_workspace(
+                &home,
+                ws,
+                &sess.project_dir,
+                &sess.strategy,
+                sess.from_ref.as_deref(),
+            )?;
+        } else {
+            eprintln!(
+                "Warning: project directory '{}' no longer exists; workspace '{}' was not recreated.",
+                sess.project_dir, ws
+            );
+        }
+    }
+
+    println!("Session '{}' restored.", full);
+    Ok(0)
+}
+
+/// Remove every stopped session, or only those of the current project.
+fn cmd_prune(dry_run: bool, project_only: bool, yes: bool, force: bool) -> Result<i32> {
+    let mut sessions = session::list()?;
+    mark_running(&mut sessions)?;
+    sessions.retain(|s| !s.running);
+    if project_only {
+        let cwd = std::env::current_dir()?;
+        let cwd = std::fs::canonicalize(&cwd).unwrap_or(cwd);
+        match resolve_project_dir(&cwd, &sessions) {
+            Some(project) => sessions.retain(|s| s.project_dir == project),
+            None => sessions.clear(),
+        }
+    }
+
+    // Pruning every session of a workspace deletes the workspace too, so
+    // leave those with work that exists nowhere else unless forced
+    if !force {
+        let home = config::home_dir()?;
+        let mut workspaces: Vec<&str> = sessions
+            .iter()
+            .map(|s| session::workspace_name(&s.name))
+            .collect();
+        workspaces.sort_unstable();
+        workspaces.dedup();
+        let mut keep = Vec::new();
+        for ws in workspaces {
+            let pruned = sessions
+                .iter()
+                .filter(|s| session::workspace_name(&s.name) == ws)
+                .count();
+            let removes_workspace = session::workspace_sessions(ws)?.len() == pruned
+                && session::archived_workspace_sessions(ws)?.is_empty();
+            let Some(project_dir) = sessions
+                .iter()
+                .find(|s| session::workspace_name(&s.name) == ws)
+                .map(|s| s.project_dir.clone())
+            else {
+                continue;
+            };
+            let dir = config::workspaces_dir_in(&home).join(ws);
+            if removes_workspace && workspace_has_unsaved_work(&dir, &project_dir) {
+                println!(
+                    "Skipping workspace '{}': it has uncommitted changes or unpushed commits (use --force to remove it).",
+                    ws
+                );
+                keep.push(ws.to_string());
+            }
+        }
+        sessions.retain(|s| !keep.iter().any(|ws| ws == session::workspace_name(&s.name)));
+    }
+
+    if dry_run {
+        for s in &sessions {
+            println!("Would remove '{}'.", s.name);
+        }
+        println!("Would remove {} session(s).", sessions.len());
+        return Ok(0);
+    }
+    if sessions.is_empty() {
+        println!("Removed 0 session(s).");
+        return Ok(0);
+    }
+    if !yes && !confirm(&format!("Remove {} stopped session(s)?", sessions.len()))? {
+        println!("Aborted.");
+        return Ok(1);
+    }
+
+    let mut removed = 0;
+    let mut failed = 0;
+    for s in &sessions {
+        match session::load(&s.name).and_then(|sess| remove_session_files(&s.name, &sess)) {
+            Ok(()) => {
+                println!("Session '{}' removed.", s.name);
+                removed += 1;
+            }
+            Err(e) => {
+                eprintln!("Error: {}: {}", s.name, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        println!("Removed {} session(s), {} failed.", removed, failed);
+        Ok(1)
+    } else {
+        println!("Removed {} session(s).", removed);
+        Ok(0)
+    }
+}
+
+/// Whether a workspace has uncommitted changes or commits that exist nowhere
+/// else, which deleting it would lose.
+fn workspace_has_unsaved_work(workspace: &Path, project_dir: &str) -> bool {
+    workspace.is_dir()
+        && (git::has_uncommitted_changes(workspace)
+            || git::unsaved_commits(workspace, Path::new(project_dir))
+                .map(|commits| !commits.is_empty())
+                .unwrap_or(false))
+}
+
+fn cmd_stop(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+
+    let full = session::resolve_prefix(name)?;
+
+    if !session::session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+
+    let sess = session::load(&full)?;
+
+    if sess.local {
         if !session::is_local_running(&full) {
             bail!("Session '{}' is not running.", full);
         }
         mux::send_kill(&full)?;
+        session::record_event("stop", &full);
         println!("Session '{}' stopped.", full);
         return Ok(0);
     }
@@ -852,13 +2586,86 @@ fn cmd_stop(name: &str) -> Result<i32> {
         bail!("Session '{}' is not running.", full);
     }
 
-    docker::stop_container(&full)
+    let code = docker::stop_container(&full)?;
+    if code == 0 {
+        session::record_event("stop", &full);
+    }
+    Ok(code)
 }
 
-fn cmd_exec(name: &str, cmd: &[String]) -> Result<i32> {
+/// Block until a session's command exits and return its exit code: the
+/// code recorded by the mux server for a local session, `docker wait` for a
+/// Docker session.
+fn cmd_wait(name: &str) -> Result<i32> {
     session::validate_name(name)?;
 
-    let full = session::full_name(name);
+    let full = session::resolve_prefix(name)?;
+
+    if !session::session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+
+    let sess = session::load(&full)?;
+
+    if sess.local {
+        while session::is_local_running(&full) {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+        return match session::read_exit_code(&full) {
+            Some(code) => Ok(code),
+            None => bail!("Session '{}' has no recorded exit status.", full),
+        };
+    }
+
+    docker::check()?;
+    if !docker::container_exists(&full) {
+        bail!("Container for session '{}' not found.", full);
+    }
+    docker::wait_container(&full)
+}
+
+/// Stop every running session, or only those of the current project.
+fn cmd_stop_many(project_only: bool) -> Result<i32> {
+    let mut sessions = session::list()?;
+    mark_running(&mut sessions)?;
+    sessions.retain(|s| s.running);
+    if project_only {
+        let cwd = std::env::current_dir()?;
+        let cwd = std::fs::canonicalize(&cwd).unwrap_or(cwd);
+        match resolve_project_dir(&cwd, &sessions) {
+            Some(project) => sessions.retain(|s| s.project_dir == project),
+            None => sessions.clear(),
+        }
+    }
+
+    let mut stopped = 0;
+    let mut failed = 0;
+    for s in &sessions {
+        match cmd_stop(&s.name) {
+            Ok(0) => stopped += 1,
+            Ok(code) => {
+                eprintln!("Error: stopping '{}' exited with code {}", s.name, code);
+                failed += 1;
+            }
+            Err(e) => {
+                eprintln!("Error: {}: {}", s.name, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        println!("Stopped {} session(s), {} failed.", stopped, failed);
+        Ok(1)
+    } else {
+        println!("Stopped {} session(s).", stopped);
+        Ok(0)
+    }
+}
+
+fn cmd_exec(name: &str, cmd: &[String], detach: bool, no_tty: bool) -> Result<i32> {
+    session::validate_name(name)?;
+
+    let full = session::resolve_prefix(name)?;
     let ws = session::workspace_name(&full);
 
     if !session::session_exists(&full)? {
@@ -869,13 +2676,23 @@ fn cmd_exec(name: &str, cmd: &[String]) -> Result<i32> {
 
     if sess.local {
         let home = config::home_dir()?;
-        let workspace = Path::new(&home).join(".box").join("workspaces").join(ws);
-        return mux::run_standalone(mux::MuxConfig {
+        let workspace = config::workspaces_dir_in(&home).join(ws);
+        if detach {
+            mux::start_detached(&full, cmd, &sess.working_dir(&workspace))?;
+            println!("Started in {}: {}", full, shell_words::join(cmd));
+            return Ok(0);
+        }
+        let mux_config = mux::MuxConfig {
             session_name: full.clone(),
             command: cmd.to_vec(),
-            working_dir: Some(workspace.to_string_lossy().to_string()),
+            working_dir: Some(sess.working_dir(&workspace).to_string_lossy().to_string()),
             prefix_key: config::load_mux_prefix_key(),
-        });
+            resize_on_attach: false,
+        };
+        if no_tty {
+            return mux::run_fallback(&mux_config);
+        }
+        return mux::run_standalone(mux_config);
     }
 
     docker::check()?;
@@ -884,39 +2701,187 @@ fn cmd_exec(name: &str, cmd: &[String]) -> Result<i32> {
         bail!("Session '{}' is not running.", full);
     }
 
+    if detach {
+        let code = docker::exec_container_detached(&full, cmd)?;
+        if code == 0 {
+            println!("Started in {}: {}", full, shell_words::join(cmd));
+        }
+        return Ok(code);
+    }
+    if no_tty {
+        return docker::exec_container_plain(&full, cmd);
+    }
     docker::exec_container(&full, cmd)
 }
 
-fn cmd_cd(name: &str) -> Result<i32> {
+/// Everything `box info` reports about a session.
+#[derive(serde::Serialize)]
+struct SessionInfo {
+    name: String,
+    mode: &'static str,
+    image: String,
+    mount_path: String,
+    command: String,
+    env: Vec<String>,
+    strategy: String,
+    from_ref: Option<String>,
+    docker_args: Option<String>,
+    storage_driver: Option<String>,
+    storage_opts: Vec<String>,
+    project_dir: String,
+    workspace: String,
+    created_at: String,
+    resumed_at: Option<String>,
+    created_by: Option<String>,
+    created_host: Option<String>,
+    running: bool,
+    container_id: Option<String>,
+    container_status: Option<String>,
+}
+
+fn cmd_info(name: &str, json: bool) -> Result<i32> {
     session::validate_name(name)?;
     let full = session::full_name(name);
-    let ws = session::workspace_name(&full);
-    if !session::session_exists(&full)? {
-        bail!("Session '{}' not found.", full);
+    let sess = session::load(&full)?;
+    let summary = session::summary(&full)?;
+
+    let (running, container) = if sess.local {
+        (session::is_local_running(&full), None)
+    } else {
+        docker::check()?;
+        (
+            docker::container_is_running(&full),
+            docker::container_state(&full),
+        )
+    };
+    let (container_id, container_status) = container.unzip();
+    let info = SessionInfo {
+        mode: if sess.local { "local" } else { "docker" },
+        image: sess.image,
+        mount_path: sess.mount_path,
+        command: shell_words::join(&sess.command),
+        env: sess.env,
+        strategy: sess.strategy,
+        from_ref: sess.from_ref,
+        docker_args: sess.docker_args,
+        storage_driver: sess.storage_driver,
+        storage_opts: sess.storage_opts,
+        project_dir: sess.project_dir,
+        workspace: config::workspaces_dir()?
+            .join(session::workspace_name(&full))
+            .to_string_lossy()
+            .to_string(),
+        created_at: summary.created_at,
+        resumed_at: summary.resumed_at,
+        created_by: summary.created_by,
+        created_host: summary.created_host,
+        running,
+        container_id,
+        container_status,
+        name: full,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(0);
+    }
+
+    let or_none = |s: &str| {
+        if s.is_empty() {
+            "(none)".to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    println!("Name:         {}", info.name);
+    println!("Mode:         {}", info.mode);
+    println!(
+        "Status:       {}",
+        if info.running { "running" } else { "stopped" }
+    );
+    println!("Image:        {}", info.image);
+    println!("Mount path:   {}", info.mount_path);
+    println!("Command:      {}", or_none(&info.command));
+    println!("Strategy:     {}", info.strategy);
+    if let Some(ref from) = info.from_ref {
+        println!("From:         {}", from);
+    }
+    println!("Project:      {}", info.project_dir);
+    println!("Workspace:    {}", info.workspace);
+    println!("Created:      {}", info.created_at);
+    match (&info.created_by, &info.created_host) {
+        (Some(user), Some(host)) => println!("Created by:   {}@{}", user, host),
+        (Some(user), None) => println!("Created by:   {}", user),
+        (None, Some(host)) => println!("Created on:   {}", host),
+        (None, None) => {}
+    }
+    if let Some(ref resumed) = info.resumed_at {
+        println!("Resumed:      {}", resumed);
+    }
+    if !sess.local {
+        println!(
+            "Docker args:  {}",
+            or_none(info.docker_args.as_deref().unwrap_or_default())
+        );
+        println!(
+            "Storage:      {}",
+            or_none(info.storage_driver.as_deref().unwrap_or_default())
+        );
+        if !info.storage_opts.is_empty() {
+            println!("Storage opts: {}", info.storage_opts.join(", "));
+        }
+        println!(
+            "Container:    {}",
+            match (&info.container_id, &info.container_status) {
+                (Some(id), Some(status)) => format!("{} ({})", &id[..id.len().min(12)], status),
+                _ => "(none)".to_string(),
+            }
+        );
+    }
+    println!(
+        "Env:          {}",
+        if info.env.is_empty() { "(none)" } else { "" }
+    );
+    for entry in &info.env {
+        println!("  {}", entry);
     }
-    let home = config::home_dir()?;
-    let path = Path::new(&home).join(".box").join("workspaces").join(ws);
-    output_cd_path(&path.to_string_lossy());
     Ok(0)
 }
 
-fn cmd_path(name: &str) -> Result<i32> {
+/// Resolve the directory `box cd` / `box path` point at: the session's
+/// workspace, or with `origin` the original project directory behind it.
+fn session_dir(name: &str, origin: bool) -> Result<String> {
     session::validate_name(name)?;
     let full = session::full_name(name);
     let ws = session::workspace_name(&full);
     if !session::session_exists(&full)? {
         bail!("Session '{}' not found.", full);
     }
+    if origin {
+        let sess = session::load(&full)?;
+        return Ok(session::resolve_original_project_dir(&sess.project_dir));
+    }
     let home = config::home_dir()?;
-    let path = Path::new(&home).join(".box").join("workspaces").join(ws);
-    println!("{}", path.display());
+    let path = config::workspaces_dir_in(&home).join(ws);
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn cmd_cd(name: &str, origin: bool) -> Result<i32> {
+    let path = session_dir(name, origin)?;
+    output_cd_path(&path);
+    Ok(0)
+}
+
+fn cmd_path(name: &str, origin: bool) -> Result<i32> {
+    let path = session_dir(name, origin)?;
+    println!("{}", path);
     Ok(0)
 }
 
 fn cmd_origin() -> Result<i32> {
     let cwd = std::env::current_dir()?;
     let home = config::home_dir()?;
-    let workspaces = Path::new(&home).join(".box").join("workspaces");
+    let workspaces = config::workspaces_dir_in(&home);
     let workspaces = std::fs::canonicalize(&workspaces).unwrap_or(workspaces);
     let cwd_canon = std::fs::canonicalize(&cwd).unwrap_or_else(|_| cwd.clone());
 
@@ -945,657 +2910,4068 @@ fn cmd_origin() -> Result<i32> {
     Ok(0)
 }
 
-fn cmd_config_zsh() -> Result<i32> {
-    print!(
-        r#"__box_sessions() {{
-    local -a sessions
-    if [[ -d "$HOME/.box/sessions" ]]; then
-        for ws in "$HOME/.box/sessions"/*(N/); do
-            local ws_name=${{ws:t}}
-            for sess in "$ws"/*(N/); do
-                if [[ -f "$sess/project_dir" ]]; then
-                    local sess_name=${{sess:t}}
-                    local full_name="$ws_name/$sess_name"
-                    local desc=""
-                    desc=$(< "$sess/project_dir")
-                    desc=${{desc/#$HOME/\~}}
-                    sessions+=("$full_name:[$desc]")
-                fi
-            done
-        done
-    fi
-    if (( ${{#sessions}} )); then
-        _describe 'session' sessions
-    fi
-}}
+/// Merge environment layers into a sorted `(key, value, source)` list.
+/// Later layers override earlier ones. Session entries without `=` are
+/// passed through from the host (like `docker run -e KEY`), so their value
+/// comes from `host` and they are tagged `inherited`.
+fn merge_env(
+    image_env: &[String],
+    session_env: &[String],
+    host: impl Fn(&str) -> Option<String>,
+) -> Vec<(String, String, &'static str)> {
+    let mut merged: std::collections::BTreeMap<String, (String, &'static str)> =
+        std::collections::BTreeMap::new();
+    for entry in image_env {
+        if let Some((k, v)) = entry.split_once('=') {
+            merged.insert(k.to_string(), (v.to_string(), "image"));
+        }
+    }
+    for entry in session_env {
+        match entry.split_once('=') {
+            Some((k, v)) => {
+                merged.insert(k.to_string(), (v.to_string(), "session"));
+            }
+            None => {
+                if let Some(v) = host(entry) {
+                    merged.insert(entry.clone(), (v, "inherited"));
+                }
+            }
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(k, (v, src))| (k, v, src))
+        .collect()
+}
 
-_box() {{
-    local curcontext="$curcontext" state line
-    typeset -A opt_args
+/// The environment of a local session's command: the mux server's child
+/// inherits the `host` variables box is started with, overlaid with the
+/// session's `KEY=VALUE` entries and `BOX_SESSION`.
+fn local_env(
+    name: &str,
+    host: &[String],
+    session_env: &[String],
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Vec<(String, String, &'static str)> {
+    let mut env = host.to_vec();
+    env.extend(session_env.iter().filter(|e| e.contains('=')).cloned());
+    let mut vars = merge_env(&[], &env, lookup);
+    vars.retain(|(k, _, _)| k != "BOX_SESSION");
+    vars.push(("BOX_SESSION".to_string(), name.to_string(), "session"));
+    vars.sort_by(|a, b| a.0.cmp(&b.0));
+    vars
+}
 
-    _arguments -C \
-        '1: :->subcmd' \
-        '*:: :->args'
+fn cmd_show_env(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    if !session::session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+    let sess = session::load(&full)?;
 
-    case $state in
-        subcmd)
-            local -a subcmds
-            subcmds=(
-                'create:Create a new session'
-                'resume:Resume an existing session'
-                'remove:Remove a session'
-                'stop:Stop a running session'
-                'exec:Run a command in a running session'
-                'list:List sessions'
-                'cd:Print the host project directory for a session'
-                'path:Print workspace path for a session'
-                'origin:Navigate back to the original project directory'
-                'upgrade:Self-update to the latest version'
-                'config:Output shell configuration'
-            )
-            _describe 'subcommand' subcmds
-            ;;
-        args)
-            case $words[1] in
-                create)
-                    _arguments \
-                        '-d[Run in the background]' \
-                        '--image=[Docker image to use]:image' \
-                        '--docker-args=[Extra Docker flags]:args' \
-                        '--local[Create a local session (default)]' \
-                        '--docker[Create a Docker session]' \
-                        '--strategy=[Workspace strategy (clone or worktree)]:strategy:(clone worktree)' \
-                        '1:session name:' \
-                        '*:command:'
-                    ;;
-                resume)
-                    _arguments \
-                        '-d[Run container in the background]' \
-                        '--docker-args=[Extra Docker flags]:args' \
-                        '1:session name:__box_sessions'
-                    ;;
-                exec)
-                    _arguments \
-                        '1:session name:__box_sessions' \
-                        '*:command:'
-                    ;;
-                list|ls)
-                    _arguments \
-                        '--running[Show only running sessions]' \
-                        '-r[Show only running sessions]' \
-                        '--stopped[Show only stopped sessions]' \
-                        '-s[Show only stopped sessions]' \
-                        '--project[Show only sessions for the current project]' \
-                        '-p[Show only sessions for the current project]' \
-                        '--quiet[Only print session names]' \
-                        '-q[Only print session names]'
-                    ;;
-                remove|stop|path|cd)
-                    if (( CURRENT == 2 )); then
-                        __box_sessions
-                    fi
-                    ;;
-                config)
-                    if (( CURRENT == 2 )); then
-                        local -a shells
-                        shells=('zsh:Zsh completion script' 'bash:Bash completion script')
-                        _describe 'shell' shells
-                    fi
-                    ;;
-            esac
-            ;;
-    esac
-}}
-compdef _box box
+    let vars = if sess.local {
+        let host: Vec<String> = std::env::vars()
+            .filter(|(k, _)| k != "BOX_CD_FILE")
+            .map(|(k, _)| k)
+            .collect();
+        local_env(&full, &host, &sess.env, |k| std::env::var(k).ok())
+    } else {
+        docker::check()?;
+        let image_env = docker::image_env(&sess.image)?;
+        merge_env(&image_env, &sess.env, |k| std::env::var(k).ok())
+    };
 
-box() {{
-    local __box_cd_file
-    __box_cd_file=$(mktemp "/tmp/.box-cd.XXXXXX")
-    BOX_CD_FILE="$__box_cd_file" command box "$@"
-    local __box_exit=$?
-    if [[ -s "$__box_cd_file" ]]; then
-        local __box_dir
-        __box_dir=$(<"$__box_cd_file")
-        cd "$__box_dir"
-    fi
-    rm -f "$__box_cd_file"
-    return $__box_exit
-}}
-"#
-    );
+    for (key, value, source) in &vars {
+        println!("{}={}  \x1b[2m[{}]\x1b[0m", key, value, source);
+    }
     Ok(0)
 }
 
-fn cmd_config_bash() -> Result<i32> {
-    print!(
-        r#"_box() {{
-    local cur prev words cword
-    _init_completion || return
+/// Debug types `connect-to-debugger` can write a `launch.json` entry for.
+const DEBUG_TYPES: &[&str] = &["debugpy", "node"];
 
-    local subcommands="create resume remove stop exec list cd path origin upgrade config"
-    local session_cmds="resume remove stop exec cd path"
+/// Build a VS Code `launch.json` attach configuration for a DAP endpoint.
+fn debugger_launch_config(
+    name: &str,
+    debug_type: &str,
+    host: &str,
+    port: u16,
+    path_mapping: Option<(&str, &str)>,
+) -> serde_json::Value {
+    let mut config = serde_json::json!({
+        "name": format!("Attach to box {}", name),
+        "type": debug_type,
+        "request": "attach",
+    });
+    if debug_type == "node" {
+        config["address"] = host.into();
+        config["port"] = port.into();
+        if let Some((local_root, remote_root)) = path_mapping {
+            config["localRoot"] = local_root.into();
+            config["remoteRoot"] = remote_root.into();
+        }
+    } else {
+        config["connect"] = serde_json::json!({ "host": host, "port": port });
+        if let Some((local_root, remote_root)) = path_mapping {
+            config["pathMappings"] =
+                serde_json::json!([{ "localRoot": local_root, "remoteRoot": remote_root }]);
+        }
+    }
+    config
+}
 
-    if [[ $cword -eq 1 ]]; then
-        COMPREPLY=($(compgen -W "$subcommands" -- "$cur"))
-        return
-    fi
+/// Whether `host:port` accepts a TCP connection within `timeout`.
+fn port_accepts(host: &str, port: u16, timeout: std::time::Duration) -> bool {
+    use std::net::ToSocketAddrs;
+    (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .is_some_and(|addr| std::net::TcpStream::connect_timeout(&addr, timeout).is_ok())
+}
 
-    local subcmd="${{words[1]}}"
+fn cmd_connect_to_debugger(name: &str, port: u16, debug_type: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
 
-    case "$subcmd" in
-        create)
-            case "$cur" in
-                -*)
-                    COMPREPLY=($(compgen -W "-d --image --docker-args --local --docker --strategy" -- "$cur"))
-                    ;;
-            esac
-            ;;
-        resume)
-            case "$cur" in
-                -*)
-                    COMPREPLY=($(compgen -W "-d --docker-args" -- "$cur"))
-                    ;;
-                *)
-                    if [[ $cword -eq 2 ]]; then
-                        local sessions=""
-                        if [[ -d "$HOME/.box/sessions" ]]; then
-                            for ws in "$HOME/.box/sessions"/*/; do
-                                local ws_name=$(basename "$ws")
-                                for sess in "$ws"*/; do
-                                    [[ -f "$sess/project_dir" ]] && sessions+=" $ws_name/$(basename "$sess")"
-                                done
-                            done
-                        fi
-                        COMPREPLY=($(compgen -W "$sessions" -- "$cur"))
-                    fi
-                    ;;
-            esac
-            ;;
-        exec)
-            if [[ $cword -eq 2 ]]; then
-                local sessions=""
-                if [[ -d "$HOME/.box/sessions" ]]; then
-                    for ws in "$HOME/.box/sessions"/*/; do
-                        local ws_name=$(basename "$ws")
-                        for sess in "$ws"*/; do
-                            [[ -f "$sess/project_dir" ]] && sessions+=" $ws_name/$(basename "$sess")"
-                        done
-                    done
-                fi
-                COMPREPLY=($(compgen -W "$sessions" -- "$cur"))
-            fi
-            ;;
-        list|ls)
-            case "$cur" in
-                -*)
-                    COMPREPLY=($(compgen -W "--running -r --stopped -s --project -p --quiet -q" -- "$cur"))
-                    ;;
-            esac
-            ;;
-        remove|stop|path|cd)
-            if [[ $cword -eq 2 ]]; then
-                local sessions=""
-                if [[ -d "$HOME/.box/sessions" ]]; then
-                    for ws in "$HOME/.box/sessions"/*/; do
-                        local ws_name=$(basename "$ws")
-                        for sess in "$ws"*/; do
-                            [[ -f "$sess/project_dir" ]] && sessions+=" $ws_name/$(basename "$sess")"
-                        done
-                    done
-                fi
-                COMPREPLY=($(compgen -W "$sessions" -- "$cur"))
-            fi
-            ;;
-        config)
-            if [[ $cword -eq 2 ]]; then
-                COMPREPLY=($(compgen -W "zsh bash" -- "$cur"))
-            fi
-            ;;
-    esac
-}}
-complete -F _box box
+    let (host, port, path_mapping) = if sess.local {
+        if !port_accepts("127.0.0.1", port, std::time::Duration::from_secs(1)) {
+            bail!(
+                "Nothing is listening on localhost:{}. Start the debug adapter in session '{}' first.",
+                port,
+                full
+            );
+        }
+        ("localhost".to_string(), port, None)
+    } else {
+        docker::check()?;
+        if !docker::container_is_running(&full) {
+            bail!(
+                "Session '{}' is not running. Use `box resume {}` first.",
+                full,
+                full
+            );
+        }
+        let workspace = config::workspaces_dir()?
+            .join(session::workspace_name(&full))
+            .to_string_lossy()
+            .to_string();
+        let published = docker::container_port_mapping(&full, port).is_some();
+        let (host, port) = docker_port_endpoint(&full, port)?;
+        // An adapter listening only on the container's loopback can't be
+        // reached on its IP; tunnel to it
+        if !published && !port_accepts(&host, port, std::time::Duration::from_secs(1)) {
+            docker::start_port_tunnel(&full, &host, port)?;
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(3);
+            while !port_accepts(&host, port, std::time::Duration::from_millis(200)) {
+                if std::time::Instant::now() >= deadline {
+                    bail!(
+                        "Port {} in session '{}' is not reachable at {}:{}, even through a socat tunnel. Is the debug adapter running?",
+                        port,
+                        full,
+                        host,
+                        port
+                    );
+                }
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            eprintln!(
+                "\x1b[2mtunnelling {}:{} to the container's localhost:{} with socat (until the session stops).\x1b[0m",
+                host, port, port
+            );
+        }
+        (host, port, Some((workspace, sess.mount_path.clone())))
+    };
 
-box() {{
-    local __box_cd_file
-    __box_cd_file=$(mktemp "/tmp/.box-cd.XXXXXX")
-    BOX_CD_FILE="$__box_cd_file" command box "$@"
-    local __box_exit=$?
-    if [[ -s "$__box_cd_file" ]]; then
-        local __box_dir
-        __box_dir=$(<"$__box_cd_file")
-        cd "$__box_dir"
-    fi
-    rm -f "$__box_cd_file"
-    return $__box_exit
-}}
-"#
+    println!("Debugger endpoint: {}:{}", host, port);
+    println!();
+    println!("VS Code launch.json configuration:");
+    let config = debugger_launch_config(
+        &full,
+        debug_type,
+        &host,
+        port,
+        path_mapping
+            .as_ref()
+            .map(|(local, remote)| (local.as_str(), remote.as_str())),
     );
+    println!("{}", serde_json::to_string_pretty(&config)?);
     Ok(0)
 }
 
-fn cmd_upgrade() -> Result<i32> {
-    let current_version = env!("CARGO_PKG_VERSION");
-    println!("Current version: {}", current_version);
+/// Resolve the host address a Docker session's container `port` is reachable
+/// on: the published host port if any, otherwise the container IP.
+fn docker_port_endpoint(full: &str, port: u16) -> Result<(String, u16)> {
+    if let Some(mapping) = docker::container_port_mapping(full, port) {
+        let (host, host_port) = mapping.rsplit_once(':').unwrap_or((&mapping, ""));
+        return Ok((host.to_string(), host_port.parse().unwrap_or(port)));
+    }
+    let ip = docker::container_ip(full).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Port {} is not published for session '{}' and the container has no IP address.",
+            port,
+            full
+        )
+    })?;
+    eprintln!(
+        "\x1b[2mport {} is not published; using the container IP (reachable from Linux hosts only).\x1b[0m",
+        port
+    );
+    eprintln!(
+        "\x1b[2mrecreate the session with --docker-args \"-p {}:{}\" to publish it.\x1b[0m",
+        port, port
+    );
+    Ok((ip, port))
+}
 
-    println!("Checking for updates...");
-    let releases = self_update::backends::github::ReleaseList::configure()
-        .repo_owner("yusukeshib")
-        .repo_name("box")
-        .build()
-        .map_err(|e| anyhow::anyhow!("Failed to build release list: {}", e))?
-        .fetch()
-        .map_err(|e| anyhow::anyhow!("Failed to fetch releases: {}", e))?;
+fn cmd_wait_for_port(name: &str, port: u16, timeout: u64, message: Option<&str>) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
 
-    let latest = releases
-        .first()
-        .ok_or_else(|| anyhow::anyhow!("No releases found"))?;
-    let latest_version = latest.version.trim_start_matches('v');
+    let (host, port) = if sess.local {
+        ("127.0.0.1".to_string(), port)
+    } else {
+        docker::check()?;
+        if !docker::container_is_running(&full) {
+            bail!(
+                "Session '{}' is not running. Use `box resume {} -d` first.",
+                full,
+                full
+            );
+        }
+        docker_port_endpoint(&full, port)?
+    };
 
-    println!("Latest version: {}", latest_version);
+    let default_message = format!("Waiting for {}:{}", host, port);
+    let message = message.unwrap_or(&default_message);
+    let timeout = std::time::Duration::from_secs(timeout);
+    if wait_for_tcp(&host, port, timeout, message) {
+        eprintln!("{}:{} is ready.", host, port);
+        Ok(0)
+    } else {
+        eprintln!(
+            "Timed out after {}s waiting for {}:{}.",
+            timeout.as_secs(),
+            host,
+            port
+        );
+        Ok(1)
+    }
+}
 
-    if current_version == latest_version {
-        println!("Already at latest version.");
+/// Poll `host:port` until a TCP connection succeeds or `timeout` elapses,
+/// drawing a spinner with `message` on stderr when it is a terminal.
+fn wait_for_tcp(host: &str, port: u16, timeout: std::time::Duration, message: &str) -> bool {
+    use std::io::{IsTerminal, Write};
+    use std::net::ToSocketAddrs;
+
+    const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    let connect_timeout = std::time::Duration::from_millis(200);
+    let interval = std::time::Duration::from_millis(500);
+    let spinner = std::io::stderr().is_terminal();
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let start = std::time::Instant::now();
+
+    let mut frame = 0;
+    let ready = loop {
+        let connected = (host, port)
+            .to_socket_addrs()
+            .map(|addrs| {
+                addrs.into_iter().any(|addr| {
+                    std::net::TcpStream::connect_timeout(&addr, connect_timeout).is_ok()
+                })
+            })
+            .unwrap_or(false);
+        if connected {
+            break true;
+        }
+        if start.elapsed() >= timeout {
+            break false;
+        }
+        if spinner {
+            eprint!("\r{} {}", FRAMES[frame % FRAMES.len()], message);
+            let _ = std::io::stderr().flush();
+            frame += 1;
+        }
+        std::thread::sleep(interval.min(timeout.saturating_sub(start.elapsed())));
+    };
+    if spinner && frame > 0 {
+        eprint!("\r\x1b[2K");
+        let _ = std::io::stderr().flush();
+    }
+    ready
+}
+
+/// Load a Docker session and ensure its container is running, for commands
+/// that inspect the live container.
+fn running_docker_session(name: &str) -> Result<session::Session> {
+    session::validate_name(name)?;
+    let sess = session::load(name)?;
+    if sess.local {
+        bail!(
+            "Session '{}' is a local session; this command requires a Docker session.",
+            sess.name
+        );
+    }
+    docker::check()?;
+    if !docker::container_is_running(&sess.name) {
+        bail!(
+            "Session '{}' is not running. Use `box resume {} -d` first.",
+            sess.name,
+            sess.name
+        );
+    }
+    Ok(sess)
+}
+
+fn cmd_environment_baseline(name: &str) -> Result<i32> {
+    let sess = running_docker_session(name)?;
+    let snapshot = docker::container_env_snapshot(&sess.name)?;
+    let hash = session::save_baseline(&sess.name, &snapshot)?;
+    println!("Recorded baseline for session '{}' ({}).", sess.name, hash);
+    Ok(0)
+}
+
+fn cmd_environment_drift(name: &str, reset: bool) -> Result<i32> {
+    let sess = running_docker_session(name)?;
+    let baseline = session::load_baseline_hash(&sess.name)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Session '{}' has no baseline. Run `box environment-baseline {}` first.",
+            sess.name,
+            sess.name
+        )
+    })?;
+    let snapshot = docker::container_env_snapshot(&sess.name)?;
+    let current = session::snapshot_hash(&snapshot);
+
+    let code = if current == baseline {
+        println!("No drift from baseline for session '{}'.", sess.name);
+        0
+    } else {
+        println!("Session '{}' has drifted from its baseline:", sess.name);
+        let baseline_path = session::baseline_snapshot_path(&sess.name)?;
+        let mut diff = std::process::Command::new("diff")
+            .args(["-u", "--label", "baseline", "--label", "current"])
+            .arg(&baseline_path)
+            .arg("-")
+            .stdin(std::process::Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = diff.stdin.take() {
+            use std::io::Write;
+            stdin.write_all(snapshot.as_bytes())?;
+        }
+        diff.wait()?;
+        1
+    };
+
+    if reset {
+        let hash = session::save_baseline(&sess.name, &snapshot)?;
+        println!("Baseline reset for session '{}' ({}).", sess.name, hash);
         return Ok(0);
     }
+    Ok(code)
+}
 
-    let asset_name = upgrade_asset_name()?;
-    println!("Looking for asset: {}", asset_name);
+fn cmd_set_strategy(name: &str, strategy: &str, force: bool) -> Result<i32> {
+    session::validate_name(name)?;
+    config::validate_strategy(strategy)?;
 
-    let asset_exists = latest.assets.iter().any(|a| a.name == asset_name);
-    if !asset_exists {
+    let full = session::full_name(name);
+    if !session::session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+    let ws = session::workspace_name(&full);
+
+    // All sessions in a workspace share one workspace directory, so they
+    // must all be stopped and switch strategy together.
+    let mut sessions = Vec::new();
+    for sess_name in session::workspace_sessions(ws)? {
+        sessions.push(session::load(&format!("{}/{}", ws, sess_name))?);
+    }
+    if sessions.iter().any(|s| !s.local) {
+        docker::check()?;
+    }
+    for sess in &sessions {
+        let running = if sess.local {
+            session::is_local_running(&sess.name)
+        } else {
+            docker::container_is_running(&sess.name)
+        };
+        if running {
+            bail!(
+                "Session '{}' is still running. Stop it first with `box stop {}`.",
+                sess.name,
+                sess.name
+            );
+        }
+    }
+
+    let current = sessions
+        .iter()
+        .find(|s| s.name == full)
+        .map(|s| s.strategy.clone())
+        .unwrap_or_default();
+    if sessions.iter().all(|s| s.strategy == strategy) {
+        println!("Workspace '{}' already uses the {} strategy.", ws, strategy);
+        return Ok(0);
+    }
+
+    let home = config::home_dir()?;
+    let workspace = config::workspaces_dir_in(&home).join(ws);
+    if !force && git::has_uncommitted_changes(&workspace) {
         bail!(
-            "Asset '{}' not found for this platform. Available assets: {}",
-            asset_name,
-            latest
-                .assets
-                .iter()
-                .map(|a| a.name.as_str())
-                .collect::<Vec<_>>()
-                .join(", ")
+            "Workspace '{}' has uncommitted changes. Commit them or use --force to discard.",
+            ws
         );
     }
 
-    let download_url = format!(
-        "https://github.com/yusukeshib/box/releases/download/v{}/{}",
-        latest_version, asset_name
-    );
+    let project_dir = sessions[0].project_dir.clone();
+    if !force && workspace.exists() {
+        let unsaved = git::unsaved_commits(&workspace, Path::new(&project_dir))?;
+        if !unsaved.is_empty() {
+            bail!(
+                "Workspace '{}' has {} commit(s) that are not in '{}' or any remote. Push or merge them first, or use --force to discard them.",
+                ws,
+                unsaved.len(),
+                project_dir
+            );
+        }
+    }
+    docker::remove_workspace(ws, &current, &project_dir);
+    if workspace.exists() {
+        fs::remove_dir_all(&workspace)?;
+    }
+    docker::ensure_workspace(
+        &home,
+        ws,
+        &project_dir,
+        strategy,
+        sessions[0].from_ref.as_deref(),
+    )?;
+
+    for mut sess in sessions {
+        sess.strategy = strategy.to_string();
+        session::save(&sess)?;
+    }
+    println!("Workspace '{}' now uses the {} strategy.", ws, strategy);
+    Ok(0)
+}
 
-    println!("Downloading new version...");
-    let tmp_path = upgrade_download(&download_url)?;
-    let _guard = UpgradeTempGuard(tmp_path.clone());
+fn cmd_rebuild_workspace(name: &str, keep_changes: bool, force: bool) -> Result<i32> {
+    session::validate_name(name)?;
+    let ws = session::workspace_name(name);
+    let mut sessions = Vec::new();
+    for sess_name in session::workspace_sessions(ws)? {
+        sessions.push(session::load(&format!("{}/{}", ws, sess_name))?);
+    }
+    if sessions.is_empty() {
+        bail!("Workspace '{}' not found.", ws);
+    }
+    if sessions.iter().any(|s| !s.local) {
+        docker::check()?;
+    }
+
+    let home = config::home_dir()?;
+    let workspace = config::workspaces_dir_in(&home).join(ws);
+    let strategy = sessions[0].strategy.clone();
+    let project_dir = sessions[0].project_dir.clone();
+    if !force && workspace.exists() {
+        let unsaved = git::unsaved_commits(&workspace, Path::new(&project_dir))?;
+        if !unsaved.is_empty() {
+            bail!(
+                "Workspace '{}' has {} commit(s) that are not in '{}' or any remote. Push or merge them first, or use --force to discard them.",
+                ws,
+                unsaved.len(),
+                project_dir
+            );
+        }
+    }
+    // The rebuilt workspace goes back to the same commit (and branch, for a
+    // clone, whose branches are its own) before changes are re-applied
+    let old_head = git::head_commit(&workspace);
+    let old_branch = match strategy.as_str() {
+        "clone" => git::current_branch(&workspace),
+        _ => None,
+    };
+    let patch = if keep_changes && workspace.exists() {
+        git::uncommitted_patch(&workspace)?
+    } else {
+        None
+    };
+    if !keep_changes && !force && git::has_uncommitted_changes(&workspace) {
+        bail!(
+            "Workspace '{}' has uncommitted changes. Use --keep-changes to carry them over or --force to discard them.",
+            ws
+        );
+    }
+    let patch_path = session::sessions_dir()?.join(ws).join("rebuild.patch");
+    if let Some(ref patch) = patch {
+        fs::write(&patch_path, patch)?;
+    }
+
+    // Stop everything that uses the workspace, remembering what to restart
+    let mut running = Vec::new();
+    for sess in &sessions {
+        if sess.local {
+            if session::is_local_running(&sess.name) {
+                mux::send_kill(&sess.name)?;
+                for _ in 0..50 {
+                    if !session::is_local_running(&sess.name) {
+                        break;
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                }
+                if session::is_local_running(&sess.name) {
+                    bail!(
+                        "Session '{}' did not stop; workspace '{}' was not rebuilt.",
+                        sess.name,
+                        ws
+                    );
+                }
+                println!("Session '{}' stopped.", sess.name);
+                running.push(sess);
+            }
+        } else if docker::container_is_running(&sess.name) {
+            if docker::stop_container(&sess.name)? != 0 {
+                bail!(
+                    "Session '{}' did not stop; workspace '{}' was not rebuilt.",
+                    sess.name,
+                    ws
+                );
+            }
+            running.push(sess);
+        }
+    }
+
+    docker::remove_workspace(ws, &strategy, &project_dir);
+    if workspace.exists() {
+        fs::remove_dir_all(&workspace)?;
+    }
+    docker::ensure_workspace(
+        &home,
+        ws,
+        &project_dir,
+        &strategy,
+        sessions[0].from_ref.as_deref(),
+    )?;
+    if sessions.iter().any(|s| s.inherit_git_config) {
+        git::inherit_config(&workspace, git::INHERITED_CONFIG_KEYS)?;
+    }
+
+    // With --force the previous commit may have been discarded on purpose
+    let mut at_old_head = true;
+    if let Some(ref head) = old_head {
+        if !git::ref_exists(&workspace, head) {
+            at_old_head = false;
+        } else if git::head_commit(&workspace).as_ref() != Some(head) {
+            if let Err(e) = git::checkout_commit(&workspace, head, old_branch.as_deref()) {
+                at_old_head = false;
+                eprintln!(
+                    "Warning: could not check out the workspace's previous commit {} ({:#}).",
+                    head, e
+                );
+            }
+        }
+    }
+
+    if patch.is_some() && !at_old_head {
+        eprintln!(
+            "Warning: uncommitted changes were not re-applied. The patch is kept at {}.",
+            patch_path.display()
+        );
+    } else if patch.is_some() {
+        match git::apply_patch(&workspace, &patch_path) {
+            Ok(()) => {
+                let _ = fs::remove_file(&patch_path);
+                println!("Re-applied uncommitted changes.");
+            }
+            Err(e) => eprintln!(
+                "Warning: could not re-apply uncommitted changes ({:#}). The patch is kept at {}.",
+                e,
+                patch_path.display()
+            ),
+        }
+    }
+
+    for sess in running {
+        if sess.local {
+            mux::ensure_server(&sess.name)?;
+            println!("Session '{}' restarted in background.", sess.name);
+        } else {
+            docker::start_container_detached(&sess.name)?;
+        }
+    }
+    println!("Workspace '{}' rebuilt.", ws);
+    Ok(0)
+}
+
+fn cmd_recover(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
+
+    if sess.local {
+        // A live socket means the server is healthy; a PID without one is stale
+        if std::os::unix::net::UnixStream::connect(session::socket_path(&full)?).is_ok() {
+            println!("Session '{}' is running; nothing to recover.", full);
+            return Ok(0);
+        }
+        let home = config::home_dir()?;
+        let workspace = config::workspaces_dir_in(&home).join(session::workspace_name(&full));
+        if !workspace.is_dir() {
+            bail!(
+                "Workspace '{}' no longer exists; session '{}' cannot be recovered.",
+                workspace.display(),
+                full
+            );
+        }
+
+        eprintln!("\x1b[2mcleaning up stale server files\x1b[0m");
+        mux::clean_stale_server(&full);
+        println!("Recovering session '{}'...", full);
+        session::touch_resumed_at(&full)?;
+        output_cd_path(&workspace.to_string_lossy());
+        if !sess.command.is_empty() {
+            return run_local_command(&full);
+        }
+        return Ok(0);
+    }
+
+    docker::check()?;
+    if docker::container_is_running(&full) {
+        println!("Session '{}' is running; nothing to recover.", full);
+        return Ok(0);
+    }
+    if !docker::container_exists(&full) {
+        bail!(
+            "Container for session '{}' no longer exists. Use `box resume {}` to recreate it.",
+            full,
+            full
+        );
+    }
+    println!("Recovering session '{}'...", full);
+    session::touch_resumed_at(&full)?;
+    docker::start_container(&full)
+}
+
+fn cmd_fork(name: &str, new_name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    session::validate_name(new_name)?;
+    let full = session::full_name(name);
+    let new_full = session::full_name(new_name);
+    let src = session::load(&full)?;
+
+    // Sessions in a workspace share one directory, so a fork needs its own
+    // workspace to get an independent working tree.
+    let new_ws = session::workspace_name(&new_full);
+    if session::workspace_exists(new_ws)? {
+        bail!(
+            "Workspace '{}' already exists. Fork into a new workspace name.",
+            new_ws
+        );
+    }
+
+    let home = config::home_dir()?;
+    let workspaces = config::workspaces_dir_in(&home);
+    let src_workspace = workspaces.join(session::workspace_name(&full));
+    let dst_workspace = workspaces.join(new_ws);
+    if !src_workspace.is_dir() {
+        bail!(
+            "Workspace '{}' does not exist; nothing to fork.",
+            src_workspace.display()
+        );
+    }
+    if dst_workspace.exists() {
+        bail!(
+            "Workspace directory '{}' already exists.",
+            dst_workspace.display()
+        );
+    }
+
+    // The fork is a workspace of the same project made with the source's
+    // strategy, so remove, prune and rename treat it like any other; it
+    // starts at a snapshot of the source workspace, pending changes included.
+    let branch = match src.strategy.as_str() {
+        "branch" => docker::workspace_branch(new_ws),
+        _ => format!("box-fork/{}", new_ws),
+    };
+    if src.strategy != "clone"
+        && git::ref_exists(
+            Path::new(&src.project_dir),
+            &format!("refs/heads/{}", branch),
+        )
+    {
+        bail!(
+            "Branch '{}' already exists in '{}'. Fork into another workspace name.",
+            branch,
+            src.project_dir
+        );
+    }
+    let start = git::snapshot_commit(&src_workspace)?;
+    match src.strategy.as_str() {
+        "clone" => {
+            let dir = docker::ensure_workspace_with(
+                &home,
+                new_ws,
+                &src.project_dir,
+                "clone",
+                None,
+                None,
+                false,
+            )?;
+            let dir = Path::new(&dir);
+            if let Err(e) = git::fetch_commit(dir, &src_workspace, &start)
+                .and_then(|()| git::checkout_commit(dir, &start, Some(&branch)))
+            {
+                let _ = fs::remove_dir_all(dir);
+                return Err(e);
+            }
+        }
+        strategy => {
+            let dir = docker::ensure_workspace_with(
+                &home,
+                new_ws,
+                &src.project_dir,
+                strategy,
+                Some(&start),
+                None,
+                false,
+            )?;
+            // A worktree would otherwise be left on a detached HEAD
+            if strategy == "worktree" {
+                if let Err(e) = git::checkout_commit(Path::new(&dir), &start, Some(&branch)) {
+                    docker::remove_workspace(new_ws, strategy, &src.project_dir);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    let dropped_ports = src.ports.join(", ");
+    let forked = session::Session {
+        name: new_full.clone(),
+        from_ref: None,
+        // The source session's container already holds its published host ports
+        ports: Vec::new(),
+        ..src
+    };
+    session::save(&forked)?;
+
+    println!(
+        "Forked '{}' into '{}' on branch '{}'. Run `box resume {}` to start it.",
+        full, new_full, branch, new_full
+    );
+    if !dropped_ports.is_empty() {
+        println!(
+            "Published ports ({}) were not copied; the fork would conflict with '{}'.",
+            dropped_ports, full
+        );
+    }
+    Ok(0)
+}
+
+fn cmd_duplicate(src: &str, dest: &str, detach: bool) -> Result<i32> {
+    session::validate_name(src)?;
+    let src = session::load(&session::full_name(src))?;
+
+    cmd_create(
+        dest,
+        CreateOptions {
+            image: (!src.local).then_some(src.image),
+            docker_args: src
+                .docker_args
+                .or_else(|| std::env::var("BOX_DOCKER_ARGS").ok())
+                .unwrap_or_default(),
+            cmd: Some(src.command),
+            detach,
+            local: src.local,
+            strategy: Some(src.strategy),
+            env: src.env,
+            project_dir: Some(src.project_dir),
+            mount_path: (!src.local).then_some(src.mount_path),
+            workdir: src.workdir,
+            resources: src.resources,
+            ..Default::default()
+        },
+    )
+}
+
+/// Move a workspace directory. Worktrees are moved with git, run from the
+/// worktree itself so it works whichever repository it belongs to.
+fn move_workspace_dir(src: &Path, dst: &Path, strategy: &str) -> Result<()> {
+    if !config::is_worktree_strategy(strategy) {
+        return Ok(fs::rename(src, dst)?);
+    }
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(src)
+        .args(["worktree", "move"])
+        .arg(src)
+        .arg(dst)
+        .stderr(std::process::Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "git worktree move failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn cmd_rename(old: &str, new: &str, force: bool) -> Result<i32> {
+    session::validate_name(old)?;
+    session::validate_name(new)?;
+    let renaming_workspace = !old.contains('/');
+    let (ws, _) = session::parse_name(old);
+
+    // A bare new name for a session renames it within its workspace
+    let (old, new) = if renaming_workspace {
+        if new.contains('/') {
+            bail!(
+                "'{}' is a workspace; give a workspace name (without '/') to rename it.",
+                old
+            );
+        }
+        if !session::workspace_exists(old)? {
+            bail!("Workspace '{}' not found.", old);
+        }
+        (old.to_string(), new.to_string())
+    } else {
+        let new = if new.contains('/') {
+            new.to_string()
+        } else {
+            format!("{}/{}", ws, new)
+        };
+        if session::workspace_name(&new) != ws {
+            bail!(
+                "Cannot move session '{}' to another workspace. Use `box promote` or rename the workspace.",
+                old
+            );
+        }
+        if !session::session_exists(old)? {
+            bail!("Session '{}' not found.", old);
+        }
+        (old.to_string(), new)
+    };
+    if old == new {
+        println!("'{}' already has that name.", old);
+        return Ok(0);
+    }
+
+    let names: Vec<String> = if renaming_workspace {
+        session::workspace_sessions(ws)?
+            .into_iter()
+            .map(|s| format!("{}/{}", ws, s))
+            .collect()
+    } else {
+        vec![old.clone()]
+    };
+    let mut sessions = Vec::new();
+    for name in &names {
+        sessions.push(session::load(name)?);
+    }
+    if sessions.iter().any(|s| !s.local) {
+        docker::check()?;
+    }
+    for sess in &sessions {
+        let running = if sess.local {
+            session::is_local_running(&sess.name)
+        } else {
+            docker::container_is_running(&sess.name)
+        };
+        if running {
+            bail!(
+                "Session '{}' is still running. Stop it first with `box stop {}`.",
+                sess.name,
+                sess.name
+            );
+        }
+    }
+
+    let home = config::home_dir()?;
+    let workspaces = config::workspaces_dir_in(&home);
+    let (src_workspace, dst_workspace) = (workspaces.join(&old), workspaces.join(&new));
+
+    // Everything is validated first, then the workspace and container are
+    // moved, and the metadata last, undoing earlier steps if a later one fails
+    session::check_rename(&old, &new)?;
+
+    if !renaming_workspace {
+        let renames_container = !sessions[0].local && docker::container_exists(&old);
+        if renames_container {
+            docker::rename_container(&old, &new)?;
+        }
+        if let Err(e) = session::rename(&old, &new) {
+            if renames_container {
+                let _ = docker::rename_container(&new, &old);
+            }
+            return Err(e);
+        }
+        println!("Session '{}' renamed to '{}'.", old, new);
+        return Ok(0);
+    }
+
+    if dst_workspace.exists() {
+        bail!(
+            "Workspace directory '{}' already exists.",
+            dst_workspace.display()
+        );
+    }
+    // A container bind-mounts the old workspace path, so it can't simply be
+    // renamed; it has to be removed and is recreated on the next resume,
+    // losing any state outside the workspace.
+    let containers: Vec<&str> = sessions
+        .iter()
+        .filter(|s| !s.local && docker::container_exists(&s.name))
+        .map(|s| s.name.as_str())
+        .collect();
+    if !containers.is_empty() {
+        if !force {
+            bail!(
+                "Renaming workspace '{}' removes the container(s) of {}; anything outside the workspace is lost. Use --force to rename anyway.",
+                old,
+                containers.join(", ")
+            );
+        }
+        eprintln!(
+            "\x1b[2mremoving container(s) of {}; they will be recreated on the next resume.\x1b[0m",
+            containers.join(", ")
+        );
+    }
+    let strategy = sessions[0].strategy.as_str();
+    let branches = (strategy == "branch" && src_workspace.exists()).then(|| {
+        (
+            docker::workspace_branch(&old),
+            docker::workspace_branch(&new),
+        )
+    });
+    if let Some((_, ref new_branch)) = branches {
+        if git::ref_exists(&src_workspace, &format!("refs/heads/{}", new_branch)) {
+            bail!(
+                "Branch '{}' already exists in '{}'.",
+                new_branch,
+                sessions[0].project_dir
+            );
+        }
+    }
+
+    if src_workspace.exists() {
+        move_workspace_dir(&src_workspace, &dst_workspace, strategy)
+            .map_err(|e| e.context(format!("Failed to move workspace '{}'", old)))?;
+    }
+    let renamed = branches
+        .as_ref()
+        .map(|(old_branch, new_branch)| git::rename_branch(&dst_workspace, old_branch, new_branch))
+        .transpose()
+        .and_then(|_| session::rename(&old, &new));
+    if let Err(e) = renamed {
+        if let Some((ref old_branch, ref new_branch)) = branches {
+            let _ = git::rename_branch(&dst_workspace, new_branch, old_branch);
+        }
+        if dst_workspace.exists() {
+            let _ = move_workspace_dir(&dst_workspace, &src_workspace, strategy);
+        }
+        return Err(e);
+    }
+
+    for name in &containers {
+        docker::remove_container(name);
+    }
+
+    // Sessions created from inside the old workspace point their project_dir into it
+    let old_prefix = src_workspace.to_string_lossy().to_string();
+    for summary in session::list()? {
+        let Some(rest) = summary.project_dir.strip_prefix(&old_prefix) else {
+            continue;
+        };
+        if !rest.is_empty() && !rest.starts_with('/') {
+            continue;
+        }
+        let mut sess = session::load(&summary.name)?;
+        sess.project_dir = format!("{}{}", dst_workspace.display(), rest);
+        session::save(&sess)?;
+    }
+
+    println!("Workspace '{}' renamed to '{}'.", old, new);
+    Ok(0)
+}
+
+fn cmd_promote(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
+    let (ws, sess_part) = session::parse_name(&full);
+    let new_ws = sess_part;
+    let new_full = format!("{}/default", new_ws);
+
+    if session::workspace_exists(new_ws)? {
+        bail!(
+            "Workspace '{}' already exists. Remove or rename it before promoting.",
+            new_ws
+        );
+    }
+    if !sess.local {
+        docker::check()?;
+    }
+    let running = if sess.local {
+        session::is_local_running(&full)
+    } else {
+        docker::container_is_running(&full)
+    };
+    if running {
+        bail!(
+            "Session '{}' is still running. Stop it first with `box stop {}`.",
+            full,
+            full
+        );
+    }
+
+    let home = config::home_dir()?;
+    let workspaces = config::workspaces_dir_in(&home);
+    let src_workspace = workspaces.join(ws);
+    let dst_workspace = workspaces.join(new_ws);
+    if dst_workspace.exists() {
+        bail!(
+            "Workspace directory '{}' already exists.",
+            dst_workspace.display()
+        );
+    }
+
+    // Give the promoted session its own copy of the workspace. A worktree
+    // can't be copied as-is, so it gets a new worktree carrying its changes.
+    if src_workspace.is_dir() {
+        eprintln!("\x1b[2mcopying workspace:\x1b[0m");
+        if config::is_worktree_strategy(&sess.strategy) {
+            let branch = docker::workspace_branch(new_ws);
+            if git::ref_exists(&src_workspace, &format!("refs/heads/{}", branch)) {
+                bail!(
+                    "Branch '{}' already exists in '{}'.",
+                    branch,
+                    sess.project_dir
+                );
+            }
+            eprintln!("git worktree add -b {} {}", branch, dst_workspace.display());
+            git::fork_workspace(&src_workspace, &dst_workspace, &branch)?;
+        } else {
+            eprintln!(
+                "cp -a {} {}",
+                src_workspace.display(),
+                dst_workspace.display()
+            );
+            let status = std::process::Command::new("cp")
+                .arg("-a")
+                .arg(&src_workspace)
+                .arg(&dst_workspace)
+                .status()?;
+            if !status.success() {
+                bail!("Failed to copy workspace '{}'.", ws);
+            }
+        }
+    }
+
+    if let Err(e) = session::move_session(&full, new_ws) {
+        if src_workspace.is_dir() {
+            // A worktree copy was created on its own box/<name> branch
+            let copy_strategy = if config::is_worktree_strategy(&sess.strategy) {
+                "branch"
+            } else {
+                "clone"
+            };
+            docker::remove_workspace(new_ws, copy_strategy, &sess.project_dir);
+        }
+        return Err(e);
+    }
+    if !sess.local {
+        // The container name is derived from the session name; it is
+        // recreated under the new name on the next resume.
+        docker::remove_container(&full);
+    }
+    let mut promoted = session::load(&new_full)?;
+    promoted.project_dir = session::resolve_original_project_dir(&promoted.project_dir);
+    session::save(&promoted)?;
+
+    if !session::workspace_in_use(ws)? {
+        docker::remove_workspace(ws, &sess.strategy, &sess.project_dir);
+        let _ = session::remove_workspace_dir(ws);
+    }
+
+    println!("Session '{}' promoted to '{}'.", full, new_full);
+    Ok(0)
+}
+
+fn cmd_list_capabilities(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
+    if sess.local {
+        bail!(
+            "Session '{}' is a local session; capabilities apply to Docker sessions only.",
+            full
+        );
+    }
+    docker::check()?;
+    let caps = docker::container_capabilities(&full)?;
+
+    let width = caps
+        .added
+        .iter()
+        .map(|c| c.len())
+        .max()
+        .unwrap_or(0)
+        .max("ADDED".len());
+    println!("{:<width$}  DROPPED", "ADDED", width = width);
+    for i in 0..caps.added.len().max(caps.dropped.len()) {
+        let added = caps.added.get(i).map(|s| s.as_str()).unwrap_or("");
+        let dropped = caps.dropped.get(i).map(|s| s.as_str()).unwrap_or("");
+        println!("{:<width$}  {}", added, dropped, width = width);
+    }
+    println!();
+    println!("Effective: {}", caps.effective().join(" "));
+    Ok(0)
+}
+
+fn cmd_describe_image(name: &str, history: bool) -> Result<i32> {
+    session::validate_name(name)?;
+    let sess = session::load(&session::full_name(name))?;
+    if sess.local {
+        bail!(
+            "Session '{}' is a local session; it has no Docker image.",
+            sess.name
+        );
+    }
+    docker::check()?;
+    let meta = docker::image_metadata(&sess.image)?;
+
+    let created = chrono::DateTime::parse_from_rfc3339(&meta.created)
+        .map(|t| {
+            t.with_timezone(&chrono::Utc)
+                .format("%Y-%m-%d %H:%M:%S UTC")
+                .to_string()
+        })
+        .unwrap_or(meta.created.clone());
+    let words = |v: &[String]| {
+        if v.is_empty() {
+            "(none)".to_string()
+        } else {
+            shell_words::join(v)
+        }
+    };
+    println!("Image:        {}", sess.image);
+    println!("ID:           {}", meta.id);
+    println!("Created:      {}", created);
+    println!("Size:         {}", docker::format_bytes(meta.size));
+    println!("Platform:     {}/{}", meta.os, meta.architecture);
+    println!("Entrypoint:   {}", words(&meta.entrypoint));
+    println!("Cmd:          {}", words(&meta.cmd));
+
+    let labels: Vec<String> = meta
+        .labels
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect();
+    for (title, items) in [
+        ("Exposed ports", &meta.exposed_ports),
+        ("Environment", &meta.env),
+        ("Labels", &labels),
+    ] {
+        println!();
+        println!("{}:", title);
+        if items.is_empty() {
+            println!("  (none)");
+        }
+        for item in items {
+            println!("  {}", item);
+        }
+    }
+
+    if history {
+        println!();
+        println!("History:");
+        return docker::image_history(&sess.image);
+    }
+    Ok(0)
+}
+
+fn cmd_check_image_updates(name: Option<&str>, all: bool) -> Result<i32> {
+    let sessions: Vec<(String, String)> = if all {
+        session::list()?
+            .into_iter()
+            .filter(|s| !s.local)
+            .map(|s| (s.name, s.image))
+            .collect()
+    } else {
+        let name = name.unwrap_or_default();
+        session::validate_name(name)?;
+        let sess = session::load(&session::full_name(name))?;
+        if sess.local {
+            bail!(
+                "Session '{}' is a local session; it has no Docker image.",
+                sess.name
+            );
+        }
+        vec![(sess.name, sess.image)]
+    };
+    if sessions.is_empty() {
+        println!("No Docker sessions found.");
+        return Ok(0);
+    }
+    docker::check()?;
+
+    // Sessions often share an image; query each image once
+    let mut results: std::collections::HashMap<String, String> = Default::default();
+    let name_w = sessions.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+    for (name, image) in &sessions {
+        let status =
+            results.entry(image.clone()).or_insert_with(|| {
+                match docker::check_image_update(image) {
+                    Ok(status) => describe_image_update(&status),
+                    Err(e) => format!("Unknown ({})", e),
+                }
+            });
+        if all {
+            println!("{:<name_w$}  {}: {}", name, image, status, name_w = name_w);
+        } else {
+            println!("{}", status);
+        }
+    }
+    Ok(0)
+}
+
+fn describe_image_update(status: &docker::ImageUpdateStatus) -> String {
+    let short = |d: &str| format!("{}...", d.get(..19).unwrap_or(d));
+    match status {
+        docker::ImageUpdateStatus::UpToDate => "Up to date".to_string(),
+        docker::ImageUpdateStatus::UpdateAvailable {
+            local_digest,
+            remote_digest,
+        } => format!(
+            "New version available (local: {}, remote: {})",
+            short(local_digest),
+            short(remote_digest)
+        ),
+        docker::ImageUpdateStatus::Unknown => {
+            "Unknown (image has no registry digest or the registry is unreachable)".to_string()
+        }
+    }
+}
+
+fn cmd_list_changes(name: &str, stat: bool, since_commit: Option<&str>) -> Result<i32> {
+    use std::io::IsTerminal;
+
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    if !session::session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+    let workspace = config::workspaces_dir()?.join(session::workspace_name(&full));
+    if !workspace.exists() {
+        bail!("Workspace for session '{}' not found.", full);
+    }
+
+    if stat {
+        let out = git::diff_stat(&workspace, since_commit)?;
+        if out.is_empty() {
+            println!("No changes.");
+        } else {
+            println!("{}", out);
+        }
+        return Ok(0);
+    }
+
+    let changes = git::changed_files(&workspace, since_commit)?;
+    if changes.is_empty() {
+        println!("No changes.");
+        return Ok(0);
+    }
+    let color = std::io::stdout().is_terminal();
+    for change in &changes {
+        let indicator = change.status.indicator();
+        if color {
+            println!(
+                "{}{}\x1b[0m {}",
+                git_status_color(change.status),
+                indicator,
+                change.path
+            );
+        } else {
+            println!("{} {}", indicator, change.path);
+        }
+    }
+    Ok(0)
+}
+
+fn git_status_color(status: git::GitStatus) -> &'static str {
+    match status {
+        git::GitStatus::Modified => "\x1b[33m",
+        git::GitStatus::Added => "\x1b[32m",
+        git::GitStatus::Deleted => "\x1b[31m",
+        git::GitStatus::Renamed => "\x1b[36m",
+        git::GitStatus::Untracked => "\x1b[90m",
+    }
+}
+
+fn cmd_show_resource_limits(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
+    if sess.local {
+        bail!(
+            "Session '{}' is a local session; resource limits apply to Docker sessions only.",
+            full
+        );
+    }
+    docker::check()?;
+    let limits = docker::container_resource_limits(&full)?;
+
+    let rows = limits.rows();
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in &rows {
+        println!("{:<width$}  {}", label, value, width = width);
+    }
+
+    println!();
+    if limits.ulimits.is_empty() {
+        println!("Ulimits: none set (daemon defaults apply)");
+        return Ok(0);
+    }
+    let show = |v: Option<u64>| {
+        v.map(|n| n.to_string())
+            .unwrap_or_else(|| "unlimited".to_string())
+    };
+    let name_w = limits
+        .ulimits
+        .iter()
+        .map(|u| u.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("ULIMIT".len());
+    let soft_w = limits
+        .ulimits
+        .iter()
+        .map(|u| show(u.soft).len())
+        .max()
+        .unwrap_or(0)
+        .max("SOFT".len());
+    println!("{:<name_w$}  {:<soft_w$}  HARD", "ULIMIT", "SOFT");
+    for u in &limits.ulimits {
+        println!(
+            "{:<name_w$}  {:<soft_w$}  {}",
+            u.name,
+            show(u.soft),
+            show(u.hard)
+        );
+    }
+    Ok(0)
+}
+
+fn cmd_list_processes(name: &str, sort_by: &str, watch: bool) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
+
+    if !sess.local {
+        docker::check()?;
+    }
+    let fetch = || -> Result<Vec<String>> {
+        let mut procs = if sess.local {
+            let pid = session::read_pid(&full)
+                .filter(|_| session::is_local_running(&full))
+                .ok_or_else(|| anyhow::anyhow!("Session '{}' is not running.", full))?;
+            process::local_process_tree(pid)?
+        } else {
+            if !docker::container_is_running(&full) {
+                bail!("Session '{}' is not running.", full);
+            }
+            process::parse_ps_output(&docker::container_top(&full)?)
+        };
+        process::sort_processes(&mut procs, sort_by);
+        Ok(process::format_table(&procs))
+    };
+
+    if watch {
+        tui::watch_lines(
+            &format!("Processes in {}", full),
+            std::time::Duration::from_secs(2),
+            fetch,
+        )?;
+    } else {
+        for line in fetch()? {
+            println!("{}", line);
+        }
+    }
+    Ok(0)
+}
+
+/// Rows for `box top`: every session's mode and state, plus CPU and memory
+/// for running Docker sessions (`-` where not available).
+fn top_rows(
+    sessions: &[session::SessionSummary],
+    stats: &std::collections::HashMap<String, (f64, String)>,
+) -> Vec<Vec<String>> {
+    sessions
+        .iter()
+        .map(|s| {
+            let (cpu, mem) = match stats.get(&s.name) {
+                Some((cpu, mem)) => (format!("{:.1}%", cpu), mem.clone()),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            vec![
+                s.name.clone(),
+                if s.local { "local" } else { "docker" }.to_string(),
+                if s.running { "running" } else { "stopped" }.to_string(),
+                cpu,
+                mem,
+            ]
+        })
+        .collect()
+}
+
+fn cmd_top() -> Result<i32> {
+    tui::watch_table(
+        "box top",
+        std::time::Duration::from_secs(1),
+        &["NAME", "MODE", "STATUS", "CPU", "MEM"],
+        || {
+            let mut sessions = session::list()?;
+            mark_running(&mut sessions)?;
+            let running: Vec<String> = sessions
+                .iter()
+                .filter(|s| s.running && !s.local)
+                .map(|s| s.name.clone())
+                .collect();
+            Ok(top_rows(&sessions, &docker::stats(&running)))
+        },
+    )?;
+    Ok(0)
+}
+
+fn cmd_tail_output(name: &str, lines: usize, follow: bool) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
+
+    if sess.local {
+        if !session::is_local_running(&full) {
+            bail!("Session '{}' is not running.", full);
+        }
+        return mux::tail_output(&full, lines, follow);
+    }
+
+    docker::check()?;
+    if !docker::container_exists(&full) {
+        bail!("Container for session '{}' not found.", full);
+    }
+    docker::container_logs(&full, Some(lines), follow)
+}
+
+fn cmd_logs(name: &str, follow: bool) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    let sess = session::load(&full)?;
+
+    if sess.local {
+        let log_path = session::server_log_path(&full)?;
+        if !log_path.exists() && !follow {
+            println!("Session '{}' has no server log.", full);
+            return Ok(0);
+        }
+        stream_file(&log_path, follow)?;
+        return Ok(0);
+    }
+
+    docker::check()?;
+    if !docker::container_exists(&full) {
+        bail!("Container for session '{}' not found.", full);
+    }
+    docker::container_logs(&full, None, follow)
+}
+
+/// Copy a file to stdout. With `follow`, keep polling every 200ms and print
+/// bytes appended since the last read; a file that shrinks (e.g. after log
+/// rotation) is read again from the start.
+fn stream_file(path: &Path, follow: bool) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut offset = 0u64;
+    let mut stdout = std::io::stdout().lock();
+    loop {
+        if let Ok(mut file) = std::fs::File::open(path) {
+            let len = file.metadata()?.len();
+            if len < offset {
+                offset = 0;
+            }
+            if len > offset {
+                file.seek(SeekFrom::Start(offset))?;
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf)?;
+                offset += buf.len() as u64;
+                stdout.write_all(&buf)?;
+                stdout.flush()?;
+            }
+        }
+        if !follow {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+}
+
+fn cmd_log_rotate(name: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    if !session::session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+    if session::rotate_log(&full, true)? {
+        println!("Rotated server log for session '{}'.", full);
+    } else {
+        println!("Session '{}' has no server log.", full);
+    }
+    Ok(0)
+}
+
+/// Apply `KEY=VALUE` settings and removals to a session's environment list,
+/// replacing any existing entry for the same key.
+fn edit_env(env: &mut Vec<String>, set: &[String], unset: &[String]) -> Result<()> {
+    let key_of = |entry: &str| entry.split_once('=').map_or(entry, |(k, _)| k).to_string();
+    for entry in set {
+        match entry.split_once('=') {
+            Some((key, _)) if !key.is_empty() => {}
+            _ => bail!("Invalid --env '{}': expected KEY=VALUE.", entry),
+        }
+    }
+    env.retain(|e| {
+        let key = key_of(e);
+        !unset.contains(&key) && !set.iter().any(|s| key_of(s) == key)
+    });
+    env.extend(set.iter().cloned());
+    Ok(())
+}
+
+fn cmd_edit(args: &EditArgs) -> Result<i32> {
+    session::validate_name(&args.name)?;
+    let full = session::full_name(&args.name);
+    if !session::session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+    if args.image.is_none()
+        && args.env.is_empty()
+        && args.unset_env.is_empty()
+        && args.cmd.is_empty()
+    {
+        bail!("Nothing to change. Pass --image, --env, --unset-env or -- <cmd...>.");
+    }
+
+    let mut sess = session::load(&full)?;
+    if sess.local && args.recreate {
+        bail!(
+            "Session '{}' is a local session; --recreate requires a Docker session.",
+            full
+        );
+    }
+    if !sess.local {
+        docker::check()?;
+    }
+    let running = if sess.local {
+        session::is_local_running(&full)
+    } else {
+        docker::container_is_running(&full)
+    };
+    if running {
+        bail!(
+            "Session '{}' is still running. Stop it first with `box stop {}`.",
+            full,
+            full
+        );
+    }
+
+    if let Some(ref image) = args.image {
+        set_session_image(&mut sess, image)?;
+    }
+    edit_env(&mut sess.env, &args.env, &args.unset_env)?;
+    if !args.cmd.is_empty() {
+        sess.command = args.cmd.clone();
+    }
+
+    // The image, command and environment are fixed when a container is
+    // created, so a stopped one keeps the old ones until it is recreated.
+    let stale_container = !sess.local && docker::container_exists(&full);
+    if stale_container && args.recreate {
+        docker::remove_container(&full);
+    }
+
+    session::save(&sess)?;
+    if stale_container && !args.recreate {
+        println!(
+            "Session '{}' updated. Its stopped container still has the old settings; run `box resume {} --recreate` to apply them.",
+            full, full
+        );
+    } else {
+        println!("Session '{}' updated; changes apply on next resume.", full);
+    }
+    Ok(0)
+}
+
+/// Point a Docker session at a new image, pulling it if it isn't present.
+/// Shared by `set-image` and `edit --image`; the caller saves the session.
+fn set_session_image(sess: &mut session::Session, image: &str) -> Result<()> {
+    if image.trim().is_empty() {
+        bail!("Image name is required.");
+    }
+    if sess.local {
+        bail!(
+            "Session '{}' is a local session and has no Docker image.",
+            sess.name
+        );
+    }
+    if !docker::image_exists(image) {
+        docker::pull_image(image)?;
+    }
+    sess.image = image.to_string();
+    Ok(())
+}
+
+fn cmd_set_image(name: &str, image: &str) -> Result<i32> {
+    session::validate_name(name)?;
+    let full = session::full_name(name);
+    if !session::session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+    if image.trim().is_empty() {
+        bail!("Image name is required.");
+    }
+
+    let mut sess = session::load(&full)?;
+    if sess.local {
+        bail!(
+            "Session '{}' is a local session and has no Docker image.",
+            full
+        );
+    }
+
+    docker::check()?;
+
+    if docker::container_is_running(&full) {
+        bail!(
+            "Session '{}' is still running. Stop it first with `box stop {}`.",
+            full,
+            full
+        );
+    }
+
+    set_session_image(&mut sess, image)?;
+
+    // A stopped container would be restarted as-is on resume, so drop it to
+    // force recreation from the new image.
+    if docker::container_exists(&full) {
+        docker::remove_container(&full);
+    }
+
+    session::save(&sess)?;
+    println!(
+        "Session '{}' will use image '{}' on next resume.",
+        full, image
+    );
+    Ok(0)
+}
+
+/// Completion candidates for session names, optionally described by their
+/// project directory with the home directory shown as `~`.
+fn complete_session_lines(
+    sessions: &[session::SessionSummary],
+    home: &str,
+    describe: bool,
+) -> Vec<String> {
+    sessions
+        .iter()
+        .map(|s| {
+            if !describe {
+                return s.name.clone();
+            }
+            let project = match s.project_dir.strip_prefix(home) {
+                Some(rest) if !home.is_empty() && (rest.is_empty() || rest.starts_with('/')) => {
+                    format!("~{}", rest)
+                }
+                _ => s.project_dir.clone(),
+            };
+            format!("{}\t{}", s.name, project)
+        })
+        .collect()
+}
+
+fn cmd_complete_sessions(describe: bool) -> Result<i32> {
+    // Completion must never print errors into the user's prompt, nor migrate
+    // session directories as a side effect
+    let sessions = session::list_read_only().unwrap_or_default();
+    let home = config::home_dir().unwrap_or_default();
+    for line in complete_session_lines(&sessions, &home, describe) {
+        println!("{}", line);
+    }
+    Ok(0)
+}
+
+fn cmd_config_zsh() -> Result<i32> {
+    print!(
+        r#"__box_sessions() {{
+    local -a sessions
+    local line
+    for line in "${{(@f)$(command box __complete sessions --describe 2>/dev/null)}}"; do
+        [[ -n "$line" ]] && sessions+=("${{line%%$'\t'*}}:[${{line#*$'\t'}}]")
+    done
+    if (( ${{#sessions}} )); then
+        _describe 'session' sessions
+    fi
+}}
+
+_box() {{
+    local curcontext="$curcontext" state line
+    typeset -A opt_args
+
+    _arguments -C \
+        '1: :->subcmd' \
+        '*:: :->args'
+
+    case $state in
+        subcmd)
+            local -a subcmds
+            subcmds=(
+                'create:Create a new session'
+                'resume:Resume an existing session'
+                'remove:Remove a session'
+                'stop:Stop a running session'
+                'wait:Wait for a session to exit and return its exit code'
+                'prune:Remove all stopped sessions'
+                'archive:Archive a stopped session'
+                'unarchive:Restore an archived session'
+                'exec:Run a command in a running session'
+                'list:List sessions'
+                'info:Show all details of a session'
+                'cd:Print the host project directory for a session'
+                'path:Print workspace path for a session'
+                'origin:Navigate back to the original project directory'
+                'show-env:Show the environment of a session'
+                'set-image:Change the Docker image of a stopped session'
+                'recover:Recover a crashed or unexpectedly stopped session'
+                'rebuild-workspace:Recreate a workspace from its git repository'
+                'rename:Rename a session or workspace'
+                'fork:Fork a session workspace into a new session'
+                'duplicate:Create a new session with the settings of another'
+                'promote:Move a session into its own workspace'
+                'batch-create:Create sessions from a TOML manifest'
+                'list-capabilities:Show the Linux capabilities of a Docker session'
+                'tail-output:Print recent terminal output of a session'
+                'list-changes:Show files changed in a session workspace'
+                'describe-image:Show metadata about a session Docker image'
+                'check-image-updates:Check a Docker session image for updates'
+                'show-resource-limits:Show the resource limits of a Docker session'
+                'list-processes:List the processes running in a session'
+                'wait-for-port:Wait until a port in a session accepts connections'
+                'logs:Print the server or container log of a session'
+                'log-rotate:Rotate the server log of a session'
+                'set-strategy:Change the workspace strategy of a stopped session'
+                'connect-to-debugger:Print connection details for a debugger'
+                'environment-baseline:Record a session environment baseline'
+                'environment-drift:Compare a session environment against its baseline'
+                'edit:Change the command, image or environment of a stopped session'
+                'top:Show a live view of sessions and their resource use'
+                'doctor:Check the setup for common problems'
+                'upgrade:Self-update to the latest version'
+                'config:Output shell configuration'
+            )
+            _describe 'subcommand' subcmds
+            ;;
+        args)
+            case $words[1] in
+                create)
+                    _arguments \
+                        '-d[Run in the background]' \
+                        '--image=[Docker image to use]:image' \
+                        '--docker-args=[Extra Docker flags]:args' \
+                        '--local[Create a local session (default)]' \
+                        '--docker[Create a Docker session]' \
+                        '--strategy=[Workspace strategy (clone, worktree or branch)]:strategy:(clone worktree branch)' \
+                        '--minimal-caps[Drop all capabilities except a minimal set]' \
+                        '--notify[Notify when the command exits]' \
+                        '--volumes-from=[Share volumes with another Docker session]:session:__box_sessions' \
+                        '--docker-socket=[Mount the host Docker socket]:path:_files' \
+                        '--docker-in-docker[Docker session with the host Docker socket mounted]' \
+                        '--inherit-git-config[Copy global git identity into the workspace]' \
+                        '--storage-driver=[Required Docker storage driver]:driver:(overlay2 devicemapper btrfs zfs aufs)' \
+                        '*--storage-opt=[Docker storage option]:key=value' \
+                        '*--port=[Publish a port as HOST:CONTAINER]:host\:container' \
+                        '--memory=[Container memory limit]:size' \
+                        '--cpus=[Number of CPUs for the container]:cpus' \
+                        '--pids-limit=[Container process limit]:count' \
+                        '*--env-file=[Read environment variables from a file]:file:_files' \
+                        '--workdir=[Start in a subdirectory of the workspace]:path' \
+                        '-q[Suppress the creation banner]' \
+                        '--quiet[Suppress the creation banner]' \
+                        '--pull[Pull the image even if it exists locally]' \
+                        '--adopt[Manage an existing container with the session name]' \
+                        '--from=[Check out the workspace at a branch, tag or commit]:ref' \
+                        '--clone-depth=[Shallow-clone the workspace with N commits]:depth' \
+                        '1:session name:' \
+                        '*:command:'
+                    ;;
+                resume)
+                    _arguments \
+                        '-d[Run container in the background]' \
+                        '--docker-args=[Extra Docker flags]:args' \
+                        '--image=[Run with a different Docker image]:image' \
+                        '--recreate[Replace the stopped container]' \
+                        '--pull[Pull the image before recreating the container]' \
+                        '--workdir=[cd the shell into a subdirectory of the workspace]:path' \
+                        '--read-only[Watch without sending input]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                exec)
+                    _arguments \
+                        '-d[Run the command in the background]' \
+                        '--detach[Run the command in the background]' \
+                        '--no-tty[Stream output without a TTY]' \
+                        '--stdin[Read the command line from stdin]' \
+                        '1:session name:__box_sessions' \
+                        '*:command:'
+                    ;;
+                list|ls)
+                    _arguments \
+                        '--running[Show only running sessions]' \
+                        '-r[Show only running sessions]' \
+                        '--stopped[Show only stopped sessions]' \
+                        '-s[Show only stopped sessions]' \
+                        '--project[Show only sessions for the current project]' \
+                        '-p[Show only sessions for the current project]' \
+                        '--quiet[Only print session names]' \
+                        '-q[Only print session names]' \
+                        '--json[Print sessions as JSON]' \
+                        '--since=[Only sessions created within a duration]:duration' \
+                        '--before=[Only sessions created before a duration ago]:duration' \
+                        '--format-width=[Cap the table width]:width' \
+                        '--sort=[Sort order]:order:(name created resumed)' \
+                        '--archived[Show only archived sessions]' \
+                        '--tree[Group sessions under their workspace]' \
+                        '--mine[Only sessions created by the current user]' \
+                        '--output-file=[Also write the sessions as JSON to a file]:file:_files' \
+                        '--append[Append to the output file]'
+                    ;;
+                stop)
+                    _arguments \
+                        '--all[Stop every running session]' \
+                        '--project[Stop the running sessions of the current project]' \
+                        '-p[Stop the running sessions of the current project]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                prune)
+                    _arguments \
+                        '--dry-run[Print what would be removed]' \
+                        '--project[Only prune sessions of the current project]' \
+                        '-p[Only prune sessions of the current project]' \
+                        '--yes[Remove without asking for confirmation]' \
+                        '-y[Remove without asking for confirmation]' \
+                        '--force[Also remove workspaces with unsaved work]' \
+                        '-f[Also remove workspaces with unsaved work]'
+                    ;;
+                path|cd)
+                    _arguments \
+                        '--origin[Use the original project directory]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                edit)
+                    _arguments \
+                        '--image=[New Docker image]:image' \
+                        '*--env=[Set an environment variable]:KEY=VALUE' \
+                        '*--unset-env=[Remove an environment variable]:KEY' \
+                        '--recreate[Remove the stopped container so changes apply]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                remove|wait|archive|show-env|set-image|recover|fork|promote|list-capabilities|show-resource-limits|log-rotate|environment-baseline)
+                    if (( CURRENT == 2 )); then
+                        __box_sessions
+                    fi
+                    ;;
+                rename)
+                    _arguments \
+                        '-f[Remove containers when renaming a workspace]' \
+                        '--force[Remove containers when renaming a workspace]' \
+                        '1:session name:__box_sessions' \
+                        '2:new name:'
+                    ;;
+                duplicate)
+                    _arguments \
+                        '-d[Run in the background]' \
+                        '--detach[Run in the background]' \
+                        '1:source session:__box_sessions' \
+                        '2:new session name:'
+                    ;;
+                batch-create)
+                    _arguments \
+                        '--continue-on-error[Keep going after a failure]' \
+                        '1:manifest:_files'
+                    ;;
+                list-processes)
+                    _arguments \
+                        '--sort-by=[Sort order]:field:(cpu mem pid)' \
+                        '-w[Refresh every 2 seconds]' \
+                        '--watch[Refresh every 2 seconds]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                info)
+                    _arguments \
+                        '--json[Print as JSON]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                rebuild-workspace)
+                    _arguments \
+                        '--keep-changes[Carry uncommitted changes over]' \
+                        '-f[Discard uncommitted changes and unpushed commits]' \
+                        '--force[Discard uncommitted changes and unpushed commits]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                list-changes)
+                    _arguments \
+                        '--stat[Show a diffstat]' \
+                        '--since-commit=[Show changes since a commit]:commit' \
+                        '1:session name:__box_sessions'
+                    ;;
+                describe-image)
+                    _arguments \
+                        '--history[Show the image build history]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                check-image-updates)
+                    _arguments \
+                        '--all[Check every Docker session]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                wait-for-port)
+                    _arguments \
+                        '--timeout=[Seconds to wait before giving up]:seconds' \
+                        '--message=[Text shown while waiting]:message' \
+                        '1:session name:__box_sessions' \
+                        '2:port'
+                    ;;
+                tail-output)
+                    _arguments \
+                        '-n[Number of lines to show]:lines' \
+                        '--lines=[Number of lines to show]:lines' \
+                        '-f[Keep printing new output]' \
+                        '--follow[Keep printing new output]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                logs)
+                    _arguments \
+                        '-f[Keep printing new output]' \
+                        '--follow[Keep printing new output]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                set-strategy)
+                    _arguments \
+                        '-f[Discard uncommitted changes in the workspace]' \
+                        '--force[Discard uncommitted changes in the workspace]' \
+                        '1:session name:__box_sessions' \
+                        '2:strategy:(clone worktree branch)'
+                    ;;
+                environment-drift)
+                    _arguments \
+                        '--reset[Replace the baseline with the current environment]' \
+                        '1:session name:__box_sessions'
+                    ;;
+                connect-to-debugger)
+                    _arguments \
+                        '--port=[Port the debug adapter listens on]:port' \
+                        '--type=[VS Code debug type]:type:(debugpy node)' \
+                        '1:session name:__box_sessions'
+                    ;;
+                config)
+                    if (( CURRENT == 2 )); then
+                        local -a shells
+                        shells=('zsh:Zsh completion script' 'bash:Bash completion script' 'fish:Fish completion script')
+                        _describe 'shell' shells
+                    fi
+                    ;;
+            esac
+            ;;
+    esac
+}}
+compdef _box box
+
+box() {{
+    local __box_cd_file
+    __box_cd_file=$(mktemp "/tmp/.box-cd.XXXXXX")
+    BOX_CD_FILE="$__box_cd_file" command box "$@"
+    local __box_exit=$?
+    if [[ -s "$__box_cd_file" ]]; then
+        local __box_dir
+        __box_dir=$(<"$__box_cd_file")
+        cd "$__box_dir"
+    fi
+    rm -f "$__box_cd_file"
+    # 100 means the user detached from a session, which is not a failure
+    # (`command box` still reports it)
+    [[ $__box_exit -eq 100 ]] && __box_exit=0
+    return $__box_exit
+}}
+"#
+    );
+    Ok(0)
+}
+
+fn cmd_config_fish() -> Result<i32> {
+    print!(
+        r#"function __box_sessions
+    command box __complete sessions --describe 2>/dev/null
+end
+
+function __box_needs_session
+    set -l tokens (commandline -opc)
+    test (count $tokens) -eq 2; and contains -- $tokens[2] resume remove stop wait archive exec info cd path show-env set-image set-strategy recover rebuild-workspace rename fork duplicate promote list-capabilities list-changes describe-image check-image-updates show-resource-limits tail-output list-processes wait-for-port logs log-rotate connect-to-debugger environment-baseline environment-drift edit
+end
+
+complete -c box -f
+complete -c box -n __fish_use_subcommand -a create -d 'Create a new session'
+complete -c box -n __fish_use_subcommand -a resume -d 'Resume an existing session'
+complete -c box -n __fish_use_subcommand -a remove -d 'Remove a session'
+complete -c box -n __fish_use_subcommand -a stop -d 'Stop a running session'
+complete -c box -n __fish_use_subcommand -a wait -d 'Wait for a session to exit and return its exit code'
+complete -c box -n __fish_use_subcommand -a prune -d 'Remove all stopped sessions'
+complete -c box -n __fish_use_subcommand -a archive -d 'Archive a stopped session'
+complete -c box -n __fish_use_subcommand -a unarchive -d 'Restore an archived session'
+complete -c box -n __fish_use_subcommand -a exec -d 'Run a command in a running session'
+complete -c box -n __fish_use_subcommand -a list -d 'List sessions'
+complete -c box -n __fish_use_subcommand -a info -d 'Show all details of a session'
+complete -c box -n __fish_use_subcommand -a cd -d 'Print the host project directory for a session'
+complete -c box -n __fish_use_subcommand -a path -d 'Print workspace path for a session'
+complete -c box -n __fish_use_subcommand -a origin -d 'Navigate back to the original project directory'
+complete -c box -n __fish_use_subcommand -a show-env -d 'Show the environment of a session'
+complete -c box -n __fish_use_subcommand -a set-image -d 'Change the Docker image of a stopped session'
+complete -c box -n __fish_use_subcommand -a recover -d 'Recover a crashed or unexpectedly stopped session'
+complete -c box -n __fish_use_subcommand -a rebuild-workspace -d 'Recreate a workspace from its git repository'
+complete -c box -n __fish_use_subcommand -a rename -d 'Rename a session or workspace'
+complete -c box -n __fish_use_subcommand -a fork -d 'Fork a session workspace into a new session'
+complete -c box -n __fish_use_subcommand -a duplicate -d 'Create a new session with the settings of another'
+complete -c box -n __fish_use_subcommand -a promote -d 'Move a session into its own workspace'
+complete -c box -n __fish_use_subcommand -a batch-create -d 'Create sessions from a TOML manifest'
+complete -c box -n __fish_use_subcommand -a list-capabilities -d 'Show the Linux capabilities of a Docker session'
+complete -c box -n __fish_use_subcommand -a tail-output -d 'Print recent terminal output of a session'
+complete -c box -n __fish_use_subcommand -a list-changes -d 'Show files changed in a session workspace'
+complete -c box -n __fish_use_subcommand -a describe-image -d 'Show metadata about a session Docker image'
+complete -c box -n __fish_use_subcommand -a check-image-updates -d 'Check a Docker session image for updates'
+complete -c box -n __fish_use_subcommand -a show-resource-limits -d 'Show the resource limits of a Docker session'
+complete -c box -n __fish_use_subcommand -a list-processes -d 'List the processes running in a session'
+complete -c box -n __fish_use_subcommand -a wait-for-port -d 'Wait until a port in a session accepts connections'
+complete -c box -n __fish_use_subcommand -a logs -d 'Print the server or container log of a session'
+complete -c box -n __fish_use_subcommand -a log-rotate -d 'Rotate the server log of a session'
+complete -c box -n __fish_use_subcommand -a set-strategy -d 'Change the workspace strategy of a stopped session'
+complete -c box -n __fish_use_subcommand -a connect-to-debugger -d 'Print connection details for a debugger'
+complete -c box -n __fish_use_subcommand -a environment-baseline -d 'Record a session environment baseline'
+complete -c box -n __fish_use_subcommand -a environment-drift -d 'Compare a session environment against its baseline'
+complete -c box -n __fish_use_subcommand -a edit -d 'Change the command, image or environment of a stopped session'
+complete -c box -n __fish_use_subcommand -a top -d 'Show a live view of sessions and their resource use'
+complete -c box -n __fish_use_subcommand -a doctor -d 'Check the setup for common problems'
+complete -c box -n __fish_use_subcommand -a upgrade -d 'Self-update to the latest version'
+complete -c box -n __fish_use_subcommand -a config -d 'Output shell configuration'
+complete -c box -n __box_needs_session -a '(__box_sessions)'
+complete -c box -n '__fish_seen_subcommand_from path cd' -l origin -d 'Use the original project directory'
+complete -c box -n '__fish_seen_subcommand_from config' -a 'zsh bash fish'
+complete -c box -n '__fish_seen_subcommand_from batch-create' -F
+
+function box
+    set -l __box_cd_file (mktemp /tmp/.box-cd.XXXXXX)
+    BOX_CD_FILE=$__box_cd_file command box $argv
+    set -l __box_exit $status
+    if test -s $__box_cd_file
+        cd (cat $__box_cd_file)
+    end
+    rm -f $__box_cd_file
+    # 100 means the user detached from a session, which is not a failure
+    # (`command box` still reports it)
+    if test $__box_exit -eq 100
+        set __box_exit 0
+    end
+    return $__box_exit
+end
+"#
+    );
+    Ok(0)
+}
+
+fn cmd_config_bash() -> Result<i32> {
+    print!(
+        r#"_box() {{
+    local cur prev words cword
+    _init_completion || return
+
+    local subcommands="create resume remove stop wait prune archive unarchive exec list info cd path origin show-env set-image set-strategy recover rebuild-workspace rename fork duplicate promote batch-create list-capabilities list-changes describe-image check-image-updates show-resource-limits tail-output list-processes wait-for-port logs log-rotate connect-to-debugger environment-baseline environment-drift edit top doctor upgrade config"
+    local session_cmds="resume remove stop wait archive exec info cd path show-env set-image set-strategy recover rebuild-workspace rename fork duplicate promote list-capabilities list-changes describe-image check-image-updates show-resource-limits tail-output list-processes wait-for-port logs log-rotate connect-to-debugger environment-baseline environment-drift edit"
+
+    if [[ $cword -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "$subcommands" -- "$cur"))
+        return
+    fi
+
+    local subcmd="${{words[1]}}"
+
+    case "$subcmd" in
+        create)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "-d --image --docker-args --local --docker --strategy --minimal-caps --notify --volumes-from --docker-socket --docker-in-docker --inherit-git-config --storage-driver --storage-opt --port --memory --cpus --pids-limit --env-file --workdir --pull --adopt --from --clone-depth -q --quiet" -- "$cur"))
+                    ;;
+            esac
+            ;;
+        prune)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--dry-run --project -p --yes -y --force -f" -- "$cur"))
+                    ;;
+            esac
+            ;;
+        resume)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "-d --docker-args --image --recreate --pull --workdir --read-only" -- "$cur"))
+                    ;;
+                *)
+                    if [[ $cword -eq 2 ]]; then
+                        COMPREPLY=($(compgen -W "$(command box __complete sessions 2>/dev/null)" -- "$cur"))
+                    fi
+                    ;;
+            esac
+            ;;
+        exec)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "-d --detach --no-tty --stdin" -- "$cur"))
+                    ;;
+                *)
+                    if [[ $cword -eq 2 ]]; then
+                        COMPREPLY=($(compgen -W "$(command box __complete sessions 2>/dev/null)" -- "$cur"))
+                    fi
+                    ;;
+            esac
+            ;;
+        list|ls)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--running -r --stopped -s --project -p --quiet -q --json --since --before --format-width --sort --archived --tree --mine --output-file --append" -- "$cur"))
+                    ;;
+            esac
+            ;;
+        batch-create)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--continue-on-error" -- "$cur"))
+                    ;;
+                *)
+                    COMPREPLY=($(compgen -f -- "$cur"))
+                    ;;
+            esac
+            ;;
+        list-processes)
+            if [[ "$prev" == "--sort-by" ]]; then
+                COMPREPLY=($(compgen -W "cpu mem pid" -- "$cur"))
+                return
+            fi
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--sort-by -w --watch" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        info)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--json" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        rebuild-workspace)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--keep-changes -f --force" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        list-changes)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--stat --since-commit" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        describe-image)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--history" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        stop)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--all --project -p" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        check-image-updates)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--all" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        wait-for-port)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--timeout --message" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        tail-output)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "-n --lines -f --follow" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        logs)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "-f --follow" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        duplicate)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "-d --detach" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        rename)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "-f --force" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        set-strategy)
+            if [[ $cword -eq 3 ]]; then
+                COMPREPLY=($(compgen -W "clone worktree branch" -- "$cur"))
+                return
+            fi
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "-f --force" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        path|cd)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--origin" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        environment-drift)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--reset" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        edit)
+            case "$cur" in
+                -*)
+                    COMPREPLY=($(compgen -W "--image --env --unset-env --recreate" -- "$cur"))
+                    ;;
+            esac
+            ;;&
+        remove|stop|wait|archive|info|path|cd|show-env|edit|set-image|set-strategy|recover|rebuild-workspace|rename|fork|duplicate|promote|list-capabilities|list-changes|describe-image|check-image-updates|show-resource-limits|tail-output|list-processes|wait-for-port|logs|log-rotate|connect-to-debugger|environment-baseline|environment-drift)
+            if [[ $cword -eq 2 ]]; then
+                COMPREPLY=($(compgen -W "$(command box __complete sessions 2>/dev/null)" -- "$cur"))
+            fi
+            ;;
+        config)
+            if [[ $cword -eq 2 ]]; then
+                COMPREPLY=($(compgen -W "zsh bash fish" -- "$cur"))
+            fi
+            ;;
+    esac
+}}
+complete -F _box box
+
+box() {{
+    local __box_cd_file
+    __box_cd_file=$(mktemp "/tmp/.box-cd.XXXXXX")
+    BOX_CD_FILE="$__box_cd_file" command box "$@"
+    local __box_exit=$?
+    if [[ -s "$__box_cd_file" ]]; then
+        local __box_dir
+        __box_dir=$(<"$__box_cd_file")
+        cd "$__box_dir"
+    fi
+    rm -f "$__box_cd_file"
+    # 100 means the user detached from a session, which is not a failure
+    # (`command box` still reports it)
+    [[ $__box_exit -eq 100 ]] && __box_exit=0
+    return $__box_exit
+}}
+"#
+    );
+    Ok(0)
+}
+
+/// Print one `box doctor` result: a pass/fail marker, what was checked and,
+/// for a failure, how to fix it.
+fn doctor_report(ok: bool, what: &str, hint: &str) {
+    if ok {
+        println!("\u{2713} {}", what);
+    } else {
+        println!("\u{2717} {}", what);
+        println!("  \x1b[2m{}\x1b[0m", hint);
+    }
+}
+
+fn cmd_doctor() -> Result<i32> {
+    let mut critical_failed = false;
+
+    let home = config::home_dir();
+    doctor_report(
+        home.is_ok(),
+        "HOME is set",
+        "Set HOME to your home directory; box keeps its state in ~/.box.",
+    );
+    critical_failed |= home.is_err();
+
+    if let Ok(ref home) = home {
+        let box_dir = Path::new(home).join(".box");
+        if box_dir.is_dir() {
+            let probe = box_dir.join(".doctor");
+            let writable = fs::write(&probe, "").is_ok();
+            let _ = fs::remove_file(&probe);
+            doctor_report(
+                writable,
+                &format!("{} is writable", box_dir.display()),
+                &format!(
+                    "Fix the ownership of {}, e.g. `sudo chown -R \"$USER\" ~/.box`.",
+                    box_dir.display()
+                ),
+            );
+            critical_failed |= !writable;
+
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&box_dir)?.permissions().mode() & 0o777;
+            doctor_report(
+                mode & 0o077 == 0,
+                &format!("{} is private (mode {:o})", box_dir.display(), mode),
+                "Other users can read session metadata; run `chmod 700 ~/.box`.",
+            );
+        } else {
+            println!(
+                "\u{2713} {} does not exist yet (created by the first `box create`)",
+                box_dir.display()
+            );
+        }
+    }
+
+    // Docker only matters when sessions default to containers
+    let docker = docker::check();
+    let docker_critical = !is_local_mode();
+    match docker {
+        Ok(()) => doctor_report(
+            true,
+            &format!(
+                "{} is running (server {})",
+                docker::runtime(),
+                docker::server_version().unwrap_or_else(|| "version unknown".to_string())
+            ),
+            "",
+        ),
+        Err(ref e) => doctor_report(
+            false,
+            &format!("{} is available", docker::runtime()),
+            &format!(
+                "{}{}",
+                e,
+                if docker_critical {
+                    ""
+                } else {
+                    " Only needed for --docker sessions."
+                }
+            ),
+        ),
+    }
+    critical_failed |= docker.is_err() && docker_critical;
+
+    let cwd = std::env::current_dir()?;
+    let root = git::find_root(&cwd);
+    doctor_report(
+        root.is_some(),
+        &match root {
+            Some(root) => format!(
+                "{} is in a git repository ({})",
+                cwd.display(),
+                root.display()
+            ),
+            None => format!("{} is in a git repository", cwd.display()),
+        },
+        "`box create` needs a git repository; cd into one or run `git init`.",
+    );
+
+    if home.is_ok() {
+        let stale: Vec<std::path::PathBuf> = session::list()?
+            .iter()
+            .flat_map(|s| session::stale_server_files(&s.name))
+            .collect();
+        let hint = format!(
+            "Left by servers that are no longer running; remove them with `rm {}`.",
+            stale
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        doctor_report(
+            stale.is_empty(),
+            &match stale.len() {
+                0 => "No stale session sockets or PID files".to_string(),
+                n => format!("{} stale session socket/PID file(s)", n),
+            },
+            &hint,
+        );
+    }
+
+    Ok(if critical_failed { 1 } else { 0 })
+}
+
+fn cmd_upgrade() -> Result<i32> {
+    let current_version = env!("CARGO_PKG_VERSION");
+    println!("Current version: {}", current_version);
+
+    println!("Checking for updates...");
+    let releases = self_update::backends::github::ReleaseList::configure()
+        .repo_owner("yusukeshib")
+        .repo_name("box")
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build release list: {}", e))?
+        .fetch()
+        .map_err(|e| anyhow::anyhow!("Failed to fetch releases: {}", e))?;
+
+    let latest = releases
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("No releases found"))?;
+    let latest_version = latest.version.trim_start_matches('v');
+
+    println!("Latest version: {}", latest_version);
+
+    if current_version == latest_version {
+        println!("Already at latest version.");
+        return Ok(0);
+    }
+
+    let asset_name = upgrade_asset_name()?;
+    println!("Looking for asset: {}", asset_name);
+
+    let asset_exists = latest.assets.iter().any(|a| a.name == asset_name);
+    if !asset_exists {
+        bail!(
+            "Asset '{}' not found for this platform. Available assets: {}",
+            asset_name,
+            latest
+                .assets
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let download_url = format!(
+        "https://github.com/yusukeshib/box/releases/download/v{}/{}",
+        latest_version, asset_name
+    );
+
+    println!("Downloading new version...");
+    let tmp_path = upgrade_download(&download_url)?;
+    let _guard = UpgradeTempGuard(tmp_path.clone());
+
+    let checksum_name = format!("{}.sha256", asset_name);
+    if latest.assets.iter().any(|a| a.name == checksum_name) {
+        println!("Verifying checksum...");
+        let mut body = Vec::new();
+        self_update::Download::from_url(&format!("{}.sha256", download_url))
+            .download_to(&mut body)
+            .map_err(|e| anyhow::anyhow!("Checksum download failed: {}", e))?;
+        let expected = parse_sha256_file(&String::from_utf8_lossy(&body))
+            .ok_or_else(|| anyhow::anyhow!("Malformed checksum file '{}'", checksum_name))?;
+        let actual = sha256_file(&tmp_path)?;
+        if actual != expected {
+            bail!(
+                "Checksum mismatch for '{}' (expected {}, got {}). Aborting upgrade.",
+                asset_name,
+                expected,
+                actual
+            );
+        }
+    }
+
+    println!("Installing update...");
+    self_update::self_replace::self_replace(&tmp_path).map_err(|e| {
+        let msg = e.to_string();
+        if msg.to_lowercase().contains("permission denied") {
+            anyhow::anyhow!(
+                "Permission denied. Try running with elevated privileges (e.g., sudo box upgrade)."
+            )
+        } else {
+            anyhow::anyhow!("{}", msg)
+        }
+    })?;
+
+    println!("Upgraded from {} to {}.", current_version, latest_version);
+    Ok(0)
+}
+
+/// RAII guard that removes the temp file on drop.
+struct UpgradeTempGuard(std::path::PathBuf);
+
+impl Drop for UpgradeTempGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn upgrade_asset_name() -> Result<String> {
+    let arch = std::env::consts::ARCH;
+    let os_name = match std::env::consts::OS {
+        "macos" => "darwin",
+        "linux" => "linux",
+        other => bail!("Unsupported platform: {}", other),
+    };
+    Ok(format!("box-{}-{}", arch, os_name))
+}
+
+/// Temp file being downloaded, removed by `handle_upgrade_sigint`.
+static UPGRADE_TMP_PATH: std::sync::OnceLock<std::ffi::CString> = std::sync::OnceLock::new();
+
+extern "C" fn handle_upgrade_sigint(_: libc::c_int) {
+    // Only async-signal-safe calls here: unlink the partial file and exit.
+    if let Some(path) = UPGRADE_TMP_PATH.get() {
+        unsafe { libc::unlink(path.as_ptr()) };
+    }
+    unsafe { libc::_exit(130) };
+}
+
+fn upgrade_download(url: &str) -> Result<std::path::PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let tmp_path = std::env::temp_dir().join(format!("box-update-{}", std::process::id()));
+    let mut tmp_file = fs::File::create(&tmp_path)?;
+    let guard = UpgradeTempGuard(tmp_path.clone());
+
+    // Ctrl+C would otherwise skip the guard and leave a partial binary behind.
+    if let Ok(c_path) = std::ffi::CString::new(tmp_path.as_os_str().as_bytes()) {
+        let _ = UPGRADE_TMP_PATH.set(c_path);
+    }
+    let previous = unsafe {
+        libc::signal(
+            libc::SIGINT,
+            handle_upgrade_sigint as *const () as libc::sighandler_t,
+        )
+    };
+    let result = self_update::Download::from_url(url).download_to(&mut tmp_file);
+    unsafe { libc::signal(libc::SIGINT, previous) };
+    result.map_err(|e| anyhow::anyhow!("Download failed: {}", e))?;
+
+    tmp_file.flush()?;
+    drop(tmp_file);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(&tmp_path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(&tmp_path, perms)?;
+    }
+
+    // The caller takes over cleanup from here.
+    std::mem::forget(guard);
+    Ok(tmp_path)
+}
+
+/// Extract the hex digest from a `sha256sum`-style checksum file.
+fn parse_sha256_file(contents: &str) -> Option<String> {
+    let digest = contents.split_whitespace().next()?.to_ascii_lowercase();
+    (digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit())).then_some(digest)
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    let data = fs::read(path)?;
+    Ok(Sha256::digest(&data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn parse(args: &[&str]) -> Cli {
+        let mut full_args = vec!["box"];
+        full_args.extend_from_slice(args);
+        Cli::try_parse_from(full_args).unwrap()
+    }
+
+    fn try_parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        let mut full_args = vec!["box"];
+        full_args.extend_from_slice(args);
+        Cli::try_parse_from(full_args)
+    }
+
+    // -- No args = TUI --
+
+    #[test]
+    fn test_no_args_launches_tui() {
+        let cli = parse(&[]);
+        assert!(cli.command.is_none());
+    }
+
+    // -- create subcommand --
+
+    #[test]
+    fn test_create_name_only() {
+        let cli = parse(&["create", "my-session"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.name.as_deref(), Some("my-session"));
+                assert!(!args.detach);
+                assert!(!args.local);
+                assert!(args.image.is_none());
+                assert!(args.docker_args.is_none());
+                assert!(args.cmd.is_empty());
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_local_flag() {
+        let cli = parse(&["create", "my-session", "--local"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.name.as_deref(), Some("my-session"));
+                assert!(args.local);
+                assert!(!args.detach);
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_with_all_options() {
+        let cli = parse(&[
+            "create",
+            "full-session",
+            "-d",
+            "--image",
+            "python:3.11",
+            "--docker-args",
+            "-e FOO=bar --network host",
+            "--",
+            "python",
+            "main.py",
+        ]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.name.as_deref(), Some("full-session"));
+                assert!(args.detach);
+                assert_eq!(args.image.as_deref(), Some("python:3.11"));
+                assert_eq!(
+                    args.docker_args.as_deref(),
+                    Some("-e FOO=bar --network host")
+                );
+                assert_eq!(args.cmd, vec!["python", "main.py"]);
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_with_image() {
+        let cli = parse(&["create", "my-session", "--image", "ubuntu:latest"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.name.as_deref(), Some("my-session"));
+                assert_eq!(args.image.as_deref(), Some("ubuntu:latest"));
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_with_command() {
+        let cli = parse(&["create", "my-session", "--", "bash", "-c", "echo hi"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.name.as_deref(), Some("my-session"));
+                assert_eq!(args.cmd, vec!["bash", "-c", "echo hi"]);
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_detach() {
+        let cli = parse(&["create", "my-session", "-d"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.name.as_deref(), Some("my-session"));
+                assert!(args.detach);
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_local_start() {
+        let cmd = vec!["npm".to_string(), "start".to_string()];
+        assert_eq!(local_start(&cmd, true), LocalStart::Background);
+        assert_eq!(local_start(&cmd, false), LocalStart::Attach);
+        assert_eq!(local_start(&[], true), LocalStart::Nothing);
+        assert_eq!(local_start(&[], false), LocalStart::Nothing);
+    }
+
+    #[test]
+    fn test_create_minimal_caps() {
+        let cli = parse(&["create", "my-session", "--docker", "--minimal-caps"]);
+        match cli.command {
+            Some(Commands::Create(args)) => assert!(args.minimal_caps),
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_with_minimal_caps_prepends_preset() {
+        assert_eq!(with_minimal_caps(""), MINIMAL_CAPS_ARGS);
+        let args = with_minimal_caps("--cap-add NET_BIND_SERVICE");
+        assert!(args.starts_with("--cap-drop all "));
+        assert!(args.ends_with("--cap-add NET_BIND_SERVICE"));
+    }
+
+    #[test]
+    fn test_create_notify_flag() {
+        let cli = parse(&["create", "my-session", "--notify", "--", "make"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert!(args.notify);
+                assert_eq!(args.cmd, vec!["make"]);
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_volumes_from() {
+        let cli = parse(&["create", "my-session", "--docker", "--volumes-from", "data"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.volumes_from.as_deref(), Some("data"));
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_local_and_docker_conflict() {
+        let err = try_parse(&["create", "my-session", "--local", "--docker"])
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(try_parse(&["create", "my-session", "--docker", "--local"]).is_err());
+    }
+
+    #[test]
+    fn test_create_docker_socket_default_path() {
+        let cli = parse(&["create", "my-session", "--docker", "--docker-socket"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.docker_socket.as_deref(), Some("/var/run/docker.sock"));
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_docker_socket_custom_path() {
+        let cli = parse(&[
+            "create",
+            "my-session",
+            "--docker-socket",
+            "/run/user/1000/docker.sock",
+            "--",
+            "sh",
+        ]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(
+                    args.docker_socket.as_deref(),
+                    Some("/run/user/1000/docker.sock")
+                );
+                assert_eq!(args.cmd, vec!["sh"]);
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_docker_in_docker_conflicts() {
+        assert!(parse(&["create", "s", "--docker-in-docker"])
+            .command
+            .is_some());
+        assert!(
+            Cli::try_parse_from(["box", "create", "s", "--docker-in-docker", "--local"]).is_err()
+        );
+        assert!(Cli::try_parse_from([
+            "box",
+            "create",
+            "s",
+            "--docker-in-docker",
+            "--minimal-caps"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_create_inherit_git_config() {
+        let cli = parse(&["create", "my-session", "--inherit-git-config"]);
+        match cli.command {
+            Some(Commands::Create(args)) => assert!(args.inherit_git_config),
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_storage_driver_and_opts() {
+        let cli = parse(&[
+            "create",
+            "my-session",
+            "--docker",
+            "--storage-driver",
+            "overlay2",
+            "--storage-opt",
+            "size=20G",
+            "--storage-opt",
+            "overlay2.size=10G",
+        ]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.storage_driver.as_deref(), Some("overlay2"));
+                assert_eq!(args.storage_opt, vec!["size=20G", "overlay2.size=10G"]);
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_quiet() {
+        match parse(&["create", "foo", "-q", "-d"]).command {
+            Some(Commands::Create(args)) => assert!(args.quiet && args.detach),
+            other => panic!("expected Create, got {:?}", other),
+        }
+        match parse(&["create", "foo", "--quiet"]).command {
+            Some(Commands::Create(args)) => assert!(args.quiet),
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_workdir() {
+        match parse(&["create", "my-session", "--workdir", "packages/api/"]).command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.workdir.as_deref(), Some("packages/api"))
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+        assert!(try_parse(&["create", "my-session", "--workdir", "../x"]).is_err());
+        assert!(try_parse(&["create", "my-session", "--workdir", "/abs"]).is_err());
+    }
+
+    #[test]
+    fn test_create_resource_limits() {
+        let cli = parse(&[
+            "create",
+            "my-session",
+            "--docker",
+            "--memory",
+            "2g",
+            "--cpus",
+            "1.5",
+            "--pids-limit",
+            "256",
+        ]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert_eq!(args.memory.as_deref(), Some("2g"));
+                assert_eq!(args.cpus.as_deref(), Some("1.5"));
+                assert_eq!(args.pids_limit, Some(256));
+            }
+            other => panic!("expected Create, got {:?}", other),
+        }
+        for bad in ["", "g", "0", "-1g", "2gb", "1.5g"] {
+            assert!(
+                try_parse(&["create", "my-session", "--memory", bad]).is_err(),
+                "{}",
+                bad
+            );
+        }
+        for bad in ["0", "-1", "abc", "inf"] {
+            assert!(
+                try_parse(&["create", "my-session", "--cpus", bad]).is_err(),
+                "{}",
+                bad
+            );
+        }
+        assert!(try_parse(&["create", "my-session", "--pids-limit", "-2"]).is_err());
+    }
+
+    #[test]
+    fn test_create_ports() {
+        let cli = parse(&[
+            "create",
+            "my-session",
+            "--docker",
+            "--port",
+            "8080:80",
+            "--port",
+            "5432:5432",
+        ]);
+        match cli.command {
+            Some(Commands::Create(args)) => assert_eq!(args.port, vec!["8080:80", "5432:5432"]),
+            other => panic!("expected Create, got {:?}", other),
+        }
+        for bad in ["8080", "8080:", "http:80", "0:80", "70000:80", "8080:80:1"] {
+            assert!(
+                try_parse(&["create", "my-session", "--port", bad]).is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_env_file() {
+        let cli = parse(&[
+            "create",
+            "my-session",
+            "--env-file",
+            ".env",
+            "--env-file",
+            "secrets.env",
+        ]);
+        match cli.command {
+            Some(Commands::Create(args)) => assert_eq!(
+                args.env_file,
+                vec![
+                    std::path::PathBuf::from(".env"),
+                    std::path::PathBuf::from("secrets.env")
+                ]
+            ),
+            other => panic!("expected Create, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_load_env_files_concatenates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let a = tmp.path().join("a.env");
+        let b = tmp.path().join("b.env");
+        std::fs::write(&a, "A=1\n").unwrap();
+        std::fs::write(&b, "# comment\nB=2\n").unwrap();
+        assert_eq!(load_env_files(&[a, b]).unwrap(), vec!["A=1", "B=2"]);
+        assert!(load_env_files(&[tmp.path().join("missing.env")]).is_err());
+    }
+
+    #[test]
+    fn test_create_storage_driver_rejects_unknown() {
+        assert!(try_parse(&["create", "s", "--storage-driver", "vfs2"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_storage_opt() {
+        assert_eq!(parse_storage_opt("size=20G").unwrap(), "size=20G");
+        assert!(parse_storage_opt("size").is_err());
+        assert!(parse_storage_opt("=20G").is_err());
+    }
+
+    #[test]
+    fn test_validate_docker_socket() {
+        let tmp = tempfile::tempdir().unwrap();
+        let socket = tmp.path().join("docker.sock");
+        std::fs::write(&socket, "").unwrap();
+        assert!(validate_docker_socket(socket.to_str().unwrap()).is_ok());
+        assert!(validate_docker_socket("relative/docker.sock").is_err());
+        assert!(validate_docker_socket("/nonexistent/docker.sock").is_err());
+    }
+
+    #[test]
+    fn test_create_no_name_opens_tui() {
+        let cli = parse(&["create"]);
+        match cli.command {
+            Some(Commands::Create(args)) => {
+                assert!(args.name.is_none());
+            }
+            _ => panic!("expected Create"),
+        }
+    }
+
+    // -- resume subcommand --
+
+    #[test]
+    fn test_resume_name_only() {
+        let cli = parse(&["resume", "my-session"]);
+        match cli.command {
+            Some(Commands::Resume(args)) => {
+                assert_eq!(args.name, "my-session");
+                assert!(!args.detach);
+                assert!(args.docker_args.is_none());
+            }
+            other => panic!("expected Resume, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resume_detach() {
+        let cli = parse(&["resume", "my-session", "-d"]);
+        match cli.command {
+            Some(Commands::Resume(args)) => {
+                assert_eq!(args.name, "my-session");
+                assert!(args.detach);
+            }
+            other => panic!("expected Resume, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resume_with_docker_args() {
+        let cli = parse(&["resume", "my-session", "--docker-args", "-e KEY=val"]);
+        match cli.command {
+            Some(Commands::Resume(args)) => {
+                assert_eq!(args.name, "my-session");
+                assert_eq!(args.docker_args.as_deref(), Some("-e KEY=val"));
+            }
+            other => panic!("expected Resume, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resume_requires_name() {
+        let result = try_parse(&["resume"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_resume_image_and_recreate() {
+        let cli = parse(&["resume", "my-session", "--image", "ubuntu", "--recreate"]);
+        match cli.command {
+            Some(Commands::Resume(args)) => {
+                assert_eq!(args.image.as_deref(), Some("ubuntu"));
+                assert!(args.recreate);
+                assert!(!args.pull);
+            }
+            other => panic!("expected Resume, got {:?}", other),
+        }
+        assert!(try_parse(&["resume", "my-session", "--image"]).is_err());
+    }
+
+    #[test]
+    fn test_pull_flag() {
+        match parse(&["resume", "my-session", "--pull"]).command {
+            Some(Commands::Resume(args)) => assert!(args.pull),
+            other => panic!("expected Resume, got {:?}", other),
+        }
+        match parse(&["create", "my-session", "--pull"]).command {
+            Some(Commands::Create(args)) => assert!(args.pull),
+            other => panic!("expected Create, got {:?}", other),
+        }
+        assert!(try_parse(&["create", "my-session", "--pull", "--local"]).is_err());
+    }
+
+    #[test]
+    fn test_create_adopt_flag() {
+        match parse(&["create", "my-session", "--adopt"]).command {
+            Some(Commands::Create(args)) => assert!(args.adopt),
+            other => panic!("expected Create, got {:?}", other),
+        }
+        assert!(try_parse(&["create", "my-session", "--adopt", "--local"]).is_err());
+        assert!(try_parse(&["create", "my-session", "--adopt", "--pull"]).is_err());
+    }
+
+    #[test]
+    fn test_create_from_flag() {
+        match parse(&["create", "my-session", "--from", "release/1.2"]).command {
+            Some(Commands::Create(args)) => assert_eq!(args.from.as_deref(), Some("release/1.2")),
+            other => panic!("expected Create, got {:?}", other),
+        }
+        assert!(try_parse(&["create", "my-session", "--from"]).is_err());
+    }
+
+    #[test]
+    fn test_create_clone_depth_flag() {
+        match parse(&["create", "my-session", "--clone-depth", "50"]).command {
+            Some(Commands::Create(args)) => assert_eq!(args.clone_depth, Some(50)),
+            other => panic!("expected Create, got {:?}", other),
+        }
+        assert!(try_parse(&["create", "my-session", "--clone-depth", "0"]).is_err());
+        assert!(try_parse(&["create", "my-session", "--clone-depth", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_resume_workdir() {
+        match parse(&["resume", "my-session", "--workdir", "packages/api"]).command {
+            Some(Commands::Resume(args)) => {
+                assert_eq!(args.workdir.as_deref(), Some("packages/api"))
+            }
+            other => panic!("expected Resume, got {:?}", other),
+        }
+        assert!(try_parse(&["resume", "my-session", "--workdir", "../x"]).is_err());
+    }
+
+    #[test]
+    fn test_resume_read_only() {
+        match parse(&["resume", "my-session", "--read-only"]).command {
+            Some(Commands::Resume(args)) => assert!(args.read_only),
+            other => panic!("expected Resume, got {:?}", other),
+        }
+        assert!(try_parse(&["resume", "my-session", "--read-only", "--workdir", "src"]).is_err());
+    }
+
+    // -- remove subcommand --
+
+    #[test]
+    fn test_remove_parses() {
+        let cli = parse(&["remove", "my-session"]);
+        match cli.command {
+            Some(Commands::Remove(args)) => {
+                assert_eq!(args.name, "my-session");
+            }
+            other => panic!("expected Remove, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_remove_requires_name() {
+        let result = try_parse(&["remove"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_remove_rejects_unknown_flags() {
+        let result = try_parse(&["remove", "my-session", "-d"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_remove_yes_flag() {
+        for flag in ["--yes", "-y"] {
+            match parse(&["remove", flag, "my-feature"]).command {
+                Some(Commands::Remove(args)) => assert!(args.yes),
+                other => panic!("expected Remove, got {:?}", other),
+            }
+        }
+        assert!(is_yes("y\n"));
+        assert!(is_yes(" YES "));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("no"));
+    }
+
+    #[test]
+    fn test_remove_force_flag() {
+        let cli = parse(&["remove", "--force", "my-session"]);
+        match cli.command {
+            Some(Commands::Remove(args)) => {
+                assert_eq!(args.name, "my-session");
+                assert!(args.force);
+            }
+            other => panic!("expected Remove, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_remove_short_force_flag() {
+        let cli = parse(&["remove", "-f", "my-session"]);
+        match cli.command {
+            Some(Commands::Remove(args)) => {
+                assert_eq!(args.name, "my-session");
+                assert!(args.force);
+            }
+            other => panic!("expected Remove, got {:?}", other),
+        }
+    }
+
+    // -- stop subcommand --
+
+    #[test]
+    fn test_stop_parses() {
+        let cli = parse(&["stop", "my-session"]);
+        match cli.command {
+            Some(Commands::Stop(args)) => {
+                assert_eq!(args.name.as_deref(), Some("my-session"));
+                assert!(!args.all && !args.project);
+            }
+            other => panic!("expected Stop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stop_requires_name() {
+        let result = try_parse(&["stop"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stop_rejects_flags() {
+        let result = try_parse(&["stop", "my-session", "-d"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stop_all_and_project() {
+        match parse(&["stop", "--all"]).command {
+            Some(Commands::Stop(args)) => assert!(args.all && args.name.is_none()),
+            other => panic!("expected Stop, got {:?}", other),
+        }
+        match parse(&["stop", "-p"]).command {
+            Some(Commands::Stop(args)) => assert!(args.project && args.name.is_none()),
+            other => panic!("expected Stop, got {:?}", other),
+        }
+        assert!(try_parse(&["stop", "my-session", "--all"]).is_err());
+        assert!(try_parse(&["stop", "--all", "--project"]).is_err());
+    }
+
+    // -- exec subcommand --
+
+    #[test]
+    fn test_exec_parses() {
+        let cli = parse(&["exec", "my-session", "--", "ls", "-la"]);
+        match cli.command {
+            Some(Commands::Exec(args)) => {
+                assert_eq!(args.name, "my-session");
+                assert_eq!(args.cmd, vec!["ls", "-la"]);
+                assert!(!args.detach);
+            }
+            other => panic!("expected Exec, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_exec_detach() {
+        let cli = parse(&["exec", "-d", "my-session", "--", "make", "build"]);
+        match cli.command {
+            Some(Commands::Exec(args)) => {
+                assert!(args.detach);
+                assert_eq!(args.cmd, vec!["make", "build"]);
+            }
+            other => panic!("expected Exec, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_exec_no_tty() {
+        let cli = parse(&["exec", "--no-tty", "my-session", "--", "make", "test"]);
+        match cli.command {
+            Some(Commands::Exec(args)) => assert!(args.no_tty && !args.detach),
+            other => panic!("expected Exec, got {:?}", other),
+        }
+        assert!(try_parse(&["exec", "-d", "--no-tty", "my-session", "--", "ls"]).is_err());
+    }
+
+    #[test]
+    fn test_exec_requires_name() {
+        let result = try_parse(&["exec"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_exec_requires_command() {
+        let result = try_parse(&["exec", "my-session"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_exec_stdin() {
+        match parse(&["exec", "my-session", "--stdin"]).command {
+            Some(Commands::Exec(args)) => {
+                assert!(args.stdin);
+                assert!(args.cmd.is_empty());
+            }
+            other => panic!("expected Exec, got {:?}", other),
+        }
+        assert!(try_parse(&["exec", "my-session", "--stdin", "--", "ls"]).is_err());
+
+        assert_eq!(
+            parse_command_line("grep -r 'foo bar' src\n").unwrap(),
+            vec!["grep", "-r", "foo bar", "src"]
+        );
+        assert!(parse_command_line("  \n").is_err());
+        assert!(parse_command_line("echo 'unterminated").is_err());
+    }
+
+    // -- path subcommand --
+
+    #[test]
+    fn test_path_subcommand_parses() {
+        let cli = parse(&["path", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Path { ref name, origin: false }) if name == "my-session"
+        ));
+    }
+
+    #[test]
+    fn test_path_origin_flag() {
+        let cli = parse(&["path", "my-session", "--origin"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Path { origin: true, .. })
+        ));
+    }
+
+    #[test]
+    fn test_path_requires_name() {
+        let result = try_parse(&["path"]);
+        assert!(result.is_err());
+    }
+
+    // -- info subcommand --
+
+    #[test]
+    fn test_info_parses() {
+        let cli = parse(&["info", "my-session", "--json"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Info { ref name, json: true }) if name == "my-session"
+        ));
+    }
+
+    #[test]
+    fn test_info_requires_name() {
+        assert!(try_parse(&["info"]).is_err());
+    }
+
+    // -- cd subcommand --
+
+    #[test]
+    fn test_cd_subcommand_parses() {
+        let cli = parse(&["cd", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Cd { ref name, origin: false }) if name == "my-session"
+        ));
+    }
+
+    #[test]
+    fn test_cd_origin_flag() {
+        let cli = parse(&["cd", "--origin", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Cd { origin: true, .. })
+        ));
+    }
+
+    #[test]
+    fn test_cd_requires_name() {
+        let result = try_parse(&["cd"]);
+        assert!(result.is_err());
+    }
+
+    // -- show-env subcommand --
+
+    #[test]
+    fn test_show_env_parses() {
+        let cli = parse(&["show-env", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::ShowEnv { ref name }) if name == "my-session"
+        ));
+    }
+
+    #[test]
+    fn test_merge_env_layers_and_sorts() {
+        let image_env = vec!["PATH=/usr/bin".to_string(), "LANG=C".to_string()];
+        let session_env = vec![
+            "LANG=en_US.UTF-8".to_string(),
+            "TOKEN".to_string(),
+            "MISSING".to_string(),
+        ];
+        let vars = merge_env(&image_env, &session_env, |k| {
+            (k == "TOKEN").then(|| "secret".to_string())
+        });
+        assert_eq!(
+            vars,
+            vec![
+                ("LANG".to_string(), "en_US.UTF-8".to_string(), "session"),
+                ("PATH".to_string(), "/usr/bin".to_string(), "image"),
+                ("TOKEN".to_string(), "secret".to_string(), "inherited"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_env_overlays_session_env() {
+        let host = vec!["HOME".to_string(), "DEBUG".to_string()];
+        let session_env = vec!["DEBUG=1".to_string(), "TOKEN".to_string()];
+        let vars = local_env("app/default", &host, &session_env, |k| match k {
+            "HOME" => Some("/home/me".to_string()),
+            "DEBUG" => Some("0".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            vars,
+            vec![
+                (
+                    "BOX_SESSION".to_string(),
+                    "app/default".to_string(),
+                    "session"
+                ),
+                ("DEBUG".to_string(), "1".to_string(), "session"),
+                ("HOME".to_string(), "/home/me".to_string(), "inherited"),
+            ]
+        );
+    }
+
+    // -- set-image subcommand --
+
+    #[test]
+    fn test_set_image_parses() {
+        let cli = parse(&["set-image", "my-session", "python:3.12"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::SetImage { ref name, ref image })
+                if name == "my-session" && image == "python:3.12"
+        ));
+    }
+
+    #[test]
+    fn test_set_image_requires_image() {
+        let result = try_parse(&["set-image", "my-session"]);
+        assert!(result.is_err());
+    }
+
+    // -- connect-to-debugger subcommand --
+
+    #[test]
+    fn test_connect_to_debugger_parses() {
+        let cli = parse(&["connect-to-debugger", "my-session", "--port", "5678"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::ConnectToDebugger { ref name, port: 5678, .. }) if name == "my-session"
+        ));
+    }
+
+    #[test]
+    fn test_connect_to_debugger_requires_port() {
+        assert!(Cli::try_parse_from(["box", "connect-to-debugger", "my-session"]).is_err());
+    }
+
+    #[test]
+    fn test_connect_to_debugger_type() {
+        match parse(&[
+            "connect-to-debugger",
+            "s",
+            "--port",
+            "9229",
+            "--type",
+            "node",
+        ])
+        .command
+        {
+            Some(Commands::ConnectToDebugger { debug_type, .. }) => assert_eq!(debug_type, "node"),
+            other => panic!("expected ConnectToDebugger, got {:?}", other),
+        }
+        assert!(try_parse(&["connect-to-debugger", "s", "--port", "1", "--type", "gdb"]).is_err());
+    }
+
+    #[test]
+    fn test_debugger_launch_config_local() {
+        let config = debugger_launch_config("ws/\"q\"", "debugpy", "localhost", 5678, None);
+        assert_eq!(
+            config,
+            serde_json::json!({
+                "name": "Attach to box ws/\"q\"",
+                "type": "debugpy",
+                "request": "attach",
+                "connect": { "host": "localhost", "port": 5678 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_debugger_launch_config_path_mapping() {
+        let mapping = Some(("/home/u/.box/workspaces/ws", "/workspace"));
+        let config = debugger_launch_config("ws/default", "debugpy", "127.0.0.1", 5678, mapping);
+        assert_eq!(
+            config["pathMappings"],
+            serde_json::json!([{ "localRoot": "/home/u/.box/workspaces/ws", "remoteRoot": "/workspace" }])
+        );
+
+        let config = debugger_launch_config("ws/default", "node", "127.0.0.1", 9229, mapping);
+        assert_eq!(config["address"], "127.0.0.1");
+        assert_eq!(config["port"], 9229);
+        assert_eq!(config["remoteRoot"], "/workspace");
+        assert!(config.get("connect").is_none());
+    }
+
+    // -- environment-baseline / environment-drift subcommands --
+
+    #[test]
+    fn test_environment_baseline_parses() {
+        let cli = parse(&["environment-baseline", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::EnvironmentBaseline { ref name }) if name == "my-session"
+        ));
+    }
+
+    #[test]
+    fn test_environment_drift_reset() {
+        let cli = parse(&["environment-drift", "my-session", "--reset"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::EnvironmentDrift { ref name, reset: true }) if name == "my-session"
+        ));
+        let cli = parse(&["environment-drift", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::EnvironmentDrift { reset: false, .. })
+        ));
+    }
 
-    println!("Installing update...");
-    self_update::self_replace::self_replace(&tmp_path).map_err(|e| {
-        let msg = e.to_string();
-        if msg.to_lowercase().contains("permission denied") {
-            anyhow::anyhow!(
-                "Permission denied. Try running with elevated privileges (e.g., sudo box upgrade)."
-            )
-        } else {
-            anyhow::anyhow!("{}", msg)
-        }
-    })?;
+    // -- set-strategy subcommand --
 
-    println!("Upgraded from {} to {}.", current_version, latest_version);
-    Ok(0)
-}
+    #[test]
+    fn test_set_strategy_parses() {
+        let cli = parse(&["set-strategy", "my-session", "worktree"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::SetStrategy { ref name, ref strategy, force: false })
+                if name == "my-session" && strategy == "worktree"
+        ));
+    }
 
-/// RAII guard that removes the temp file on drop.
-struct UpgradeTempGuard(std::path::PathBuf);
+    #[test]
+    fn test_set_strategy_force() {
+        let cli = parse(&["set-strategy", "my-session", "clone", "-f"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::SetStrategy { force: true, .. })
+        ));
+    }
 
-impl Drop for UpgradeTempGuard {
-    fn drop(&mut self) {
-        let _ = fs::remove_file(&self.0);
+    #[test]
+    fn test_set_strategy_rejects_unknown_strategy() {
+        let err = cmd_set_strategy("my-session", "copy", false).unwrap_err();
+        assert!(err.to_string().contains("Invalid strategy 'copy'"));
     }
-}
 
-fn upgrade_asset_name() -> Result<String> {
-    let arch = std::env::consts::ARCH;
-    let os_name = match std::env::consts::OS {
-        "macos" => "darwin",
-        "linux" => "linux",
-        other => bail!("Unsupported platform: {}", other),
-    };
-    Ok(format!("box-{}-{}", arch, os_name))
-}
+    // -- recover subcommand --
 
-fn upgrade_download(url: &str) -> Result<std::path::PathBuf> {
-    let tmp_path = std::env::temp_dir().join(format!("box-update-{}", std::process::id()));
-    let mut tmp_file = fs::File::create(&tmp_path)?;
+    #[test]
+    fn test_recover_parses() {
+        let cli = parse(&["recover", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Recover { ref name }) if name == "my-session"
+        ));
+    }
 
-    self_update::Download::from_url(url)
-        .download_to(&mut tmp_file)
-        .map_err(|e| anyhow::anyhow!("Download failed: {}", e))?;
+    // -- fork subcommand --
 
-    tmp_file.flush()?;
-    drop(tmp_file);
+    #[test]
+    fn test_fork_parses() {
+        let cli = parse(&["fork", "app/feature-a", "app-b/feature-b"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Fork { ref name, ref new_name })
+                if name == "app/feature-a" && new_name == "app-b/feature-b"
+        ));
+    }
 
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&tmp_path)?.permissions();
-        perms.set_mode(0o755);
-        fs::set_permissions(&tmp_path, perms)?;
+    #[test]
+    fn test_fork_requires_new_name() {
+        assert!(Cli::try_parse_from(["box", "fork", "app"]).is_err());
     }
 
-    Ok(tmp_path)
-}
+    #[test]
+    fn test_prune_parses() {
+        let cli = parse(&["prune", "--dry-run", "-p"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Prune {
+                dry_run: true,
+                project: true,
+                yes: false,
+                force: false,
+            })
+        ));
+        let cli = parse(&["prune"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Prune {
+                dry_run: false,
+                project: false,
+                ..
+            })
+        ));
+        let cli = parse(&["prune", "-y", "-f"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Prune {
+                yes: true,
+                force: true,
+                ..
+            })
+        ));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use clap::Parser;
+    #[test]
+    fn test_archive_parses() {
+        let cli = parse(&["archive", "app/old"]);
+        assert!(matches!(cli.command, Some(Commands::Archive { ref name }) if name == "app/old"));
+        let cli = parse(&["unarchive", "app/old"]);
+        assert!(matches!(cli.command, Some(Commands::Unarchive { ref name }) if name == "app/old"));
+        let cli = parse(&["list", "--archived"]);
+        match cli.command {
+            Some(Commands::List(args)) => assert!(args.archived),
+            other => panic!("expected List, got {:?}", other),
+        }
+        assert!(try_parse(&["list", "--archived", "--running"]).is_err());
+    }
 
-    fn parse(args: &[&str]) -> Cli {
-        let mut full_args = vec!["box"];
-        full_args.extend_from_slice(args);
-        Cli::try_parse_from(full_args).unwrap()
+    #[test]
+    fn test_duplicate_parses() {
+        let cli = parse(&["duplicate", "app/claude", "app-b", "-d"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Duplicate { ref src, ref dest, detach: true })
+                if src == "app/claude" && dest == "app-b"
+        ));
+        assert!(Cli::try_parse_from(["box", "duplicate", "app"]).is_err());
     }
 
-    fn try_parse(args: &[&str]) -> Result<Cli, clap::Error> {
-        let mut full_args = vec!["box"];
-        full_args.extend_from_slice(args);
-        Cli::try_parse_from(full_args)
+    // -- promote subcommand --
+
+    #[test]
+    fn test_promote_parses() {
+        let cli = parse(&["promote", "app/server"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Promote { ref name }) if name == "app/server"
+        ));
     }
 
-    // -- No args = TUI --
+    // -- list-capabilities subcommand --
 
     #[test]
-    fn test_no_args_launches_tui() {
-        let cli = parse(&[]);
-        assert!(cli.command.is_none());
+    fn test_list_capabilities_parses() {
+        let cli = parse(&["list-capabilities", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::ListCapabilities { ref name }) if name == "my-session"
+        ));
     }
 
-    // -- create subcommand --
+    // -- batch-create subcommand --
 
     #[test]
-    fn test_create_name_only() {
-        let cli = parse(&["create", "my-session"]);
+    fn test_batch_create_parses() {
+        let cli = parse(&["batch-create", "box.toml", "--continue-on-error"]);
         match cli.command {
-            Some(Commands::Create(args)) => {
-                assert_eq!(args.name.as_deref(), Some("my-session"));
-                assert!(!args.detach);
-                assert!(!args.local);
-                assert!(args.image.is_none());
-                assert!(args.docker_args.is_none());
-                assert!(args.cmd.is_empty());
+            Some(Commands::BatchCreate {
+                file,
+                continue_on_error,
+            }) => {
+                assert_eq!(file, std::path::PathBuf::from("box.toml"));
+                assert!(continue_on_error);
             }
-            other => panic!("expected Create, got {:?}", other),
+            other => panic!("expected BatchCreate, got {:?}", other),
         }
     }
 
     #[test]
-    fn test_create_local_flag() {
-        let cli = parse(&["create", "my-session", "--local"]);
-        match cli.command {
-            Some(Commands::Create(args)) => {
-                assert_eq!(args.name.as_deref(), Some("my-session"));
-                assert!(args.local);
-                assert!(!args.detach);
-            }
-            other => panic!("expected Create, got {:?}", other),
-        }
+    fn test_batch_create_requires_file() {
+        assert!(Cli::try_parse_from(["box", "batch-create"]).is_err());
     }
 
+    // -- list-processes subcommand --
+
     #[test]
-    fn test_create_with_all_options() {
-        let cli = parse(&[
-            "create",
-            "full-session",
-            "-d",
-            "--image",
-            "python:3.11",
-            "--docker-args",
-            "-e FOO=bar --network host",
-            "--",
-            "python",
-            "main.py",
-        ]);
-        match cli.command {
-            Some(Commands::Create(args)) => {
-                assert_eq!(args.name.as_deref(), Some("full-session"));
-                assert!(args.detach);
-                assert_eq!(args.image.as_deref(), Some("python:3.11"));
-                assert_eq!(
-                    args.docker_args.as_deref(),
-                    Some("-e FOO=bar --network host")
-                );
-                assert_eq!(args.cmd, vec!["python", "main.py"]);
-            }
-            other => panic!("expected Create, got {:?}", other),
-        }
+    fn test_list_processes_defaults() {
+        let cli = parse(&["list-processes", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::ListProcesses { ref name, ref sort_by, watch: false })
+                if name == "my-session" && sort_by == "pid"
+        ));
     }
 
     #[test]
-    fn test_create_with_image() {
-        let cli = parse(&["create", "my-session", "--image", "ubuntu:latest"]);
-        match cli.command {
-            Some(Commands::Create(args)) => {
-                assert_eq!(args.name.as_deref(), Some("my-session"));
-                assert_eq!(args.image.as_deref(), Some("ubuntu:latest"));
-            }
-            other => panic!("expected Create, got {:?}", other),
-        }
+    fn test_list_processes_sort_and_watch() {
+        let cli = parse(&["list-processes", "my-session", "--sort-by", "cpu", "-w"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::ListProcesses { ref sort_by, watch: true, .. }) if sort_by == "cpu"
+        ));
     }
 
     #[test]
-    fn test_create_with_command() {
-        let cli = parse(&["create", "my-session", "--", "bash", "-c", "echo hi"]);
-        match cli.command {
-            Some(Commands::Create(args)) => {
-                assert_eq!(args.name.as_deref(), Some("my-session"));
-                assert_eq!(args.cmd, vec!["bash", "-c", "echo hi"]);
-            }
-            other => panic!("expected Create, got {:?}", other),
-        }
+    fn test_list_processes_rejects_unknown_sort() {
+        assert!(Cli::try_parse_from(["box", "list-processes", "s", "--sort-by", "name"]).is_err());
     }
 
+    // -- rename subcommand --
+
     #[test]
-    fn test_create_detach() {
-        let cli = parse(&["create", "my-session", "-d"]);
-        match cli.command {
-            Some(Commands::Create(args)) => {
-                assert_eq!(args.name.as_deref(), Some("my-session"));
-                assert!(args.detach);
-            }
-            other => panic!("expected Create, got {:?}", other),
-        }
+    fn test_rename_parses() {
+        let cli = parse(&["rename", "app/default", "feature"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Rename { ref old, ref new, force: false }) if old == "app/default" && new == "feature"
+        ));
+        let cli = parse(&["rename", "app", "web", "--force"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Rename { force: true, .. })
+        ));
     }
 
     #[test]
-    fn test_create_no_name_opens_tui() {
-        let cli = parse(&["create"]);
-        match cli.command {
-            Some(Commands::Create(args)) => {
-                assert!(args.name.is_none());
-            }
-            _ => panic!("expected Create"),
-        }
+    fn test_rename_requires_new_name() {
+        assert!(try_parse(&["rename", "app"]).is_err());
     }
 
-    // -- resume subcommand --
+    // -- rebuild-workspace subcommand --
 
     #[test]
-    fn test_resume_name_only() {
-        let cli = parse(&["resume", "my-session"]);
-        match cli.command {
-            Some(Commands::Resume(args)) => {
-                assert_eq!(args.name, "my-session");
-                assert!(!args.detach);
-                assert!(args.docker_args.is_none());
-            }
-            other => panic!("expected Resume, got {:?}", other),
-        }
+    fn test_rebuild_workspace_parses() {
+        let cli = parse(&["rebuild-workspace", "myapp", "--keep-changes"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::RebuildWorkspace { ref name, keep_changes: true, force: false })
+                if name == "myapp"
+        ));
     }
 
     #[test]
-    fn test_resume_detach() {
-        let cli = parse(&["resume", "my-session", "-d"]);
-        match cli.command {
-            Some(Commands::Resume(args)) => {
-                assert_eq!(args.name, "my-session");
-                assert!(args.detach);
-            }
-            other => panic!("expected Resume, got {:?}", other),
-        }
+    fn test_rebuild_workspace_keep_changes_conflicts_with_force() {
+        assert!(try_parse(&["rebuild-workspace", "myapp", "--keep-changes", "-f"]).is_err());
     }
 
+    // -- list-changes subcommand --
+
     #[test]
-    fn test_resume_with_docker_args() {
-        let cli = parse(&["resume", "my-session", "--docker-args", "-e KEY=val"]);
-        match cli.command {
-            Some(Commands::Resume(args)) => {
-                assert_eq!(args.name, "my-session");
-                assert_eq!(args.docker_args.as_deref(), Some("-e KEY=val"));
-            }
-            other => panic!("expected Resume, got {:?}", other),
-        }
+    fn test_list_changes_defaults() {
+        let cli = parse(&["list-changes", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::ListChanges { ref name, stat: false, since_commit: None })
+                if name == "my-session"
+        ));
     }
 
     #[test]
-    fn test_resume_requires_name() {
-        let result = try_parse(&["resume"]);
-        assert!(result.is_err());
+    fn test_list_changes_stat_since_commit() {
+        let cli = parse(&[
+            "list-changes",
+            "my-session",
+            "--stat",
+            "--since-commit",
+            "abc123",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::ListChanges { stat: true, ref since_commit, .. })
+                if since_commit.as_deref() == Some("abc123")
+        ));
     }
 
+    // -- describe-image subcommand --
+
     #[test]
-    fn test_resume_rejects_image() {
-        let result = try_parse(&["resume", "my-session", "--image", "ubuntu"]);
-        assert!(result.is_err());
+    fn test_describe_image_parses() {
+        let cli = parse(&["describe-image", "my-session", "--history"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::DescribeImage { ref name, history: true }) if name == "my-session"
+        ));
     }
 
-    // -- remove subcommand --
+    // -- check-image-updates subcommand --
 
     #[test]
-    fn test_remove_parses() {
-        let cli = parse(&["remove", "my-session"]);
-        match cli.command {
-            Some(Commands::Remove(args)) => {
-                assert_eq!(args.name, "my-session");
-            }
-            other => panic!("expected Remove, got {:?}", other),
-        }
+    fn test_check_image_updates_name() {
+        let cli = parse(&["check-image-updates", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::CheckImageUpdates { ref name, all: false })
+                if name.as_deref() == Some("my-session")
+        ));
     }
 
     #[test]
-    fn test_remove_requires_name() {
-        let result = try_parse(&["remove"]);
-        assert!(result.is_err());
+    fn test_check_image_updates_all() {
+        let cli = parse(&["check-image-updates", "--all"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::CheckImageUpdates {
+                name: None,
+                all: true
+            })
+        ));
     }
 
     #[test]
-    fn test_remove_rejects_unknown_flags() {
-        let result = try_parse(&["remove", "my-session", "-d"]);
-        assert!(result.is_err());
+    fn test_check_image_updates_requires_name_or_all() {
+        assert!(try_parse(&["check-image-updates"]).is_err());
+        assert!(try_parse(&["check-image-updates", "s", "--all"]).is_err());
     }
 
     #[test]
-    fn test_remove_force_flag() {
-        let cli = parse(&["remove", "--force", "my-session"]);
-        match cli.command {
-            Some(Commands::Remove(args)) => {
-                assert_eq!(args.name, "my-session");
-                assert!(args.force);
-            }
-            other => panic!("expected Remove, got {:?}", other),
-        }
+    fn test_describe_image_update() {
+        assert_eq!(
+            describe_image_update(&docker::ImageUpdateStatus::UpToDate),
+            "Up to date"
+        );
+        assert_eq!(
+            describe_image_update(&docker::ImageUpdateStatus::UpdateAvailable {
+                local_digest: "sha256:0123456789abcdef0123".to_string(),
+                remote_digest: "sha256:fedcba9876543210fedc".to_string(),
+            }),
+            "New version available (local: sha256:0123456789ab..., remote: sha256:fedcba987654...)"
+        );
     }
 
+    // -- show-resource-limits subcommand --
+
     #[test]
-    fn test_remove_short_force_flag() {
-        let cli = parse(&["remove", "-f", "my-session"]);
-        match cli.command {
-            Some(Commands::Remove(args)) => {
-                assert_eq!(args.name, "my-session");
-                assert!(args.force);
-            }
-            other => panic!("expected Remove, got {:?}", other),
-        }
+    fn test_show_resource_limits_parses() {
+        let cli = parse(&["show-resource-limits", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::ShowResourceLimits { ref name }) if name == "my-session"
+        ));
     }
 
-    // -- stop subcommand --
+    // -- wait-for-port subcommand --
 
     #[test]
-    fn test_stop_parses() {
-        let cli = parse(&["stop", "my-session"]);
-        match cli.command {
-            Some(Commands::Stop(args)) => {
-                assert_eq!(args.name, "my-session");
-            }
-            other => panic!("expected Stop, got {:?}", other),
-        }
+    fn test_wait_for_port_defaults() {
+        let cli = parse(&["wait-for-port", "my-session", "3000"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::WaitForPort { ref name, port: 3000, timeout: 60, message: None })
+                if name == "my-session"
+        ));
     }
 
     #[test]
-    fn test_stop_requires_name() {
-        let result = try_parse(&["stop"]);
-        assert!(result.is_err());
+    fn test_wait_for_port_timeout_and_message() {
+        let cli = parse(&[
+            "wait-for-port",
+            "my-session",
+            "8080",
+            "--timeout",
+            "5",
+            "--message",
+            "Waiting for dev server",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::WaitForPort { port: 8080, timeout: 5, ref message, .. })
+                if message.as_deref() == Some("Waiting for dev server")
+        ));
     }
 
     #[test]
-    fn test_stop_rejects_flags() {
-        let result = try_parse(&["stop", "my-session", "-d"]);
-        assert!(result.is_err());
+    fn test_wait_for_port_rejects_invalid_port() {
+        assert!(Cli::try_parse_from(["box", "wait-for-port", "s", "70000"]).is_err());
     }
 
-    // -- exec subcommand --
+    #[test]
+    fn test_wait_for_tcp_ready() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(wait_for_tcp(
+            "127.0.0.1",
+            port,
+            std::time::Duration::from_secs(2),
+            "test"
+        ));
+    }
 
     #[test]
-    fn test_exec_parses() {
-        let cli = parse(&["exec", "my-session", "--", "ls", "-la"]);
-        match cli.command {
-            Some(Commands::Exec(args)) => {
-                assert_eq!(args.name, "my-session");
-                assert_eq!(args.cmd, vec!["ls", "-la"]);
-            }
-            other => panic!("expected Exec, got {:?}", other),
-        }
+    fn test_wait_for_tcp_times_out() {
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        assert!(!wait_for_tcp(
+            "127.0.0.1",
+            port,
+            std::time::Duration::from_millis(300),
+            "test"
+        ));
     }
 
+    // -- tail-output subcommand --
+
     #[test]
-    fn test_exec_requires_name() {
-        let result = try_parse(&["exec"]);
-        assert!(result.is_err());
+    fn test_tail_output_defaults() {
+        let cli = parse(&["tail-output", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::TailOutput { ref name, lines: 20, follow: false }) if name == "my-session"
+        ));
     }
 
     #[test]
-    fn test_exec_requires_command() {
-        let result = try_parse(&["exec", "my-session"]);
-        assert!(result.is_err());
+    fn test_tail_output_lines_and_follow() {
+        let cli = parse(&["tail-output", "my-session", "-n", "100", "--follow"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::TailOutput {
+                lines: 100,
+                follow: true,
+                ..
+            })
+        ));
     }
 
-    // -- path subcommand --
+    // -- logs subcommand --
 
     #[test]
-    fn test_path_subcommand_parses() {
-        let cli = parse(&["path", "my-session"]);
+    fn test_logs_parses() {
+        let cli = parse(&["logs", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Logs { ref name, follow: false }) if name == "my-session"
+        ));
+        let cli = parse(&["logs", "my-session", "-f"]);
         assert!(matches!(
             cli.command,
-            Some(Commands::Path { ref name }) if name == "my-session"
+            Some(Commands::Logs { follow: true, .. })
         ));
     }
 
+    // -- wait subcommand --
+
     #[test]
-    fn test_path_requires_name() {
-        let result = try_parse(&["path"]);
-        assert!(result.is_err());
+    fn test_wait_parses() {
+        let cli = parse(&["wait", "my-session"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Wait { ref name }) if name == "my-session"
+        ));
+        assert!(try_parse(&["wait"]).is_err());
     }
 
-    // -- cd subcommand --
+    // -- log-rotate subcommand --
 
     #[test]
-    fn test_cd_subcommand_parses() {
-        let cli = parse(&["cd", "my-session"]);
+    fn test_log_rotate_parses() {
+        let cli = parse(&["log-rotate", "my-session"]);
         assert!(matches!(
             cli.command,
-            Some(Commands::Cd { ref name }) if name == "my-session"
+            Some(Commands::LogRotate { ref name }) if name == "my-session"
         ));
     }
 
+    // -- edit subcommand --
+
     #[test]
-    fn test_cd_requires_name() {
-        let result = try_parse(&["cd"]);
-        assert!(result.is_err());
+    fn test_edit_parses_command_and_env() {
+        let cli = parse(&[
+            "edit",
+            "my-session",
+            "--env",
+            "A=1",
+            "--unset-env",
+            "B",
+            "--",
+            "zsh",
+            "-l",
+        ]);
+        match cli.command {
+            Some(Commands::Edit(args)) => {
+                assert_eq!(args.name, "my-session");
+                assert_eq!(args.env, vec!["A=1"]);
+                assert_eq!(args.unset_env, vec!["B"]);
+                assert_eq!(args.cmd, vec!["zsh", "-l"]);
+                assert!(args.image.is_none());
+                assert!(!args.recreate);
+            }
+            _ => panic!("expected Edit"),
+        }
+        match parse(&["edit", "my-session", "--env", "A=1", "--recreate"]).command {
+            Some(Commands::Edit(args)) => assert!(args.recreate),
+            _ => panic!("expected Edit"),
+        }
+    }
+
+    #[test]
+    fn test_edit_env() {
+        let mut env = vec!["A=1".to_string(), "B=2".to_string(), "C=3".to_string()];
+        edit_env(
+            &mut env,
+            &["A=10".to_string(), "D=4".to_string()],
+            &["B".to_string()],
+        )
+        .unwrap();
+        assert_eq!(env, vec!["C=3", "A=10", "D=4"]);
+        assert!(edit_env(&mut env, &["=x".to_string()], &[]).is_err());
+        assert!(edit_env(&mut env, &["NOVALUE".to_string()], &[]).is_err());
+    }
+
+    // -- top subcommand --
+
+    #[test]
+    fn test_top_rows() {
+        let summary = |name: &str, local: bool, running: bool| session::SessionSummary {
+            name: name.to_string(),
+            project_dir: "/p".to_string(),
+            running,
+            local,
+            strategy: "clone".to_string(),
+            ..Default::default()
+        };
+        let sessions = vec![summary("a/zsh", true, true), summary("b/zsh", false, true)];
+        let stats = std::collections::HashMap::from([(
+            "b/zsh".to_string(),
+            (12.345, "10MiB / 1GiB".to_string()),
+        )]);
+        assert_eq!(
+            top_rows(&sessions, &stats),
+            vec![
+                vec!["a/zsh", "local", "running", "-", "-"],
+                vec!["b/zsh", "docker", "running", "12.3%", "10MiB / 1GiB"],
+            ]
+        );
+        assert!(matches!(parse(&["top"]).command, Some(Commands::Top)));
+    }
+
+    // -- doctor subcommand --
+
+    #[test]
+    fn test_doctor_subcommand_parses() {
+        let cli = parse(&["doctor"]);
+        assert!(matches!(cli.command, Some(Commands::Doctor)));
+        assert!(try_parse(&["doctor", "extra"]).is_err());
     }
 
     // -- upgrade subcommand --
@@ -1606,6 +6982,22 @@ mod tests {
         assert!(matches!(cli.command, Some(Commands::Upgrade)));
     }
 
+    #[test]
+    fn test_parse_sha256_file() {
+        let digest = "a".repeat(64);
+        assert_eq!(
+            parse_sha256_file(&format!("{}  box-x86_64-linux\n", digest)),
+            Some(digest.clone())
+        );
+        assert_eq!(
+            parse_sha256_file(&digest.to_uppercase()),
+            Some(digest.clone())
+        );
+        assert_eq!(parse_sha256_file(""), None);
+        assert_eq!(parse_sha256_file("deadbeef  box"), None);
+        assert_eq!(parse_sha256_file(&"z".repeat(64)), None);
+    }
+
     #[test]
     fn test_upgrade_rejects_flags() {
         let result = try_parse(&["upgrade", "-d"]);
@@ -1614,6 +7006,13 @@ mod tests {
 
     // -- config subcommand --
 
+    #[test]
+    fn test_help_documents_detach_exit_code() {
+        use clap::CommandFactory;
+        let help = Cli::command().render_long_help().to_string();
+        assert!(help.contains(&format!("or {} when you detach", mux::DETACH_EXIT_CODE)));
+    }
+
     #[test]
     fn test_config_zsh_subcommand_parses() {
         let cli = parse(&["config", "zsh"]);
@@ -1636,6 +7035,17 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_config_fish_subcommand_parses() {
+        let cli = parse(&["config", "fish"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config {
+                shell: ConfigShell::Fish
+            })
+        ));
+    }
+
     #[test]
     fn test_config_requires_shell() {
         let result = try_parse(&["config"]);
@@ -1731,6 +7141,272 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_list_since_and_before() {
+        let cli = parse(&["list", "--since", "2d", "--before", "1h", "-r"]);
+        match cli.command {
+            Some(Commands::List(args)) => {
+                assert_eq!(args.since, Some(std::time::Duration::from_secs(2 * 86400)));
+                assert_eq!(args.before, Some(std::time::Duration::from_secs(3600)));
+                assert!(args.running);
+            }
+            other => panic!("expected List, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_list_since_rejects_invalid_duration() {
+        assert!(try_parse(&["list", "--since", "yesterday"]).is_err());
+    }
+
+    #[test]
+    fn test_list_json() {
+        let cli = parse(&["list", "--json", "-q", "-r"]);
+        match cli.command {
+            Some(Commands::List(args)) => {
+                assert!(args.json);
+                assert!(args.quiet);
+                assert!(args.running);
+            }
+            other => panic!("expected List, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_shorten_project_path() {
+        let path = "/Users/yusuke/projects/my-app";
+        assert_eq!(shorten_project_path(path, "", 1), "/U/y/p/my-app");
+        assert_eq!(shorten_project_path(path, "", 2), "/U/y/projects/my-app");
+        assert_eq!(shorten_project_path(path, "", 0), "/U/y/p/my-app");
+        assert_eq!(shorten_project_path(path, "", 9), path);
+        assert_eq!(shorten_project_path(path, "/Users/yusuke", 1), "~/p/my-app");
+        assert_eq!(
+            shorten_project_path(path, "/Users/yusuke", 2),
+            "~/projects/my-app"
+        );
+    }
+
+    #[test]
+    fn test_list_tree_lines() {
+        let summary = |name: &str, local: bool, running: bool| session::SessionSummary {
+            name: name.to_string(),
+            project_dir: "/home/u/src/app".to_string(),
+            image: "alpine:latest".to_string(),
+            command: "bash".to_string(),
+            running,
+            local,
+            strategy: "clone".to_string(),
+            ..Default::default()
+        };
+        let sessions = [
+            summary("app/default", false, true),
+            summary("web/default", true, false),
+            summary("app/tests", true, false),
+        ];
+        assert_eq!(
+            list_tree_lines(&sessions, "/home/u", 1),
+            [
+                "app  \x1b[2m~/s/app\x1b[0m",
+                "  default  docker  running  bash  alpine:latest",
+                "  tests    local   stopped  bash",
+                "web  \x1b[2m~/s/app\x1b[0m",
+                "  default  local   stopped  bash",
+            ]
+        );
+        assert!(try_parse(&["list", "--tree", "--json"]).is_err());
+        match parse(&["list", "--mine"]).command {
+            Some(Commands::List(args)) => assert!(args.mine),
+            other => panic!("expected List, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_list_output_file_args() {
+        match parse(&["list", "--output-file", "s.json", "--append"]).command {
+            Some(Commands::List(args)) => {
+                assert_eq!(args.output_file, Some(std::path::PathBuf::from("s.json")));
+                assert!(args.append);
+            }
+            other => panic!("expected List, got {:?}", other),
+        }
+        assert!(try_parse(&["list", "--append"]).is_err());
+    }
+
+    #[test]
+    fn test_write_list_snapshot() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("sessions.json");
+        let sessions = vec![session::SessionSummary {
+            name: "app/default".to_string(),
+            running: true,
+            ..Default::default()
+        }];
+        let now = chrono::DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        write_list_snapshot(&path, &sessions, now, false).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written[0]["name"], "app/default");
+        assert_eq!(written[0]["running"], true);
+        assert_eq!(written[0]["snapshot_time"], "2024-05-01T12:00:00Z");
+
+        // Overwriting replaces the file; appending adds one array per line
+        write_list_snapshot(&path, &sessions, now, false).unwrap();
+        write_list_snapshot(&path, &[], now, true).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.ends_with("\n[]\n"));
+        assert_eq!(contents.matches("snapshot_time").count(), 1);
+    }
+
+    #[test]
+    fn test_complete_session_lines() {
+        let summary = |name: &str, project_dir: &str| session::SessionSummary {
+            name: name.to_string(),
+            project_dir: project_dir.to_string(),
+            image: "alpine:latest".to_string(),
+            command: "bash".to_string(),
+            local: true,
+            strategy: "clone".to_string(),
+            ..Default::default()
+        };
+        let sessions = [
+            summary("app/default", "/home/u/src/app"),
+            summary("web/default", "/home/user2/web"),
+        ];
+        assert_eq!(
+            complete_session_lines(&sessions, "/home/u", false),
+            ["app/default", "web/default"]
+        );
+        assert_eq!(
+            complete_session_lines(&sessions, "/home/u", true),
+            ["app/default\t~/src/app", "web/default\t/home/user2/web"]
+        );
+        match parse(&["__complete", "sessions", "--describe"]).command {
+            Some(Commands::Complete {
+                target: CompleteTarget::Sessions { describe },
+            }) => assert!(describe),
+            other => panic!("expected Complete, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_session_summary_json_fields() {
+        let summary = session::SessionSummary {
+            name: "app/default".to_string(),
+            project_dir: "/tmp/app".to_string(),
+            image: "alpine:latest".to_string(),
+            command: "bash".to_string(),
+            created_at: "2024-01-01 09:00:00 JST".to_string(),
+            created: chrono::DateTime::from_timestamp(1_704_067_200, 0),
+            running: true,
+            strategy: "clone".to_string(),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&summary).unwrap();
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "command",
+                "created_at",
+                "created_by",
+                "created_host",
+                "docker_args",
+                "image",
+                "local",
+                "name",
+                "project_dir",
+                "resumed_at",
+                "running",
+                "strategy"
+            ]
+        );
+        assert_eq!(value["running"], true);
+        assert_eq!(value["name"], "app/default");
+        assert_eq!(value["created_at"], "2024-01-01T00:00:00+00:00");
+        assert!(value["resumed_at"].is_null());
+    }
+
+    #[test]
+    fn test_list_format_width() {
+        let cli = parse(&["list", "--format-width", "80"]);
+        match cli.command {
+            Some(Commands::List(args)) => assert_eq!(args.format_width, Some(80)),
+            other => panic!("expected List, got {:?}", other),
+        }
+        assert!(try_parse(&["list", "--format-width", "wide"]).is_err());
+    }
+
+    #[test]
+    fn test_list_sort() {
+        match parse(&["list"]).command {
+            Some(Commands::List(args)) => assert_eq!(args.sort, "name"),
+            other => panic!("expected List, got {:?}", other),
+        }
+        match parse(&["list", "--sort", "resumed"]).command {
+            Some(Commands::List(args)) => assert_eq!(args.sort, "resumed"),
+            other => panic!("expected List, got {:?}", other),
+        }
+        assert!(try_parse(&["list", "--sort", "size"]).is_err());
+    }
+
+    #[test]
+    fn test_truncate_to() {
+        assert_eq!(truncate_to("short", 10), "short");
+        assert_eq!(truncate_to("exactly", 7), "exactly");
+        assert_eq!(truncate_to("~/projects/app", 8), "~/proje…");
+        assert_eq!(truncate_to("abc", 0), "");
+        assert_eq!(truncate_to("abc", 1), "…");
+    }
+
+    #[test]
+    fn test_truncate_to_multibyte() {
+        assert_eq!(truncate_to("café-app", 5), "café…");
+    }
+
+    #[test]
+    fn test_truncate_to_wide_chars() {
+        // Each CJK character takes two columns
+        assert_eq!(truncate_to("プロジェクト", 12), "プロジェクト");
+        assert_eq!(truncate_to("プロジェクト", 5), "プロ…");
+        assert_eq!(truncate_to("プロジェクト", 4), "プ…");
+        assert_eq!(truncate_to("プロジェクト", 2), "…");
+    }
+
+    #[test]
+    fn test_pad_to() {
+        assert_eq!(pad_to("abc", 5), "abc  ");
+        assert_eq!(pad_to("プロ", 6), "プロ  ");
+        assert_eq!(pad_to("toolong", 3), "toolong");
+    }
+
+    #[test]
+    fn test_fit_columns_no_shrink_when_fits() {
+        assert_eq!(fit_columns(&[(20, 7), (10, 3)], 40), vec![20, 10]);
+    }
+
+    #[test]
+    fn test_fit_columns_proportional() {
+        assert_eq!(
+            fit_columns(&[(40, 7), (20, 3), (20, 5)], 40),
+            vec![20, 10, 10]
+        );
+    }
+
+    #[test]
+    fn test_fit_columns_respects_minimum() {
+        assert_eq!(fit_columns(&[(40, 7), (4, 3)], 10), vec![9, 3]);
+        assert_eq!(fit_columns(&[(40, 7), (2, 3)], 0), vec![7, 2]);
+    }
+
     #[test]
     fn test_list_rejects_positional_args() {
         let result = try_parse(&["list", "my-session"]);
@@ -10,6 +10,7 @@ use super::terminal::{
     self, extract_selection_text, scrollback_line_count, write_osc52_clipboard, DrawFrameParams,
     InputAction, InputState, ScrollState,
 };
+use crate::config::CommandKeys;
 use crate::{docker, session};
 
 pub enum ClientResult {
@@ -90,7 +91,7 @@ fn delete_session(name: &str) {
     let ws = session::workspace_name(name);
     let remaining = session::workspace_sessions(ws).unwrap_or_default();
     if remaining.is_empty() {
-        docker::remove_workspace(ws, &sess.strategy);
+        docker::remove_workspace(ws, &sess.strategy, &sess.project_dir);
         let _ = session::remove_workspace_dir(ws);
     }
 }
@@ -171,6 +172,16 @@ fn build_sidebar_entries(current_session: &str) -> (Vec<SidebarEntry>, usize) {
     (entries, selected)
 }
 
+/// Carry the sidebar over to `next` when the current session went away:
+/// refresh the list and move the highlight onto `next`, keeping focus.
+fn sidebar_for_switch(mut sidebar: SidebarState, next: &str) -> SidebarState {
+    let (entries, selected) = build_sidebar_entries(next);
+    sidebar.entries = entries;
+    sidebar.selected = selected;
+    sidebar.new_session_input = None;
+    sidebar
+}
+
 /// Calculate sidebar width from entries.
 fn sidebar_width(entries: &[SidebarEntry]) -> u16 {
     let max_name = entries
@@ -185,12 +196,21 @@ fn sidebar_width(entries: &[SidebarEntry]) -> u16 {
     w as u16
 }
 
+/// The prefix and COMMAND mode keys the command bar's hints name.
+struct BarKeys {
+    prefix: u8,
+    command: CommandKeys,
+}
+
 /// Draw the global command bar at the bottom of the screen (like nvim's command line).
 fn draw_command_bar(
     f: &mut ratatui::Frame,
     area: Rect,
     sidebar: &SidebarState,
     command_mode: bool,
+    search: Option<&str>,
+    read_only: bool,
+    keys: &BarKeys,
 ) {
     if area.width == 0 || area.height == 0 {
         return;
@@ -212,20 +232,42 @@ fn draw_command_bar(
     // Determine content as styled spans: (text, style) pairs
     let key_style = Style::default().bg(Color::Indexed(236)).fg(Color::White);
     let input_style = Style::default().bg(Color::Indexed(236)).fg(Color::White);
+    let prefix = crate::config::key_label(keys.prefix);
+    let read_only_hint = format!(
+        " {} then {} to quit, / to search",
+        prefix,
+        crate::config::key_label(keys.command.detach)
+    );
+    let command_hint = format!(" {} to enter command mode", prefix);
+    let label = |key: u8| format!(" {}", crate::config::key_label(key));
+    let (detach, sidebar_key, new, split) = (
+        label(keys.command.detach),
+        label(keys.command.sidebar),
+        label(keys.command.new),
+        label(keys.command.split),
+    );
     let spans: Vec<(&str, Style)> = if sidebar.new_session_input.is_some() {
         // Built below from formatted string
         vec![]
+    } else if let Some(search) = search {
+        vec![(" ", bar_style), (search, input_style)]
+    } else if read_only {
+        vec![(" [read-only]", key_style), (&read_only_hint, bar_style)]
     } else if command_mode {
         vec![
-            (" Q", key_style),
-            (" Quit  ", bar_style),
-            ("A", key_style),
-            (" Focus sidebar  ", bar_style),
-            ("N", key_style),
-            (" New session", bar_style),
+            (&detach, key_style),
+            (" Quit ", bar_style),
+            (&sidebar_key, key_style),
+            (" Focus sidebar ", bar_style),
+            (&new, key_style),
+            (" New session ", bar_style),
+            (&split, key_style),
+            (" Split ", bar_style),
+            (" /", key_style),
+            (" Search", bar_style),
         ]
     } else {
-        vec![(" Ctrl+P to enter command mode", bar_style)]
+        vec![(&command_hint, bar_style)]
     };
 
     // For input mode, build the formatted string separately
@@ -730,18 +772,139 @@ fn parse_sidebar_mouse(
     None
 }
 
+/// A shell opened below the session's terminal by splitting the content
+/// area. It runs in its own PTY on the server and only this client sees it.
+struct SplitPane {
+    /// Server-side pane id; None until the server answers `PaneOpened`.
+    id: Option<u16>,
+    parser: vt100::Parser,
+    /// Input goes to this pane instead of the session's terminal.
+    focused: bool,
+}
+
+/// Row heights (top, bottom) of a horizontal split of `rows` rows, with one
+/// row between them for the divider. None when there is no room for both.
+/// Actions a `--read-only` client still performs: nothing that sends input to
+/// the session or changes it (kill, new session, split, sidebar).
+fn allowed_read_only(action: &InputAction) -> bool {
+    matches!(
+        action,
+        InputAction::Detach
+            | InputAction::Redraw
+            | InputAction::CopyToClipboard
+            | InputAction::Search(_)
+            | InputAction::SwitchPane
+    )
+}
+
+fn split_rows(rows: u16) -> Option<(u16, u16)> {
+    if rows < 3 {
+        return None;
+    }
+    let top = rows / 2;
+    Some((top, rows - 1 - top))
+}
+
+/// Which pane of a split content area a 1-indexed row falls in: Some(true)
+/// for the bottom pane, Some(false) for the top one and None for the divider
+/// (or when there is no room to split).
+fn pane_at_row(row: u16, inner_rows: u16) -> Option<bool> {
+    let (top, _) = split_rows(inner_rows)?;
+    match row {
+        r if r <= top => Some(false),
+        r if r == top + 1 => None,
+        _ => Some(true),
+    }
+}
+
+/// Height and first row (relative to the content area) of the pane that has
+/// input focus.
+fn focused_rows(split: Option<&SplitPane>, inner_rows: u16) -> (u16, u16) {
+    match (split, split_rows(inner_rows)) {
+        (Some(s), Some((top, bottom))) if s.focused => (bottom, top + 1),
+        (Some(_), Some((top, _))) => (top, 0),
+        _ => (inner_rows, 0),
+    }
+}
+
+/// The parser of the pane that has input focus.
+fn focused_parser<'a>(
+    parser: &'a mut vt100::Parser,
+    split: &'a mut Option<SplitPane>,
+) -> &'a mut vt100::Parser {
+    match split {
+        Some(s) if s.focused => &mut s.parser,
+        _ => parser,
+    }
+}
+
+/// Send input bytes to the pane that has input focus. Input for a split pane
+/// the server hasn't confirmed yet is dropped.
+fn forward_input(sock_writer: &mut UnixStream, split: Option<&SplitPane>, data: Vec<u8>) {
+    let msg = match split {
+        Some(s) if s.focused => match s.id {
+            Some(pane) => ClientMsg::PaneInput { pane, data },
+            None => return,
+        },
+        _ => ClientMsg::Input(data),
+    };
+    let _ = protocol::write_client_msg(sock_writer, &msg);
+}
+
+/// Close the split pane and give the session's terminal the whole content
+/// area again.
+fn close_split(
+    sock_writer: &mut UnixStream,
+    parser: &mut vt100::Parser,
+    split: &mut Option<SplitPane>,
+    cols: u16,
+    rows: u16,
+) {
+    let Some(pane) = split.take() else {
+        return;
+    };
+    if let Some(id) = pane.id {
+        let _ = protocol::write_client_msg(sock_writer, &ClientMsg::ClosePane(id));
+    }
+    let _ = protocol::write_client_msg(sock_writer, &ClientMsg::Resize { cols, rows });
+    parser.set_size(rows, cols);
+}
+
+/// Draw the row between the panes of a split, with an arrow pointing at the
+/// pane that has input focus.
+fn draw_split_divider(f: &mut ratatui::Frame, area: Rect, bottom_focused: bool) {
+    let buf = f.buffer_mut();
+    let style = Style::default().fg(Color::Indexed(240));
+    let marker = if bottom_focused { "▼" } else { "▲" };
+    for x in area.x..area.x + area.width {
+        if x < buf.area().width && area.y < buf.area().height {
+            let cell = &mut buf[(x, area.y)];
+            cell.set_symbol(if x == area.x + 1 { marker } else { "─" });
+            cell.set_style(style);
+        }
+    }
+}
+
 pub fn run(
     session_name: &str,
     socket_path: &Path,
     tty_fd: i32,
     initial_sidebar: Option<SidebarState>,
+    attach_input: Option<Vec<u8>>,
+    read_only: bool,
 ) -> Result<ClientResult> {
-    let (term_cols, term_rows) = terminal::get_term_size(tty_fd)?;
-
-    let inner_rows = term_rows.saturating_sub(1); // reserve 1 row for command bar
-    if inner_rows == 0 || term_cols == 0 {
-        anyhow::bail!("Terminal too small");
-    }
+    // Without the header, the command bar only overlays the last row on demand
+    let bar_rows = u16::from(crate::config::load_mux_header());
+    let (term_cols, term_rows) = terminal::wait_for_usable_size(
+        tty_fd,
+        bar_rows,
+        &mut InputState::new(
+            crate::config::load_mux_prefix_key(),
+            crate::config::load_mux_command_keys(),
+            crate::config::load_mux_scroll_settings(),
+        ),
+    )?;
+    let inner_rows = term_rows - bar_rows;
 
     // Build sidebar early so we know its width for the initial resize.
     let mut sidebar: SidebarState = initial_sidebar.unwrap_or_else(|| {
@@ -764,14 +927,17 @@ pub fn run(
     // indefinitely if the server is slow to read.
     let _ = sock_writer.set_write_timeout(Some(Duration::from_secs(5)));
 
-    // Send initial Resize to server (subtract sidebar width)
-    protocol::write_client_msg(
-        &mut sock_writer,
-        &ClientMsg::Resize {
+    // Send initial Resize to server (subtract sidebar width). A read-only
+    // client observes instead, so it never changes the PTY size.
+    let hello = if read_only {
+        ClientMsg::Observe
+    } else {
+        ClientMsg::Resize {
             cols: content_cols,
             rows: inner_rows,
-        },
-    )?;
+        }
+    };
+    protocol::write_client_msg(&mut sock_writer, &hello)?;
 
     // Wait for Resized from server to know the PTY dimensions.
     // Use a timeout so we don't block forever in raw mode if the server hangs.
@@ -789,7 +955,7 @@ pub fn run(
     };
 
     // Create local parser with server's PTY dimensions
-    let mut parser = vt100::Parser::new(pty_rows, pty_cols, super::SCROLLBACK_LINES);
+    let mut parser = vt100::Parser::new(pty_rows, pty_cols, crate::config::load_mux_scrollback());
 
     // Process the screen dump that follows
     match protocol::read_server_msg(&mut sock_reader) {
@@ -804,12 +970,26 @@ pub fn run(
     // Clear timeout for normal operation (reader thread handles its own blocking)
     sock_reader.set_read_timeout(None)?;
 
+    if let Some(data) = attach_input {
+        protocol::write_client_msg(&mut sock_writer, &ClientMsg::Input(data))?;
+    }
+
     // Create ratatui terminal.  Both internal buffers start empty, so the
     // first draw() will output every cell as a full diff — no clear() needed.
     let mut terminal = terminal::create_terminal(tty_fd, term_cols, term_rows)?;
 
     let prefix_key = crate::config::load_mux_prefix_key();
-    let mut input_state = InputState::new(prefix_key);
+    let bar_keys = BarKeys {
+        prefix: prefix_key,
+        command: crate::config::load_mux_command_keys(),
+    };
+    let mut input_state = InputState::new(
+        prefix_key,
+        bar_keys.command,
+        crate::config::load_mux_scroll_settings(),
+    );
+    let show_scroll_status = crate::config::load_mux_show_scroll_status();
+    let bracketed_paste = crate::config::load_mux_bracketed_paste();
 
     // Draw the first frame immediately so the user sees content right
     // after a session switch instead of a blank screen.
@@ -872,6 +1052,7 @@ pub fn run(
     let mut last_cols = term_cols;
     let mut last_rows = term_rows;
     let mut current_inner_rows = inner_rows;
+    let mut split: Option<SplitPane> = None;
 
     loop {
         let timeout = if dirty {
@@ -899,10 +1080,49 @@ pub fn run(
                 ServerMsg::Exited(code) => {
                     if let Some(next) = find_any_running_session(session_name) {
                         unsafe { libc::close(tty_input_fd) };
-                        return Ok(ClientResult::SwitchSession(next, None));
+                        let sidebar = sidebar_for_switch(sidebar, &next);
+                        return Ok(ClientResult::SwitchSession(next, Some(sidebar)));
                     }
                     return Ok(ClientResult::Exit(code));
                 }
+                ServerMsg::PaneOpened(id) => match split {
+                    Some(ref mut s) if s.id.is_none() => s.id = Some(id),
+                    // The split was closed before the server answered
+                    _ => {
+                        let _ =
+                            protocol::write_client_msg(&mut sock_writer, &ClientMsg::ClosePane(id));
+                    }
+                },
+                ServerMsg::PaneOutput { pane, data } => {
+                    if let Some(ref mut s) = split {
+                        if s.id == Some(pane) {
+                            s.parser.process(&data);
+                            dirty = true;
+                        }
+                    }
+                }
+                ServerMsg::PaneClosed(pane) => {
+                    // Pane 0: the server could not open the pane we asked for
+                    let ours = split
+                        .as_ref()
+                        .is_some_and(|s| s.id == Some(pane) || (pane == 0 && s.id.is_none()));
+                    if ours {
+                        if split.as_ref().is_some_and(|s| s.focused) {
+                            input_state.scroll_offset = 0;
+                            input_state.selection = None;
+                        }
+                        let content_cols =
+                            last_cols.saturating_sub(sidebar_width(&sidebar.entries));
+                        close_split(
+                            &mut sock_writer,
+                            &mut parser,
+                            &mut split,
+                            content_cols,
+                            current_inner_rows,
+                        );
+                        dirty = true;
+                    }
+                }
             },
             Ok(ClientEvent::InputBytes(data)) => {
                 // Sidebar always handles mouse events in its area
@@ -929,7 +1149,11 @@ pub fn run(
                                 match find_any_running_session(&name) {
                                     Some(next_session) => {
                                         unsafe { libc::close(tty_input_fd) };
-                                        return Ok(ClientResult::SwitchSession(next_session, None));
+                                        let sidebar = sidebar_for_switch(sidebar, &next_session);
+                                        return Ok(ClientResult::SwitchSession(
+                                            next_session,
+                                            Some(sidebar),
+                                        ));
                                     }
                                     None => {
                                         unsafe { libc::close(tty_input_fd) };
@@ -983,6 +1207,10 @@ pub fn run(
                         in_sidebar
                     };
 
+                // Sidebar clicks can switch, create or delete sessions
+                if is_sidebar_mouse && read_only {
+                    continue;
+                }
                 if is_sidebar_mouse {
                     match process_sidebar_input(&data, &mut sidebar, session_name, sb_width) {
                         SidebarAction::Switch { name: next } => {
@@ -999,7 +1227,11 @@ pub fn run(
                                 match find_any_running_session(&name) {
                                     Some(next_session) => {
                                         unsafe { libc::close(tty_input_fd) };
-                                        return Ok(ClientResult::SwitchSession(next_session, None));
+                                        let sidebar = sidebar_for_switch(sidebar, &next_session);
+                                        return Ok(ClientResult::SwitchSession(
+                                            next_session,
+                                            Some(sidebar),
+                                        ));
                                     }
                                     None => {
                                         unsafe { libc::close(tty_input_fd) };
@@ -1020,22 +1252,39 @@ pub fn run(
                     continue;
                 }
 
-                let max_scrollback = scrollback_line_count(&mut parser);
+                // A click or wheel in the other pane of a split focuses it
+                // first, so it acts on the pane under the pointer
+                if let (Some(s), Some(row)) = (split.as_mut(), terminal::mouse_press_row(&data)) {
+                    match pane_at_row(row, current_inner_rows) {
+                        Some(bottom) if bottom != s.focused => {
+                            s.focused = bottom;
+                            input_state.reset_view();
+                            dirty = true;
+                        }
+                        Some(_) => {}
+                        None => continue,
+                    }
+                }
+
+                let max_scrollback = scrollback_line_count(focused_parser(&mut parser, &mut split));
                 let content_cols = last_cols.saturating_sub(sb_width);
-                let actions = input_state.process(
-                    &data,
-                    current_inner_rows,
-                    content_cols,
-                    max_scrollback,
-                    sb_width,
-                );
+                let (pane_rows, row_offset) = focused_rows(split.as_ref(), current_inner_rows);
+                input_state.row_offset = row_offset;
+                let mut actions =
+                    input_state.process(&data, pane_rows, content_cols, max_scrollback, sb_width);
+                if read_only {
+                    actions.retain(allowed_read_only);
+                }
                 for action in actions {
                     match action {
                         InputAction::Forward(bytes) => {
-                            let _ = protocol::write_client_msg(
-                                &mut sock_writer,
-                                &ClientMsg::Input(bytes),
-                            );
+                            let bytes = if bracketed_paste {
+                                let active = focused_parser(&mut parser, &mut split);
+                                terminal::bracket_paste(active.screen(), bytes)
+                            } else {
+                                bytes
+                            };
+                            forward_input(&mut sock_writer, split.as_ref(), bytes);
                         }
                         InputAction::Detach => {
                             return Ok(ClientResult::Quit);
@@ -1065,14 +1314,68 @@ pub fn run(
                         }
                         InputAction::CopyToClipboard => {
                             if let Some(ref sel) = input_state.selection {
-                                parser.set_scrollback(input_state.scroll_offset);
-                                let text = extract_selection_text(parser.screen(), sel);
-                                parser.set_scrollback(0);
+                                let active = focused_parser(&mut parser, &mut split);
+                                active.set_scrollback(input_state.scroll_offset);
+                                let text = extract_selection_text(active.screen(), sel);
+                                active.set_scrollback(0);
                                 if !text.is_empty() {
                                     write_osc52_clipboard(tty_fd, &text);
                                 }
                             }
                         }
+                        InputAction::Search(older) => {
+                            let lines =
+                                super::parser_lines(focused_parser(&mut parser, &mut split));
+                            input_state.search_step(&lines, max_scrollback, pane_rows, older);
+                            dirty = true;
+                        }
+                        InputAction::ToggleSplit => {
+                            input_state.scroll_offset = 0;
+                            input_state.selection = None;
+                            input_state.drag_start = None;
+                            if split.is_some() {
+                                close_split(
+                                    &mut sock_writer,
+                                    &mut parser,
+                                    &mut split,
+                                    content_cols,
+                                    current_inner_rows,
+                                );
+                            } else if let Some((top, bottom)) = split_rows(current_inner_rows) {
+                                let _ = protocol::write_client_msg(
+                                    &mut sock_writer,
+                                    &ClientMsg::OpenPane {
+                                        cols: content_cols,
+                                        rows: bottom,
+                                    },
+                                );
+                                let _ = protocol::write_client_msg(
+                                    &mut sock_writer,
+                                    &ClientMsg::Resize {
+                                        cols: content_cols,
+                                        rows: top,
+                                    },
+                                );
+                                parser.set_size(top, content_cols);
+                                split = Some(SplitPane {
+                                    id: None,
+                                    parser: vt100::Parser::new(
+                                        bottom,
+                                        content_cols,
+                                        crate::config::load_mux_scrollback(),
+                                    ),
+                                    focused: true,
+                                });
+                            }
+                            dirty = true;
+                        }
+                        InputAction::SwitchPane => {
+                            if let Some(ref mut s) = split {
+                                s.focused = !s.focused;
+                            }
+                            input_state.reset_view();
+                            dirty = true;
+                        }
                     }
                 }
             }
@@ -1082,22 +1385,17 @@ pub fn run(
             Err(mpsc::RecvTimeoutError::Timeout) => {
                 // Flush any buffered incomplete escape sequence
                 if sidebar.new_session_input.is_none() {
-                    let max_scrollback = scrollback_line_count(&mut parser);
+                    let max_scrollback =
+                        scrollback_line_count(focused_parser(&mut parser, &mut split));
                     let sb_w = sidebar_width(&sidebar.entries);
                     let content_cols = last_cols.saturating_sub(sb_w);
-                    let pending_actions = input_state.flush_pending(
-                        current_inner_rows,
-                        content_cols,
-                        max_scrollback,
-                        sb_w,
-                    );
+                    let (pane_rows, _) = focused_rows(split.as_ref(), current_inner_rows);
+                    let pending_actions =
+                        input_state.flush_pending(pane_rows, content_cols, max_scrollback, sb_w);
                     for action in pending_actions {
                         match action {
-                            InputAction::Forward(bytes) => {
-                                let _ = protocol::write_client_msg(
-                                    &mut sock_writer,
-                                    &ClientMsg::Input(bytes),
-                                );
+                            InputAction::Forward(bytes) if !read_only => {
+                                forward_input(&mut sock_writer, split.as_ref(), bytes);
                             }
                             InputAction::Redraw => {
                                 dirty = true;
@@ -1130,21 +1428,54 @@ pub fn run(
                         let cols_changed = cols != last_cols;
                         last_cols = cols;
                         last_rows = rows;
-                        let new_inner = rows.saturating_sub(1); // reserve 1 row for command bar
+                        let new_inner = rows.saturating_sub(bar_rows);
                         let sb_w = sidebar_width(&sidebar.entries);
                         let content_cols = cols.saturating_sub(sb_w);
                         if new_inner > 0 && content_cols > 0 {
                             current_inner_rows = new_inner;
-                            let _ = protocol::write_client_msg(
-                                &mut sock_writer,
-                                &ClientMsg::Resize {
-                                    cols: content_cols,
-                                    rows: new_inner,
-                                },
-                            );
-                            parser.set_size(new_inner, content_cols);
-                            if cols_changed {
-                                parser.process(b"\x1b[H\x1b[2J");
+                            if split_rows(new_inner).is_none() {
+                                close_split(
+                                    &mut sock_writer,
+                                    &mut parser,
+                                    &mut split,
+                                    content_cols,
+                                    new_inner,
+                                );
+                            }
+                            let main_rows = match (split.as_mut(), split_rows(new_inner)) {
+                                (Some(s), Some((top, bottom))) => {
+                                    if let Some(pane) = s.id {
+                                        let _ = protocol::write_client_msg(
+                                            &mut sock_writer,
+                                            &ClientMsg::PaneResize {
+                                                pane,
+                                                cols: content_cols,
+                                                rows: bottom,
+                                            },
+                                        );
+                                    }
+                                    s.parser.set_size(bottom, content_cols);
+                                    if cols_changed {
+                                        s.parser.process(b"\x1b[H\x1b[2J");
+                                    }
+                                    top
+                                }
+                                _ => new_inner,
+                            };
+                            // A read-only client keeps showing the PTY at the
+                            // size the other clients give it
+                            if !read_only {
+                                let _ = protocol::write_client_msg(
+                                    &mut sock_writer,
+                                    &ClientMsg::Resize {
+                                        cols: content_cols,
+                                        rows: main_rows,
+                                    },
+                                );
+                                parser.set_size(main_rows, content_cols);
+                                if cols_changed {
+                                    parser.process(b"\x1b[H\x1b[2J");
+                                }
                             }
                             terminal = terminal::create_terminal(tty_fd, cols, rows)?;
                             terminal.clear()?;
@@ -1164,17 +1495,58 @@ pub fn run(
                         terminal::set_mouse_tracking(tty_fd, true);
                     }
 
-                    parser.set_scrollback(input_state.scroll_offset);
-                    let screen = parser.screen();
+                    // Scroll, selection and search belong to the focused pane
+                    let pane_focused = split.as_ref().is_some_and(|s| s.focused);
+                    let (pane_rows, _) = focused_rows(split.as_ref(), current_inner_rows);
+                    let pane_max = split
+                        .as_mut()
+                        .map_or(0, |s| scrollback_line_count(&mut s.parser));
+                    let focused_max = if pane_focused {
+                        pane_max
+                    } else {
+                        max_scrollback
+                    };
+                    let search_selection = input_state.search_selection(focused_max, pane_rows);
+                    let selection = input_state.selection.as_ref().or(search_selection.as_ref());
+                    let search_prompt = input_state.search_prompt();
+
+                    let main_offset = if pane_focused {
+                        0
+                    } else {
+                        input_state.scroll_offset
+                    };
+                    parser.set_scrollback(main_offset);
                     let scroll = ScrollState {
-                        offset: input_state.scroll_offset,
+                        offset: main_offset,
                         max: max_scrollback,
                     };
                     let params = DrawFrameParams {
-                        screen,
+                        screen: parser.screen(),
                         scroll: &scroll,
-                        selection: input_state.selection.as_ref(),
+                        selection: if pane_focused { None } else { selection },
+                        prompt: None,
+                        show_scroll_status,
+                        show_cursor: !pane_focused,
                     };
+                    let pane_scroll = ScrollState {
+                        offset: if pane_focused {
+                            input_state.scroll_offset
+                        } else {
+                            0
+                        },
+                        max: pane_max,
+                    };
+                    let pane_params = split.as_mut().map(|s| {
+                        s.parser.set_scrollback(pane_scroll.offset);
+                        DrawFrameParams {
+                            screen: s.parser.screen(),
+                            scroll: &pane_scroll,
+                            selection: if pane_focused { selection } else { None },
+                            prompt: None,
+                            show_scroll_status,
+                            show_cursor: pane_focused,
+                        }
+                    });
                     // Write BSU/ESU through the same BufWriter as the
                     // frame data so the terminal emulator receives them
                     // as one contiguous byte stream (avoids the render
@@ -1188,7 +1560,7 @@ pub fn run(
                     terminal
                         .draw(|f| {
                             let full = f.area();
-                            let body_height = full.height.saturating_sub(1);
+                            let body_height = full.height.saturating_sub(bar_rows);
                             let sb_width = sb_w.min(full.width);
                             let right_width = full.width.saturating_sub(sb_width);
                             let sb_area = Rect {
@@ -1205,13 +1577,48 @@ pub fn run(
                             };
                             let bar_area = Rect {
                                 x: full.x,
-                                y: full.y + body_height,
+                                y: full.y + full.height.saturating_sub(1),
                                 width: full.width,
                                 height: 1,
                             };
+                            let show_bar = bar_rows > 0
+                                || input_state.command_mode
+                                || search_prompt.is_some()
+                                || sidebar.new_session_input.is_some();
                             draw_sidebar(f, &sidebar, sb_area);
-                            terminal::draw_frame(f, &params, right_area);
-                            draw_command_bar(f, bar_area, &sidebar, input_state.command_mode);
+                            match (&pane_params, split_rows(right_area.height)) {
+                                (Some(pane_params), Some((top, bottom))) => {
+                                    let top_area = Rect {
+                                        height: top,
+                                        ..right_area
+                                    };
+                                    let divider_area = Rect {
+                                        y: right_area.y + top,
+                                        height: 1,
+                                        ..right_area
+                                    };
+                                    let bottom_area = Rect {
+                                        y: right_area.y + top + 1,
+                                        height: bottom,
+                                        ..right_area
+                                    };
+                                    terminal::draw_frame(f, &params, top_area);
+                                    draw_split_divider(f, divider_area, pane_focused);
+                                    terminal::draw_frame(f, pane_params, bottom_area);
+                                }
+                                _ => terminal::draw_frame(f, &params, right_area),
+                            }
+                            if show_bar {
+                                draw_command_bar(
+                                    f,
+                                    bar_area,
+                                    &sidebar,
+                                    input_state.command_mode,
+                                    search_prompt.as_deref(),
+                                    read_only,
+                                    &bar_keys,
+                                );
+                            }
                         })
                         .context("Failed to draw terminal frame")?;
                     {
@@ -1220,6 +1627,9 @@ pub fn run(
                         let _ = std::io::Write::flush(terminal.backend_mut());
                     }
                     parser.set_scrollback(0);
+                    if let Some(ref mut s) = split {
+                        s.parser.set_scrollback(0);
+                    }
                     dirty = false;
                 }
             }
@@ -7,7 +7,7 @@ use anyhow::{Context, Result};
 use std::io::Read;
 use std::os::unix::io::{AsRawFd, FromRawFd};
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::session;
 
@@ -36,16 +36,21 @@ fn acquire_session_lock(session_name: &str) -> Result<std::fs::File> {
     Ok(lock_file)
 }
 
-/// Maximum lines of scrollback history kept per terminal.
-const SCROLLBACK_LINES: usize = 10_000;
-
 pub struct MuxConfig {
     pub session_name: String,
     pub command: Vec<String>,
     pub working_dir: Option<String>,
     pub prefix_key: u8,
+    /// Force a size change shortly after start so an attached program (e.g.
+    /// `docker attach`) pushes the current terminal size to its target.
+    pub resize_on_attach: bool,
 }
 
+/// How long after start the attach resize begins, and how long the PTY
+/// stays one row short before it is restored.
+const ATTACH_RESIZE_DELAY: Duration = Duration::from_millis(300);
+const ATTACH_RESIZE_HOLD: Duration = Duration::from_millis(100);
+
 /// Ensure a session's mux server is running. Starts one if needed.
 /// Returns the socket path once ready.
 pub fn ensure_server(session_name: &str) -> Result<std::path::PathBuf> {
@@ -74,7 +79,7 @@ pub fn ensure_server(session_name: &str) -> Result<std::path::PathBuf> {
     let _ = std::fs::remove_file(&socket_path);
 
     // Spawn server daemon
-    spawn_server(session_name)?;
+    spawn_server(session_name, None)?;
 
     // Poll for socket (up to 3s), then return once server is ready.
     // Lock is released here (dropped at end of scope) once server is ready.
@@ -82,10 +87,65 @@ pub fn ensure_server(session_name: &str) -> Result<std::path::PathBuf> {
     Ok(socket_path)
 }
 
+/// Start `command` in a local session without attaching. A stopped session's
+/// mux server is started running `command` instead of the session's own
+/// command. In a running session, `command` runs as a background process in
+/// `working_dir` (like `docker exec -d`), its output appended to the server log.
+pub fn start_detached(
+    session_name: &str,
+    command: &[String],
+    working_dir: &std::path::Path,
+) -> Result<()> {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    let socket_path = session::socket_path(session_name)?;
+    let _lock = acquire_session_lock(session_name)?;
+    if std::os::unix::net::UnixStream::connect(&socket_path).is_err() {
+        kill_stale_server(session_name);
+        let _ = std::fs::remove_file(&socket_path);
+        spawn_server(session_name, Some(command))?;
+        return wait_for_socket(session_name, &socket_path);
+    }
+
+    let sess = session::load(session_name)?;
+    let log_file = open_server_log(session_name)?;
+    let mut child = Command::new(&command[0]);
+    child.args(&command[1..]);
+    for (key, value) in sess.env.iter().filter_map(|e| e.split_once('=')) {
+        child.env(key, value);
+    }
+    child.env("BOX_SESSION", session_name);
+    if working_dir.is_dir() {
+        child.current_dir(working_dir);
+    }
+    unsafe {
+        child
+            .stdin(Stdio::null())
+            .stdout(Stdio::from(log_file.try_clone()?))
+            .stderr(Stdio::from(log_file))
+            .pre_exec(|| {
+                // Detach from the terminal so the command outlives it.
+                libc::setsid();
+                libc::signal(libc::SIGHUP, libc::SIG_IGN);
+                Ok(())
+            })
+            .spawn()
+            .with_context(|| format!("Failed to run command: {}", command.join(" ")))?;
+    }
+    Ok(())
+}
+
+/// Exit code returned when the user detaches instead of the command exiting,
+/// so callers can tell a detach apart from a clean `exit 0`.
+pub const DETACH_EXIT_CODE: i32 = 100;
+
 /// Client-server mode for local sessions.
 /// Starts server if not running, then attaches as client.
 /// Supports switching sessions via the sidebar without detaching.
-pub fn run(session_name: &str) -> Result<i32> {
+/// `attach_input` is typed into the first session once, right after attaching.
+/// With `read_only`, nothing typed is sent to any session.
+pub fn run(session_name: &str, attach_input: Option<Vec<u8>>, read_only: bool) -> Result<i32> {
     // Open /dev/tty and enter raw mode once for the entire run.
     // This avoids a visible blackout when switching sessions, since we
     // never leave the alternate screen between switches.
@@ -101,10 +161,18 @@ pub fn run(session_name: &str) -> Result<i32> {
 
     let mut current = session_name.to_string();
     let mut sidebar_state: Option<client::SidebarState> = None;
+    let mut attach_input = attach_input;
     loop {
         let socket_path = ensure_server(&current)?;
-        match client::run(&current, &socket_path, tty_fd, sidebar_state.take())? {
-            client::ClientResult::Quit => return Ok(0),
+        match client::run(
+            &current,
+            &socket_path,
+            tty_fd,
+            sidebar_state.take(),
+            attach_input.take(),
+            read_only,
+        )? {
+            client::ClientResult::Quit => return Ok(DETACH_EXIT_CODE),
             client::ClientResult::Exit(code) => {
                 // If another session in the same workspace is running, switch to it
                 let ws = session::workspace_name(&current);
@@ -227,6 +295,18 @@ fn create_sub_session(workspace: &str, command: &str) -> Result<String> {
         env: parent.env.clone(),
         local: parent.local,
         strategy: parent.strategy.clone(),
+        notify_on_exit: parent.notify_on_exit,
+        volumes_from: parent.volumes_from.clone(),
+        docker_socket_mount: parent.docker_socket_mount.clone(),
+        inherit_git_config: parent.inherit_git_config,
+        storage_driver: parent.storage_driver.clone(),
+        storage_opts: parent.storage_opts.clone(),
+        // The parent's container already holds its published host ports
+        ports: Vec::new(),
+        resources: parent.resources.clone(),
+        workdir: parent.workdir.clone(),
+        from_ref: parent.from_ref.clone(),
+        docker_args: parent.docker_args.clone(),
     };
     session::save(&sess)?;
 
@@ -247,7 +327,7 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
     };
     let tty_fd = tty.as_raw_fd();
 
-    let (term_cols, term_rows) = match terminal::get_term_size(tty_fd) {
+    let (mut term_cols, mut term_rows) = match terminal::get_term_size(tty_fd) {
         Ok(size) => size,
         Err(_) => return run_fallback(&config),
     };
@@ -260,10 +340,19 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
         }
     }
 
-    let inner_rows = term_rows;
-    if inner_rows == 0 || term_cols == 0 {
-        anyhow::bail!("Terminal too small");
+    if term_rows == 0 || term_cols == 0 {
+        let _guard = RawModeGuard::activate(&mut tty)?;
+        (term_cols, term_rows) = terminal::wait_for_usable_size(
+            tty_fd,
+            0,
+            &mut InputState::new(
+                config.prefix_key,
+                crate::config::load_mux_command_keys(),
+                crate::config::load_mux_scroll_settings(),
+            ),
+        )?;
     }
+    let inner_rows = term_rows;
 
     // Open PTY
     let pty = pty_process::blocking::Pty::new().context("Failed to open PTY")?;
@@ -273,6 +362,9 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
     // Build command
     let mut cmd = pty_process::blocking::Command::new(&config.command[0]);
     cmd.args(&config.command[1..]);
+    for (key, value) in local_session_env(&config.session_name) {
+        cmd.env(key, value);
+    }
     cmd.env("BOX_SESSION", &config.session_name);
     cmd.env_remove("__BOX_MUX_SERVER");
     if let Some(ref dir) = config.working_dir {
@@ -285,7 +377,8 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
     drop(pts);
 
     // Create vt100 parser with scrollback
-    let mut parser = vt100::Parser::new(inner_rows, term_cols, SCROLLBACK_LINES);
+    let mut parser =
+        vt100::Parser::new(inner_rows, term_cols, crate::config::load_mux_scrollback());
 
     // Install panic hook
     terminal::install_panic_hook();
@@ -348,7 +441,13 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
         }
     });
 
-    let mut input_state = InputState::new(config.prefix_key);
+    let mut input_state = InputState::new(
+        config.prefix_key,
+        crate::config::load_mux_command_keys(),
+        crate::config::load_mux_scroll_settings(),
+    );
+    let show_scroll_status = crate::config::load_mux_show_scroll_status();
+    let bracketed_paste = crate::config::load_mux_bracketed_paste();
     let mut dirty = true;
     let mut child_exited = false;
     let mut mouse_tracking_on = false;
@@ -357,6 +456,13 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
     let mut last_rows = term_rows;
     let mut current_inner_rows = inner_rows;
 
+    // `docker attach` only forwards size *changes*, so a container started
+    // detached at another size keeps it. Shrinking the PTY by a row and
+    // restoring it makes the client resize the container, and full-screen
+    // programs redraw at the right size. (deadline, currently shrunk)
+    let mut attach_resize = (config.resize_on_attach && inner_rows > 1)
+        .then(|| (Instant::now() + ATTACH_RESIZE_DELAY, false));
+
     loop {
         // When dirty, use a short timeout to coalesce rapid output bursts
         // (e.g. a TUI app's multi-chunk SIGWINCH redraw) into a single
@@ -379,6 +485,11 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
                 for action in actions {
                     match action {
                         InputAction::Forward(bytes) => {
+                            let bytes = if bracketed_paste {
+                                terminal::bracket_paste(parser.screen(), bytes)
+                            } else {
+                                bytes
+                            };
                             let _ = terminal::write_bytes_to_pty(&pty, &bytes);
                         }
                         InputAction::Detach => {
@@ -386,7 +497,7 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
                             // session alive, so kill the child to avoid orphans/zombies.
                             let _ = child.kill();
                             let _ = child.wait();
-                            return Ok(0);
+                            return Ok(DETACH_EXIT_CODE);
                         }
                         InputAction::Kill => {
                             let _ = child.kill();
@@ -396,8 +507,11 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
                         InputAction::Redraw => {
                             dirty = true;
                         }
-                        InputAction::FocusSidebar | InputAction::NewSession => {
-                            // Sidebar/new session not available in standalone mode
+                        InputAction::FocusSidebar
+                        | InputAction::NewSession
+                        | InputAction::ToggleSplit
+                        | InputAction::SwitchPane => {
+                            // Sidebar/new session/split not available in standalone mode
                         }
                         InputAction::CopyToClipboard => {
                             if let Some(ref sel) = input_state.selection {
@@ -409,6 +523,16 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
                                 }
                             }
                         }
+                        InputAction::Search(older) => {
+                            let lines = parser_lines(&mut parser);
+                            input_state.search_step(
+                                &lines,
+                                max_scrollback,
+                                current_inner_rows,
+                                older,
+                            );
+                            dirty = true;
+                        }
                     }
                 }
             }
@@ -437,12 +561,30 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
                     }
                 }
 
+                if let Some((deadline, shrunk)) = attach_resize {
+                    if Instant::now() >= deadline {
+                        if shrunk {
+                            let _ = terminal::set_pty_size(&pty, current_inner_rows, last_cols);
+                            attach_resize = None;
+                        } else {
+                            let _ = terminal::set_pty_size(
+                                &pty,
+                                current_inner_rows.saturating_sub(1).max(1),
+                                last_cols,
+                            );
+                            attach_resize = Some((Instant::now() + ATTACH_RESIZE_HOLD, true));
+                        }
+                    }
+                }
+
                 // Check for terminal resize
                 if let Ok((cols, rows)) = terminal::get_term_size(tty_fd) {
                     if cols != last_cols || rows != last_rows {
                         let cols_changed = cols != last_cols;
                         last_cols = cols;
                         last_rows = rows;
+                        // A real resize supersedes the attach resize
+                        attach_resize = None;
                         let new_inner = rows;
                         if new_inner > 0 && cols > 0 {
                             current_inner_rows = new_inner;
@@ -486,10 +628,16 @@ pub fn run_standalone(config: MuxConfig) -> Result<i32> {
                         offset: input_state.scroll_offset,
                         max: max_scrollback,
                     };
+                    let search_selection =
+                        input_state.search_selection(max_scrollback, current_inner_rows);
+                    let search_prompt = input_state.search_prompt();
                     let params = DrawFrameParams {
                         screen,
                         scroll: &scroll,
-                        selection: input_state.selection.as_ref(),
+                        selection: input_state.selection.as_ref().or(search_selection.as_ref()),
+                        prompt: search_prompt.as_deref(),
+                        show_scroll_status,
+                        show_cursor: true,
                     };
                     terminal::begin_sync_update(tty_fd);
                     term.draw(|f| {
@@ -536,27 +684,167 @@ pub fn send_kill(session_name: &str) -> Result<()> {
     Ok(())
 }
 
-// --- Private helpers ---
+/// Print the last `lines` lines of a running session's output to stdout.
+/// Attaches read-only (the PTY size is left untouched); with `follow`, keeps
+/// streaming new lines until the session exits.
+pub fn tail_output(session_name: &str, lines: usize, follow: bool) -> Result<i32> {
+    use std::io::Write;
 
-fn spawn_server(session_name: &str) -> Result<()> {
-    use std::os::unix::process::CommandExt;
-    use std::process::{Command, Stdio};
+    let socket_path = session::socket_path(session_name)?;
+    let sock = std::os::unix::net::UnixStream::connect(&socket_path)
+        .context("Failed to connect to mux server")?;
+    let mut writer = sock.try_clone().context("Failed to clone socket")?;
+    let mut reader = sock;
+    protocol::write_client_msg(&mut writer, &protocol::ClientMsg::Observe)?;
+
+    reader
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .context("Failed to set handshake read timeout")?;
+    let (cols, rows) = match protocol::read_server_msg(&mut reader) {
+        Ok(protocol::ServerMsg::Resized { cols, rows }) => (cols, rows),
+        Ok(protocol::ServerMsg::Exited(code)) => return Ok(code),
+        Ok(_) => (80, 24),
+        Err(_) => anyhow::bail!("Timed out waiting for server handshake"),
+    };
+    let mut parser = vt100::Parser::new(rows, cols, crate::config::load_mux_scrollback());
+    match protocol::read_server_msg(&mut reader) {
+        Ok(protocol::ServerMsg::Output(data)) => parser.process(&data),
+        Ok(protocol::ServerMsg::Exited(code)) => return Ok(code),
+        Ok(_) => {}
+        Err(_) => anyhow::bail!("Timed out waiting for server handshake"),
+    }
 
-    let exe = std::env::current_exe().context("Failed to get current executable path")?;
+    let mut stdout = std::io::stdout().lock();
+    let all = parser_lines(&mut parser);
+    for line in &all[all.len().saturating_sub(lines)..] {
+        writeln!(stdout, "{}", line)?;
+    }
+    stdout.flush()?;
+    if !follow {
+        return Ok(0);
+    }
 
-    // Redirect server stderr to a log file for debugging (append mode)
+    // Stream complete lines as they arrive. Each line is rendered through its
+    // own vt100 parser so escape sequences, carriage returns and backspaces
+    // resolve to the text a terminal would show.
+    reader.set_read_timeout(None)?;
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        match protocol::read_server_msg(&mut reader) {
+            Ok(protocol::ServerMsg::Output(data)) => {
+                pending.extend_from_slice(&data);
+                while let Some(pos) = pending.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=pos).collect();
+                    writeln!(stdout, "{}", render_line(&line[..pos]))?;
+                }
+                stdout.flush()?;
+            }
+            // Observers never open panes
+            Ok(protocol::ServerMsg::Resized { .. })
+            | Ok(protocol::ServerMsg::PaneOpened(_))
+            | Ok(protocol::ServerMsg::PaneOutput { .. })
+            | Ok(protocol::ServerMsg::PaneClosed(_)) => {}
+            Ok(protocol::ServerMsg::Exited(code)) => {
+                if !pending.is_empty() {
+                    writeln!(stdout, "{}", render_line(&pending))?;
+                }
+                return Ok(code);
+            }
+            Err(_) => return Ok(0),
+        }
+    }
+}
+
+/// All lines held by a parser — scrollback followed by the visible screen —
+/// with trailing blank lines removed.
+fn parser_lines(parser: &mut vt100::Parser) -> Vec<String> {
+    let scrollback = scrollback_line_count(parser);
+    let (rows, cols) = parser.screen().size();
+    let mut lines = Vec::with_capacity(scrollback + rows as usize);
+    // With scrollback offset `o`, the top row shows line `scrollback - o`.
+    for offset in (1..=scrollback).rev() {
+        parser.set_scrollback(offset);
+        lines.push(row_text(parser.screen(), 0, cols));
+    }
+    parser.set_scrollback(0);
+    for row in 0..rows {
+        lines.push(row_text(parser.screen(), row, cols));
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn row_text(screen: &vt100::Screen, row: u16, cols: u16) -> String {
+    extract_selection_text(
+        screen,
+        &terminal::Selection {
+            start_row: row,
+            start_col: 0,
+            end_row: row,
+            end_col: cols.saturating_sub(1),
+        },
+    )
+}
+
+/// Render one line of raw PTY output to plain text.
+fn render_line(bytes: &[u8]) -> String {
+    const MAX_LINE_COLS: u16 = 4096;
+    let mut parser = vt100::Parser::new(1, MAX_LINE_COLS, 0);
+    parser.process(bytes);
+    row_text(parser.screen(), 0, MAX_LINE_COLS)
+}
+
+// --- Private helpers ---
+
+/// The stored `KEY=VALUE` env of a local session, applied to every command
+/// it runs. Docker sessions get theirs from the container instead.
+fn local_session_env(session_name: &str) -> Vec<(String, String)> {
+    match session::load(session_name) {
+        Ok(sess) if sess.local => sess
+            .env
+            .iter()
+            .filter_map(|e| e.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Open a session's server log for appending, rotating it first if it has
+/// grown past the configured limit.
+fn open_server_log(session_name: &str) -> Result<std::fs::File> {
+    let _ = session::rotate_log(session_name, false);
     let log_path = session::sessions_dir()?
         .join(session_name)
         .join("server.log");
-    let log_file = std::fs::OpenOptions::new()
+    std::fs::OpenOptions::new()
         .create(true)
         .append(true)
         .open(&log_path)
-        .with_context(|| format!("Failed to create server log: {}", log_path.display()))?;
+        .with_context(|| format!("Failed to create server log: {}", log_path.display()))
+}
+
+/// Spawn the mux server daemon. `command` overrides the session's stored
+/// command (passed JSON-encoded via `server::COMMAND_ENV`).
+fn spawn_server(session_name: &str, command: Option<&[String]>) -> Result<()> {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
 
+    let exe = std::env::current_exe().context("Failed to get current executable path")?;
+
+    // Redirect server stderr to a log file for debugging (append mode).
+    let log_file = open_server_log(session_name)?;
+
+    let mut server = Command::new(exe);
+    server.env("__BOX_MUX_SERVER", session_name);
+    match command {
+        Some(command) => server.env(server::COMMAND_ENV, server::encode_command(command)),
+        None => server.env_remove(server::COMMAND_ENV),
+    };
     unsafe {
-        Command::new(exe)
-            .env("__BOX_MUX_SERVER", session_name)
+        server
             .stdin(Stdio::null())
             .stdout(Stdio::null())
             .stderr(Stdio::from(log_file))
@@ -609,6 +897,13 @@ fn wait_for_socket(session_name: &str, socket_path: &std::path::Path) -> Result<
     anyhow::bail!("Timed out waiting for mux server to start")
 }
 
+/// Remove the leftovers of a crashed mux server: kill a hung server process
+/// if one is still around, then delete its PID file and socket.
+pub fn clean_stale_server(session_name: &str) {
+    kill_stale_server(session_name);
+    session::remove_socket(session_name);
+}
+
 /// Kill a stale server process for this session (if any) via its PID file.
 /// This prevents orphaned server processes when a server dies from a signal
 /// but its PID file was not cleaned up.
@@ -687,16 +982,58 @@ enum StandaloneEvent {
     ChildExited,
 }
 
-/// Fallback: run command with inherited stdio (no mux chrome).
-fn run_fallback(config: &MuxConfig) -> Result<i32> {
+/// Fallback: run command with inherited stdio (no mux chrome). Also used
+/// directly for non-interactive `box exec`.
+pub fn run_fallback(config: &MuxConfig) -> Result<i32> {
     let mut child = std::process::Command::new(&config.command[0])
         .args(&config.command[1..])
         .current_dir(config.working_dir.as_deref().unwrap_or("."))
+        .envs(local_session_env(&config.session_name))
+        .env("BOX_SESSION", &config.session_name)
+        .env_remove("__BOX_MUX_SERVER")
         .stdin(std::process::Stdio::inherit())
         .stdout(std::process::Stdio::inherit())
         .stderr(std::process::Stdio::inherit())
         .spawn()
         .with_context(|| format!("Failed to run command: {}", config.command.join(" ")))?;
     let status = child.wait()?;
-    Ok(status.code().unwrap_or(1))
+    Ok(exit_code(status))
+}
+
+/// Exit code of a finished child, using the shell's 128+N convention when it
+/// was killed by signal N.
+pub fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .or_else(|| status.signal().map(|sig| 128 + sig))
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_line_strips_escapes() {
+        assert_eq!(render_line(b"\x1b[31mERROR\x1b[0m: boom\r"), "ERROR: boom");
+        assert_eq!(render_line(b"abc\rX"), "Xbc");
+    }
+
+    #[test]
+    fn test_parser_lines_includes_scrollback() {
+        let mut parser = vt100::Parser::new(3, 20, 100);
+        parser.process(b"one\r\ntwo\r\nthree\r\nfour\r\nfive");
+        assert_eq!(
+            parser_lines(&mut parser),
+            vec!["one", "two", "three", "four", "five"]
+        );
+    }
+
+    #[test]
+    fn test_parser_lines_trims_blank_tail() {
+        let mut parser = vt100::Parser::new(5, 20, 100);
+        parser.process(b"only\r\n");
+        assert_eq!(parser_lines(&mut parser), vec!["only"]);
+    }
 }
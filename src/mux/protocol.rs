@@ -8,6 +8,12 @@ pub enum ServerMsg {
     Resized { cols: u16, rows: u16 },
     /// Child exited with code
     Exited(i32),
+    /// An extra pane requested with `ClientMsg::OpenPane` is running
+    PaneOpened(u16),
+    /// Output bytes from an extra pane
+    PaneOutput { pane: u16, data: Vec<u8> },
+    /// An extra pane's shell exited or it could not be opened (pane 0)
+    PaneClosed(u16),
 }
 
 /// Messages sent from client to server.
@@ -18,6 +24,17 @@ pub enum ClientMsg {
     Resize { cols: u16, rows: u16 },
     /// Kill child process
     Kill,
+    /// Read-only attach: request the screen dump and output stream without
+    /// taking part in PTY sizing
+    Observe,
+    /// Open an extra pane (a shell in its own PTY) owned by this client
+    OpenPane { cols: u16, rows: u16 },
+    /// Raw bytes for an extra pane's PTY
+    PaneInput { pane: u16, data: Vec<u8> },
+    /// Extra pane size
+    PaneResize { pane: u16, cols: u16, rows: u16 },
+    /// Kill an extra pane's shell
+    ClosePane(u16),
 }
 
 // Wire format: [u8 tag][u32 BE payload_len][payload]
@@ -26,11 +43,19 @@ pub enum ClientMsg {
 //   0x01 = Output(payload)
 //   0x02 = Resized(cols: u16 BE, rows: u16 BE)
 //   0x03 = Exited(code: i32 BE)
+//   0x04 = PaneOpened(pane: u16 BE)
+//   0x05 = PaneOutput(pane: u16 BE, data)
+//   0x06 = PaneClosed(pane: u16 BE)
 //
 // Client→Server tags:
 //   0x11 = Input(payload)
 //   0x12 = Resize(cols: u16 BE, rows: u16 BE)
 //   0x13 = Kill (no payload)
+//   0x14 = Observe (no payload)
+//   0x15 = OpenPane(cols: u16 BE, rows: u16 BE)
+//   0x16 = PaneInput(pane: u16 BE, data)
+//   0x17 = PaneResize(pane: u16 BE, cols: u16 BE, rows: u16 BE)
+//   0x18 = ClosePane(pane: u16 BE)
 
 fn write_frame(w: &mut impl Write, tag: u8, payload: &[u8]) -> io::Result<()> {
     w.write_all(&[tag])?;
@@ -41,6 +66,25 @@ fn write_frame(w: &mut impl Write, tag: u8, payload: &[u8]) -> io::Result<()> {
     w.flush()
 }
 
+/// Payload of a pane-addressed message: the pane id followed by `data`.
+fn pane_payload(pane: u16, data: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(2 + data.len());
+    buf.extend_from_slice(&pane.to_be_bytes());
+    buf.extend_from_slice(data);
+    buf
+}
+
+/// Read the big-endian u16 at `payload[i..i + 2]`.
+fn read_u16(payload: &[u8], i: usize, what: &str) -> io::Result<u16> {
+    match payload.get(i..i + 2) {
+        Some(b) => Ok(u16::from_be_bytes([b[0], b[1]])),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("short {}", what),
+        )),
+    }
+}
+
 /// Maximum payload size (16 MB) to prevent OOM on corrupted frames.
 const MAX_PAYLOAD: usize = 16 * 1024 * 1024;
 
@@ -78,6 +122,9 @@ pub fn write_server_msg(w: &mut impl Write, msg: &ServerMsg) -> io::Result<()> {
             write_frame(w, 0x02, &buf)
         }
         ServerMsg::Exited(code) => write_frame(w, 0x03, &code.to_be_bytes()),
+        ServerMsg::PaneOpened(pane) => write_frame(w, 0x04, &pane.to_be_bytes()),
+        ServerMsg::PaneOutput { pane, data } => write_frame(w, 0x05, &pane_payload(*pane, data)),
+        ServerMsg::PaneClosed(pane) => write_frame(w, 0x06, &pane.to_be_bytes()),
     }
 }
 
@@ -100,6 +147,12 @@ pub fn read_server_msg(r: &mut impl Read) -> io::Result<ServerMsg> {
             let code = i32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
             Ok(ServerMsg::Exited(code))
         }
+        0x04 => Ok(ServerMsg::PaneOpened(read_u16(&payload, 0, "PaneOpened")?)),
+        0x05 => Ok(ServerMsg::PaneOutput {
+            pane: read_u16(&payload, 0, "PaneOutput")?,
+            data: payload[2..].to_vec(),
+        }),
+        0x06 => Ok(ServerMsg::PaneClosed(read_u16(&payload, 0, "PaneClosed")?)),
         _ => Err(io::Error::new(
             io::ErrorKind::InvalidData,
             format!("unknown server tag: 0x{:02x}", tag),
@@ -117,6 +170,22 @@ pub fn write_client_msg(w: &mut impl Write, msg: &ClientMsg) -> io::Result<()> {
             write_frame(w, 0x12, &buf)
         }
         ClientMsg::Kill => write_frame(w, 0x13, &[]),
+        ClientMsg::Observe => write_frame(w, 0x14, &[]),
+        ClientMsg::OpenPane { cols, rows } => {
+            let mut buf = [0u8; 4];
+            buf[0..2].copy_from_slice(&cols.to_be_bytes());
+            buf[2..4].copy_from_slice(&rows.to_be_bytes());
+            write_frame(w, 0x15, &buf)
+        }
+        ClientMsg::PaneInput { pane, data } => write_frame(w, 0x16, &pane_payload(*pane, data)),
+        ClientMsg::PaneResize { pane, cols, rows } => {
+            let mut buf = [0u8; 6];
+            buf[0..2].copy_from_slice(&pane.to_be_bytes());
+            buf[2..4].copy_from_slice(&cols.to_be_bytes());
+            buf[4..6].copy_from_slice(&rows.to_be_bytes());
+            write_frame(w, 0x17, &buf)
+        }
+        ClientMsg::ClosePane(pane) => write_frame(w, 0x18, &pane.to_be_bytes()),
     }
 }
 
@@ -149,6 +218,21 @@ pub fn read_client_msg(r: &mut impl Read) -> io::Result<ClientMsg> {
             Ok(ClientMsg::Resize { cols, rows })
         }
         0x13 => Ok(ClientMsg::Kill),
+        0x14 => Ok(ClientMsg::Observe),
+        0x15 => Ok(ClientMsg::OpenPane {
+            cols: read_u16(&payload, 0, "OpenPane")?,
+            rows: read_u16(&payload, 2, "OpenPane")?,
+        }),
+        0x16 => Ok(ClientMsg::PaneInput {
+            pane: read_u16(&payload, 0, "PaneInput")?,
+            data: payload[2..].to_vec(),
+        }),
+        0x17 => Ok(ClientMsg::PaneResize {
+            pane: read_u16(&payload, 0, "PaneResize")?,
+            cols: read_u16(&payload, 2, "PaneResize")?,
+            rows: read_u16(&payload, 4, "PaneResize")?,
+        }),
+        0x18 => Ok(ClientMsg::ClosePane(read_u16(&payload, 0, "ClosePane")?)),
         _ => Err(io::Error::new(
             io::ErrorKind::InvalidData,
             format!("unknown client tag: 0x{:02x}", tag),
@@ -238,6 +322,15 @@ mod tests {
         assert!(matches!(decoded, ClientMsg::Kill));
     }
 
+    #[test]
+    fn test_client_observe_roundtrip() {
+        let mut buf = Vec::new();
+        write_client_msg(&mut buf, &ClientMsg::Observe).unwrap();
+        assert_eq!(buf, [0x14, 0, 0, 0, 0]);
+        let decoded = read_client_msg(&mut &buf[..]).unwrap();
+        assert!(matches!(decoded, ClientMsg::Observe));
+    }
+
     #[test]
     fn test_server_output_empty() {
         let msg = ServerMsg::Output(vec![]);
@@ -262,16 +355,101 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pane_server_msgs_roundtrip() {
+        let mut buf = Vec::new();
+        write_server_msg(&mut buf, &ServerMsg::PaneOpened(3)).unwrap();
+        write_server_msg(
+            &mut buf,
+            &ServerMsg::PaneOutput {
+                pane: 3,
+                data: b"ls\r\n".to_vec(),
+            },
+        )
+        .unwrap();
+        write_server_msg(&mut buf, &ServerMsg::PaneClosed(3)).unwrap();
+        let mut r = &buf[..];
+        assert!(matches!(
+            read_server_msg(&mut r).unwrap(),
+            ServerMsg::PaneOpened(3)
+        ));
+        match read_server_msg(&mut r).unwrap() {
+            ServerMsg::PaneOutput { pane, data } => {
+                assert_eq!(pane, 3);
+                assert_eq!(data, b"ls\r\n");
+            }
+            _ => panic!("expected PaneOutput"),
+        }
+        assert!(matches!(
+            read_server_msg(&mut r).unwrap(),
+            ServerMsg::PaneClosed(3)
+        ));
+    }
+
+    #[test]
+    fn test_pane_client_msgs_roundtrip() {
+        let mut buf = Vec::new();
+        write_client_msg(&mut buf, &ClientMsg::OpenPane { cols: 80, rows: 11 }).unwrap();
+        write_client_msg(
+            &mut buf,
+            &ClientMsg::PaneInput {
+                pane: 1,
+                data: b"x".to_vec(),
+            },
+        )
+        .unwrap();
+        write_client_msg(
+            &mut buf,
+            &ClientMsg::PaneResize {
+                pane: 1,
+                cols: 100,
+                rows: 20,
+            },
+        )
+        .unwrap();
+        write_client_msg(&mut buf, &ClientMsg::ClosePane(1)).unwrap();
+        let mut r = &buf[..];
+        assert!(matches!(
+            read_client_msg(&mut r).unwrap(),
+            ClientMsg::OpenPane { cols: 80, rows: 11 }
+        ));
+        match read_client_msg(&mut r).unwrap() {
+            ClientMsg::PaneInput { pane, data } => {
+                assert_eq!(pane, 1);
+                assert_eq!(data, b"x");
+            }
+            _ => panic!("expected PaneInput"),
+        }
+        assert!(matches!(
+            read_client_msg(&mut r).unwrap(),
+            ClientMsg::PaneResize {
+                pane: 1,
+                cols: 100,
+                rows: 20
+            }
+        ));
+        assert!(matches!(
+            read_client_msg(&mut r).unwrap(),
+            ClientMsg::ClosePane(1)
+        ));
+    }
+
+    #[test]
+    fn test_short_pane_payload() {
+        let buf = [0x16, 0, 0, 0, 1, 0];
+        assert!(read_client_msg(&mut &buf[..]).is_err());
+    }
+
     #[test]
     fn test_unknown_server_tag() {
-        let buf = vec![0xFF, 0, 0, 0, 0]; // unknown tag, 0-length payload
+        let buf = [0xFF, 0, 0, 0, 0]; // unknown tag, 0-length payload
         let result = read_server_msg(&mut &buf[..]);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_unknown_client_tag() {
-        let buf = vec![0xFF, 0, 0, 0, 0];
+        let buf = [0xFF, 0, 0, 0, 0];
         let result = read_client_msg(&mut &buf[..]);
         assert!(result.is_err());
     }
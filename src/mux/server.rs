@@ -7,7 +7,7 @@ use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::config;
 use crate::session;
@@ -27,6 +27,8 @@ enum ServerEvent {
     ClientMsg { id: u64, msg: ClientMsg },
     ClientDisconnected(u64),
     ChildExited,
+    PaneOutput { pane: u16, data: Vec<u8> },
+    PaneExited(u16),
 }
 
 struct ClientEntry {
@@ -38,6 +40,96 @@ struct ClientEntry {
     has_resized: bool,
 }
 
+/// An extra shell opened by a client to split its view. Only the owning
+/// client sees its output, and it dies with that client.
+struct Pane {
+    pty: pty_process::blocking::Pty,
+    child: std::process::Child,
+    owner: u64,
+}
+
+impl Pane {
+    fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Take the next pane id from `next` that no live pane uses, wrapping past
+/// `u16::MAX`. Pane 0 is never handed out, so `PaneClosed(0)` means an open
+/// failed. None when every id is taken.
+fn allocate_pane_id(next: &mut u16, in_use: impl Fn(u16) -> bool) -> Option<u16> {
+    for _ in 0..u16::MAX {
+        let id = *next;
+        *next = next.checked_add(1).unwrap_or(1);
+        if !in_use(id) {
+            return Some(id);
+        }
+    }
+    None
+}
+
+/// Start `$SHELL` in a new PTY with the session's environment, in `dir` when
+/// it exists. Output is sent as `ServerEvent::PaneOutput` until the shell
+/// exits.
+fn spawn_pane(
+    pane: u16,
+    owner: u64,
+    (cols, rows): (u16, u16),
+    sess: &session::Session,
+    dir: &Path,
+    tx: &mpsc::Sender<ServerEvent>,
+) -> Result<Pane> {
+    let pty = pty_process::blocking::Pty::new().context("Failed to open PTY")?;
+    let pts = pty.pts().context("Failed to get PTY slave")?;
+    terminal::set_pty_size(&pty, rows, cols)?;
+
+    let shell = std::env::var("SHELL")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "sh".to_string());
+    let mut cmd = pty_process::blocking::Command::new(&shell);
+    for (key, value) in sess.env.iter().filter_map(|e| e.split_once('=')) {
+        cmd.env(key, value);
+    }
+    cmd.env("BOX_SESSION", &sess.name);
+    cmd.env_remove("__BOX_MUX_SERVER");
+    cmd.env_remove(COMMAND_ENV);
+    if dir.is_dir() {
+        cmd.current_dir(dir);
+    }
+    let child = cmd
+        .spawn(&pts)
+        .with_context(|| format!("Failed to spawn {} in PTY", shell))?;
+    drop(pts);
+
+    let read_fd = unsafe { libc::dup(pty.as_raw_fd()) };
+    if read_fd < 0 {
+        anyhow::bail!("Failed to dup PTY fd");
+    }
+    let mut reader = unsafe { std::fs::File::from_raw_fd(read_fd) };
+    let tx = tx.clone();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => {
+                    let _ = tx.send(ServerEvent::PaneExited(pane));
+                    break;
+                }
+                Ok(n) => {
+                    let data = buf[..n].to_vec();
+                    if tx.send(ServerEvent::PaneOutput { pane, data }).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(Pane { pty, child, owner })
+}
+
 /// RAII guard that removes socket + PID file on drop (including panics).
 struct CleanupGuard {
     session_name: String,
@@ -50,6 +142,21 @@ impl Drop for CleanupGuard {
     }
 }
 
+/// Environment variable carrying a JSON-encoded command that replaces the
+/// session's stored command (used by `box exec -d`).
+pub const COMMAND_ENV: &str = "__BOX_MUX_COMMAND";
+
+/// Encode a command for `COMMAND_ENV`. Environment values can't hold NUL, so
+/// the argv is passed as a JSON array of strings.
+pub fn encode_command(command: &[String]) -> String {
+    serde_json::to_string(command).unwrap_or_default()
+}
+
+/// Decode a `COMMAND_ENV` value written by `encode_command`.
+pub fn decode_command(value: &str) -> Option<Vec<String>> {
+    serde_json::from_str(value).ok()
+}
+
 pub fn run(session_name: &str) -> Result<()> {
     // Install signal handlers
     unsafe {
@@ -62,17 +169,22 @@ pub fn run(session_name: &str) -> Result<()> {
     }
 
     // Load session metadata
-    let sess = session::load(session_name)?;
+    let mut sess = session::load(session_name)?;
+    if let Some(command) = std::env::var(COMMAND_ENV)
+        .ok()
+        .and_then(|value| decode_command(&value))
+    {
+        sess.command = command;
+    }
     if sess.command.is_empty() {
         anyhow::bail!("Session '{}' has no command configured.", session_name);
     }
 
     // Derive workspace path (use workspace name, not full session name)
-    let home = config::home_dir()?;
-    let workspace = Path::new(&home)
-        .join(".box")
-        .join("workspaces")
-        .join(session::workspace_name(session_name));
+    let workspace = config::workspaces_dir()?.join(session::workspace_name(session_name));
+    let working_dir = Some(sess.working_dir(&workspace))
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(|| workspace.clone());
 
     // Ensure session directory has restricted permissions before creating socket
     let sess_dir = session::sessions_dir()?.join(session_name);
@@ -84,6 +196,9 @@ pub fn run(session_name: &str) -> Result<()> {
         }
     }
 
+    // A new run has no exit status yet
+    session::remove_exit_code(session_name);
+
     // Create Unix socket
     let socket_path = session::socket_path(session_name)?;
     // Remove stale socket if it exists
@@ -109,10 +224,14 @@ pub fn run(session_name: &str) -> Result<()> {
     // Spawn child
     let mut cmd = pty_process::blocking::Command::new(&sess.command[0]);
     cmd.args(&sess.command[1..]);
+    for (key, value) in sess.env.iter().filter_map(|e| e.split_once('=')) {
+        cmd.env(key, value);
+    }
     cmd.env("BOX_SESSION", session_name);
     cmd.env_remove("__BOX_MUX_SERVER");
-    if workspace.is_dir() {
-        cmd.current_dir(&workspace);
+    cmd.env_remove(COMMAND_ENV);
+    if working_dir.is_dir() {
+        cmd.current_dir(&working_dir);
     }
     let mut child = cmd
         .spawn(&pts)
@@ -121,7 +240,7 @@ pub fn run(session_name: &str) -> Result<()> {
     drop(pts);
 
     // Create vt100 parser for screen state
-    let mut parser = vt100::Parser::new(default_rows, default_cols, super::SCROLLBACK_LINES);
+    let mut parser = vt100::Parser::new(default_rows, default_cols, config::load_mux_scrollback());
 
     // Raw PTY output history for replaying scrollback to new clients.
     // Capped at 4MB — enough for ~10k lines of typical terminal output.
@@ -180,9 +299,30 @@ pub fn run(session_name: &str) -> Result<()> {
 
     let mut clients: HashMap<u64, ClientEntry> = HashMap::new();
     let mut next_client_id: u64 = 0;
+    let mut panes: HashMap<u16, Pane> = HashMap::new();
+    let mut next_pane_id: u16 = 1;
+
+    // Stop after running this long with no client attached. Only attachment
+    // is tracked; a busy program with no viewers still counts as idle.
+    let idle_timeout = config::load_mux_idle_timeout();
+    let mut idle_since: Option<Instant> = None;
 
     // Main event loop
     loop {
+        if let Some(timeout) = idle_timeout {
+            if !clients.is_empty() {
+                idle_since = None;
+            } else if idle_since.get_or_insert_with(Instant::now).elapsed() >= timeout {
+                eprintln!(
+                    "mux server: no client attached for {}s, stopping",
+                    timeout.as_secs()
+                );
+                let _ = child.kill();
+                let _ = child.wait();
+                // Cleanup happens via CleanupGuard on drop
+                break;
+            }
+        }
         let event = rx.recv_timeout(Duration::from_millis(100));
         match event {
             Ok(ServerEvent::PtyOutput(data)) => {
@@ -305,35 +445,7 @@ pub fn run(session_name: &str) -> Result<()> {
                         client.has_resized = true;
 
                         if first_resize {
-                            // Send current PTY size
-                            let _ = client.tx.send(Arc::from(protocol::serialize_server_msg(
-                                &ServerMsg::Resized {
-                                    cols: pty_cols,
-                                    rows: pty_rows,
-                                },
-                            )));
-                            // Replay raw PTY history + formatted screen dump as
-                            // a single Output message so the client's synchronous
-                            // handshake read consumes everything in one shot.
-                            // The history rebuilds scrollback and the screen dump
-                            // ensures the visible area matches exactly.
-                            //
-                            // make_contiguous() arranges the VecDeque in-place
-                            // (no heap alloc) so we can borrow without cloning.
-                            let contents = parser.screen().contents_formatted();
-                            if !history.is_empty() {
-                                let hist = history.make_contiguous();
-                                let mut combined = Vec::with_capacity(hist.len() + contents.len());
-                                combined.extend_from_slice(hist);
-                                combined.extend_from_slice(&contents);
-                                let _ = client
-                                    .tx
-                                    .send(Arc::from(protocol::serialize_output_slice(&combined)));
-                            } else {
-                                let _ = client.tx.send(Arc::from(protocol::serialize_server_msg(
-                                    &ServerMsg::Output(contents),
-                                )));
-                            }
+                            send_handshake(client, pty_cols, pty_rows, &mut history, &parser);
                         }
 
                         // Recalculate effective size
@@ -346,6 +458,13 @@ pub fn run(session_name: &str) -> Result<()> {
                         );
                     }
                 }
+                ClientMsg::Observe => {
+                    // Observers get the same handshake but never send Resize,
+                    // so they are excluded from PTY size calculation.
+                    if let Some(client) = clients.get(&id) {
+                        send_handshake(client, pty_cols, pty_rows, &mut history, &parser);
+                    }
+                }
                 ClientMsg::Input(data) => {
                     let _ = terminal::write_bytes_to_pty(&pty, &data);
                 }
@@ -353,9 +472,81 @@ pub fn run(session_name: &str) -> Result<()> {
                     let _ = child.kill();
                     let _ = child.wait();
                 }
+                ClientMsg::OpenPane { cols, rows } => {
+                    let Some(client) = clients.get(&id) else {
+                        continue;
+                    };
+                    let pane_id = allocate_pane_id(&mut next_pane_id, |p| panes.contains_key(&p));
+                    let reply = match pane_id.filter(|_| cols > 0 && rows > 0).map(|pane_id| {
+                        spawn_pane(pane_id, id, (cols, rows), &sess, &working_dir, &tx)
+                            .map(|pane| (pane_id, pane))
+                    }) {
+                        Some(Ok((pane_id, pane))) => {
+                            panes.insert(pane_id, pane);
+                            ServerMsg::PaneOpened(pane_id)
+                        }
+                        _ => ServerMsg::PaneClosed(0),
+                    };
+                    let _ = client
+                        .tx
+                        .try_send(Arc::from(protocol::serialize_server_msg(&reply)));
+                }
+                ClientMsg::PaneInput { pane, data } => {
+                    if let Some(p) = panes.get(&pane).filter(|p| p.owner == id) {
+                        let _ = terminal::write_bytes_to_pty(&p.pty, &data);
+                    }
+                }
+                ClientMsg::PaneResize { pane, cols, rows } => {
+                    if cols == 0 || rows == 0 {
+                        continue;
+                    }
+                    if let Some(p) = panes.get(&pane).filter(|p| p.owner == id) {
+                        let _ = terminal::set_pty_size(&p.pty, rows, cols);
+                    }
+                }
+                ClientMsg::ClosePane(pane) => {
+                    if panes.get(&pane).is_some_and(|p| p.owner == id) {
+                        if let Some(p) = panes.remove(&pane) {
+                            p.kill();
+                        }
+                    }
+                }
             },
+            Ok(ServerEvent::PaneOutput { pane, data }) => {
+                let Some(owner) = panes.get(&pane).map(|p| p.owner) else {
+                    continue;
+                };
+                if let Some(client) = clients.get(&owner) {
+                    let msg = ServerMsg::PaneOutput { pane, data };
+                    let _ = client
+                        .tx
+                        .try_send(Arc::from(protocol::serialize_server_msg(&msg)));
+                }
+            }
+            Ok(ServerEvent::PaneExited(pane)) => {
+                // Already gone if the client closed it
+                let Some(p) = panes.remove(&pane) else {
+                    continue;
+                };
+                if let Some(client) = clients.get(&p.owner) {
+                    let _ = client.tx.try_send(Arc::from(protocol::serialize_server_msg(
+                        &ServerMsg::PaneClosed(pane),
+                    )));
+                }
+                p.kill();
+            }
             Ok(ServerEvent::ClientDisconnected(id)) => {
                 clients.remove(&id);
+                let owned: Vec<u16> = panes
+                    .iter()
+                    .filter(|(_, p)| p.owner == id)
+                    .map(|(&pane, _)| pane)
+                    .collect();
+                for pane in owned {
+                    if let Some(p) = panes.remove(&pane) {
+                        p.kill();
+                    }
+                }
                 if !clients.is_empty() {
                     recalc_size(
                         &mut clients,
@@ -368,8 +559,14 @@ pub fn run(session_name: &str) -> Result<()> {
                 // Server keeps running with zero clients
             }
             Ok(ServerEvent::ChildExited) => {
-                // Drain remaining PTY output
-                while let Ok(ServerEvent::PtyOutput(data)) = rx.try_recv() {
+                for (_, pane) in panes.drain() {
+                    pane.kill();
+                }
+                // Drain remaining PTY output (other events no longer matter)
+                while let Ok(event) = rx.try_recv() {
+                    let ServerEvent::PtyOutput(data) = event else {
+                        continue;
+                    };
                     parser.process(&data);
                     let msg_bytes: Arc<[u8]> =
                         Arc::from(protocol::serialize_server_msg(&ServerMsg::Output(data)));
@@ -379,7 +576,16 @@ pub fn run(session_name: &str) -> Result<()> {
                 }
 
                 // Get exit code
-                let code = child.wait().ok().and_then(|s| s.code()).unwrap_or(0);
+                let status = child.wait().ok();
+                let code = status.and_then(|s| s.code()).unwrap_or(0);
+                let _ = session::write_exit_code(
+                    session_name,
+                    status.map(super::exit_code).unwrap_or(code),
+                );
+
+                if sess.notify_on_exit {
+                    notify_exit(session_name, code);
+                }
 
                 // Broadcast Exited to all clients
                 let exit_bytes: Arc<[u8]> =
@@ -397,7 +603,9 @@ pub fn run(session_name: &str) -> Result<()> {
                 // Check for SIGTERM
                 if SHUTDOWN.load(Ordering::SeqCst) {
                     let _ = child.kill();
-                    let _ = child.wait();
+                    if let Ok(status) = child.wait() {
+                        let _ = session::write_exit_code(session_name, super::exit_code(status));
+                    }
 
                     // Broadcast Exited to all clients
                     let exit_bytes: Arc<[u8]> =
@@ -417,12 +625,99 @@ pub fn run(session_name: &str) -> Result<()> {
         }
     }
 
+    // Split panes don't outlive the session
+    for (_, pane) in panes.drain() {
+        pane.kill();
+    }
+
     Ok(())
 }
 
-/// Recalculate effective PTY size = min(cols), min(rows) across all connected clients
-/// that have sent at least one Resize. Resize PTY + parser if changed, and broadcast
-/// the new size to all clients.
+/// Send the connection handshake to a client: the current PTY size followed
+/// by the replayed history and screen dump.
+fn send_handshake(
+    client: &ClientEntry,
+    pty_cols: u16,
+    pty_rows: u16,
+    history: &mut VecDeque<u8>,
+    parser: &vt100::Parser,
+) {
+    // Send current PTY size
+    let _ = client.tx.send(Arc::from(protocol::serialize_server_msg(
+        &ServerMsg::Resized {
+            cols: pty_cols,
+            rows: pty_rows,
+        },
+    )));
+    // Replay raw PTY history + formatted screen dump as
+    // a single Output message so the client's synchronous
+    // handshake read consumes everything in one shot.
+    // The history rebuilds scrollback and the screen dump
+    // ensures the visible area matches exactly.
+    //
+    // make_contiguous() arranges the VecDeque in-place
+    // (no heap alloc) so we can borrow without cloning.
+    // The input modes (bracketed paste, application cursor keys, ...) ride
+    // along so the client's parser knows what the program asked for even
+    // when the enabling sequence has scrolled out of the history.
+    let mut contents = parser.screen().contents_formatted();
+    contents.extend_from_slice(&parser.screen().input_mode_formatted());
+    if !history.is_empty() {
+        let hist = history.make_contiguous();
+        let mut combined = Vec::with_capacity(hist.len() + contents.len());
+        combined.extend_from_slice(hist);
+        combined.extend_from_slice(&contents);
+        let _ = client
+            .tx
+            .send(Arc::from(protocol::serialize_output_slice(&combined)));
+    } else {
+        let _ = client.tx.send(Arc::from(protocol::serialize_server_msg(
+            &ServerMsg::Output(contents),
+        )));
+    }
+}
+
+/// Fire a desktop notification that the session's command exited.
+/// Uses `osascript` on macOS and `notify-send` elsewhere; failures are ignored
+/// since a missing notifier must not keep the server from shutting down.
+fn notify_exit(session_name: &str, code: i32) {
+    let title = format!("box: {}", session_name);
+    let body = format!("Command exited with code {}", code);
+    let mut cmd = if cfg!(target_os = "macos") {
+        let script = format!("display notification {:?} with title {:?}", body, title);
+        let mut c = std::process::Command::new("osascript");
+        c.args(["-e", &script]);
+        c
+    } else {
+        let mut c = std::process::Command::new("notify-send");
+        c.args([&title, &body]);
+        c
+    };
+    let _ = cmd
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+}
+
+/// The PTY size shared by all attached clients: the smallest width and the
+/// smallest height among clients that have sent a Resize (observers that never
+/// resize don't count), like tmux. Larger clients letterbox the rest of their
+/// view. `None` when no client has a size yet.
+fn min_client_size<'a>(clients: impl IntoIterator<Item = &'a ClientEntry>) -> Option<(u16, u16)> {
+    clients
+        .into_iter()
+        .filter(|c| c.has_resized)
+        .fold(None, |size, c| match size {
+            Some((cols, rows)) => Some((c.cols.min(cols), c.rows.min(rows))),
+            None => Some((c.cols, c.rows)),
+        })
+}
+
+/// Resize the PTY + parser to `min_client_size` whenever it changes (on every
+/// client resize, connect and disconnect), and broadcast the new size to all
+/// clients. Clients that can't take the broadcast are dropped, which may in
+/// turn let the PTY grow, so repeat until the size is stable.
 fn recalc_size(
     clients: &mut HashMap<u64, ClientEntry>,
     pty: &pty_process::blocking::Pty,
@@ -430,15 +725,10 @@ fn recalc_size(
     pty_cols: &mut u16,
     pty_rows: &mut u16,
 ) {
-    let resized_clients: Vec<&ClientEntry> = clients.values().filter(|c| c.has_resized).collect();
-    if resized_clients.is_empty() {
-        return;
-    }
-
-    let new_cols = resized_clients.iter().map(|c| c.cols).min().unwrap_or(80);
-    let new_rows = resized_clients.iter().map(|c| c.rows).min().unwrap_or(24);
-
-    if new_cols != *pty_cols || new_rows != *pty_rows {
+    while let Some((new_cols, new_rows)) = min_client_size(clients.values()) {
+        if new_cols == *pty_cols && new_rows == *pty_rows {
+            return;
+        }
         *pty_cols = new_cols;
         *pty_rows = new_rows;
         let _ = terminal::set_pty_size(pty, new_rows, new_cols);
@@ -460,3 +750,59 @@ fn recalc_size(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_pane_id_skips_live_panes() {
+        let mut next = u16::MAX;
+        let live = [u16::MAX, 1, 3];
+        assert_eq!(allocate_pane_id(&mut next, |p| live.contains(&p)), Some(2));
+        assert_eq!(allocate_pane_id(&mut next, |p| live.contains(&p)), Some(4));
+        assert_eq!(allocate_pane_id(&mut next, |_| true), None);
+    }
+
+    #[test]
+    fn test_command_env_survives_spawn() {
+        let command: Vec<String> = ["make", "build", "--jobs=4", "a b", "quote\"d"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let output = std::process::Command::new("sh")
+            .args(["-c", "printf %s \"$__BOX_MUX_COMMAND\""])
+            .env(COMMAND_ENV, encode_command(&command))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let value = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(decode_command(&value), Some(command));
+        assert_eq!(decode_command("not json"), None);
+    }
+
+    fn client(cols: u16, rows: u16, has_resized: bool) -> ClientEntry {
+        let (tx, _rx) = mpsc::sync_channel(1);
+        ClientEntry {
+            tx,
+            cols,
+            rows,
+            has_resized,
+        }
+    }
+
+    #[test]
+    fn test_min_client_size() {
+        assert_eq!(min_client_size(&[]), None);
+        assert_eq!(min_client_size(&[client(0, 0, false)]), None);
+        // Width and height are minimised independently
+        let clients = [
+            client(120, 30, true),
+            client(80, 50, true),
+            client(40, 10, false),
+        ];
+        assert_eq!(min_client_size(&clients), Some((80, 30)));
+        // The larger client gets the full size back once the smaller one leaves
+        assert_eq!(min_client_size(&clients[..1]), Some((120, 30)));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::config::{CommandKeys, ScrollSettings};
 use anyhow::{Context, Result};
 use ratatui::prelude::*;
 use ratatui::widgets::Widget;
@@ -277,6 +278,52 @@ impl Selection {
     }
 }
 
+/// A search hit: `line` indexes all lines with the oldest scrollback line
+/// first (as returned by `parser_lines`), `col`/`len` are in cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+/// Find the nearest occurrence of `query` strictly before (`older`) or after
+/// the `(line, col)` position. Does not wrap around.
+pub fn find_match(
+    lines: &[String],
+    query: &str,
+    from: (usize, usize),
+    older: bool,
+) -> Option<SearchMatch> {
+    if query.is_empty() {
+        return None;
+    }
+    let len = query.chars().count();
+    let hits = |line: usize| -> Vec<usize> {
+        lines.get(line).map_or_else(Vec::new, |text| {
+            text.match_indices(query)
+                .map(|(byte, _)| text[..byte].chars().count())
+                .collect()
+        })
+    };
+    let (from_line, from_col) = from;
+    if older {
+        (0..=from_line.min(lines.len())).rev().find_map(|line| {
+            hits(line)
+                .into_iter()
+                .rfind(|&col| line < from_line || col < from_col)
+                .map(|col| SearchMatch { line, col, len })
+        })
+    } else {
+        (from_line..lines.len()).find_map(|line| {
+            hits(line)
+                .into_iter()
+                .find(|&col| line > from_line || col > from_col)
+                .map(|col| SearchMatch { line, col, len })
+        })
+    }
+}
+
 /// Scrollback state passed to `draw_frame` for rendering the scrollbar.
 pub struct ScrollState {
     pub offset: usize,
@@ -288,6 +335,12 @@ pub struct DrawFrameParams<'a> {
     pub screen: &'a vt100::Screen,
     pub scroll: &'a ScrollState,
     pub selection: Option<&'a Selection>,
+    /// Text drawn over the bottom grid row (used where there is no command bar).
+    pub prompt: Option<&'a str>,
+    /// Draw `-- SCROLL offset/max --` over the bottom grid row while scrolled.
+    pub show_scroll_status: bool,
+    /// Draw the cursor (false for an unfocused pane of a split).
+    pub show_cursor: bool,
 }
 
 /// Render the mux frame: terminal grid (full area, no header).
@@ -302,7 +355,7 @@ pub fn draw_frame(f: &mut ratatui::Frame, params: &DrawFrameParams, area: Rect)
 
     let widget = TerminalWidget {
         screen,
-        show_cursor: !scrolled_up,
+        show_cursor: params.show_cursor && !scrolled_up,
         selection: params.selection,
     };
     f.render_widget(widget, grid_area);
@@ -315,11 +368,9 @@ pub fn draw_frame(f: &mut ratatui::Frame, params: &DrawFrameParams, area: Rect)
         let thumb_size = (track_height * track_height / total_lines).max(1);
         // Thumb position: 0 = bottom (scroll_offset 0), top = max scroll
         let max_thumb_top = track_height.saturating_sub(thumb_size);
-        let thumb_top = if max_scrollback > 0 {
-            scroll_offset * max_thumb_top / max_scrollback
-        } else {
-            0
-        };
+        let thumb_top = (scroll_offset * max_thumb_top)
+            .checked_div(max_scrollback)
+            .unwrap_or(0);
         // Invert: scroll_offset=max means thumb at top (y=0)
         let thumb_y_start = max_thumb_top - thumb_top;
 
@@ -342,6 +393,35 @@ pub fn draw_frame(f: &mut ratatui::Frame, params: &DrawFrameParams, area: Rect)
             }
         }
     }
+
+    if let Some(prompt) = params.prompt {
+        if grid_area.height > 0 {
+            let y = grid_area.y + grid_area.height - 1;
+            let style = Style::default().fg(Color::White).bg(Color::Indexed(236));
+            let text: String = prompt.chars().take(grid_area.width as usize).collect();
+            let line = format!("{:<width$}", text, width = grid_area.width as usize);
+            f.buffer_mut().set_string(grid_area.x, y, line, style);
+        }
+    }
+
+    // The cursor is hidden while scrolled, so the bottom row can be covered
+    // without resizing the PTY.
+    if params.show_scroll_status && scrolled_up && grid_area.height > 0 {
+        let text = scroll_status_text(scroll_offset, max_scrollback);
+        let width = text.chars().count() as u16;
+        // Keep clear of the scrollbar in the last column.
+        let right = grid_area.x + grid_area.width.saturating_sub(1);
+        if width <= right.saturating_sub(grid_area.x) {
+            let y = grid_area.y + grid_area.height - 1;
+            let style = Style::default().fg(Color::Black).bg(Color::Yellow);
+            f.buffer_mut().set_string(right - width, y, text, style);
+        }
+    }
+}
+
+/// Status overlay text shown while scrolled back into the history.
+fn scroll_status_text(offset: usize, max: usize) -> String {
+    format!("-- SCROLL {}/{} --", offset, max)
 }
 
 /// Input processing state machine for COMMAND mode and scroll.
@@ -350,6 +430,10 @@ pub struct InputState {
     pub scroll_offset: usize,
     /// The control byte that enters COMMAND mode (default 0x10 = Ctrl+P).
     prefix_key: u8,
+    /// Key bindings for actions in COMMAND mode.
+    keys: CommandKeys,
+    /// Mouse wheel and page scroll step sizes.
+    scroll: ScrollSettings,
     /// True while the user is click-dragging the scrollbar thumb.
     dragging_scrollbar: bool,
     /// Bytes from an incomplete escape sequence carried over from the
@@ -360,6 +444,15 @@ pub struct InputState {
     /// Grid cell where the left mouse button went down (row, col; 0-indexed).
     /// Used to distinguish a click from a drag.
     pub drag_start: Option<(u16, u16)>,
+    /// Search query being typed after `/` in COMMAND mode.
+    search_input: Option<String>,
+    /// Last submitted search query; while set, `n`/`N` step through matches.
+    search_query: Option<String>,
+    /// The match currently highlighted, if the last search step found one.
+    search_match: Option<SearchMatch>,
+    /// Subtracted from mouse row coords, for a pane below the top of the
+    /// content area.
+    pub row_offset: u16,
 }
 
 pub enum InputAction {
@@ -377,6 +470,13 @@ pub enum InputAction {
     NewSession,
     /// Copy the current selection to clipboard via OSC 52
     CopyToClipboard,
+    /// Jump to the next older (`true`) or newer search match; the caller
+    /// supplies the scrollback text via `InputState::search_step`.
+    Search(bool),
+    /// Split the content area into two panes, or close the second pane
+    ToggleSplit,
+    /// Move input focus to the other pane of a split
+    SwitchPane,
 }
 
 struct SgrMouseEvent {
@@ -386,6 +486,13 @@ struct SgrMouseEvent {
     pressed: bool,
 }
 
+/// Row (1-indexed) of a button press or wheel event at the start of `data`,
+/// the events that pick which pane of a split they apply to.
+pub fn mouse_press_row(data: &[u8]) -> Option<u16> {
+    let (mouse, _) = parse_sgr_mouse(data, 0)?;
+    (mouse.pressed && matches!(mouse.button, 0..=2 | 64 | 65)).then_some(mouse.row)
+}
+
 /// Try to parse an SGR mouse sequence at data[i..].
 /// Format: \x1b[<Btn;Col;RowM (press) or \x1b[<Btn;Col;Rowm (release).
 /// Returns (event, bytes_consumed) on success, None if not a valid sequence.
@@ -429,16 +536,92 @@ fn parse_sgr_mouse(data: &[u8], i: usize) -> Option<(SgrMouseEvent, usize)> {
 }
 
 impl InputState {
-    pub fn new(prefix_key: u8) -> Self {
+    pub fn new(prefix_key: u8, keys: CommandKeys, scroll: ScrollSettings) -> Self {
         Self {
             command_mode: false,
             scroll_offset: 0,
             prefix_key,
+            keys,
+            scroll,
             dragging_scrollbar: false,
             pending: Vec::new(),
             selection: None,
             drag_start: None,
+            search_input: None,
+            search_query: None,
+            search_match: None,
+            row_offset: 0,
+        }
+    }
+
+    /// Text describing the search state for the command bar: the query being
+    /// typed, or the submitted query and whether it matched.
+    pub fn search_prompt(&self) -> Option<String> {
+        if let Some(ref input) = self.search_input {
+            return Some(format!("/{}", input));
+        }
+        let query = self.search_query.as_ref()?;
+        Some(match self.search_match {
+            Some(_) => format!("/{}  (n: older, N: newer)", query),
+            None => format!("/{}  (not found)", query),
+        })
+    }
+
+    /// Forget the scroll position, selection and search, which belong to
+    /// the pane that had focus.
+    pub fn reset_view(&mut self) {
+        self.scroll_offset = 0;
+        self.selection = None;
+        self.drag_start = None;
+        self.dragging_scrollbar = false;
+        self.clear_search();
+    }
+
+    fn clear_search(&mut self) {
+        self.search_input = None;
+        self.search_query = None;
+        self.search_match = None;
+    }
+
+    /// Move to the next older or newer match of the submitted query in
+    /// `lines` (from `parser_lines`), scrolling so it is visible. A first
+    /// search starts at the bottom of the current view.
+    pub fn search_step(&mut self, lines: &[String], max_scrollback: usize, rows: u16, older: bool) {
+        let Some(query) = self.search_query.as_deref() else {
+            return;
+        };
+        let rows = rows as usize;
+        let top = max_scrollback.saturating_sub(self.scroll_offset);
+        let from = match self.search_match {
+            Some(m) => (m.line, m.col),
+            None => (top + rows, 0),
+        };
+        // When stepping past the last match, keep the current one highlighted.
+        let Some(found) = find_match(lines, query, from, older) else {
+            return;
+        };
+        if found.line < top || found.line >= top + rows {
+            let new_top = found.line.saturating_sub(rows / 2);
+            self.scroll_offset = max_scrollback.saturating_sub(new_top);
         }
+        self.search_match = Some(found);
+    }
+
+    /// Selection covering the current search match, in grid coordinates for
+    /// the current scroll offset, if the match is on screen.
+    pub fn search_selection(&self, max_scrollback: usize, rows: u16) -> Option<Selection> {
+        let m = self.search_match?;
+        let top = max_scrollback.saturating_sub(self.scroll_offset);
+        let row = m.line.checked_sub(top)?;
+        if row >= rows as usize {
+            return None;
+        }
+        Some(Selection {
+            start_row: row as u16,
+            start_col: m.col as u16,
+            end_row: row as u16,
+            end_col: (m.col + m.len).saturating_sub(1) as u16,
+        })
     }
 
     /// Flush any buffered bytes that didn't form a complete escape
@@ -536,6 +719,7 @@ impl InputState {
             if let Some((mut mouse, consumed)) = parse_sgr_mouse(data, i) {
                 // Adjust column for sidebar offset (absolute → pane-relative)
                 mouse.col = mouse.col.saturating_sub(col_offset);
+                mouse.row = mouse.row.saturating_sub(self.row_offset);
                 // Motion events (button 35 = motion with no button pressed)
                 if mouse.button == 35 {
                     i += consumed;
@@ -566,14 +750,16 @@ impl InputState {
                         // Scroll wheel up — clear selection
                         self.selection = None;
                         self.drag_start = None;
-                        self.scroll_offset = (self.scroll_offset + 3).min(max_scrollback);
+                        self.scroll_offset =
+                            (self.scroll_offset + self.scroll.wheel_lines).min(max_scrollback);
                         actions.push(InputAction::Redraw);
                     }
                     65 => {
                         // Scroll wheel down — clear selection
                         self.selection = None;
                         self.drag_start = None;
-                        self.scroll_offset = self.scroll_offset.saturating_sub(3);
+                        self.scroll_offset =
+                            self.scroll_offset.saturating_sub(self.scroll.wheel_lines);
                         actions.push(InputAction::Redraw);
                     }
                     // Left click on scrollbar column (SGR coords are 1-indexed)
@@ -626,50 +812,134 @@ impl InputState {
                 continue;
             }
 
+            // Gate 2a: typing a search query in COMMAND mode
+            if let Some(ref mut input) = self.search_input {
+                match b {
+                    // Enter — submit the query and jump to the first match
+                    b'\r' | b'\n' => {
+                        let query = std::mem::take(input);
+                        self.search_input = None;
+                        self.search_match = None;
+                        if query.is_empty() {
+                            self.search_query = None;
+                        } else {
+                            self.search_query = Some(query);
+                            actions.push(InputAction::Search(true));
+                        }
+                    }
+                    // Backspace
+                    0x7f | 0x08 => {
+                        input.pop();
+                    }
+                    // Bare ESC cancels the query (stays in COMMAND mode)
+                    0x1b if i + 1 >= data.len() || data[i + 1] != b'[' => {
+                        self.search_input = None;
+                    }
+                    // Ignore other escape sequences (arrow keys etc.)
+                    0x1b => {
+                        i += 2;
+                        while i < data.len() && data[i] < 0x40 {
+                            i += 1;
+                        }
+                        i += 1;
+                        continue;
+                    }
+                    0x20.. => {
+                        // Take the whole UTF-8 character starting here
+                        let end = (i + 1..=data.len())
+                            .find(|&e| std::str::from_utf8(&data[i..e]).is_ok())
+                            .unwrap_or(i + 1);
+                        if let Ok(s) = std::str::from_utf8(&data[i..end]) {
+                            input.push_str(s);
+                        }
+                        i = end;
+                        actions.push(InputAction::Redraw);
+                        continue;
+                    }
+                    _ => {}
+                }
+                actions.push(InputAction::Redraw);
+                i += 1;
+                continue;
+            }
+
             // Gate 2: COMMAND mode — intercept keys directly
             if self.command_mode {
                 // Bare ESC (not part of a CSI sequence) exits COMMAND mode
                 if b == 0x1b && (i + 1 >= data.len() || data[i + 1] != b'[') {
                     self.command_mode = false;
                     self.scroll_offset = 0;
+                    self.clear_search();
                     actions.push(InputAction::Redraw);
                     i += 1;
                     continue;
                 }
-                // Ctrl+Q — detach
-                if b == 0x11 {
+                // Detach (default Ctrl+Q)
+                if b == self.keys.detach {
                     actions.push(InputAction::Detach);
                     return actions;
                 }
-                // 'a' or Ctrl+A — refresh session sidebar
-                if b == b'a' || b == 0x01 {
+                // '/' — start typing a search query
+                if b == b'/' {
+                    self.search_input = Some(String::new());
+                    actions.push(InputAction::Redraw);
+                    i += 1;
+                    continue;
+                }
+                // 'n' / 'N' — next older / newer match while a search is active
+                if self.search_query.is_some() && (b == b'n' || b == b'N') {
+                    actions.push(InputAction::Search(b == b'n'));
+                    i += 1;
+                    continue;
+                }
+                // Refresh session sidebar (default 'a')
+                if b == self.keys.sidebar {
                     self.command_mode = false;
+                    self.clear_search();
                     actions.push(InputAction::FocusSidebar);
                     i += 1;
                     continue;
                 }
-                // 'n' — create new session in same workspace
-                if b == b'n' {
+                // Create new session in same workspace (default 'n')
+                if b == self.keys.new {
                     self.command_mode = false;
+                    self.clear_search();
                     actions.push(InputAction::NewSession);
                     i += 1;
                     continue;
                 }
-                // Ctrl+X — kill
-                if b == 0x18 {
+                // Split the content area / close the split (default '-')
+                if b == self.keys.split {
+                    self.command_mode = false;
+                    self.clear_search();
+                    actions.push(InputAction::ToggleSplit);
+                    i += 1;
+                    continue;
+                }
+                // Focus the other pane (default 'o'); scroll and selection
+                // belong to the pane that had focus
+                if b == self.keys.pane {
+                    self.command_mode = false;
+                    self.reset_view();
+                    actions.push(InputAction::SwitchPane);
+                    i += 1;
+                    continue;
+                }
+                // Kill (default Ctrl+X)
+                if b == self.keys.kill {
                     actions.push(InputAction::Kill);
                     i += 1;
                     continue;
                 }
-                // Ctrl+P — scroll up 1 line
-                if b == 0x10 {
+                // Scroll up 1 line (default Ctrl+P)
+                if b == self.keys.scroll_up {
                     self.scroll_offset = (self.scroll_offset + 1).min(max_scrollback);
                     actions.push(InputAction::Redraw);
                     i += 1;
                     continue;
                 }
-                // Ctrl+N — scroll down 1 line
-                if b == 0x0E {
+                // Scroll down 1 line (default Ctrl+N)
+                if b == self.keys.scroll_down {
                     self.scroll_offset = self.scroll_offset.saturating_sub(1);
                     actions.push(InputAction::Redraw);
                     i += 1;
@@ -677,7 +947,7 @@ impl InputState {
                 }
                 // Ctrl+U — half page up
                 if b == 0x15 {
-                    let half = (current_inner_rows / 2) as usize;
+                    let half = self.scroll.page_lines(current_inner_rows);
                     self.scroll_offset = (self.scroll_offset + half).min(max_scrollback);
                     actions.push(InputAction::Redraw);
                     i += 1;
@@ -685,7 +955,7 @@ impl InputState {
                 }
                 // Ctrl+D — half page down
                 if b == 0x04 {
-                    let half = (current_inner_rows / 2) as usize;
+                    let half = self.scroll.page_lines(current_inner_rows);
                     self.scroll_offset = self.scroll_offset.saturating_sub(half);
                     actions.push(InputAction::Redraw);
                     i += 1;
@@ -710,7 +980,7 @@ impl InputState {
                         }
                         b'5' if i + 3 < data.len() && data[i + 3] == b'~' => {
                             // PgUp
-                            let half = (current_inner_rows / 2) as usize;
+                            let half = self.scroll.page_lines(current_inner_rows);
                             self.scroll_offset = (self.scroll_offset + half).min(max_scrollback);
                             actions.push(InputAction::Redraw);
                             i += 4;
@@ -718,7 +988,7 @@ impl InputState {
                         }
                         b'6' if i + 3 < data.len() && data[i + 3] == b'~' => {
                             // PgDn
-                            let half = (current_inner_rows / 2) as usize;
+                            let half = self.scroll.page_lines(current_inner_rows);
                             self.scroll_offset = self.scroll_offset.saturating_sub(half);
                             actions.push(InputAction::Redraw);
                             i += 4;
@@ -803,6 +1073,57 @@ fn tty_write(tty_fd: i32, data: &[u8]) {
     }
 }
 
+const TOO_SMALL_NOTICE: &str = "Terminal too small";
+
+/// Escape sequence that clears the screen and centres the too-small notice,
+/// truncated to the terminal width.
+fn too_small_notice(cols: u16, rows: u16) -> Vec<u8> {
+    let text: String = TOO_SMALL_NOTICE.chars().take(cols as usize).collect();
+    let row = rows / 2 + 1;
+    let col = (cols as usize).saturating_sub(text.len()) / 2 + 1;
+    format!("\x1b[2J\x1b[{};{}H{}", row, col, text).into_bytes()
+}
+
+/// Wait until the terminal has at least one column and more than
+/// `reserved_rows` rows, showing a centred notice in the meantime (e.g. while
+/// a tiling window manager settles its layout). The terminal must already be
+/// in raw mode. Only gives up if the user detaches while it is too small.
+pub fn wait_for_usable_size(
+    tty_fd: i32,
+    reserved_rows: u16,
+    input: &mut InputState,
+) -> Result<(u16, u16)> {
+    let mut shown = None;
+    loop {
+        let (cols, rows) = get_term_size(tty_fd)?;
+        if cols > 0 && rows > reserved_rows {
+            return Ok((cols, rows));
+        }
+        if shown != Some((cols, rows)) {
+            tty_write(tty_fd, &too_small_notice(cols, rows));
+            shown = Some((cols, rows));
+        }
+
+        let mut pfd = libc::pollfd {
+            fd: tty_fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut pfd, 1, 100) } <= 0 {
+            continue;
+        }
+        let mut buf = [0u8; 256];
+        let n = unsafe { libc::read(tty_fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n <= 0 {
+            continue;
+        }
+        let actions = input.process(&buf[..n as usize], 1, cols.max(1), 0, 0);
+        if actions.iter().any(|a| matches!(a, InputAction::Detach)) {
+            anyhow::bail!("Terminal too small");
+        }
+    }
+}
+
 /// Begin DEC synchronized update mode (DECSM 2026).
 /// Tells the terminal to buffer all output until `end_sync_update` is called,
 /// then render everything in a single atomic frame.
@@ -826,6 +1147,37 @@ pub fn set_mouse_tracking(tty_fd: i32, enable: bool) {
     tty_write(tty_fd, seq);
 }
 
+/// Reads at least this long count as a paste even without a line break;
+/// typing never delivers this many bytes in one read.
+const PASTE_MIN_LEN: usize = 16;
+
+/// Whether forwarded input looks pasted rather than typed: a burst of bytes
+/// from one read with no escape sequence that is either long or spans lines.
+/// A lone trailing Enter (`ls\r` typed quickly) doesn't count.
+pub fn looks_like_paste(data: &[u8]) -> bool {
+    if data.len() < 2 || data.contains(&0x1b) {
+        return false;
+    }
+    data.len() >= PASTE_MIN_LEN
+        || data[..data.len() - 1]
+            .iter()
+            .any(|&b| b == b'\r' || b == b'\n')
+}
+
+/// Wrap input that looks pasted in `ESC[200~`/`ESC[201~` when the program
+/// has turned on bracketed paste mode, so a multi-line paste is inserted as
+/// text instead of running each line.
+pub fn bracket_paste(screen: &vt100::Screen, data: Vec<u8>) -> Vec<u8> {
+    if !screen.bracketed_paste() || !looks_like_paste(&data) {
+        return data;
+    }
+    let mut wrapped = Vec::with_capacity(data.len() + 12);
+    wrapped.extend_from_slice(b"\x1b[200~");
+    wrapped.extend_from_slice(&data);
+    wrapped.extend_from_slice(b"\x1b[201~");
+    wrapped
+}
+
 /// Write text to the system clipboard via the OSC 52 escape sequence.
 /// The terminal emulator intercepts this and sets the clipboard contents.
 pub fn write_osc52_clipboard(tty_fd: i32, text: &str) {
@@ -859,17 +1211,30 @@ pub fn write_osc52_clipboard(tty_fd: i32, text: &str) {
 }
 
 /// Extract the text covered by `selection` from a vt100 screen.
-/// Trims trailing whitespace per line and joins with newlines.
+/// Trims trailing whitespace per line and joins with newlines. A wide
+/// character is emitted once, even when the selection covers only one of
+/// its two cells.
 pub fn extract_selection_text(screen: &vt100::Screen, selection: &Selection) -> String {
     let (top_row, top_col, bot_row, bot_col) = selection.ordered();
     let cols = screen.size().1;
     let mut lines: Vec<String> = Vec::new();
     for row in top_row..=bot_row {
-        let col_start = if row == top_row { top_col } else { 0 };
+        let mut col_start = if row == top_row { top_col } else { 0 };
         let col_end = if row == bot_row { bot_col } else { cols - 1 };
+        // Starting on the right half of a wide character: include all of it
+        if col_start > 0
+            && screen
+                .cell(row, col_start)
+                .is_some_and(|c| c.is_wide_continuation())
+        {
+            col_start -= 1;
+        }
         let mut line = String::new();
         for col in col_start..=col_end {
             if let Some(cell) = screen.cell(row, col) {
+                if cell.is_wide_continuation() {
+                    continue;
+                }
                 let contents = cell.contents();
                 if contents.is_empty() {
                     line.push(' ');
@@ -909,3 +1274,252 @@ pub fn install_panic_hook() {
         default_hook(info);
     }));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_find_match_older_and_newer() {
+        let lines = lines(&["error one", "ok", "an error, another error", "ok"]);
+        let m = find_match(&lines, "error", (4, 0), true).unwrap();
+        assert_eq!(
+            m,
+            SearchMatch {
+                line: 2,
+                col: 18,
+                len: 5
+            }
+        );
+        let m = find_match(&lines, "error", (m.line, m.col), true).unwrap();
+        assert_eq!((m.line, m.col), (2, 3));
+        let m = find_match(&lines, "error", (m.line, m.col), true).unwrap();
+        assert_eq!((m.line, m.col), (0, 0));
+        assert!(find_match(&lines, "error", (0, 0), true).is_none());
+        let m = find_match(&lines, "error", (0, 0), false).unwrap();
+        assert_eq!((m.line, m.col), (2, 3));
+        assert!(find_match(&lines, "missing", (4, 0), true).is_none());
+    }
+
+    #[test]
+    fn test_find_match_counts_cells_not_bytes() {
+        let lines = lines(&["héllo wörld"]);
+        let m = find_match(&lines, "wörld", (1, 0), true).unwrap();
+        assert_eq!(
+            m,
+            SearchMatch {
+                line: 0,
+                col: 6,
+                len: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_search_input_and_step() {
+        let mut state = InputState::new(0x10, CommandKeys::default(), ScrollSettings::default());
+        state.process(b"\x10/err", 10, 80, 0, 0);
+        assert_eq!(state.search_prompt().as_deref(), Some("/err"));
+        let actions = state.process(b"\x7fr\r", 10, 80, 0, 0);
+        assert!(actions
+            .iter()
+            .any(|a| matches!(a, InputAction::Search(true))));
+
+        // 30 scrollback lines + 10 rows; the only match is at line 5.
+        let mut text: Vec<String> = (0..40).map(|i| format!("line {}", i)).collect();
+        text[5] = "an err here".to_string();
+        state.search_step(&text, 30, 10, true);
+        assert_eq!(state.search_match.map(|m| m.line), Some(5));
+        // Scrolled so the match is roughly centred: top = 0 → offset 30.
+        assert_eq!(state.scroll_offset, 30);
+        let sel = state.search_selection(30, 10).unwrap();
+        assert_eq!(sel.ordered(), (5, 3, 5, 5));
+    }
+
+    #[test]
+    fn test_scroll_status_text() {
+        assert_eq!(scroll_status_text(120, 3400), "-- SCROLL 120/3400 --");
+    }
+
+    #[test]
+    fn test_n_without_search_creates_session() {
+        let mut state = InputState::new(0x10, CommandKeys::default(), ScrollSettings::default());
+        let actions = state.process(b"\x10n", 10, 80, 0, 0);
+        assert!(actions.iter().any(|a| matches!(a, InputAction::NewSession)));
+    }
+
+    #[test]
+    fn test_remapped_detach_key() {
+        let keys = CommandKeys {
+            detach: 0x02,
+            ..CommandKeys::default()
+        };
+        let mut state = InputState::new(0x10, keys, ScrollSettings::default());
+        let actions = state.process(b"\x10\x11", 10, 80, 0, 0);
+        assert!(!actions.iter().any(|a| matches!(a, InputAction::Detach)));
+        let actions = state.process(b"\x02", 10, 80, 0, 0);
+        assert!(actions.iter().any(|a| matches!(a, InputAction::Detach)));
+    }
+
+    #[test]
+    fn test_remapped_new_and_scroll_keys() {
+        let keys = CommandKeys {
+            new: b'c',
+            scroll_up: b'k',
+            scroll_down: b'j',
+            ..CommandKeys::default()
+        };
+        let mut state = InputState::new(0x10, keys, ScrollSettings::default());
+        state.process(b"\x10kkkj", 10, 80, 50, 0);
+        assert_eq!(state.scroll_offset, 2);
+        let actions = state.process(b"n", 10, 80, 50, 0);
+        assert!(!actions.iter().any(|a| matches!(a, InputAction::NewSession)));
+        let actions = state.process(b"c", 10, 80, 50, 0);
+        assert!(actions.iter().any(|a| matches!(a, InputAction::NewSession)));
+    }
+
+    #[test]
+    fn test_looks_like_paste() {
+        assert!(looks_like_paste(b"echo a\necho b\n"));
+        assert!(looks_like_paste(b"a single long line of text"));
+        assert!(!looks_like_paste(b"x"));
+        assert!(!looks_like_paste(b"ls\r"));
+        assert!(!looks_like_paste(b"\x1b[A\x1b[A\x1b[A\x1b[A\x1b[A"));
+    }
+
+    #[test]
+    fn test_bracket_paste_follows_program_mode() {
+        let mut parser = vt100::Parser::new(24, 80, 0);
+        let paste = b"rm a\nrm b\n".to_vec();
+        assert_eq!(bracket_paste(parser.screen(), paste.clone()), paste);
+        parser.process(b"\x1b[?2004h");
+        assert_eq!(
+            bracket_paste(parser.screen(), paste),
+            b"\x1b[200~rm a\nrm b\n\x1b[201~".to_vec()
+        );
+        assert_eq!(bracket_paste(parser.screen(), b"ls\r".to_vec()), b"ls\r");
+    }
+
+    #[test]
+    fn test_remapped_sidebar_key() {
+        let keys = CommandKeys {
+            sidebar: b's',
+            ..CommandKeys::default()
+        };
+        let mut state = InputState::new(0x10, keys, ScrollSettings::default());
+        for unbound in [&b"\x10a"[..], &b"\x10\x01"[..]] {
+            let actions = state.process(unbound, 10, 80, 0, 0);
+            assert!(!actions
+                .iter()
+                .any(|a| matches!(a, InputAction::FocusSidebar)));
+            state.process(b"\x1b", 10, 80, 0, 0);
+        }
+        let actions = state.process(b"\x10s", 10, 80, 0, 0);
+        assert!(actions
+            .iter()
+            .any(|a| matches!(a, InputAction::FocusSidebar)));
+    }
+
+    #[test]
+    fn test_split_and_pane_keys() {
+        let mut state = InputState::new(0x10, CommandKeys::default(), ScrollSettings::default());
+        let actions = state.process(b"\x10-", 10, 80, 0, 0);
+        assert!(actions
+            .iter()
+            .any(|a| matches!(a, InputAction::ToggleSplit)));
+        assert!(!state.command_mode);
+
+        state.process(b"\x10\x10\x10", 10, 80, 50, 0);
+        assert_eq!(state.scroll_offset, 2);
+        let actions = state.process(b"o", 10, 80, 50, 0);
+        assert!(actions.iter().any(|a| matches!(a, InputAction::SwitchPane)));
+        assert_eq!(state.scroll_offset, 0);
+
+        // Outside COMMAND mode the keys are plain input
+        let actions = state.process(b"o", 10, 80, 50, 0);
+        assert!(matches!(&actions[..], [InputAction::Forward(b)] if b == b"o"));
+    }
+
+    #[test]
+    fn test_mouse_press_row() {
+        assert_eq!(mouse_press_row(b"\x1b[<0;5;8M"), Some(8));
+        assert_eq!(mouse_press_row(b"\x1b[<64;5;3M"), Some(3));
+        assert_eq!(mouse_press_row(b"\x1b[<0;5;8m"), None);
+        assert_eq!(mouse_press_row(b"\x1b[<32;5;8M"), None);
+        assert_eq!(mouse_press_row(b"ls"), None);
+    }
+
+    #[test]
+    fn test_mouse_row_offset() {
+        let mut state = InputState::new(0x10, CommandKeys::default(), ScrollSettings::default());
+        state.row_offset = 6;
+        state.process(b"\x1b[<0;5;8M", 10, 80, 0, 0);
+        assert_eq!(state.drag_start, Some((1, 4)));
+    }
+
+    #[test]
+    fn test_scroll_settings_steps() {
+        let scroll = ScrollSettings {
+            wheel_lines: 5,
+            page_fraction: 0.25,
+        };
+        let mut state = InputState::new(0x10, CommandKeys::default(), scroll);
+        // SGR mouse wheel up at column 10, row 5
+        state.process(b"\x1b[<64;10;5M", 20, 80, 100, 0);
+        assert_eq!(state.scroll_offset, 5);
+        // Ctrl+U in COMMAND mode moves a quarter of 20 rows
+        state.process(b"\x10\x15", 20, 80, 100, 0);
+        assert_eq!(state.scroll_offset, 10);
+    }
+
+    #[test]
+    fn test_extract_selection_wide_chars() {
+        let mut parser = vt100::Parser::new(2, 12, 0);
+        parser.process("日本語abc".as_bytes());
+        let select = |start_col, end_col| Selection {
+            start_row: 0,
+            start_col,
+            end_row: 0,
+            end_col,
+        };
+        assert_eq!(
+            extract_selection_text(parser.screen(), &select(0, 11)),
+            "日本語abc"
+        );
+        // Boundaries in the middle of 日 and 語 include the whole characters
+        assert_eq!(
+            extract_selection_text(parser.screen(), &select(1, 4)),
+            "日本語"
+        );
+        assert_eq!(
+            extract_selection_text(parser.screen(), &select(3, 6)),
+            "本語a"
+        );
+    }
+
+    #[test]
+    fn test_esc_clears_search() {
+        let mut state = InputState::new(0x10, CommandKeys::default(), ScrollSettings::default());
+        state.process(b"\x10/x\r", 10, 80, 0, 0);
+        assert!(state.search_prompt().is_some());
+        state.flush_pending(10, 80, 0, 0);
+        state.process(b"\x1b", 10, 80, 0, 0);
+        state.flush_pending(10, 80, 0, 0);
+        assert!(!state.command_mode);
+        assert!(state.search_prompt().is_none());
+    }
+
+    #[test]
+    fn test_too_small_notice() {
+        assert_eq!(
+            too_small_notice(40, 0),
+            b"\x1b[2J\x1b[1;12HTerminal too small".to_vec()
+        );
+        assert_eq!(too_small_notice(8, 3), b"\x1b[2J\x1b[2;1HTerminal".to_vec());
+        assert_eq!(too_small_notice(0, 10), b"\x1b[2J\x1b[6;1H".to_vec());
+    }
+}
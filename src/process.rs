@@ -0,0 +1,178 @@
+use anyhow::{bail, Result};
+use std::process::Command;
+
+/// `ps` output columns shared by local listing and `docker top`.
+pub const PS_FIELDS: &str = "pid,ppid,user,pcpu,pmem,args";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    pub user: String,
+    pub cpu: f32,
+    pub mem: f32,
+    pub command: String,
+}
+
+/// Parse `ps -o pid,ppid,user,pcpu,pmem,args` style output. A header line
+/// (as printed by `docker top`) and malformed lines are skipped.
+pub fn parse_ps_output(output: &str) -> Vec<ProcessInfo> {
+    output.lines().filter_map(parse_ps_line).collect()
+}
+
+fn parse_ps_line(line: &str) -> Option<ProcessInfo> {
+    let mut rest = line.trim_start();
+    let mut fields = Vec::with_capacity(5);
+    for _ in 0..5 {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Some(ProcessInfo {
+        pid: fields[0].parse().ok()?,
+        ppid: fields[1].parse().ok()?,
+        user: fields[2].to_string(),
+        cpu: fields[3].parse().ok()?,
+        mem: fields[4].parse().ok()?,
+        command: rest.to_string(),
+    })
+}
+
+/// List `root` and all of its descendant processes on the host.
+pub fn local_process_tree(root: u32) -> Result<Vec<ProcessInfo>> {
+    let output = Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,user=,pcpu=,pmem=,args="])
+        .output()?;
+    if !output.status.success() {
+        bail!("ps failed");
+    }
+    let all = parse_ps_output(&String::from_utf8_lossy(&output.stdout));
+    Ok(descendants(&all, root))
+}
+
+/// Select `root` and its descendants from a flat process list.
+fn descendants(all: &[ProcessInfo], root: u32) -> Vec<ProcessInfo> {
+    let mut pids = vec![root];
+    let mut i = 0;
+    while i < pids.len() {
+        let parent = pids[i];
+        pids.extend(all.iter().filter(|p| p.ppid == parent).map(|p| p.pid));
+        i += 1;
+    }
+    all.iter()
+        .filter(|p| pids.contains(&p.pid))
+        .cloned()
+        .collect()
+}
+
+/// Sort processes by `cpu` or `mem` (descending) or `pid` (ascending).
+pub fn sort_processes(procs: &mut [ProcessInfo], sort_by: &str) {
+    match sort_by {
+        "cpu" => procs.sort_by(|a, b| b.cpu.total_cmp(&a.cpu)),
+        "mem" => procs.sort_by(|a, b| b.mem.total_cmp(&a.mem)),
+        _ => procs.sort_by_key(|p| p.pid),
+    }
+}
+
+/// Format processes as table lines (header first).
+pub fn format_table(procs: &[ProcessInfo]) -> Vec<String> {
+    let user_w = procs
+        .iter()
+        .map(|p| p.user.len())
+        .max()
+        .unwrap_or(0)
+        .max("USER".len());
+    let mut lines = vec![format!(
+        "{:>7}  {:>7}  {:<user_w$}  {:>5}  {:>5}  COMMAND",
+        "PID",
+        "PPID",
+        "USER",
+        "CPU%",
+        "MEM%",
+        user_w = user_w
+    )];
+    for p in procs {
+        lines.push(format!(
+            "{:>7}  {:>7}  {:<user_w$}  {:>5.1}  {:>5.1}  {}",
+            p.pid,
+            p.ppid,
+            p.user,
+            p.cpu,
+            p.mem,
+            p.command,
+            user_w = user_w
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proc(pid: u32, ppid: u32, cpu: f32, mem: f32) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            ppid,
+            user: "root".to_string(),
+            cpu,
+            mem,
+            command: format!("cmd{}", pid),
+        }
+    }
+
+    #[test]
+    fn test_parse_ps_output_skips_header() {
+        let out =
+            "PID PPID USER %CPU %MEM COMMAND\n  12   1 root  0.5  1.2 npm run dev --port 3000\n";
+        let procs = parse_ps_output(out);
+        assert_eq!(procs.len(), 1);
+        assert_eq!(procs[0].pid, 12);
+        assert_eq!(procs[0].ppid, 1);
+        assert_eq!(procs[0].user, "root");
+        assert_eq!(procs[0].cpu, 0.5);
+        assert_eq!(procs[0].mem, 1.2);
+        assert_eq!(procs[0].command, "npm run dev --port 3000");
+    }
+
+    #[test]
+    fn test_parse_ps_output_skips_malformed() {
+        assert!(parse_ps_output("garbage\n1 2 3\n").is_empty());
+    }
+
+    #[test]
+    fn test_descendants() {
+        let all = vec![
+            proc(1, 0, 0.0, 0.0),
+            proc(10, 1, 0.0, 0.0),
+            proc(11, 10, 0.0, 0.0),
+            proc(12, 11, 0.0, 0.0),
+            proc(20, 1, 0.0, 0.0),
+        ];
+        let tree: Vec<u32> = descendants(&all, 10).iter().map(|p| p.pid).collect();
+        assert_eq!(tree, vec![10, 11, 12]);
+    }
+
+    #[test]
+    fn test_sort_processes() {
+        let mut procs = vec![
+            proc(3, 1, 5.0, 1.0),
+            proc(1, 0, 1.0, 9.0),
+            proc(2, 1, 9.0, 5.0),
+        ];
+        sort_processes(&mut procs, "cpu");
+        assert_eq!(procs.iter().map(|p| p.pid).collect::<Vec<_>>(), [2, 3, 1]);
+        sort_processes(&mut procs, "mem");
+        assert_eq!(procs.iter().map(|p| p.pid).collect::<Vec<_>>(), [1, 2, 3]);
+        sort_processes(&mut procs, "pid");
+        assert_eq!(procs.iter().map(|p| p.pid).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_format_table() {
+        let lines = format_table(&[proc(42, 1, 12.5, 3.0)]);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("    PID     PPID  USER   CPU%   MEM%  COMMAND"));
+        assert!(lines[1].contains("   42        1  root   12.5    3.0  cmd42"));
+    }
+}
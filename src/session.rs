@@ -1,13 +1,13 @@
 use anyhow::{bail, Context, Result};
-use chrono::{Local, NaiveDateTime, Utc};
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
 use std::fs;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::config;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Session {
     pub name: String,
     pub project_dir: String,
@@ -17,6 +17,72 @@ pub struct Session {
     pub env: Vec<String>,
     pub local: bool,
     pub strategy: String,
+    pub notify_on_exit: bool,
+    /// Docker session whose container volumes are shared via `--volumes-from`.
+    pub volumes_from: Option<String>,
+    /// Host Docker socket mounted into the container at `/var/run/docker.sock`.
+    pub docker_socket_mount: Option<String>,
+    /// Global git identity settings were copied into the workspace's git config.
+    pub inherit_git_config: bool,
+    /// Docker storage driver the session was created for (the daemon's driver).
+    pub storage_driver: Option<String>,
+    /// `--storage-opt` entries passed to `docker run`.
+    pub storage_opts: Vec<String>,
+    /// `HOST:CONTAINER` port mappings published with `docker run -p`.
+    pub ports: Vec<String>,
+    /// Container resource limits given at creation.
+    pub resources: Resources,
+    /// Directory the command starts in, relative to the workspace root.
+    pub workdir: Option<String>,
+    /// Git ref the workspace was checked out at (`box create --from`).
+    pub from_ref: Option<String>,
+    /// Extra `docker run` flags given at creation, reapplied on recreation.
+    pub docker_args: Option<String>,
+}
+
+/// Docker resource limits, stored as JSON in the session's `resources` file.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Resources {
+    /// `--memory` value, e.g. `512m` or `2g`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    /// `--cpus` value, e.g. `1.5`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<String>,
+    /// `--pids-limit` value; -1 means unlimited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pids_limit: Option<i64>,
+}
+
+impl Resources {
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_none() && self.cpus.is_none() && self.pids_limit.is_none()
+    }
+
+    /// The `docker run` flags that apply these limits.
+    pub fn docker_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(ref memory) = self.memory {
+            args.extend(["--memory".to_string(), memory.clone()]);
+        }
+        if let Some(ref cpus) = self.cpus {
+            args.extend(["--cpus".to_string(), cpus.clone()]);
+        }
+        if let Some(pids) = self.pids_limit {
+            args.extend(["--pids-limit".to_string(), pids.to_string()]);
+        }
+        args
+    }
+}
+
+impl Session {
+    /// The directory the session's command starts in on the host.
+    pub fn working_dir(&self, workspace: &std::path::Path) -> PathBuf {
+        match self.workdir {
+            Some(ref dir) => workspace.join(dir),
+            None => workspace.to_path_buf(),
+        }
+    }
 }
 
 impl From<config::BoxConfig> for Session {
@@ -30,21 +96,80 @@ impl From<config::BoxConfig> for Session {
             env: cfg.env,
             local: cfg.local,
             strategy: cfg.strategy,
+            ..Default::default()
         }
     }
 }
 
-#[derive(Clone)]
-#[allow(dead_code)]
+#[derive(Clone, Default, serde::Serialize)]
 pub struct SessionSummary {
     pub name: String,
     pub project_dir: String,
     pub image: String,
     pub command: String,
+    /// Creation time for display, in the local time zone.
+    #[serde(skip)]
     pub created_at: String,
+    /// Parsed `created_at`, when the stored timestamp is in the UTC format.
+    /// Serialized as `created_at` in RFC 3339.
+    #[serde(rename = "created_at", serialize_with = "serialize_rfc3339")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub resumed_at: Option<String>,
+    /// Parsed `resumed_at`, when the stored timestamp is in the UTC format.
+    /// Serialized as `resumed_at` in RFC 3339.
+    #[serde(rename = "resumed_at", serialize_with = "serialize_rfc3339")]
+    pub resumed: Option<DateTime<Utc>>,
     pub running: bool,
     pub local: bool,
     pub strategy: String,
+    pub docker_args: Option<String>,
+    /// `$USER` that created the session; unknown for older sessions.
+    pub created_by: Option<String>,
+    /// Host the session was created on; unknown for older sessions.
+    pub created_host: Option<String>,
+}
+
+fn serialize_rfc3339<S: serde::Serializer>(
+    time: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serializer.serialize_str(&time.to_rfc3339()),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl SessionSummary {
+    /// Time elapsed since the session was created, if its creation time is known.
+    pub fn age(&self, now: DateTime<Utc>) -> Option<std::time::Duration> {
+        self.created.and_then(|c| (now - c).to_std().ok())
+    }
+
+    /// Whether `user` created the session. Sessions that predate the
+    /// recorded creator count as everyone's.
+    pub fn created_by_user(&self, user: &str) -> bool {
+        self.created_by.as_deref().is_none_or(|u| u == user)
+    }
+}
+
+/// The current user's name from `$USER` (or `$LOGNAME`).
+pub fn current_user() -> Option<String> {
+    ["USER", "LOGNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+}
+
+/// This machine's hostname.
+pub fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    let rc = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if rc != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let name = String::from_utf8_lossy(&buf[..len]).to_string();
+    (!name.is_empty()).then_some(name)
 }
 
 pub fn sessions_dir() -> Result<PathBuf> {
@@ -54,6 +179,14 @@ pub fn sessions_dir() -> Result<PathBuf> {
     Ok(dir)
 }
 
+/// Directory holding archived sessions, laid out like `sessions_dir()`.
+pub fn archive_dir() -> Result<PathBuf> {
+    let dir = PathBuf::from(config::home_dir()?)
+        .join(".box")
+        .join("archive");
+    Ok(dir)
+}
+
 const RESERVED_NAMES: &[&str] = &[
     "create", "resume", "remove", "stop", "exec", "upgrade", "path", "config", "list", "ls",
 ];
@@ -74,6 +207,35 @@ pub fn full_name(input: &str) -> String {
     format!("{}/{}", ws, sess)
 }
 
+/// Resolve a user-supplied name to an existing session's full name. An exact
+/// match wins; otherwise the name may be a prefix of exactly one session's
+/// full name (e.g. `my-long` for `my-long-feature/server`).
+pub fn resolve_prefix(input: &str) -> Result<String> {
+    let full = full_name(input);
+    if session_exists(&full)? {
+        return Ok(full);
+    }
+    let names: Vec<String> = list()?.into_iter().map(|s| s.name).collect();
+    pick_prefix(input, &names)
+}
+
+fn pick_prefix(input: &str, names: &[String]) -> Result<String> {
+    let candidates: Vec<&String> = names.iter().filter(|n| n.starts_with(input)).collect();
+    match candidates.as_slice() {
+        [only] => Ok(only.to_string()),
+        [] => bail!("Session '{}' not found.", full_name(input)),
+        _ => bail!(
+            "'{}' matches several sessions: {}",
+            input,
+            candidates
+                .iter()
+                .map(|c| c.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
 /// Return the workspace part of a full session name.
 pub fn workspace_name(name: &str) -> &str {
     parse_name(name).0
@@ -121,6 +283,31 @@ pub fn validate_name(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Validate a `--workdir` path and normalize it (dropping `.` components and
+/// trailing slashes). It must be relative and must not climb out of the
+/// workspace with `..`.
+pub fn validate_workdir(path: &str) -> Result<String> {
+    use std::path::Component;
+    let mut parts = Vec::new();
+    for component in std::path::Path::new(path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                bail!("Working directory '{}' must not contain '..'.", path)
+            }
+            Component::RootDir | Component::Prefix(_) => bail!(
+                "Working directory '{}' must be relative to the workspace root.",
+                path
+            ),
+        }
+    }
+    if parts.is_empty() {
+        bail!("Working directory '{}' does not name a subdirectory.", path);
+    }
+    Ok(parts.join("/"))
+}
+
 pub fn session_exists(name: &str) -> Result<bool> {
     let full = full_name(name);
     Ok(sessions_dir()?.join(&full).is_dir())
@@ -140,7 +327,23 @@ pub fn workspace_exists(workspace: &str) -> Result<bool> {
 
 /// List all session names within a workspace (e.g. ["default", "server"]).
 pub fn workspace_sessions(workspace: &str) -> Result<Vec<String>> {
-    let ws_dir = sessions_dir()?.join(workspace);
+    workspace_sessions_in(&sessions_dir()?, workspace)
+}
+
+/// List the archived session names within a workspace.
+pub fn archived_workspace_sessions(workspace: &str) -> Result<Vec<String>> {
+    workspace_sessions_in(&archive_dir()?, workspace)
+}
+
+/// Whether any session, live or archived, still uses a workspace, so its
+/// directory must be kept.
+pub fn workspace_in_use(workspace: &str) -> Result<bool> {
+    Ok(!workspace_sessions(workspace)?.is_empty()
+        || !archived_workspace_sessions(workspace)?.is_empty())
+}
+
+fn workspace_sessions_in(dir: &Path, workspace: &str) -> Result<Vec<String>> {
+    let ws_dir = dir.join(workspace);
     if !ws_dir.is_dir() {
         return Ok(Vec::new());
     }
@@ -164,7 +367,7 @@ pub fn resolve_original_project_dir(project_dir: &str) -> String {
         Ok(h) => h,
         Err(_) => return project_dir.to_string(),
     };
-    let workspaces_dir = PathBuf::from(&home).join(".box").join("workspaces");
+    let workspaces_dir = config::workspaces_dir_in(&home);
     let workspaces_dir = match fs::canonicalize(&workspaces_dir) {
         Ok(p) => p,
         Err(_) => return project_dir.to_string(),
@@ -227,10 +430,19 @@ pub fn save(session: &Session) -> Result<()> {
         dir.join("mode"),
         if session.local { "local" } else { "docker" },
     )?;
-    fs::write(
-        dir.join("created_at"),
-        Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
-    )?;
+    // Keep the original creation time and creator when re-saving edited metadata
+    if !dir.join("created_at").exists() {
+        fs::write(
+            dir.join("created_at"),
+            Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        )?;
+        if let Some(user) = current_user() {
+            fs::write(dir.join("created_by"), user)?;
+        }
+        if let Some(host) = hostname() {
+            fs::write(dir.join("created_host"), host)?;
+        }
+    }
     if !session.command.is_empty() {
         let content: Vec<&str> = session.command.iter().map(|s| s.as_str()).collect();
         fs::write(dir.join("command"), content.join("\0"))?;
@@ -244,6 +456,64 @@ pub fn save(session: &Session) -> Result<()> {
         let _ = fs::remove_file(dir.join("env"));
     }
     fs::write(dir.join("strategy"), &session.strategy)?;
+    if session.notify_on_exit {
+        fs::write(dir.join("notify_on_exit"), "true")?;
+    } else {
+        let _ = fs::remove_file(dir.join("notify_on_exit"));
+    }
+    if let Some(ref source) = session.volumes_from {
+        fs::write(dir.join("volumes_from"), source)?;
+    } else {
+        let _ = fs::remove_file(dir.join("volumes_from"));
+    }
+    if let Some(ref socket) = session.docker_socket_mount {
+        fs::write(dir.join("docker_socket_mount"), socket)?;
+    } else {
+        let _ = fs::remove_file(dir.join("docker_socket_mount"));
+    }
+    if session.inherit_git_config {
+        fs::write(dir.join("inherit_git_config"), "true")?;
+    } else {
+        let _ = fs::remove_file(dir.join("inherit_git_config"));
+    }
+    if let Some(ref driver) = session.storage_driver {
+        fs::write(dir.join("storage_driver"), driver)?;
+    } else {
+        let _ = fs::remove_file(dir.join("storage_driver"));
+    }
+    if !session.storage_opts.is_empty() {
+        fs::write(dir.join("storage_opts"), session.storage_opts.join("\0"))?;
+    } else {
+        let _ = fs::remove_file(dir.join("storage_opts"));
+    }
+    if !session.ports.is_empty() {
+        fs::write(dir.join("ports"), session.ports.join("\0"))?;
+    } else {
+        let _ = fs::remove_file(dir.join("ports"));
+    }
+    if !session.resources.is_empty() {
+        fs::write(
+            dir.join("resources"),
+            serde_json::to_string(&session.resources)?,
+        )?;
+    } else {
+        let _ = fs::remove_file(dir.join("resources"));
+    }
+    if let Some(ref workdir) = session.workdir {
+        fs::write(dir.join("workdir"), workdir)?;
+    } else {
+        let _ = fs::remove_file(dir.join("workdir"));
+    }
+    if let Some(ref from_ref) = session.from_ref {
+        fs::write(dir.join("from_ref"), from_ref)?;
+    } else {
+        let _ = fs::remove_file(dir.join("from_ref"));
+    }
+    if let Some(ref docker_args) = session.docker_args {
+        fs::write(dir.join("docker_args"), docker_args)?;
+    } else {
+        let _ = fs::remove_file(dir.join("docker_args"));
+    }
     Ok(())
 }
 
@@ -304,6 +574,64 @@ pub fn load(name: &str) -> Result<Session> {
         .map(|s| s.trim().to_string())
         .unwrap_or_else(|_| "clone".to_string());
 
+    let notify_on_exit = fs::read_to_string(dir.join("notify_on_exit"))
+        .map(|s| s.trim() == "true")
+        .unwrap_or(false);
+
+    let volumes_from = fs::read_to_string(dir.join("volumes_from"))
+        .map(|s| s.trim().to_string())
+        .ok()
+        .filter(|s| !s.is_empty());
+
+    let docker_socket_mount = fs::read_to_string(dir.join("docker_socket_mount"))
+        .map(|s| s.trim().to_string())
+        .ok()
+        .filter(|s| !s.is_empty());
+
+    let inherit_git_config = fs::read_to_string(dir.join("inherit_git_config"))
+        .map(|s| s.trim() == "true")
+        .unwrap_or(false);
+
+    let storage_driver = fs::read_to_string(dir.join("storage_driver"))
+        .map(|s| s.trim().to_string())
+        .ok()
+        .filter(|s| !s.is_empty());
+
+    let storage_opts = fs::read_to_string(dir.join("storage_opts"))
+        .map(|s| {
+            s.split('\0')
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let ports = fs::read_to_string(dir.join("ports"))
+        .map(|s| {
+            s.split('\0')
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let resources = fs::read_to_string(dir.join("resources"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    let workdir = fs::read_to_string(dir.join("workdir"))
+        .map(|s| s.trim().to_string())
+        .ok()
+        .filter(|s| !s.is_empty());
+
+    let from_ref = fs::read_to_string(dir.join("from_ref"))
+        .map(|s| s.trim().to_string())
+        .ok()
+        .filter(|s| !s.is_empty());
+
+    let docker_args = read_docker_args(&dir);
+
     Ok(Session {
         name: full,
         project_dir,
@@ -313,6 +641,17 @@ pub fn load(name: &str) -> Result<Session> {
         env,
         local,
         strategy,
+        notify_on_exit,
+        volumes_from,
+        docker_socket_mount,
+        inherit_git_config,
+        storage_driver,
+        storage_opts,
+        ports,
+        resources,
+        workdir,
+        from_ref,
+        docker_args,
     })
 }
 
@@ -337,6 +676,44 @@ fn migrate_flat_session(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Read a stored `YYYY-mm-dd HH:MM:SS UTC` timestamp file. Returns the parsed
+/// time (if in that format) and a display string in local time, or the raw
+/// contents when unparseable. Returns None if the file does not exist.
+fn read_timestamp(path: &std::path::Path) -> Option<(Option<DateTime<Utc>>, String)> {
+    let raw = fs::read_to_string(path).ok()?;
+    let parsed = raw
+        .trim()
+        .strip_suffix(" UTC")
+        .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok())
+        .map(|naive| naive.and_utc());
+    let display = match parsed {
+        Some(utc_dt) => utc_dt
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S %Z")
+            .to_string(),
+        None => raw.trim().to_string(),
+    };
+    Some((parsed, display))
+}
+
+/// Summary of a single session, as shown by `list`.
+pub fn summary(name: &str) -> Result<SessionSummary> {
+    let full = full_name(name);
+    let path = sessions_dir()?.join(&full);
+    if !path.join("project_dir").exists() {
+        bail!("Session '{}' not found.", full);
+    }
+    Ok(read_session_summary(&path, full))
+}
+
+/// Read a session's persisted `docker_args`, if any were given at creation.
+fn read_docker_args(session_path: &std::path::Path) -> Option<String> {
+    fs::read_to_string(session_path.join("docker_args"))
+        .map(|s| s.trim().to_string())
+        .ok()
+        .filter(|s| !s.is_empty())
+}
+
 fn read_session_summary(session_path: &std::path::Path, name: String) -> SessionSummary {
     let project_dir = fs::read_to_string(session_path.join("project_dir"))
         .map(|s| s.trim().to_string())
@@ -344,19 +721,12 @@ fn read_session_summary(session_path: &std::path::Path, name: String) -> Session
     let image = fs::read_to_string(session_path.join("image"))
         .map(|s| s.trim().to_string())
         .unwrap_or_default();
-    let created_at = fs::read_to_string(session_path.join("created_at"))
-        .map(|s| {
-            let trimmed = s.trim();
-            if let Some(naive_str) = trimmed.strip_suffix(" UTC") {
-                if let Ok(naive) = NaiveDateTime::parse_from_str(naive_str, "%Y-%m-%d %H:%M:%S") {
-                    let utc_dt = naive.and_utc();
-                    let local_dt = utc_dt.with_timezone(&Local);
-                    return local_dt.format("%Y-%m-%d %H:%M:%S %Z").to_string();
-                }
-            }
-            trimmed.to_string()
-        })
-        .unwrap_or_default();
+    let (created, created_at) =
+        read_timestamp(&session_path.join("created_at")).unwrap_or((None, String::new()));
+    let (resumed, resumed_at) = match read_timestamp(&session_path.join("resumed_at")) {
+        Some((parsed, display)) => (parsed, Some(display)),
+        None => (None, None),
+    };
     let command = fs::read_to_string(session_path.join("command"))
         .map(|s| {
             s.split('\0')
@@ -375,26 +745,76 @@ fn read_session_summary(session_path: &std::path::Path, name: String) -> Session
         .map(|s| s.trim().to_string())
         .unwrap_or_else(|_| "clone".to_string());
 
+    let docker_args = read_docker_args(session_path);
+    let read_opt = |file: &str| {
+        fs::read_to_string(session_path.join(file))
+            .map(|s| s.trim().to_string())
+            .ok()
+            .filter(|s| !s.is_empty())
+    };
+
     SessionSummary {
         name,
         project_dir,
         image,
         command,
         created_at,
+        created,
+        resumed_at,
+        resumed,
         running: false,
         local,
         strategy,
+        docker_args,
+        created_by: read_opt("created_by"),
+        created_host: read_opt("created_host"),
+    }
+}
+
+/// Sort sessions by `created` or `resumed` (most recent first, unknown times
+/// last) or by `name` (ascending).
+pub fn sort(sessions: &mut [SessionSummary], sort_by: &str) {
+    match sort_by {
+        "created" => sessions.sort_by_key(|s| std::cmp::Reverse(s.created)),
+        "resumed" => sessions.sort_by_key(|s| std::cmp::Reverse(s.resumed)),
+        _ => sessions.sort_by(|a, b| a.name.cmp(&b.name)),
     }
 }
 
 pub fn list() -> Result<Vec<SessionSummary>> {
-    let dir = sessions_dir()?;
+    list_in(&sessions_dir()?, FlatSessions::Migrate)
+}
+
+/// List sessions without touching the sessions directory: flat (old-format)
+/// sessions are reported as `<name>/default` but left unmigrated. Used by
+/// shell completion, which must not have side effects.
+pub fn list_read_only() -> Result<Vec<SessionSummary>> {
+    list_in(&sessions_dir()?, FlatSessions::Report)
+}
+
+/// List archived sessions.
+pub fn list_archived() -> Result<Vec<SessionSummary>> {
+    list_in(&archive_dir()?, FlatSessions::Ignore)
+}
+
+/// How `list_in` treats flat (old-format) session directories.
+#[derive(PartialEq)]
+enum FlatSessions {
+    /// Move them to `<name>/default` before listing.
+    Migrate,
+    /// List them as `<name>/default` where they are.
+    Report,
+    /// Skip them.
+    Ignore,
+}
+
+fn list_in(dir: &std::path::Path, flat: FlatSessions) -> Result<Vec<SessionSummary>> {
     if !dir.is_dir() {
         return Ok(Vec::new());
     }
 
     let mut sessions = Vec::new();
-    let mut ws_entries: Vec<_> = fs::read_dir(&dir)?
+    let mut ws_entries: Vec<_> = fs::read_dir(dir)?
         .filter_map(|e| e.ok())
         .filter(|e| e.path().is_dir())
         .collect();
@@ -406,8 +826,18 @@ pub fn list() -> Result<Vec<SessionSummary>> {
 
         // Check if this is a flat (old-format) session: project_dir exists directly
         if ws_path.join("project_dir").exists() {
-            // Auto-migrate to workspace/default
-            let _ = migrate_flat_session(&ws_name);
+            match flat {
+                // Auto-migrate to workspace/default
+                FlatSessions::Migrate => {
+                    let _ = migrate_flat_session(&ws_name);
+                }
+                FlatSessions::Report => {
+                    let full_name = format!("{}/default", ws_name);
+                    sessions.push(read_session_summary(&ws_path, full_name));
+                    continue;
+                }
+                FlatSessions::Ignore => {}
+            }
         }
 
         // Scan sub-directories for session entries
@@ -434,6 +864,101 @@ pub fn remove_dir(name: &str) -> Result<()> {
     fs::remove_dir_all(&dir).context(format!("Failed to remove session directory for '{}'", name))
 }
 
+/// Move a session's metadata directory to a new `workspace/session` name.
+pub fn move_session(from: &str, to: &str) -> Result<()> {
+    let dir = sessions_dir()?;
+    let src = dir.join(full_name(from));
+    let dst = dir.join(full_name(to));
+    if dst.exists() {
+        bail!("Session '{}' already exists.", full_name(to));
+    }
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&src, &dst).context(format!("Failed to move session '{}' to '{}'", from, to))
+}
+
+pub fn archived_exists(name: &str) -> Result<bool> {
+    Ok(archive_dir()?.join(full_name(name)).is_dir())
+}
+
+/// Move a session's metadata directory into the archive, keeping its
+/// workspace/session name.
+pub fn archive(name: &str) -> Result<()> {
+    let full = full_name(name);
+    if !session_exists(&full)? {
+        bail!("Session '{}' not found.", full);
+    }
+    if archived_exists(&full)? {
+        bail!("An archived session '{}' already exists.", full);
+    }
+    move_between(&full, &sessions_dir()?, &archive_dir()?)
+}
+
+/// Move an archived session's metadata directory back under `sessions/`.
+pub fn unarchive(name: &str) -> Result<()> {
+    let full = full_name(name);
+    if !archived_exists(&full)? {
+        bail!("Archived session '{}' not found.", full);
+    }
+    if session_exists(&full)? {
+        bail!(
+            "Session '{}' already exists. Rename or remove it before unarchiving.",
+            full
+        );
+    }
+    move_between(&full, &archive_dir()?, &sessions_dir()?)
+}
+
+fn move_between(full: &str, from: &std::path::Path, to: &std::path::Path) -> Result<()> {
+    let src = from.join(full);
+    let dst = to.join(full);
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&src, &dst).context(format!("Failed to move session '{}'", full))?;
+    // Drop the workspace directory it came from once it is empty
+    if let Some(parent) = src.parent() {
+        let _ = fs::remove_dir(parent);
+    }
+    Ok(())
+}
+
+/// Rename a session (`ws/old` to `ws/new`) or a whole workspace (`old` to
+/// `new`) by moving its metadata directory. Both names must be of the same
+/// kind, and the target must not exist.
+pub fn rename(old: &str, new: &str) -> Result<()> {
+    check_rename(old, new)?;
+    let dir = sessions_dir()?;
+    let dst = dir.join(new);
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(dir.join(old), &dst).context(format!("Failed to rename '{}' to '{}'", old, new))
+}
+
+/// Check that `rename(old, new)` can go ahead, so callers can validate it
+/// before changing anything else.
+pub fn check_rename(old: &str, new: &str) -> Result<()> {
+    if old.contains('/') != new.contains('/') {
+        bail!(
+            "Cannot rename '{}' to '{}': rename a session to a session or a workspace to a workspace.",
+            old,
+            new
+        );
+    }
+    let dir = sessions_dir()?;
+    let src = dir.join(old);
+    let dst = dir.join(new);
+    if !src.is_dir() {
+        bail!("'{}' not found.", old);
+    }
+    if dst.exists() {
+        bail!("'{}' already exists.", new);
+    }
+    Ok(())
+}
+
 /// Remove the entire workspace directory (all sessions within it).
 pub fn remove_workspace_dir(workspace: &str) -> Result<()> {
     let dir = sessions_dir()?.join(workspace);
@@ -443,6 +968,138 @@ pub fn remove_workspace_dir(workspace: &str) -> Result<()> {
     ))
 }
 
+/// Once `events.log` grows past this size it is copied to `events.log.1`
+/// (replacing the previous one) and truncated, so at most twice this is kept.
+const EVENTS_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+/// Path of the session event log, `~/.box/events.log`.
+pub fn events_log_path() -> Result<PathBuf> {
+    Ok(PathBuf::from(config::home_dir()?)
+        .join(".box")
+        .join("events.log"))
+}
+
+/// Append a `{ts, event, session}` line (e.g. `create`, `resume`, `stop`,
+/// `remove`) to the event log when `[events] enabled` is set (read once per
+/// process). Best effort: failing to record never fails the command.
+pub fn record_event(event: &str, name: &str) {
+    static ENABLED: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    if !*ENABLED.get_or_init(config::load_events_enabled) {
+        return;
+    }
+    if let Ok(path) = events_log_path() {
+        let _ = append_event(&path, event, name, Utc::now(), EVENTS_LOG_MAX_BYTES);
+    }
+}
+
+fn append_event(
+    path: &std::path::Path,
+    event: &str,
+    name: &str,
+    ts: DateTime<Utc>,
+    max_bytes: u64,
+) -> Result<()> {
+    use std::io::Write;
+
+    // Truncate in place rather than renaming, so `tail -f` keeps following
+    if fs::metadata(path).is_ok_and(|m| m.len() > max_bytes) {
+        fs::copy(path, path.with_file_name("events.log.1"))?;
+        fs::OpenOptions::new().write(true).open(path)?.set_len(0)?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::json!({
+        "ts": ts.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        "event": event,
+        "session": name,
+    });
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Path of a session's mux server log.
+pub fn server_log_path(name: &str) -> Result<PathBuf> {
+    Ok(sessions_dir()?.join(full_name(name)).join("server.log"))
+}
+
+/// Rotate a session's `server.log`: `server.log` is copied to `server.log.1`,
+/// `server.log.1` becomes `server.log.2`, and so on up to the configured
+/// `max_log_files`. Unless `force` is set, rotation only happens once the log
+/// exceeds `max_log_size_bytes`. Returns whether the log was rotated.
+///
+/// The current log is copied and truncated in place rather than renamed, so a
+/// running server, which holds it open for appending, keeps writing to
+/// `server.log`.
+pub fn rotate_log(name: &str, force: bool) -> Result<bool> {
+    let log_path = server_log_path(name)?;
+    let size = match fs::metadata(&log_path) {
+        Ok(m) => m.len(),
+        Err(_) => return Ok(false),
+    };
+    let limits = config::load_log_rotation();
+    if !force && size <= limits.max_bytes {
+        return Ok(false);
+    }
+
+    let rotated = |i: usize| log_path.with_file_name(format!("server.log.{}", i));
+    if limits.max_files > 0 {
+        let _ = fs::remove_file(rotated(limits.max_files));
+        for i in (1..limits.max_files).rev() {
+            if rotated(i).exists() {
+                fs::rename(rotated(i), rotated(i + 1))?;
+            }
+        }
+        fs::copy(&log_path, rotated(1))?;
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&log_path)?
+        .set_len(0)?;
+    Ok(true)
+}
+
+/// Hex-encoded SHA-256 digest of an environment snapshot.
+pub fn snapshot_hash(snapshot: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(snapshot.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Path of the stored environment baseline snapshot for a session.
+pub fn baseline_snapshot_path(name: &str) -> Result<PathBuf> {
+    Ok(sessions_dir()?
+        .join(full_name(name))
+        .join("baseline.snapshot"))
+}
+
+/// Store an environment snapshot as the session's baseline, alongside its
+/// hash in `baseline.sha256`. Returns the hash.
+pub fn save_baseline(name: &str, snapshot: &str) -> Result<String> {
+    let full = full_name(name);
+    let dir = sessions_dir()?.join(&full);
+    let hash = snapshot_hash(snapshot);
+    fs::write(dir.join("baseline.snapshot"), snapshot)?;
+    fs::write(dir.join("baseline.sha256"), &hash)?;
+    Ok(hash)
+}
+
+/// Load the stored baseline hash for a session, if one was recorded.
+pub fn load_baseline_hash(name: &str) -> Result<Option<String>> {
+    let full = full_name(name);
+    let path = sessions_dir()?.join(&full).join("baseline.sha256");
+    Ok(fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty()))
+}
+
 pub fn touch_resumed_at(name: &str) -> Result<()> {
     let full = full_name(name);
     let dir = sessions_dir()?.join(&full);
@@ -460,6 +1117,17 @@ pub fn write_pid(name: &str, pid: u32) -> Result<()> {
     Ok(())
 }
 
+/// Read the mux server PID recorded for a session, if any.
+pub fn read_pid(name: &str) -> Option<u32> {
+    let full = full_name(name);
+    let dir = sessions_dir().ok()?;
+    fs::read_to_string(dir.join(&full).join("pid"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
 pub fn remove_pid(name: &str) {
     let full = full_name(name);
     if let Ok(dir) = sessions_dir() {
@@ -467,6 +1135,32 @@ pub fn remove_pid(name: &str) {
     }
 }
 
+/// Record the exit code of a local session's command, for `box wait`.
+pub fn write_exit_code(name: &str, code: i32) -> Result<()> {
+    let full = full_name(name);
+    let dir = sessions_dir()?.join(&full);
+    fs::write(dir.join("exit_code"), code.to_string())?;
+    Ok(())
+}
+
+/// Read the exit code recorded when a local session's command last exited.
+pub fn read_exit_code(name: &str) -> Option<i32> {
+    let full = full_name(name);
+    let dir = sessions_dir().ok()?;
+    fs::read_to_string(dir.join(&full).join("exit_code"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+pub fn remove_exit_code(name: &str) {
+    let full = full_name(name);
+    if let Ok(dir) = sessions_dir() {
+        let _ = fs::remove_file(dir.join(&full).join("exit_code"));
+    }
+}
+
 pub fn socket_path(name: &str) -> Result<PathBuf> {
     let full = full_name(name);
     Ok(sessions_dir()?.join(&full).join("sock"))
@@ -503,6 +1197,50 @@ pub fn is_local_running(name: &str) -> bool {
     unsafe { libc::kill(pid, 0) == 0 }
 }
 
+/// Socket and PID files left behind by a mux server that is no longer
+/// running, e.g. after a crash or reboot.
+pub fn stale_server_files(name: &str) -> Vec<PathBuf> {
+    if is_local_running(name) {
+        return Vec::new();
+    }
+    let Ok(dir) = sessions_dir() else {
+        return Vec::new();
+    };
+    let dir = dir.join(full_name(name));
+    ["sock", "pid"]
+        .iter()
+        .map(|f| dir.join(f))
+        .filter(|p| p.exists())
+        .collect()
+}
+
+/// Remove the socket and PID files of a session whose mux server is gone.
+/// A server being started holds the session lock until its socket is up, so
+/// the files are left alone while the lock is taken.
+pub fn reap_stale(name: &str) {
+    let Ok(dir) = sessions_dir().map(|d| d.join(full_name(name))) else {
+        return;
+    };
+    if !dir.is_dir() {
+        return;
+    }
+    let Ok(lock_file) = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(dir.join("lock"))
+    else {
+        return;
+    };
+    use std::os::unix::io::AsRawFd;
+    if unsafe { libc::flock(lock_file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        return;
+    }
+    for path in stale_server_files(name) {
+        let _ = fs::remove_file(path);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -543,6 +1281,27 @@ mod tests {
         assert_eq!(full_name("my-feature/server"), "my-feature/server");
     }
 
+    #[test]
+    fn test_pick_prefix() {
+        let names: Vec<String> = ["my-long-feature/server", "my-long-feature/zsh", "other/zsh"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            pick_prefix("my-long-feature/s", &names).unwrap(),
+            "my-long-feature/server"
+        );
+        assert_eq!(pick_prefix("oth", &names).unwrap(), "other/zsh");
+        let err = pick_prefix("my-long", &names).unwrap_err().to_string();
+        assert!(
+            err.contains("my-long-feature/server, my-long-feature/zsh"),
+            "{}",
+            err
+        );
+        let err = pick_prefix("nope", &names).unwrap_err().to_string();
+        assert_eq!(err, "Session 'nope/default' not found.");
+    }
+
     #[test]
     fn test_workspace_name_bare() {
         assert_eq!(workspace_name("my-feature"), "my-feature");
@@ -627,11 +1386,8 @@ mod tests {
                 project_dir: "/tmp/myproject".to_string(),
                 image: "ubuntu:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -652,11 +1408,8 @@ mod tests {
                 project_dir: "/tmp/myproject".to_string(),
                 image: "ubuntu:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -679,10 +1432,8 @@ mod tests {
                     "-c".to_string(),
                     "echo hello".to_string(),
                 ],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -699,11 +1450,8 @@ mod tests {
                 project_dir: "/tmp/p".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -719,6 +1467,31 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_save_preserves_created_at() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "resave/default".to_string(),
+                project_dir: "/tmp/p".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                strategy: "clone".to_string(),
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+
+            let dir = sessions_dir().unwrap().join("resave/default");
+            fs::write(dir.join("created_at"), "2020-01-01 00:00:00 UTC").unwrap();
+
+            sess.image = "ubuntu:latest".to_string();
+            save(&sess).unwrap();
+
+            let created = fs::read_to_string(dir.join("created_at")).unwrap();
+            assert_eq!(created, "2020-01-01 00:00:00 UTC");
+            assert_eq!(load("resave/default").unwrap().image, "ubuntu:latest");
+        });
+    }
+
     #[test]
     fn test_load_nonexistent() {
         with_temp_home(|_| {
@@ -765,11 +1538,8 @@ mod tests {
                 project_dir: "/tmp/p".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
             assert!(session_exists("exists-test/default").unwrap());
@@ -795,11 +1565,8 @@ mod tests {
                     project_dir: format!("/tmp/{}", name.split('/').next().unwrap()),
                     image: "alpine:latest".to_string(),
                     mount_path: "/workspace".to_string(),
-                    command: vec![],
-                    env: vec![],
-                    local: false,
-
                     strategy: "clone".to_string(),
+                    ..Default::default()
                 };
                 save(&sess).unwrap();
             }
@@ -822,11 +1589,8 @@ mod tests {
                     project_dir: "/tmp/project".to_string(),
                     image: "alpine:latest".to_string(),
                     mount_path: "/workspace".to_string(),
-                    command: vec![],
-                    env: vec![],
-                    local: false,
-
                     strategy: "clone".to_string(),
+                    ..Default::default()
                 };
                 save(&sess).unwrap();
             }
@@ -847,11 +1611,8 @@ mod tests {
                 project_dir: "/home/user/project".to_string(),
                 image: "ubuntu:22.04".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -863,6 +1624,25 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_list_summary_age() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("aged/default");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("project_dir"), "/tmp/p").unwrap();
+            fs::write(dir.join("created_at"), "2024-01-01 00:00:00 UTC").unwrap();
+
+            let sessions = list().unwrap();
+            let now = NaiveDateTime::parse_from_str("2024-01-03 00:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+                .and_utc();
+            assert_eq!(
+                sessions[0].age(now),
+                Some(std::time::Duration::from_secs(2 * 86400))
+            );
+        });
+    }
+
     #[test]
     fn test_remove_dir() {
         with_temp_home(|_| {
@@ -871,11 +1651,8 @@ mod tests {
                 project_dir: "/tmp/p".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
             assert!(session_exists("to-remove/default").unwrap());
@@ -893,6 +1670,71 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_summary_and_resumed_at() {
+        with_temp_home(|_| {
+            save_test_session("info-test/default");
+            let info = summary("info-test").unwrap();
+            assert_eq!(info.name, "info-test/default");
+            assert!(info.created.is_some());
+            assert!(info.resumed_at.is_none());
+            touch_resumed_at("info-test/default").unwrap();
+            let info = summary("info-test").unwrap();
+            assert!(info.resumed_at.is_some());
+            assert!(info.resumed.is_some());
+            assert!(summary("no-such/session").is_err());
+        });
+    }
+
+    #[test]
+    fn test_exit_code_roundtrip() {
+        with_temp_home(|_| {
+            save_test_session("exit-test/default");
+            assert_eq!(read_exit_code("exit-test"), None);
+            write_exit_code("exit-test", 3).unwrap();
+            assert_eq!(read_exit_code("exit-test/default"), Some(3));
+            remove_exit_code("exit-test");
+            assert_eq!(read_exit_code("exit-test"), None);
+        });
+    }
+
+    fn summary_at(name: &str, created: Option<i64>, resumed: Option<i64>) -> SessionSummary {
+        let at = |secs: i64| DateTime::from_timestamp(secs, 0).unwrap();
+        SessionSummary {
+            name: name.to_string(),
+            created: created.map(at),
+            resumed: resumed.map(at),
+            local: true,
+            strategy: "clone".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_created_by_user() {
+        let mut summary = summary_at("a/default", None, None);
+        assert!(summary.created_by_user("alice"));
+        summary.created_by = Some("bob".to_string());
+        assert!(!summary.created_by_user("alice"));
+        assert!(summary.created_by_user("bob"));
+    }
+
+    #[test]
+    fn test_sort_sessions() {
+        let mut sessions = vec![
+            summary_at("b/default", Some(300), None),
+            summary_at("a/default", Some(100), Some(500)),
+            summary_at("c/default", Some(200), Some(900)),
+        ];
+        let names = |s: &[SessionSummary]| s.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+        sort(&mut sessions, "resumed");
+        assert_eq!(names(&sessions), ["c/default", "a/default", "b/default"]);
+        sort(&mut sessions, "created");
+        assert_eq!(names(&sessions), ["b/default", "c/default", "a/default"]);
+        sort(&mut sessions, "name");
+        assert_eq!(names(&sessions), ["a/default", "b/default", "c/default"]);
+    }
+
     #[test]
     fn test_touch_resumed_at() {
         with_temp_home(|_| {
@@ -901,11 +1743,8 @@ mod tests {
                 project_dir: "/tmp/p".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -917,6 +1756,597 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_save_and_load_volumes_from() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "vol-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                strategy: "clone".to_string(),
+                volumes_from: Some("data/default".to_string()),
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+            assert_eq!(
+                load("vol-test/default").unwrap().volumes_from.as_deref(),
+                Some("data/default")
+            );
+
+            sess.volumes_from = None;
+            save(&sess).unwrap();
+            assert!(load("vol-test/default").unwrap().volumes_from.is_none());
+        });
+    }
+
+    #[test]
+    fn test_snapshot_hash() {
+        assert_eq!(
+            snapshot_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(snapshot_hash("A=1\n"), snapshot_hash("A=2\n"));
+    }
+
+    #[test]
+    fn test_save_and_load_baseline() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("base-ws/default");
+            fs::create_dir_all(&dir).unwrap();
+
+            assert!(load_baseline_hash("base-ws/default").unwrap().is_none());
+            let hash = save_baseline("base-ws/default", "PATH=/bin\n").unwrap();
+            assert_eq!(
+                load_baseline_hash("base-ws/default").unwrap().as_deref(),
+                Some(hash.as_str())
+            );
+            assert_eq!(
+                fs::read_to_string(baseline_snapshot_path("base-ws/default").unwrap()).unwrap(),
+                "PATH=/bin\n"
+            );
+        });
+    }
+
+    #[test]
+    fn test_read_pid() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("pid-ws/default");
+            fs::create_dir_all(&dir).unwrap();
+            assert_eq!(read_pid("pid-ws/default"), None);
+            write_pid("pid-ws/default", 4242).unwrap();
+            assert_eq!(read_pid("pid-ws/default"), Some(4242));
+        });
+    }
+
+    #[test]
+    fn test_move_session() {
+        with_temp_home(|_| {
+            let sess = Session {
+                name: "app/server".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                command: vec!["npm".to_string(), "start".to_string()],
+                local: true,
+                strategy: "clone".to_string(),
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+
+            move_session("app/server", "server").unwrap();
+            assert!(!session_exists("app/server").unwrap());
+            let moved = load("server/default").unwrap();
+            assert_eq!(moved.name, "server/default");
+            assert_eq!(moved.command, vec!["npm", "start"]);
+        });
+    }
+
+    #[test]
+    fn test_archive_and_unarchive() {
+        with_temp_home(|_| {
+            save_test_session("app/one");
+            save_test_session("app/two");
+
+            archive("app/one").unwrap();
+            assert!(!session_exists("app/one").unwrap());
+            assert!(archived_exists("app/one").unwrap());
+            let names: Vec<_> = list().unwrap().into_iter().map(|s| s.name).collect();
+            assert_eq!(names, vec!["app/two"]);
+            let archived: Vec<_> = list_archived()
+                .unwrap()
+                .into_iter()
+                .map(|s| s.name)
+                .collect();
+            assert_eq!(archived, vec!["app/one"]);
+
+            // Archiving the last session drops the empty workspace directory
+            archive("app/two").unwrap();
+            assert!(!sessions_dir().unwrap().join("app").exists());
+            assert!(archive("app/two").is_err());
+
+            unarchive("app/one").unwrap();
+            let restored = load("app/one").unwrap();
+            assert_eq!(restored.project_dir, "/tmp/project");
+            assert!(!archived_exists("app/one").unwrap());
+            assert!(unarchive("app/one").is_err());
+        });
+    }
+
+    #[test]
+    fn test_workspace_in_use_counts_archived_sessions() {
+        with_temp_home(|_| {
+            save_test_session("app/one");
+            save_test_session("app/two");
+            archive("app/one").unwrap();
+            remove_dir("app/two").unwrap();
+            assert!(workspace_sessions("app").unwrap().is_empty());
+            assert_eq!(archived_workspace_sessions("app").unwrap(), vec!["one"]);
+            assert!(workspace_in_use("app").unwrap());
+
+            unarchive("app/one").unwrap();
+            remove_dir("app/one").unwrap();
+            assert!(!workspace_in_use("app").unwrap());
+        });
+    }
+
+    #[test]
+    fn test_unarchive_refuses_existing_session() {
+        with_temp_home(|_| {
+            save_test_session("app/one");
+            archive("app/one").unwrap();
+            save_test_session("app/one");
+            assert!(unarchive("app/one").is_err());
+            assert!(archived_exists("app/one").unwrap());
+        });
+    }
+
+    #[test]
+    fn test_move_session_refuses_existing_target() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap();
+            fs::create_dir_all(dir.join("a/one")).unwrap();
+            fs::create_dir_all(dir.join("b/default")).unwrap();
+            assert!(move_session("a/one", "b/default").is_err());
+            assert!(dir.join("a/one").is_dir());
+        });
+    }
+
+    #[test]
+    fn test_save_and_load_docker_socket_mount() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "dind-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "docker:cli".to_string(),
+                mount_path: "/workspace".to_string(),
+                strategy: "clone".to_string(),
+                docker_socket_mount: Some("/var/run/docker.sock".to_string()),
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+            assert_eq!(
+                load("dind-test/default")
+                    .unwrap()
+                    .docker_socket_mount
+                    .as_deref(),
+                Some("/var/run/docker.sock")
+            );
+
+            sess.docker_socket_mount = None;
+            save(&sess).unwrap();
+            assert!(load("dind-test/default")
+                .unwrap()
+                .docker_socket_mount
+                .is_none());
+        });
+    }
+
+    #[test]
+    fn test_save_and_load_inherit_git_config() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "gitcfg-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                local: true,
+                strategy: "clone".to_string(),
+                inherit_git_config: true,
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+            assert!(load("gitcfg-test/default").unwrap().inherit_git_config);
+
+            sess.inherit_git_config = false;
+            save(&sess).unwrap();
+            let dir = sessions_dir().unwrap().join("gitcfg-test/default");
+            assert!(!dir.join("inherit_git_config").exists());
+            assert!(!load("gitcfg-test/default").unwrap().inherit_git_config);
+        });
+    }
+
+    #[test]
+    fn test_save_and_load_ports() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "ports-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                strategy: "clone".to_string(),
+                ports: vec!["8080:80".to_string(), "5432:5432".to_string()],
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+            assert_eq!(
+                load("ports-test/default").unwrap().ports,
+                vec!["8080:80", "5432:5432"]
+            );
+
+            sess.ports = vec![];
+            save(&sess).unwrap();
+            let dir = sessions_dir().unwrap().join("ports-test/default");
+            assert!(!dir.join("ports").exists());
+            assert!(load("ports-test/default").unwrap().ports.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_save_and_load_resources() {
+        with_temp_home(|_| {
+            let mut sess = Session::from(config::BoxConfig {
+                name: "res-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                command: vec![],
+                env: vec![],
+                local: false,
+                strategy: "clone".to_string(),
+            });
+            sess.resources = Resources {
+                memory: Some("2g".to_string()),
+                cpus: Some("1.5".to_string()),
+                pids_limit: Some(256),
+            };
+            save(&sess).unwrap();
+            let loaded = load("res-test/default").unwrap();
+            assert_eq!(loaded.resources, sess.resources);
+            assert_eq!(
+                loaded.resources.docker_args(),
+                vec!["--memory", "2g", "--cpus", "1.5", "--pids-limit", "256"]
+            );
+
+            sess.resources = Resources::default();
+            save(&sess).unwrap();
+            let dir = sessions_dir().unwrap().join("res-test/default");
+            assert!(!dir.join("resources").exists());
+            assert!(load("res-test/default").unwrap().resources.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_save_and_load_workdir() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "workdir-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                local: true,
+                strategy: "clone".to_string(),
+                workdir: Some("packages/api".to_string()),
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+            let loaded = load("workdir-test/default").unwrap();
+            assert_eq!(loaded.workdir.as_deref(), Some("packages/api"));
+            assert_eq!(
+                loaded.working_dir(std::path::Path::new("/ws")),
+                PathBuf::from("/ws/packages/api")
+            );
+
+            sess.workdir = None;
+            save(&sess).unwrap();
+            let loaded = load("workdir-test/default").unwrap();
+            assert_eq!(loaded.workdir, None);
+            assert_eq!(
+                loaded.working_dir(std::path::Path::new("/ws")),
+                PathBuf::from("/ws")
+            );
+        });
+    }
+
+    #[test]
+    fn test_save_and_load_from_ref() {
+        with_temp_home(|_| {
+            let mut sess = Session::from(config::BoxConfig {
+                name: "from-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                command: vec![],
+                env: vec![],
+                local: true,
+                strategy: "worktree".to_string(),
+            });
+            sess.from_ref = Some("release/1.2".to_string());
+            save(&sess).unwrap();
+            let loaded = load("from-test/default").unwrap();
+            assert_eq!(loaded.from_ref.as_deref(), Some("release/1.2"));
+
+            sess.from_ref = None;
+            save(&sess).unwrap();
+            assert_eq!(load("from-test/default").unwrap().from_ref, None);
+        });
+    }
+
+    #[test]
+    fn test_save_and_load_storage() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "storage-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                strategy: "clone".to_string(),
+                storage_driver: Some("overlay2".to_string()),
+                storage_opts: vec!["size=20G".to_string(), "inodes=1000".to_string()],
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+            let loaded = load("storage-test/default").unwrap();
+            assert_eq!(loaded.storage_driver.as_deref(), Some("overlay2"));
+            assert_eq!(loaded.storage_opts, vec!["size=20G", "inodes=1000"]);
+
+            sess.storage_driver = None;
+            sess.storage_opts.clear();
+            save(&sess).unwrap();
+            let loaded = load("storage-test/default").unwrap();
+            assert_eq!(loaded.storage_driver, None);
+            assert!(loaded.storage_opts.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_append_event() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("events.log");
+        let ts = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        append_event(&path, "create", "app/default", ts, 1024).unwrap();
+        append_event(&path, "stop", "app/default", ts, 1024).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(
+            lines[0],
+            serde_json::json!({"ts": "2024-05-01T12:00:00Z", "event": "create", "session": "app/default"})
+        );
+        assert_eq!(lines[1]["event"], "stop");
+
+        // Past the cap the log starts over, keeping one previous file
+        append_event(&path, "remove", "app/default", ts, 10).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("events.log.1")).unwrap(),
+            contents
+        );
+    }
+
+    #[test]
+    fn test_stale_server_files() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("stale/default");
+            fs::create_dir_all(&dir).unwrap();
+            assert!(stale_server_files("stale/default").is_empty());
+            fs::write(dir.join("sock"), "").unwrap();
+            // No process has this PID (it is above the kernel's pid_max)
+            fs::write(dir.join("pid"), "2147483647").unwrap();
+            assert_eq!(
+                stale_server_files("stale/default"),
+                vec![dir.join("sock"), dir.join("pid")]
+            );
+        });
+    }
+
+    #[test]
+    fn test_reap_stale_respects_session_lock() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("stale/default");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("sock"), "").unwrap();
+            fs::write(dir.join("pid"), "2147483647").unwrap();
+
+            // A server starting up holds the lock
+            let lock = fs::File::create(dir.join("lock")).unwrap();
+            use std::os::unix::io::AsRawFd;
+            assert_eq!(unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX) }, 0);
+            reap_stale("stale/default");
+            assert!(dir.join("sock").exists());
+            drop(lock);
+
+            reap_stale("stale/default");
+            assert!(!dir.join("sock").exists());
+            assert!(!dir.join("pid").exists());
+        });
+    }
+
+    #[test]
+    fn test_save_and_load_docker_args() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "args-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                image: "alpine:latest".to_string(),
+                mount_path: "/workspace".to_string(),
+                strategy: "clone".to_string(),
+                docker_args: Some("--network host".to_string()),
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+            assert_eq!(
+                load("args-test/default").unwrap().docker_args.as_deref(),
+                Some("--network host")
+            );
+            assert_eq!(
+                summary("args-test/default").unwrap().docker_args.as_deref(),
+                Some("--network host")
+            );
+
+            sess.docker_args = None;
+            save(&sess).unwrap();
+            let dir = sessions_dir().unwrap().join("args-test/default");
+            assert!(!dir.join("docker_args").exists());
+            assert_eq!(load("args-test/default").unwrap().docker_args, None);
+        });
+    }
+
+    #[test]
+    fn test_validate_workdir() {
+        assert_eq!(validate_workdir("packages/api").unwrap(), "packages/api");
+        assert_eq!(validate_workdir("./packages/api/").unwrap(), "packages/api");
+        assert!(validate_workdir("../other").is_err());
+        assert!(validate_workdir("packages/../../x").is_err());
+        assert!(validate_workdir("/etc").is_err());
+        assert!(validate_workdir(".").is_err());
+        assert!(validate_workdir("").is_err());
+    }
+
+    fn save_test_session(name: &str) {
+        save(&Session {
+            name: name.to_string(),
+            project_dir: "/tmp/project".to_string(),
+            image: "alpine:latest".to_string(),
+            mount_path: "/workspace".to_string(),
+            local: true,
+            strategy: "clone".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_rename_session() {
+        with_temp_home(|_| {
+            save_test_session("rn/default");
+            rename("rn/default", "rn/feature").unwrap();
+            assert!(!session_exists("rn/default").unwrap());
+            assert_eq!(load("rn/feature").unwrap().name, "rn/feature");
+        });
+    }
+
+    #[test]
+    fn test_rename_workspace() {
+        with_temp_home(|_| {
+            save_test_session("old-ws/default");
+            save_test_session("old-ws/server");
+            rename("old-ws", "new-ws").unwrap();
+            assert!(!workspace_exists("old-ws").unwrap());
+            assert_eq!(
+                workspace_sessions("new-ws").unwrap(),
+                vec!["default", "server"]
+            );
+        });
+    }
+
+    #[test]
+    fn test_rename_refuses_existing_target_and_mixed_kinds() {
+        with_temp_home(|_| {
+            save_test_session("a/default");
+            save_test_session("a/other");
+            assert!(rename("a/default", "a/other").is_err());
+            assert!(rename("a/default", "b").is_err());
+            assert!(rename("missing/default", "missing/x").is_err());
+            assert!(session_exists("a/default").unwrap());
+        });
+    }
+
+    #[test]
+    fn test_rotate_log_below_limit_is_noop() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("log-ws/default");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("server.log"), "small").unwrap();
+
+            assert!(!rotate_log("log-ws/default", false).unwrap());
+            assert!(dir.join("server.log").exists());
+            assert!(!dir.join("server.log.1").exists());
+        });
+    }
+
+    #[test]
+    fn test_rotate_log_shifts_files() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("log-ws/default");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("server.log"), "current").unwrap();
+            fs::write(dir.join("server.log.1"), "older").unwrap();
+
+            assert!(rotate_log("log-ws/default", true).unwrap());
+            assert_eq!(fs::read_to_string(dir.join("server.log")).unwrap(), "");
+            assert_eq!(
+                fs::read_to_string(dir.join("server.log.1")).unwrap(),
+                "current"
+            );
+            assert_eq!(
+                fs::read_to_string(dir.join("server.log.2")).unwrap(),
+                "older"
+            );
+        });
+    }
+
+    #[test]
+    fn test_rotate_log_drops_oldest() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("log-ws/default");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("server.log"), "current").unwrap();
+            for i in 1..=5 {
+                fs::write(dir.join(format!("server.log.{}", i)), i.to_string()).unwrap();
+            }
+
+            rotate_log("log-ws/default", true).unwrap();
+            assert_eq!(fs::read_to_string(dir.join("server.log.5")).unwrap(), "4");
+            assert!(!dir.join("server.log.6").exists());
+        });
+    }
+
+    #[test]
+    fn test_rotate_log_keeps_open_writer() {
+        use std::io::Write;
+
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("log-ws/default");
+            fs::create_dir_all(&dir).unwrap();
+            let mut writer = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join("server.log"))
+                .unwrap();
+            write!(writer, "before").unwrap();
+
+            rotate_log("log-ws/default", true).unwrap();
+            write!(writer, "after").unwrap();
+            assert_eq!(
+                fs::read_to_string(dir.join("server.log.1")).unwrap(),
+                "before"
+            );
+            assert_eq!(fs::read_to_string(dir.join("server.log")).unwrap(), "after");
+        });
+    }
+
+    #[test]
+    fn test_rotate_log_missing_file() {
+        with_temp_home(|_| {
+            assert!(!rotate_log("nolog/default", true).unwrap());
+        });
+    }
+
     #[test]
     fn test_save_trims_whitespace_on_load() {
         with_temp_home(|_| {
@@ -942,10 +2372,8 @@ mod tests {
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
                 command: vec!["bash".to_string(), "-c".to_string(), "echo hi".to_string()],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -963,11 +2391,9 @@ mod tests {
                 project_dir: "/tmp/project".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
                 env: vec!["FOO=bar".to_string(), "BAZ".to_string()],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -980,6 +2406,29 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_save_and_load_notify_on_exit() {
+        with_temp_home(|_| {
+            let mut sess = Session {
+                name: "notify-test/default".to_string(),
+                project_dir: "/tmp/project".to_string(),
+                command: vec!["make".to_string()],
+                local: true,
+                strategy: "clone".to_string(),
+                notify_on_exit: true,
+                ..Default::default()
+            };
+            save(&sess).unwrap();
+            assert!(load("notify-test/default").unwrap().notify_on_exit);
+
+            sess.notify_on_exit = false;
+            save(&sess).unwrap();
+            let dir = sessions_dir().unwrap().join("notify-test/default");
+            assert!(!dir.join("notify_on_exit").exists());
+            assert!(!load("notify-test/default").unwrap().notify_on_exit);
+        });
+    }
+
     #[test]
     fn test_save_and_load_empty_env() {
         with_temp_home(|_| {
@@ -988,11 +2437,8 @@ mod tests {
                 project_dir: "/tmp/project".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -1026,6 +2472,24 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_list_read_only_leaves_flat_session() {
+        with_temp_home(|_| {
+            let dir = sessions_dir().unwrap().join("old-session");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("project_dir"), "/tmp/project").unwrap();
+            fs::write(dir.join("image"), "alpine:latest").unwrap();
+            save_test_session("new/default");
+
+            let sessions = list_read_only().unwrap();
+            let names: Vec<_> = sessions.iter().map(|s| s.name.as_str()).collect();
+            assert_eq!(names, vec!["new/default", "old-session/default"]);
+            assert_eq!(sessions[1].image, "alpine:latest");
+            assert!(dir.join("project_dir").exists());
+            assert!(!dir.join("default").exists());
+        });
+    }
+
     #[test]
     fn test_migration_on_load() {
         with_temp_home(|_| {
@@ -1054,11 +2518,8 @@ mod tests {
                 project_dir: "/tmp/p".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
-
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
             assert!(workspace_exists("ws-test").unwrap());
@@ -1074,11 +2535,8 @@ mod tests {
                     project_dir: "/tmp/p".to_string(),
                     image: "alpine:latest".to_string(),
                     mount_path: "/workspace".to_string(),
-                    command: vec![],
-                    env: vec![],
-                    local: false,
-
                     strategy: "clone".to_string(),
+                    ..Default::default()
                 };
                 save(&sess).unwrap();
             }
@@ -1109,10 +2567,8 @@ mod tests {
                 project_dir: "/tmp/real-repo".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -1136,10 +2592,8 @@ mod tests {
                 project_dir: "/tmp/real-repo".to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess_a).unwrap();
 
@@ -1149,10 +2603,8 @@ mod tests {
                 project_dir: ws_a_dir.to_string_lossy().to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess_b).unwrap();
 
@@ -1187,10 +2639,8 @@ mod tests {
                 project_dir: ws_dir.to_string_lossy().to_string(),
                 image: "alpine:latest".to_string(),
                 mount_path: "/workspace".to_string(),
-                command: vec![],
-                env: vec![],
-                local: false,
                 strategy: "clone".to_string(),
+                ..Default::default()
             };
             save(&sess).unwrap();
 
@@ -50,44 +50,36 @@ impl TextInput {
                 self.text.insert(self.cursor, c);
                 self.cursor += c.len_utf8();
             }
-            KeyCode::Backspace => {
-                if self.cursor > 0 {
-                    let prev = self.text[..self.cursor]
-                        .char_indices()
-                        .next_back()
-                        .map(|(i, _)| i)
-                        .unwrap_or(0);
-                    self.text.drain(prev..self.cursor);
-                    self.cursor = prev;
-                }
+            KeyCode::Backspace if self.cursor > 0 => {
+                let prev = self.text[..self.cursor]
+                    .char_indices()
+                    .next_back()
+                    .map(|(i, _)| i)
+                    .unwrap_or(0);
+                self.text.drain(prev..self.cursor);
+                self.cursor = prev;
             }
-            KeyCode::Delete => {
-                if self.cursor < self.text.len() {
-                    let next = self.text[self.cursor..]
-                        .char_indices()
-                        .nth(1)
-                        .map(|(i, _)| self.cursor + i)
-                        .unwrap_or(self.text.len());
-                    self.text.drain(self.cursor..next);
-                }
+            KeyCode::Delete if self.cursor < self.text.len() => {
+                let next = self.text[self.cursor..]
+                    .char_indices()
+                    .nth(1)
+                    .map(|(i, _)| self.cursor + i)
+                    .unwrap_or(self.text.len());
+                self.text.drain(self.cursor..next);
             }
-            KeyCode::Left => {
-                if self.cursor > 0 {
-                    self.cursor = self.text[..self.cursor]
-                        .char_indices()
-                        .next_back()
-                        .map(|(i, _)| i)
-                        .unwrap_or(0);
-                }
+            KeyCode::Left if self.cursor > 0 => {
+                self.cursor = self.text[..self.cursor]
+                    .char_indices()
+                    .next_back()
+                    .map(|(i, _)| i)
+                    .unwrap_or(0);
             }
-            KeyCode::Right => {
-                if self.cursor < self.text.len() {
-                    self.cursor = self.text[self.cursor..]
-                        .char_indices()
-                        .nth(1)
-                        .map(|(i, _)| self.cursor + i)
-                        .unwrap_or(self.text.len());
-                }
+            KeyCode::Right if self.cursor < self.text.len() => {
+                self.cursor = self.text[self.cursor..]
+                    .char_indices()
+                    .nth(1)
+                    .map(|(i, _)| self.cursor + i)
+                    .unwrap_or(self.text.len());
             }
             _ => {}
         }
@@ -137,6 +129,112 @@ fn clear_viewport(
     Ok(())
 }
 
+/// Wait up to `interval` for a key. Returns true when the user pressed q, Esc
+/// or Ctrl+C.
+fn wait_for_quit(interval: std::time::Duration) -> Result<bool> {
+    let deadline = std::time::Instant::now() + interval;
+    while let Some(remaining) = deadline.checked_duration_since(std::time::Instant::now()) {
+        if !event::poll(remaining)? {
+            break;
+        }
+        if let Event::Key(key) = event::read()? {
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if key.kind == KeyEventKind::Press
+                && (ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc))
+            {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Run `draw` in the alternate screen every `interval` until the user quits.
+fn watch(
+    interval: std::time::Duration,
+    mut draw: impl FnMut(&mut Terminal<CrosstermBackend<io::Stderr>>) -> Result<()>,
+) -> Result<()> {
+    terminal::enable_raw_mode()?;
+    let _guard = TermGuard;
+    execute!(io::stderr(), terminal::EnterAlternateScreen)?;
+    let mut term = Terminal::new(CrosstermBackend::new(io::stderr()))?;
+
+    let result = (|| -> Result<()> {
+        loop {
+            draw(&mut term)?;
+            if wait_for_quit(interval)? {
+                return Ok(());
+            }
+        }
+    })();
+
+    execute!(io::stderr(), terminal::LeaveAlternateScreen)?;
+    result
+}
+
+fn watch_title(title: &str, interval: std::time::Duration) -> Line<'static> {
+    Line::from(Span::styled(
+        format!("{}  (every {}s, q to quit)", title, interval.as_secs()),
+        Style::default().add_modifier(Modifier::DIM),
+    ))
+}
+
+/// Full-screen view that redraws `fetch()`'s lines every `interval` until the
+/// user presses q, Esc or Ctrl+C. Used by `--watch` modes.
+pub fn watch_lines(
+    title: &str,
+    interval: std::time::Duration,
+    mut fetch: impl FnMut() -> Result<Vec<String>>,
+) -> Result<()> {
+    watch(interval, |term| {
+        let lines = fetch()?;
+        term.draw(|f| {
+            let mut text = vec![watch_title(title, interval)];
+            text.extend(lines.iter().map(|l| Line::from(l.as_str())));
+            f.render_widget(ratatui::widgets::Paragraph::new(text), f.area());
+        })?;
+        Ok(())
+    })
+}
+
+/// Like `watch_lines`, but renders `fetch()`'s rows as a table under `header`,
+/// with each column as wide as its widest cell.
+pub fn watch_table(
+    title: &str,
+    interval: std::time::Duration,
+    header: &[&str],
+    mut fetch: impl FnMut() -> Result<Vec<Vec<String>>>,
+) -> Result<()> {
+    use ratatui::widgets::{Paragraph, Row, Table};
+
+    watch(interval, |term| {
+        let rows = fetch()?;
+        let widths: Vec<Constraint> = (0..header.len())
+            .map(|i| {
+                let widest = rows
+                    .iter()
+                    .filter_map(|r| r.get(i))
+                    .map(|c| c.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .max(header[i].len());
+                Constraint::Length(widest as u16)
+            })
+            .collect();
+        term.draw(|f| {
+            let [title_area, table_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(f.area());
+            f.render_widget(Paragraph::new(watch_title(title, interval)), title_area);
+            let table = Table::new(rows.iter().map(|r| Row::new(r.clone())), widths)
+                .header(Row::new(header.to_vec()).style(Style::default().bold()))
+                .column_spacing(2);
+            f.render_widget(table, table_area);
+        })?;
+        Ok(())
+    })
+}
+
 /// Minimal create-session TUI: prompts for name, (image), command.
 /// Returns `TuiAction::New` or `TuiAction::Quit`.
 pub fn create_session() -> Result<TuiAction> {